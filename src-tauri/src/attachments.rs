@@ -0,0 +1,144 @@
+//! Attachments nothing links to any more. Images and PDFs stay behind when
+//! the embed that showed them is deleted; this finds every file that isn't
+//! itself indexed (notes, canvases, CSV, drawings) and that no wikilink in
+//! the index or Markdown link in a note points at.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path};
+
+use crate::database::Database;
+use crate::indexer;
+use crate::vault::Vault;
+use crate::watcher;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanedAttachment {
+    pub path: String,
+    /// Bytes on disk
+    pub size: u64,
+}
+
+/// Unreferenced attachments, by path
+pub fn find_orphans(vault_path: &Path, db: &Database) -> Result<Vec<OrphanedAttachment>> {
+    let references = references(vault_path, db)?;
+    let mut orphans: Vec<OrphanedAttachment> = Vault::list_entries(vault_path)?
+        .into_iter()
+        .filter(|entry| !entry.is_dir && !watcher::is_indexed(&entry.path))
+        .filter(|entry| !suffixes(&normalize(&entry.path)).any(|s| references.contains(s)))
+        .map(|entry| OrphanedAttachment {
+            path: entry.path,
+            size: entry.size,
+        })
+        .collect();
+    orphans.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(orphans)
+}
+
+/// Delete the orphaned attachments among `paths`, or all of them when
+/// `None`. Files that are linked again by now are left alone. Returns the
+/// paths deleted.
+pub fn delete_orphans(
+    vault_path: &Path,
+    db: &Database,
+    paths: Option<&[String]>,
+) -> Result<Vec<String>> {
+    let mut deleted = Vec::new();
+    for orphan in find_orphans(vault_path, db)? {
+        if paths.is_some_and(|paths| !paths.contains(&orphan.path)) {
+            continue;
+        }
+        Vault::delete_entry(vault_path, &orphan.path)?;
+        db.forget_attachment(&orphan.path)?;
+        deleted.push(orphan.path);
+    }
+    Ok(deleted)
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// Every path or file name something links to, normalized. A file counts
+/// as linked if its path, or any tail of it down to the file name, is here,
+/// the way `[[shot.png]]` finds `attachments/shot.png`.
+fn references(vault_path: &Path, db: &Database) -> Result<HashSet<String>> {
+    let mut references: HashSet<String> = db
+        .get_all_links()?
+        .into_iter()
+        .map(|(_, target)| normalize(&target))
+        .collect();
+
+    for note in db.get_all_notes()? {
+        if !note.path.ends_with(".md") {
+            continue;
+        }
+        let Ok(content) = Vault::read_file(vault_path, &note.path) else {
+            continue;
+        };
+        let note_dir = Path::new(&note.path).parent().unwrap_or(Path::new(""));
+        for dest in indexer::extract_markdown_links(&content) {
+            // Relative to the note, and as written in case it meant the vault root
+            references.insert(normalize(&note_dir.join(&dest).to_string_lossy()));
+            references.insert(normalize(&dest));
+        }
+    }
+    Ok(references)
+}
+
+/// Lowercase, `/`-separated, with `.` and `..` resolved
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/").to_lowercase();
+    let mut parts: Vec<String> = Vec::new();
+    for component in Path::new(&path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+/// `a/b/c.png`, `b/c.png`, `c.png`
+fn suffixes(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(path).chain(path.match_indices('/').map(move |(i, _)| &path[i + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_find_and_delete_orphans() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Notes/Home.md",
+                "![[shot.png]]\n![diagram](../assets/diagram%20one.svg \"Flow\")\n[site](https://example.com/x.png)\n",
+            ),
+            ("Board.canvas", "{\"nodes\":[],\"edges\":[]}"),
+            ("attachments/shot.png", "png"),
+            ("assets/diagram one.svg", "<svg/>"),
+            ("attachments/old.png", "stale"),
+            ("x.png", "unlinked too"),
+        ]);
+        let db = vault.index();
+
+        let orphans = find_orphans(vault.path(), &db).unwrap();
+        let paths: Vec<&str> = orphans.iter().map(|o| o.path.as_str()).collect();
+        assert_eq!(paths, vec!["attachments/old.png", "x.png"]);
+        assert_eq!(orphans[0].size, 5);
+
+        let deleted = delete_orphans(
+            vault.path(),
+            &db,
+            Some(&["x.png".into(), "Board.canvas".into()]),
+        )
+        .unwrap();
+        assert_eq!(deleted, vec!["x.png"]);
+        assert!(!vault.path().join("x.png").exists());
+        assert!(vault.path().join("attachments/old.png").exists());
+    }
+}
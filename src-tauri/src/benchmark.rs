@@ -0,0 +1,169 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::database::Database;
+
+/// Largest synthetic vault `run` will generate
+pub const MAX_NOTES: usize = 50_000;
+/// Every Nth note is edited for the incremental reindex
+const EDIT_EVERY: usize = 100;
+const TAGS: [&str; 8] = [
+    "#project", "#idea", "#todo", "#work", "#reading", "#journal", "#ref", "#draft",
+];
+
+/// Timings from indexing a generated vault, in milliseconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub note_count: usize,
+    pub link_count: usize,
+    pub full_reindex_ms: f64,
+    /// Notes edited and reindexed one by one, as after saves
+    pub edited_notes: usize,
+    pub incremental_reindex_ms: f64,
+    pub queries: Vec<QueryTiming>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryTiming {
+    pub name: String,
+    pub ms: f64,
+}
+
+/// Generate a vault of `note_count` linked, tagged notes in a temp folder,
+/// then time a full reindex, reindexing a few edited notes and common
+/// queries. The vault is deleted afterwards.
+pub fn run(note_count: usize) -> Result<BenchmarkReport> {
+    if note_count == 0 || note_count > MAX_NOTES {
+        anyhow::bail!("Note count must be between 1 and {}", MAX_NOTES);
+    }
+    let vault = ScratchVault::new()?;
+    let mut link_count = 0;
+    for i in 0..note_count {
+        let (content, links) = synthetic_note(i, note_count, 0);
+        link_count += links;
+        vault.write(&note_path(i), &content)?;
+    }
+
+    let db = Database::init_for_vault(&vault.0)?;
+    let full_reindex_ms = time(|| db.reindex_vault(&vault.0))?;
+
+    let edited: Vec<usize> = (0..note_count).step_by(EDIT_EVERY).collect();
+    for &i in &edited {
+        vault.write(&note_path(i), &synthetic_note(i, note_count, 1).0)?;
+    }
+    let incremental_reindex_ms = time(|| {
+        edited
+            .iter()
+            .try_for_each(|&i| db.reindex_note(&vault.0, &note_path(i)))
+    })?;
+
+    let probe = format!("Note {}", note_count / 2);
+    let queries = vec![
+        query("get_all_notes", || db.get_all_notes())?,
+        query("get_backlinks", || db.get_backlinks(&probe))?,
+        query("get_all_links", || db.get_all_links())?,
+        query("get_all_tags", || db.get_all_tags())?,
+        query("get_notes_by_tag", || db.get_notes_by_tag(TAGS[0]))?,
+        query("resolve_link_target", || db.resolve_link_target(&probe))?,
+    ];
+
+    Ok(BenchmarkReport {
+        note_count,
+        link_count,
+        full_reindex_ms,
+        edited_notes: edited.len(),
+        incremental_reindex_ms,
+        queries,
+    })
+}
+
+fn note_path(i: usize) -> String {
+    format!("Folder {}/Note {}.md", i % 20, i)
+}
+
+/// A note with a few headings, tags and links to neighbouring and distant
+/// notes; `revision` changes the body so reindexing has work to do
+fn synthetic_note(i: usize, note_count: usize, revision: usize) -> (String, usize) {
+    // A cheap, deterministic spread of link targets
+    let targets: Vec<usize> = [1, 7, 31, i * 7919]
+        .iter()
+        .map(|offset| (i + offset) % note_count)
+        .filter(|&target| target != i)
+        .collect();
+    let mut content = format!(
+        "---\ntags: [{}]\n---\n# Note {}\n\nRevision {}. ",
+        TAGS[i % TAGS.len()].trim_start_matches('#'),
+        i,
+        revision
+    );
+    content.push_str(&"Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(8));
+    for (n, target) in targets.iter().enumerate() {
+        content.push_str(&format!(
+            "\n\n## Section {}\nSee [[Note {}]] {} ^block-{}\n",
+            n,
+            target,
+            TAGS[(i + n) % TAGS.len()],
+            n
+        ));
+    }
+    (content, targets.len())
+}
+
+fn time(work: impl FnOnce() -> Result<()>) -> Result<f64> {
+    let start = Instant::now();
+    work()?;
+    Ok(start.elapsed().as_secs_f64() * 1000.0)
+}
+
+fn query<T>(name: &str, work: impl FnOnce() -> Result<T>) -> Result<QueryTiming> {
+    let ms = time(|| work().map(|_| ()))?;
+    Ok(QueryTiming {
+        name: name.to_string(),
+        ms,
+    })
+}
+
+/// Temp folder for the generated vault, removed on drop
+struct ScratchVault(PathBuf);
+
+impl ScratchVault {
+    fn new() -> Result<Self> {
+        let root = std::env::temp_dir().join(format!("synapse-bench-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).context("Failed to create benchmark vault")?;
+        Ok(ScratchVault(root))
+    }
+
+    fn write(&self, path: &str, content: &str) -> Result<()> {
+        let file = self.0.join(path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file, content).with_context(|| format!("Failed to write {}", path))
+    }
+}
+
+impl Drop for ScratchVault {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            log::warn!("Failed to remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_small_vault() {
+        let report = run(120).unwrap();
+        assert_eq!(report.note_count, 120);
+        assert_eq!(report.edited_notes, 2);
+        assert!(report.link_count >= 3 * 120);
+        assert_eq!(report.queries.len(), 6);
+        assert!(run(0).is_err());
+    }
+}
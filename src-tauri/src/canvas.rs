@@ -0,0 +1,202 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A JSON Canvas document (https://jsoncanvas.org), as used by `.canvas` files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Canvas {
+    #[serde(default)]
+    pub nodes: Vec<CanvasNode>,
+    #[serde(default)]
+    pub edges: Vec<CanvasEdge>,
+    /// Top-level keys written by other apps, preserved on save
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanvasNode {
+    pub id: String,
+    /// `text`, `file`, `link` or `group`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Markdown content (text nodes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Vault-relative path (file nodes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// `#Heading` or `#^block` inside `file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subpath: Option<String>,
+    /// Link nodes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Group nodes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Any other node properties (background, styleAttributes, ...)
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CanvasEdge {
+    pub id: String,
+    pub from_node: String,
+    pub to_node: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_side: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_side: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+const SIDES: &[&str] = &["top", "right", "bottom", "left"];
+const ENDS: &[&str] = &["none", "arrow"];
+
+impl Canvas {
+    /// Parse and validate canvas JSON. An empty file is an empty canvas.
+    pub fn parse(json: &str) -> Result<Canvas> {
+        if json.trim().is_empty() {
+            return Ok(Canvas::default());
+        }
+        let canvas: Canvas = serde_json::from_str(json).context("Invalid canvas JSON")?;
+        canvas.validate()?;
+        Ok(canvas)
+    }
+
+    /// Serialize with tab indentation, matching what Obsidian writes
+    pub fn to_json(&self) -> Result<String> {
+        let mut buf = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+        let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+        self.serialize(&mut ser)
+            .context("Failed to serialize canvas")?;
+        String::from_utf8(buf).context("Canvas JSON is not UTF-8")
+    }
+
+    /// Check ids are unique, nodes carry the fields their type needs and
+    /// edges connect existing nodes
+    pub fn validate(&self) -> Result<()> {
+        let mut ids = HashSet::new();
+        for node in &self.nodes {
+            if node.id.is_empty() || !ids.insert(node.id.as_str()) {
+                bail!("Canvas node id '{}' is empty or duplicated", node.id);
+            }
+            if node.width < 0.0 || node.height < 0.0 {
+                bail!("Canvas node '{}' has a negative size", node.id);
+            }
+            let missing = match node.kind.as_str() {
+                "text" => node.text.is_none().then_some("text"),
+                "file" => node.file.is_none().then_some("file"),
+                "link" => node.url.is_none().then_some("url"),
+                "group" => None,
+                other => bail!("Canvas node '{}' has unknown type '{}'", node.id, other),
+            };
+            if let Some(field) = missing {
+                bail!(
+                    "Canvas {} node '{}' is missing '{}'",
+                    node.kind,
+                    node.id,
+                    field
+                );
+            }
+        }
+
+        let mut edge_ids = HashSet::new();
+        for edge in &self.edges {
+            if edge.id.is_empty() || ids.contains(edge.id.as_str()) || !edge_ids.insert(&edge.id) {
+                bail!("Canvas edge id '{}' is empty or duplicated", edge.id);
+            }
+            for node in [&edge.from_node, &edge.to_node] {
+                if !ids.contains(node.as_str()) {
+                    bail!(
+                        "Canvas edge '{}' points at missing node '{}'",
+                        edge.id,
+                        node
+                    );
+                }
+            }
+            let sides = [&edge.from_side, &edge.to_side];
+            if sides
+                .iter()
+                .flat_map(|s| s.as_deref())
+                .any(|s| !SIDES.contains(&s))
+            {
+                bail!("Canvas edge '{}' has an invalid side", edge.id);
+            }
+            let ends = [&edge.from_end, &edge.to_end];
+            if ends
+                .iter()
+                .flat_map(|e| e.as_deref())
+                .any(|e| !ENDS.contains(&e))
+            {
+                bail!("Canvas edge '{}' has an invalid end", edge.id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Link targets for the links table: file nodes pointing at notes (by
+    /// name, like `[[Note]]`) plus wikilinks written inside text nodes
+    pub fn link_targets(&self) -> Vec<String> {
+        let mut targets: Vec<String> = Vec::new();
+        for node in &self.nodes {
+            match (node.kind.as_str(), &node.file, &node.text) {
+                ("file", Some(file), _) if file.ends_with(".md") => {
+                    targets.push(
+                        file.trim_end_matches(".md")
+                            .rsplit('/')
+                            .next()
+                            .unwrap_or(file)
+                            .to_string(),
+                    );
+                }
+                ("text", _, Some(text)) => targets.extend(crate::indexer::extract_wikilinks(text)),
+                _ => {}
+            }
+        }
+        let mut seen = HashSet::new();
+        targets.retain(|t| seen.insert(t.clone()));
+        targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canvas_round_trip_and_links() {
+        let json = r#"{"nodes":[
+            {"id":"a","type":"file","file":"Projects/Plan.md","x":0,"y":0,"width":400,"height":300},
+            {"id":"b","type":"text","text":"See [[Ideas]]","x":500,"y":0,"width":200,"height":100,"background":"x.png"}
+        ],"edges":[{"id":"e","fromNode":"a","toNode":"b","toEnd":"arrow"}],"custom":1}"#;
+        let canvas = Canvas::parse(json).unwrap();
+        assert_eq!(canvas.link_targets(), vec!["Plan", "Ideas"]);
+
+        let saved = Canvas::parse(&canvas.to_json().unwrap()).unwrap();
+        assert_eq!(saved.nodes[1].extra["background"], "x.png");
+        assert_eq!(saved.extra["custom"], 1);
+
+        let broken = json.replace(r#""toNode":"b""#, r#""toNode":"zzz""#);
+        assert!(Canvas::parse(&broken).is_err());
+    }
+}
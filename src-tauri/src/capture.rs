@@ -0,0 +1,144 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::vault::{AppendOptions, AppendResult, Vault};
+
+/// Inbox note used when the `capture.inbox` setting is unset
+pub const DEFAULT_INBOX: &str = "Inbox.md";
+
+/// Where captured text goes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CaptureTarget {
+    /// Append a timestamped bullet to the inbox note
+    #[default]
+    Inbox,
+    /// Start a new note (titled from the first line if no title is given)
+    NewNote {
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        folder: Option<String>,
+    },
+}
+
+/// Something shared to the app from the OS share sheet or an Android intent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SharedItem {
+    Text {
+        text: String,
+    },
+    Url {
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+    },
+    /// A file the share extension copied to app storage
+    Image {
+        path: String,
+    },
+}
+
+/// Markdown for shared text and links. Images are saved as attachments
+/// first and passed in as `embeds` (vault paths).
+pub fn shared_markdown(items: &[SharedItem], embeds: &[String]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for item in items {
+        match item {
+            SharedItem::Text { text } if !text.trim().is_empty() => {
+                parts.push(text.trim().to_string())
+            }
+            SharedItem::Url { url, title } => parts.push(match title {
+                Some(title) if !title.trim().is_empty() => {
+                    format!("[{}]({})", title.trim().replace(['[', ']'], ""), url)
+                }
+                _ => format!("<{}>", url),
+            }),
+            _ => {}
+        }
+    }
+    parts.extend(embeds.iter().map(|path| format!("![[{}]]", path)));
+    parts.join("\n")
+}
+
+/// Save captured text to the inbox or a new note
+pub fn capture(
+    vault_path: &Path,
+    text: &str,
+    target: &CaptureTarget,
+    inbox: &str,
+) -> Result<AppendResult> {
+    if text.trim().is_empty() {
+        bail!("Nothing to capture");
+    }
+    match target {
+        CaptureTarget::Inbox => Vault::append_to_note(
+            vault_path,
+            inbox,
+            text,
+            &AppendOptions {
+                timestamp: true,
+                ..Default::default()
+            },
+        ),
+        CaptureTarget::NewNote { title, folder } => {
+            let title = title
+                .as_deref()
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| title_from_text(text));
+            let folder = folder.as_deref().unwrap_or("");
+            let file_name = Vault::note_relative_path("", &title);
+            let path = Vault::unique_file_path(vault_path, folder, &file_name);
+            Vault::append_to_note(vault_path, &path, text, &AppendOptions::default())
+        }
+    }
+}
+
+/// First line of the text, shortened, or a timestamp if it has no words
+fn title_from_text(text: &str) -> String {
+    let first = text
+        .lines()
+        .map(|l| l.trim_start_matches(['#', '-', '*', '>', ' ']).trim())
+        .find(|l| !l.is_empty() && !l.starts_with("![["))
+        .unwrap_or("");
+    let title: String = first.chars().take(60).collect();
+    if title.is_empty() {
+        format!("Capture {}", chrono::Local::now().format("%Y-%m-%d %H%M"))
+    } else {
+        title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_markdown() {
+        let items = vec![
+            SharedItem::Text {
+                text: "  Read later \n".to_string(),
+            },
+            SharedItem::Url {
+                url: "https://example.com".to_string(),
+                title: Some("Example [site]".to_string()),
+            },
+            SharedItem::Url {
+                url: "https://a.dev".to_string(),
+                title: None,
+            },
+            SharedItem::Image {
+                path: "/tmp/share/1.png".to_string(),
+            },
+        ];
+        assert_eq!(
+            shared_markdown(&items, &["attachments/1.png".to_string()]),
+            "Read later\n[Example site](https://example.com)\n<https://a.dev>\n![[attachments/1.png]]"
+        );
+        assert_eq!(title_from_text("## Groceries\n- milk"), "Groceries");
+    }
+}
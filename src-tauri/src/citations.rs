@@ -0,0 +1,472 @@
+//! BibTeX citations. The vault's bibliography (a `.bib` file chosen in the
+//! `citations.bib_file` setting) is parsed into the cache by citekey, and
+//! Pandoc-style `[@citekey]` references in notes are indexed so each entry
+//! knows which notes cite it. Literature notes are ordinary notes carrying
+//! the entry's fields in their frontmatter.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::database::Database;
+use crate::vault::Vault;
+
+/// Vault-relative `.bib` file to read citations from
+pub const BIB_FILE_SETTING: &str = "citations.bib_file";
+/// Folder new literature notes go in
+pub const LITERATURE_FOLDER_SETTING: &str = "citations.literature_folder";
+pub const DEFAULT_LITERATURE_FOLDER: &str = "Literature";
+/// Vault-relative template for the body of new literature notes
+pub const LITERATURE_TEMPLATE_SETTING: &str = "citations.template";
+
+/// One entry of the bibliography
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BibEntry {
+    pub citekey: String,
+    /// Lowercased entry type: `article`, `book`, `inproceedings`, ...
+    pub entry_type: String,
+    pub title: String,
+    /// `author` split on `and`, each as written (`Doe, Jane`)
+    pub authors: Vec<String>,
+    pub year: Option<String>,
+    /// Every field by lowercased name, braces removed
+    pub fields: BTreeMap<String, String>,
+}
+
+/// An entry with the notes that cite it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub entry: BibEntry,
+    /// The note whose frontmatter `citekey` is this entry's
+    pub literature_note: Option<String>,
+    /// Notes with a `[@citekey]` reference to it
+    pub cited_by: Vec<String>,
+}
+
+/// Parse BibTeX entries. `@string`, `@preamble` and `@comment` blocks and
+/// entries without a citekey are skipped; `@string` macros aren't expanded.
+pub fn parse_bib(content: &str) -> Vec<BibEntry> {
+    let mut entries = Vec::new();
+    let mut rest = content;
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let Some(open) = rest.find(['{', '(']) else {
+            break;
+        };
+        let entry_type = rest[..open].trim().to_lowercase();
+        if entry_type.is_empty() || !entry_type.chars().all(|c| c.is_ascii_alphanumeric()) {
+            continue;
+        }
+        let close_char = if rest.as_bytes()[open] == b'{' {
+            '}'
+        } else {
+            ')'
+        };
+        let body_start = open + 1;
+        let Some(body_len) = balanced_len(&rest[body_start..], close_char) else {
+            break;
+        };
+        let body = &rest[body_start..body_start + body_len];
+        rest = &rest[body_start + body_len..];
+        if matches!(entry_type.as_str(), "string" | "preamble" | "comment") {
+            continue;
+        }
+        if let Some(entry) = parse_entry(&entry_type, body) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/// Citekeys of Pandoc citations (`[@doe99]`, `[see @doe99, p. 3; -@smith04]`)
+/// in a note, in order of first use. Code blocks and inline code are skipped.
+pub fn extract_citekeys(content: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let line = strip_inline_code(line);
+        let mut rest = line.as_str();
+        while let Some(open) = rest.find('[') {
+            let after = &rest[open + 1..];
+            let Some(close) = after.find(']') else {
+                break;
+            };
+            let inner = &after[..close];
+            rest = &after[close + 1..];
+            // `[text](url)` and `[[wikilinks]]` aren't citations
+            if rest.starts_with('(') || inner.starts_with('[') {
+                continue;
+            }
+            for part in inner.split(';') {
+                if let Some(key) = citekey_in(part) {
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+    }
+    keys
+}
+
+/// Read the configured bibliography into the cache, or clear it when no
+/// `.bib` file is set. Returns the number of entries.
+pub fn reindex_bibliography(vault_path: &Path, db: &Database) -> Result<usize> {
+    let entries = match db.get_setting(BIB_FILE_SETTING)? {
+        Some(bib_path) => {
+            let content = Vault::read_file(vault_path, &bib_path)
+                .with_context(|| format!("Failed to read bibliography: {}", bib_path))?;
+            parse_bib(&content)
+        }
+        None => Vec::new(),
+    };
+    db.replace_bib_entries(&entries)?;
+    Ok(entries.len())
+}
+
+/// An entry and where it is cited, or `None` for an unknown citekey
+pub fn get_citation(db: &Database, citekey: &str) -> Result<Option<Citation>> {
+    let Some(entry) = db.get_bib_entry(citekey)? else {
+        return Ok(None);
+    };
+    Ok(Some(Citation {
+        literature_note: db.find_literature_note(citekey)?,
+        cited_by: db.get_citing_notes(citekey)?,
+        entry,
+    }))
+}
+
+/// Content of a literature note: the entry as frontmatter, then the
+/// `template` (its own frontmatter dropped) or a heading and reference line.
+/// Templates may use `{{citekey}}`, `{{authors}}`, `{{year}}` and the usual
+/// `{{title}}`, `{{date}}` and `{{time}}`.
+pub fn literature_note(entry: &BibEntry, template: Option<&str>) -> Result<String> {
+    let mut props = serde_yaml::Mapping::new();
+    props.insert("title".into(), entry.title.clone().into());
+    props.insert("citekey".into(), entry.citekey.clone().into());
+    props.insert(
+        "authors".into(),
+        serde_yaml::Value::Sequence(entry.authors.iter().map(|a| a.clone().into()).collect()),
+    );
+    if let Some(year) = &entry.year {
+        props.insert("year".into(), year.clone().into());
+    }
+    for field in ["journal", "booktitle", "publisher", "doi", "url"] {
+        if let Some(value) = entry.fields.get(field) {
+            props.insert(field.into(), value.clone().into());
+        }
+    }
+    props.insert("type".into(), entry.entry_type.clone().into());
+    props.insert(
+        "tags".into(),
+        vec![serde_yaml::Value::from("literature")].into(),
+    );
+    let yaml = serde_yaml::to_string(&props).context("Failed to build frontmatter")?;
+
+    let authors = entry.authors.join("; ");
+    let body = match template {
+        Some(template) => {
+            let (_, body) = crate::editing::split_frontmatter(template);
+            Vault::render_template(body, &entry.title)
+                .replace("{{citekey}}", &entry.citekey)
+                .replace("{{authors}}", &authors)
+                .replace("{{year}}", entry.year.as_deref().unwrap_or(""))
+        }
+        None => {
+            let mut reference = authors;
+            if let Some(year) = &entry.year {
+                reference.push_str(&format!(" ({})", year));
+            }
+            format!("# {}\n\n{}\n\n## Notes\n\n", entry.title, reference.trim())
+        }
+    };
+    Ok(format!("---\n{}---\n\n{}", yaml, body.trim_start()))
+}
+
+/// Create the literature note for `entry` in `folder`, named by its
+/// citekey, from `template` (a vault path) if given. Returns its path.
+pub fn create_literature_note(
+    vault_path: &Path,
+    entry: &BibEntry,
+    folder: &str,
+    template: Option<&str>,
+) -> Result<String> {
+    let path = Vault::note_relative_path(folder, &entry.citekey);
+    if vault_path.join(&path).exists() {
+        anyhow::bail!("A note with this name already exists");
+    }
+    let template = template
+        .map(|template_path| {
+            Vault::read_file(vault_path, template_path)
+                .with_context(|| format!("Failed to read template: {}", template_path))
+        })
+        .transpose()?;
+    let content = literature_note(entry, template.as_deref())?;
+    Vault::write_file_atomic(vault_path, &path, content)?;
+    Ok(path)
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+fn parse_entry(entry_type: &str, body: &str) -> Option<BibEntry> {
+    let (citekey, mut rest) = match body.find(',') {
+        Some(comma) => (body[..comma].trim(), &body[comma + 1..]),
+        None => (body.trim(), ""),
+    };
+    if citekey.is_empty() || citekey.contains(['=', ' ', '\n']) {
+        return None;
+    }
+
+    let mut fields = BTreeMap::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let Some(eq) = rest.find('=') else {
+            break;
+        };
+        let name = rest[..eq].trim().to_lowercase();
+        rest = rest[eq + 1..].trim_start();
+        let (value, remaining) = field_value(rest);
+        rest = remaining;
+        if !name.is_empty() {
+            fields.insert(name, value);
+        }
+    }
+
+    let authors = fields
+        .get("author")
+        .map(|authors| {
+            authors
+                .split(" and ")
+                .map(|a| a.trim().to_string())
+                .filter(|a| !a.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let year = fields.get("year").cloned().or_else(|| {
+        fields
+            .get("date")
+            .and_then(|date| date.get(..4))
+            .map(str::to_string)
+    });
+    Some(BibEntry {
+        citekey: citekey.to_string(),
+        entry_type: entry_type.to_string(),
+        title: fields
+            .get("title")
+            .cloned()
+            .unwrap_or_else(|| citekey.to_string()),
+        authors,
+        year,
+        fields,
+    })
+}
+
+/// A field value (`{...}`, `"..."`, a bare number or macro, or several
+/// joined with `#`) and the text after it
+fn field_value(text: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        let part_len = match rest.chars().next() {
+            Some('{') => balanced_len(&rest[1..], '}').map(|len| (1, len, 1)),
+            Some('"') => quoted_len(&rest[1..]).map(|len| (1, len, 1)),
+            Some(_) => Some((0, rest.find([',', '#', '\n']).unwrap_or(rest.len()), 0)),
+            None => None,
+        };
+        let Some((open, len, close)) = part_len else {
+            break;
+        };
+        value.push_str(&rest[open..open + len]);
+        rest = rest[open + len + close..].trim_start();
+        match rest.strip_prefix('#') {
+            Some(after) => rest = after,
+            None => break,
+        }
+    }
+    let cleaned: String = value.chars().filter(|c| !matches!(c, '{' | '}')).collect();
+    (
+        cleaned.split_whitespace().collect::<Vec<_>>().join(" "),
+        rest,
+    )
+}
+
+/// Length of `text` up to the `close` that balances an already consumed
+/// opener, counting nested braces
+fn balanced_len(text: &str, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            c if c == close && depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Length of a `"..."` value after its opening quote; quotes inside braces
+/// don't end it
+fn quoted_len(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '"' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The citekey of one citation in a bracket (`see @doe99, p. 3`, `-@doe99`)
+fn citekey_in(part: &str) -> Option<String> {
+    let at = part.find('@')?;
+    // An `@` inside a word is an email address, not a citation
+    let boundary = part[..at]
+        .chars()
+        .next_back()
+        .map_or(true, |c| c.is_whitespace() || c == '-');
+    if !boundary {
+        return None;
+    }
+    let key: String = part[at + 1..]
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || "_:.#$%&-+?<>~/".contains(*c))
+        .collect();
+    let key = key.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_');
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+/// The line with `inline code` spans blanked out
+fn strip_inline_code(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_code = false;
+    for c in line.chars() {
+        if c == '`' {
+            in_code = !in_code;
+            out.push(' ');
+        } else {
+            out.push(if in_code { ' ' } else { c });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    const BIB: &str = r#"
+@comment{exported from Zotero}
+@string{jnl = "Journal of Notes"}
+
+@Article{doe2019,
+  author  = {Doe, Jane and Smith, John},
+  title   = {{Linking} Your Thinking},
+  journal = jnl,
+  year    = 2019,
+  doi     = "10.1000/xyz{"}"
+}
+
+@book(ahrens2017,
+  author = "S{\"o}nke Ahrens",
+  title = "How to Take " # "Smart Notes",
+  date = {2017-02-24},
+)
+"#;
+
+    #[test]
+    fn test_parse_bib() {
+        let entries = parse_bib(BIB);
+        assert_eq!(entries.len(), 2);
+
+        let doe = &entries[0];
+        assert_eq!(doe.citekey, "doe2019");
+        assert_eq!(doe.entry_type, "article");
+        assert_eq!(doe.title, "Linking Your Thinking");
+        assert_eq!(doe.authors, vec!["Doe, Jane", "Smith, John"]);
+        assert_eq!(doe.year.as_deref(), Some("2019"));
+        assert_eq!(doe.fields["journal"], "jnl");
+        assert_eq!(doe.fields["doi"], "10.1000/xyz\"");
+
+        let ahrens = &entries[1];
+        assert_eq!(ahrens.entry_type, "book");
+        assert_eq!(ahrens.title, "How to Take Smart Notes");
+        assert_eq!(ahrens.authors, vec!["S\\\"onke Ahrens"]);
+        assert_eq!(ahrens.year.as_deref(), Some("2017"));
+    }
+
+    #[test]
+    fn test_extract_citekeys() {
+        let note = "As argued [@doe2019], and [see @ahrens2017, p. 12; -@doe2019].\n\
+                    Mail [me@example.com] or read [the docs](https://x.y/@a).\n\
+                    `[@incode]` and [[Wiki @link]]\n\
+                    ```\n[@fenced]\n```\n\
+                    Ends a sentence [@key.with:colon].\n";
+        assert_eq!(
+            extract_citekeys(note),
+            vec!["doe2019", "ahrens2017", "key.with:colon"]
+        );
+    }
+
+    #[test]
+    fn test_literature_note() {
+        let entry = &parse_bib(BIB)[0];
+        let note = literature_note(entry, None).unwrap();
+        let fm = Vault::parse_frontmatter(&note);
+        assert_eq!(fm.title.as_deref(), Some("Linking Your Thinking"));
+        assert_eq!(fm.tags, vec!["literature"]);
+        assert!(note.contains("citekey: doe2019\n"));
+        assert!(note.contains("\n# Linking Your Thinking\n\nDoe, Jane; Smith, John (2019)\n"));
+
+        let templated = literature_note(
+            entry,
+            Some("---\ntags: [x]\n---\n# {{title}}\nSee [@{{citekey}}], {{year}}\n"),
+        )
+        .unwrap();
+        assert!(templated.ends_with("---\n\n# Linking Your Thinking\nSee [@doe2019], 2019\n"));
+        assert!(!templated.contains("- x"));
+    }
+
+    #[test]
+    fn test_citations_are_indexed() {
+        let vault = TestVault::with_notes(&[
+            ("refs.bib", BIB),
+            ("Essay.md", "# Essay\nPer [@doe2019; @unknown].\n"),
+            ("Other.md", "# Other\n[@ahrens2017]\n"),
+            (
+                "Literature/doe2019.md",
+                "---\ncitekey: doe2019\n---\n# Linking\n",
+            ),
+        ]);
+        let db = vault.index();
+        assert!(get_citation(&db, "doe2019").unwrap().is_none());
+
+        db.set_setting(BIB_FILE_SETTING, "refs.bib").unwrap();
+        assert_eq!(reindex_bibliography(vault.path(), &db).unwrap(), 2);
+        let citation = get_citation(&db, "doe2019").unwrap().unwrap();
+        assert_eq!(citation.entry.title, "Linking Your Thinking");
+        assert_eq!(citation.cited_by, vec!["Essay.md"]);
+        assert_eq!(
+            citation.literature_note.as_deref(),
+            Some("Literature/doe2019.md")
+        );
+        assert!(get_citation(&db, "unknown").unwrap().is_none());
+
+        db.delete_setting(BIB_FILE_SETTING).unwrap();
+        assert_eq!(reindex_bibliography(vault.path(), &db).unwrap(), 0);
+        assert!(get_citation(&db, "ahrens2017").unwrap().is_none());
+    }
+}
@@ -1,381 +1,4270 @@
-use std::path::PathBuf;
-use tauri::State;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
 
-use crate::database::{CachedNote, Database};
+use crate::attachments::{self, OrphanedAttachment};
+use crate::backup::{self, BackupSummary, RemoteBackup, S3Config};
+use crate::benchmark::{self, BenchmarkReport};
+use crate::canvas::Canvas;
+use crate::capture::{self, CaptureTarget, SharedItem};
+use crate::citations::{self, Citation};
+use crate::credentials;
+use crate::crypto::{self, IndexScope, VaultEncryption};
+use crate::csv_file::{CsvPage, CsvSummary, CsvTable, RowRange};
+use crate::database::{
+    Bookmark, CachedNote, Database, DbTuning, DrawingTextMatch, FulltextMatch, LinkTarget,
+    LinkTargetKind, MaintenanceReport, NewBookmark, NoteCallout, NoteOpenStats, NoteTask,
+    RecentNote, ResolvedSectionLink, SavedSearch, SchemaInfo, Snippet, TaskFilter, UnresolvedLink,
+    WorkspaceSummary, ARCHIVE_FOLDER_SETTING, ATTACHMENTS_FOLDER_SETTING, AUTO_LOCK_SETTING,
+    BACKUP_LAST_RUN_SETTING, BACKUP_S3_SETTING, CAPTURE_INBOX_SETTING, DAILY_FOLDER_SETTING,
+    DEFAULT_ARCHIVE_FOLDER, DEFAULT_ATTACHMENTS_FOLDER, DEFAULT_DAILY_FOLDER,
+    LAST_SESSION_WORKSPACE, LOCK_HASH_SETTING, OS_SEARCH_FOLDER_SETTING, PRIVATE_FOLDERS_SETTING,
+    REDACTION_SETTING, SECRET_SCAN_SETTING, SECURE_ATTACHMENTS_SETTING, SNIPPET_MODE_SETTING,
+};
+use crate::deep_link::{LaunchLink, NoteLink};
+use crate::duplicates::{self, DuplicateCluster};
+use crate::editing::{self, InsertPosition};
+use crate::embeddings::{self, SemanticMatch, SentenceModel};
+use crate::embeds::{self, ResolvedEmbed};
+use crate::error::SynapseError;
+use crate::excalidraw;
+use crate::export::{self, HtmlExport, HtmlExportOptions};
+use crate::file_manager::{self, LegacyImport};
+use crate::formatter::{self, FormatResult, FormatRules};
+#[cfg(feature = "git")]
+use crate::git::{self, CommitInfo};
+use crate::graph::{Graph, LocalGraph, NodeMetrics, NoteCluster};
+use crate::health::{self, HealthReport};
 use crate::indexer;
-use crate::vault::{Vault, VaultEntry};
+use crate::kanban::{self, KanbanBoard};
+use crate::keybindings::{self, Keybinding};
+use crate::logs::{self, LogEntry};
+use crate::math::MathBlock;
+use crate::mermaid::{self, DiagramCheck, MermaidBlock};
+use crate::os_search::{self, StubIndex};
+use crate::outliner::{self, OutlinerImport};
+use crate::pdf::{self, PdfExport, PdfExportOptions};
+use crate::properties::{self, PropertyMatch, PropertySort};
+use crate::publish::{self, PublishOptions, PublishedSite};
+use crate::query::{self, EmbeddedQueryResult, Query};
+use crate::redact::{Redaction, RedactionRules, Redactor};
+use crate::related::{self, RelatedNote};
+use crate::secrets::{self, NoteSecrets};
+use crate::spellcheck::{Misspelling, SpellChecker};
+use crate::sync::LockExt;
+use crate::vault::{
+    path_in_folder, AppendOptions, AppendResult, ExtractOptions, SplitResult, Vault, VaultEntry,
+};
+use crate::watcher::{self, VaultWatcher};
+use crate::web_clip::{self, PageMetadata};
 
 /// Shared app state
+#[derive(Default)]
 pub struct AppState {
     pub vault_path: std::sync::Mutex<Option<PathBuf>>,
-    pub db: std::sync::Mutex<Option<Database>>,
+    pub db: std::sync::Mutex<Option<Arc<Database>>>,
+    /// Shared with the blocking pool, where checks load dictionaries
+    pub spellchecker: Arc<std::sync::Mutex<SpellChecker>>,
+    /// Loaded on the first semantic search, shared with the blocking pool
+    pub sentence_model: Arc<std::sync::Mutex<Option<Arc<SentenceModel>>>>,
+    pub lock: std::sync::Mutex<VaultLock>,
+    /// Reindexes the open vault on outside changes
+    pub watcher: std::sync::Mutex<Option<VaultWatcher>>,
+}
+
+/// Lock mode. While locked the open vault is moved out of `AppState` into
+/// `stash`, so every vault command fails until `unlock_vault` puts it back.
+/// The webview has no fs plugin access to the vault, so commands are the
+/// only way in.
+pub struct VaultLock {
+    stash: Option<(PathBuf, Arc<Database>)>,
+    last_activity: Instant,
+}
+
+impl Default for VaultLock {
+    fn default() -> Self {
+        VaultLock {
+            stash: None,
+            last_activity: Instant::now(),
+        }
+    }
+}
+
+impl AppState {
+    /// Make a freshly opened vault current, starting locked if it has a passphrase
+    pub fn install_vault(&self, vault_path: PathBuf, db: Database) -> Arc<Database> {
+        let db = Arc::new(db);
+        let protected = match lock_hash(&vault_path, &db) {
+            Ok(hash) => hash.is_some(),
+            Err(e) => {
+                log::warn!("Failed to read the vault lock: {}", e);
+                false
+            }
+        };
+        *self.spellchecker.lock_or_recover() = SpellChecker::default();
+        let level = match db.get_setting(logs::LOG_LEVEL_SETTING) {
+            Ok(Some(level)) => logs::parse_level(&level).unwrap_or(logs::DEFAULT_LEVEL),
+            _ => logs::DEFAULT_LEVEL,
+        };
+        if let Err(e) = logs::open_for_vault(&vault_path, level) {
+            log::warn!("Failed to open vault log: {:#}", e);
+        }
+        if protected {
+            *self.vault_path.lock_or_recover() = None;
+            *self.db.lock_or_recover() = None;
+            self.lock.lock_or_recover().stash = Some((vault_path, db.clone()));
+        } else {
+            *self.vault_path.lock_or_recover() = Some(vault_path);
+            *self.db.lock_or_recover() = Some(db.clone());
+            self.lock.lock_or_recover().stash = None;
+        }
+        db
+    }
+
+    /// The open vault's database. Commands share the handle rather than
+    /// holding the state lock, so a slow query doesn't stall the others.
+    pub fn database(&self) -> Result<Arc<Database>, SynapseError> {
+        let db = self.db.lock_or_recover().clone();
+        match db {
+            Some(db) => Ok(db),
+            None if self.is_locked() => Err(SynapseError::VaultLocked),
+            None => Err(SynapseError::VaultNotOpen),
+        }
+    }
+
+    /// Let go of the open (or locked) vault. Its cache checkpoints once the
+    /// last command using it finishes.
+    pub fn close_vault(&self) {
+        *self.watcher.lock_or_recover() = None;
+        *self.vault_path.lock_or_recover() = None;
+        *self.db.lock_or_recover() = None;
+        self.lock.lock_or_recover().stash = None;
+        logs::close();
+    }
+
+    /// Move the open vault into the lock stash. Returns false if nothing was open.
+    fn engage_lock(&self) -> bool {
+        let path = self.vault_path.lock_or_recover().take();
+        let db = self.db.lock_or_recover().take();
+        match (path, db) {
+            (Some(path), Some(db)) => {
+                self.lock.lock_or_recover().stash = Some((path, db));
+                true
+            }
+            (path, db) => {
+                *self.vault_path.lock_or_recover() = path;
+                *self.db.lock_or_recover() = db;
+                false
+            }
+        }
+    }
+
+    /// Move the stashed vault back into `AppState`, after the passphrase
+    /// has been checked, and restart the idle timer
+    fn release_lock(&self) -> Result<(), SynapseError> {
+        let mut lock = self.lock.lock_or_recover();
+        let (vault_path, db) = lock.stash.take().ok_or("Vault is not locked")?;
+        lock.last_activity = Instant::now();
+        drop(lock);
+
+        *self.vault_path.lock_or_recover() = Some(vault_path);
+        *self.db.lock_or_recover() = Some(db);
+        Ok(())
+    }
+
+    /// Watch the newly opened vault for outside changes, replacing the
+    /// previous vault's watcher
+    pub fn watch_vault(&self, app: &AppHandle, vault_path: &Path, db: Arc<Database>) {
+        let mut watcher = self.watcher.lock_or_recover();
+        *watcher = None;
+        match watcher::watch(app, vault_path, db) {
+            Ok(w) => *watcher = Some(w),
+            Err(e) => log::warn!("{:#}", e),
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.lock.lock_or_recover().stash.is_some()
+    }
+
+    /// Lock the vault if auto-lock is on and the user has been idle long
+    /// enough. Called periodically from a background thread.
+    pub fn auto_lock_if_idle(&self) -> bool {
+        let minutes = {
+            let Ok(db) = self.database() else {
+                return false;
+            };
+            let Some(vault_path) = self.vault_path.lock_or_recover().clone() else {
+                return false;
+            };
+            if !matches!(lock_hash(&vault_path, &db), Ok(Some(_))) {
+                return false;
+            }
+            match db.get_setting(AUTO_LOCK_SETTING) {
+                Ok(Some(m)) => m.parse::<u64>().unwrap_or(0),
+                _ => 0,
+            }
+        };
+        if minutes == 0 {
+            return false;
+        }
+        let idle = self.lock.lock_or_recover().last_activity.elapsed();
+        idle >= Duration::from_secs(minutes * 60) && self.engage_lock()
+    }
 }
 
 // ─── Vault commands ────────────────────────────────────────────────
 
 /// Check if a vault is already configured
 #[tauri::command]
-pub fn get_vault_path() -> Result<Option<String>, String> {
-    match Vault::get_vault_path() {
+pub async fn get_vault_path() -> Result<Option<String>, SynapseError> {
+    blocking(move || match Vault::get_vault_path() {
         Ok(Some(p)) => Ok(Some(p.to_string_lossy().to_string())),
         Ok(None) => Ok(None),
-        Err(e) => Err(e.to_string()),
+        Err(e) => Err(e.into()),
+    })
+    .await
+}
+
+/// Suggested place for a new vault: Documents on desktop and iOS (visible
+/// in Files), app storage on Android
+#[tauri::command]
+pub fn default_vault_location(app: AppHandle) -> Result<String, SynapseError> {
+    let base = if cfg!(target_os = "android") {
+        app.path().app_data_dir()
+    } else {
+        app.path().document_dir()
     }
+    .map_err(SynapseError::from)?;
+    Ok(base.join("Synapse").to_string_lossy().to_string())
 }
 
 /// Create a new vault at the given path
 #[tauri::command]
-pub fn create_vault(path: String, state: State<'_, AppState>) -> Result<(), String> {
-    let vault_path = PathBuf::from(&path);
-    Vault::create_vault(&vault_path).map_err(|e| e.to_string())?;
+pub async fn create_vault(
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = Vault::resolve_location(&path).map_err(SynapseError::from)?;
+    Vault::create_vault(&vault_path).map_err(SynapseError::from)?;
 
     // Initialize database for this vault
-    let db = Database::init_for_vault(&vault_path).map_err(|e| e.to_string())?;
-    db.reindex_vault(&vault_path).map_err(|e| e.to_string())?;
+    let db = blocking({
+        let vault_path = vault_path.clone();
+        move || {
+            let db = Database::init_for_vault(&vault_path).map_err(SynapseError::from)?;
+            db.reindex_vault(&vault_path).map_err(SynapseError::from)?;
+            Ok(db)
+        }
+    })
+    .await?;
 
-    *state.vault_path.lock().unwrap() = Some(vault_path);
-    *state.db.lock().unwrap() = Some(db);
+    let db = state.install_vault(vault_path.clone(), db);
+    state.watch_vault(&app, &vault_path, db);
+    #[cfg(desktop)]
+    crate::hotkeys::apply(&app);
 
     Ok(())
 }
 
-/// Open an existing vault
+/// Open an existing vault, returning its last-session layout (if any) for restore
 #[tauri::command]
-pub fn open_vault(path: String, state: State<'_, AppState>) -> Result<(), String> {
-    let vault_path = PathBuf::from(&path);
-    Vault::open_vault(&vault_path).map_err(|e| e.to_string())?;
+pub async fn open_vault(
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SynapseError> {
+    let vault_path = Vault::resolve_location(&path).map_err(SynapseError::from)?;
+    Vault::open_vault(&vault_path).map_err(SynapseError::from)?;
 
-    // Initialize database for this vault
-    let db = Database::init_for_vault(&vault_path).map_err(|e| e.to_string())?;
-    db.reindex_vault(&vault_path).map_err(|e| e.to_string())?;
+    // Initialize database for this vault (replacing a corrupt cache)
+    let (db, recovered) = blocking({
+        let vault_path = vault_path.clone();
+        move || {
+            let (db, recovered) =
+                Database::open_or_recover(&vault_path).map_err(SynapseError::from)?;
+            db.reindex_vault(&vault_path).map_err(SynapseError::from)?;
+            Ok((db, recovered))
+        }
+    })
+    .await?;
+    if let Some(backup) = recovered {
+        let _ = app.emit("cache-recovered", backup.to_string_lossy());
+    }
 
-    *state.vault_path.lock().unwrap() = Some(vault_path);
-    *state.db.lock().unwrap() = Some(db);
+    let db = state.install_vault(vault_path.clone(), db);
+    state.watch_vault(&app, &vault_path, db.clone());
+    #[cfg(desktop)]
+    crate::hotkeys::apply(&app);
 
-    Ok(())
+    // The layout names open notes, so a locked vault's waits for unlock_vault
+    if state.is_locked() {
+        return Ok(None);
+    }
+    blocking(move || {
+        db.load_workspace(LAST_SESSION_WORKSPACE)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Compact the open vault's cache and check it for corruption
+#[tauri::command]
+pub async fn run_db_maintenance(
+    state: State<'_, AppState>,
+) -> Result<MaintenanceReport, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.run_maintenance().map_err(SynapseError::from)).await
+}
+
+/// SQLite tuning for the open vault's cache
+#[tauri::command]
+pub async fn get_db_tuning(state: State<'_, AppState>) -> Result<DbTuning, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.tuning().map_err(SynapseError::from)).await
+}
+
+/// Save and apply SQLite tuning for the open vault's cache
+#[tauri::command]
+pub async fn set_db_tuning(
+    tuning: DbTuning,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.set_tuning(&tuning).map_err(SynapseError::from)).await
+}
+
+/// Write cache table counts, sample rows and recent indexing warnings to
+/// `.synapse/index-debug-<timestamp>.json` for bug reports. Returns the path.
+#[tauri::command]
+pub async fn dump_index_debug(state: State<'_, AppState>) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let dump = db.debug_dump().map_err(SynapseError::from)?;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let path =
+            Vault::db_path(&vault_path).with_file_name(format!("index-debug-{}.json", stamp));
+        let json = serde_json::to_string_pretty(&dump).map_err(SynapseError::from)?;
+        std::fs::write(&path, json).map_err(SynapseError::from)?;
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Cache schema of a vault, so the frontend can offer a rebuild when
+/// `open_vault` fails on a cache from a newer Synapse
+#[tauri::command]
+pub async fn get_vault_cache_info(path: String) -> Result<SchemaInfo, SynapseError> {
+    blocking(move || {
+        let vault_path = Vault::resolve_location(&path).map_err(SynapseError::from)?;
+        Database::inspect_schema(&vault_path).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Open a vault with a fresh cache, setting the old one aside. Returns the
+/// backup's path, if there was a cache to back up. Refused while locked.
+#[tauri::command]
+pub async fn rebuild_vault_cache(
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SynapseError> {
+    if state.is_locked() {
+        return Err(SynapseError::VaultLocked);
+    }
+    let vault_path = Vault::resolve_location(&path).map_err(SynapseError::from)?;
+    Vault::open_vault(&vault_path).map_err(SynapseError::from)?;
+
+    let (db, backup) = blocking({
+        let vault_path = vault_path.clone();
+        move || {
+            let (db, backup) =
+                Database::rebuild_for_vault(&vault_path).map_err(SynapseError::from)?;
+            db.reindex_vault(&vault_path).map_err(SynapseError::from)?;
+            Ok((db, backup))
+        }
+    })
+    .await?;
+
+    let db = state.install_vault(vault_path.clone(), db);
+    state.watch_vault(&app, &vault_path, db);
+    #[cfg(desktop)]
+    crate::hotkeys::apply(&app);
+
+    Ok(backup.map(|p| p.to_string_lossy().to_string()))
 }
 
 // ─── File explorer commands ────────────────────────────────────────
 
 /// List all entries in the vault (files and folders)
 #[tauri::command]
-pub fn list_vault_entries(state: State<'_, AppState>) -> Result<Vec<VaultEntry>, String> {
+pub async fn list_vault_entries(
+    state: State<'_, AppState>,
+) -> Result<Vec<VaultEntry>, SynapseError> {
     let vault_path = get_vault(&state)?;
-    Vault::list_entries(&vault_path).map_err(|e| e.to_string())
+    blocking(move || Vault::list_entries(&vault_path).map_err(SynapseError::from)).await
 }
 
 /// Create a new note in the vault
 #[tauri::command]
-pub fn create_note(
+pub async fn create_note(
     title: String,
     folder: String,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<String, SynapseError> {
     let vault_path = get_vault(&state)?;
-    let relative_path =
-        Vault::create_note(&vault_path, &folder, &title).map_err(|e| e.to_string())?;
+    let db = state.database().ok();
+    blocking(move || {
+        let relative_path =
+            Vault::create_note(&vault_path, &folder, &title).map_err(SynapseError::from)?;
 
-    // Index the new note
-    if let Some(db) = state.db.lock().unwrap().as_ref() {
-        let _ = db.reindex_note(&vault_path, &relative_path);
-    }
+        // Index the new note
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &relative_path);
+        }
 
-    Ok(relative_path)
+        Ok(relative_path)
+    })
+    .await
 }
 
 /// Create a new folder
 #[tauri::command]
-pub fn create_folder(path: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn create_folder(path: String, state: State<'_, AppState>) -> Result<(), SynapseError> {
     let vault_path = get_vault(&state)?;
-    Vault::create_folder(&vault_path, &path).map_err(|e| e.to_string())
+    blocking(move || {
+        Vault::create_folder(&vault_path, &path).map_err(|e| SynapseError::from(e).with_path(path))
+    })
+    .await
 }
 
 /// Read a file's content
 #[tauri::command]
-pub fn read_note(path: String, state: State<'_, AppState>) -> Result<String, String> {
+pub async fn read_note(path: String, state: State<'_, AppState>) -> Result<String, SynapseError> {
     let vault_path = get_vault(&state)?;
-    Vault::read_file(&vault_path, &path).map_err(|e| e.to_string())
+    blocking(move || {
+        Vault::read_file(&vault_path, &path).map_err(|e| SynapseError::from(e).with_path(path))
+    })
+    .await
 }
 
-/// Save a file's content and reindex
+/// Save a file's content and reindex. Links from other notes to a heading
+/// the save renamed follow it. With secret scanning on, a
+/// `secrets-detected` event reports anything that looks like a credential.
 #[tauri::command]
-pub fn save_note(path: String, content: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn save_note(
+    path: String,
+    content: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
     let vault_path = get_vault(&state)?;
-    Vault::write_file(&vault_path, &path, &content).map_err(|e| e.to_string())?;
+    let db = state.database().ok();
+    blocking(move || {
+        let headings_before = match &db {
+            Some(db) => db.get_headings(&path).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        Vault::write_file(&vault_path, &path, &content)
+            .map_err(|e| SynapseError::from(e).with_path(&path))?;
 
-    // Reindex this note
-    if let Some(db) = state.db.lock().unwrap().as_ref() {
-        let _ = db.reindex_note(&vault_path, &path);
-    }
+        // Reindex this note
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &path);
+            if let Err(e) = propagate_heading_renames(&vault_path, &db, &path, &headings_before) {
+                log::warn!("Failed to update links to headings in {}: {:#}", path, e);
+            }
+            if let Err(e) = update_search_stub(&vault_path, &db, &path, &content) {
+                log::warn!("Failed to update OS search stub for {}: {}", path, e);
+            }
+            #[cfg(feature = "git")]
+            if matches!(db.get_setting(git::AUTO_COMMIT_SETTING), Ok(Some(v)) if v == "true") {
+                let message = format!("Update {}", path);
+                if let Err(e) =
+                    git::commit(&vault_path, Some(std::slice::from_ref(&path)), &message)
+                {
+                    log::warn!("Failed to auto-commit {}: {:#}", path, e);
+                }
+            }
+            if matches!(db.get_setting(SECRET_SCAN_SETTING), Ok(Some(v)) if v == "true") {
+                let findings = secrets::scan(&content);
+                if !findings.is_empty() {
+                    let _ = app.emit("secrets-detected", NoteSecrets { path, findings });
+                }
+            }
+        }
 
-    Ok(())
+        Ok(())
+    })
+    .await
 }
 
-/// Delete a file or folder
+/// Append text to a note, creating it (from a template) if it doesn't exist
 #[tauri::command]
-pub fn delete_entry(path: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn append_to_note(
+    path: String,
+    text: String,
+    options: Option<AppendOptions>,
+    state: State<'_, AppState>,
+) -> Result<AppendResult, SynapseError> {
     let vault_path = get_vault(&state)?;
-    Vault::delete_entry(&vault_path, &path).map_err(|e| e.to_string())?;
+    let db = state.database().ok();
+    blocking(move || {
+        let options = options.unwrap_or_default();
+        let result = Vault::append_to_note(&vault_path, &path, &text, &options)
+            .map_err(SynapseError::from)?;
 
-    // Remove from index
-    if let Some(db) = state.db.lock().unwrap().as_ref() {
-        let _ = db.delete_note(&path);
-    }
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &result.path);
+        }
 
-    Ok(())
+        Ok(result)
+    })
+    .await
 }
 
-/// Rename/move a file or folder and update all links
+/// Insert text at a heading, block id or line number. Returns the 1-based
+/// inclusive line range of the inserted text.
 #[tauri::command]
-pub fn rename_entry(
-    old_path: String,
-    new_path: String,
+pub async fn insert_at(
+    path: String,
+    position: InsertPosition,
+    text: String,
     state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<LineRange, SynapseError> {
     let vault_path = get_vault(&state)?;
-    Vault::rename_entry(&vault_path, &old_path, &new_path).map_err(|e| e.to_string())?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let at = editing::resolve_insert_line(&content, &position)
+            .ok_or("Insert position not found in note")?;
+        let (updated, start, end) = editing::insert_lines(&content, at, &text);
+        Vault::write_file_atomic(&vault_path, &path, &updated).map_err(SynapseError::from)?;
 
-    // Update index: remove old, index new
-    if let Some(db) = state.db.lock().unwrap().as_ref() {
-        let _ = db.delete_note(&old_path);
-        if new_path.ends_with(".md") {
-            let _ = db.reindex_note(&vault_path, &new_path);
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &path);
         }
-    }
 
-    Ok(())
+        Ok(LineRange { start, end })
+    })
+    .await
 }
 
-/// Duplicate a file
+/// Move a selection (byte range) of a note into a new note, leaving a link behind
 #[tauri::command]
-pub fn duplicate_entry(path: String, state: State<'_, AppState>) -> Result<String, String> {
+pub async fn extract_to_note(
+    source_path: String,
+    byte_range: (usize, usize),
+    new_title: String,
+    folder: String,
+    options: Option<ExtractOptions>,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
     let vault_path = get_vault(&state)?;
-    let new_path = Vault::duplicate_entry(&vault_path, &path).map_err(|e| e.to_string())?;
+    let db = state.database().ok();
+    blocking(move || {
+        let options = options.unwrap_or_default();
+        let new_path = Vault::extract_to_note(
+            &vault_path,
+            &source_path,
+            byte_range.0,
+            byte_range.1,
+            &new_title,
+            &folder,
+            &options,
+        )
+        .map_err(SynapseError::from)?;
 
-    // Index the new file
-    if let Some(db) = state.db.lock().unwrap().as_ref() {
-        if new_path.ends_with(".md") {
+        if let Some(db) = db {
             let _ = db.reindex_note(&vault_path, &new_path);
+            let _ = db.reindex_note(&vault_path, &source_path);
         }
-    }
 
-    Ok(new_path)
+        Ok(new_path)
+    })
+    .await
 }
 
-// ─── Note metadata commands ───────────────────────────────────────
-
-/// Get all cached notes (for quick switcher, search, etc.)
+/// Split a note into one note per heading at `level`, turning it into an index
 #[tauri::command]
-pub fn get_all_notes(state: State<'_, AppState>) -> Result<Vec<CachedNote>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.get_all_notes().map_err(|e| e.to_string())
+pub async fn split_note(
+    path: String,
+    level: u8,
+    dest_folder: String,
+    state: State<'_, AppState>,
+) -> Result<SplitResult, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        // Notes that may hold [[Note#Section]] links into this one
+        let title = db
+            .get_note(&path)
+            .map_err(SynapseError::from)?
+            .map(|n| n.title)
+            .ok_or_else(|| SynapseError::not_found("Note is not indexed"))?;
+        let referencing = db.get_backlinks(&title).map_err(SynapseError::from)?;
+
+        let result = Vault::split_note(&vault_path, &path, level, &dest_folder, &referencing)
+            .map_err(SynapseError::from)?;
+
+        for changed in std::iter::once(&result.index_path)
+            .chain(&result.created)
+            .chain(&result.updated)
+        {
+            let _ = db.reindex_note(&vault_path, changed);
+        }
+
+        Ok(result)
+    })
+    .await
 }
 
-/// Toggle star on a note
+/// Merge note `source` into `target`, pointing every link to the source at
+/// the target, and delete the source. With `keep_source` the source and
+/// links to it are left alone; only the target changes. Returns the other
+/// notes whose links were rewritten.
 #[tauri::command]
-pub fn toggle_star(path: String, state: State<'_, AppState>) -> Result<bool, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.toggle_star(&path).map_err(|e| e.to_string())
+pub async fn merge_notes(
+    source: String,
+    target: String,
+    strategy: Option<editing::MergeStrategy>,
+    keep_source: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        merge_into(
+            &vault_path,
+            &db,
+            &source,
+            &target,
+            strategy.unwrap_or_default(),
+            keep_source.unwrap_or(false),
+        )
+    })
+    .await
 }
 
-// ─── Links & backlinks commands ────────────────────────────────────
-
-/// Get backlinks for a note (notes that link TO this note)
+/// Build a linked table of contents for a note and (by default) write it
+/// between `<!-- toc -->` markers. Returns the TOC markdown.
 #[tauri::command]
-pub fn get_backlinks(
-    note_title: String,
+pub async fn generate_toc(
+    path: String,
+    options: Option<TocOptions>,
     state: State<'_, AppState>,
-) -> Result<Vec<BacklinkResult>, String> {
+) -> Result<String, SynapseError> {
     let vault_path = get_vault(&state)?;
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
+    let db = state.database().ok();
+    blocking(move || {
+        let options = options.unwrap_or_default();
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let headings = indexer::extract_headings(&content);
+        let toc = editing::build_toc(
+            &headings,
+            options.min_level,
+            options.max_level,
+            options.links,
+        );
+
+        if options.write {
+            let updated = editing::upsert_toc(&content, &toc);
+            if updated != content {
+                Vault::write_file_atomic(&vault_path, &path, &updated)
+                    .map_err(SynapseError::from)?;
+                if let Some(db) = db {
+                    let _ = db.reindex_note(&vault_path, &path);
+                }
+            }
+        }
 
-    let source_paths = db.get_backlinks(&note_title).map_err(|e| e.to_string())?;
-    let mut results = Vec::new();
+        Ok(toc)
+    })
+    .await
+}
 
-    for source_path in source_paths {
-        // Read source note to get context around the link
-        if let Ok(content) = Vault::read_file(&vault_path, &source_path) {
-            let fm = Vault::parse_frontmatter(&content);
-            let title = fm.title.unwrap_or_else(|| {
-                std::path::Path::new(&source_path)
-                    .file_stem()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string()
-            });
+/// Promote/demote headings by `delta` levels, in `range` (1-based lines) or the
+/// whole note. Returns how many headings changed.
+#[tauri::command]
+pub async fn shift_headings(
+    path: String,
+    range: Option<LineRange>,
+    delta: i32,
+    state: State<'_, AppState>,
+) -> Result<usize, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let (updated, changed) =
+            editing::shift_headings(&content, range.map(|r| (r.start, r.end)), delta);
+        if changed > 0 {
+            Vault::write_file_atomic(&vault_path, &path, &updated).map_err(SynapseError::from)?;
+            if let Some(db) = db {
+                let _ = db.reindex_note(&vault_path, &path);
+            }
+        }
+        Ok(changed)
+    })
+    .await
+}
+
+/// List a note's footnotes with their references and definitions
+#[tauri::command]
+pub async fn list_footnotes(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<editing::Footnote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        Ok(editing::list_footnotes(&content))
+    })
+    .await
+}
 
-            // Find the context around the link
-            let context = find_link_context(&content, &note_title);
+/// Add a footnote reference at a byte offset with its definition at the bottom.
+/// Returns the label the new footnote ended up with after renumbering.
+#[tauri::command]
+pub async fn add_footnote(
+    path: String,
+    anchor_position: usize,
+    text: String,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let (updated, label) = editing::add_footnote(&content, anchor_position, &text)
+            .ok_or("Invalid footnote position or empty footnote text")?;
+        Vault::write_file_atomic(&vault_path, &path, &updated).map_err(SynapseError::from)?;
 
-            results.push(BacklinkResult {
-                source_path: source_path.clone(),
-                source_title: title,
-                context,
-            });
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &path);
         }
-    }
 
-    Ok(results)
+        Ok(label)
+    })
+    .await
 }
 
-/// Get outgoing links from a note
+/// Renumber a note's footnotes in reading order and gather definitions at the bottom
 #[tauri::command]
-pub fn get_outgoing_links(
+pub async fn renumber_footnotes(
     path: String,
     state: State<'_, AppState>,
-) -> Result<Vec<String>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.get_outgoing_links(&path).map_err(|e| e.to_string())
+) -> Result<Vec<editing::Footnote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let updated = editing::renumber_footnotes(&content);
+        if updated != content {
+            Vault::write_file_atomic(&vault_path, &path, &updated).map_err(SynapseError::from)?;
+            if let Some(db) = db {
+                let _ = db.reindex_note(&vault_path, &path);
+            }
+        }
+        Ok(editing::list_footnotes(&updated))
+    })
+    .await
 }
 
-/// Get all links in the vault (for graph view)
+/// Normalize a note's markdown. Returns a diff of the change; with `dry_run`
+/// nothing is written.
 #[tauri::command]
-pub fn get_all_links(state: State<'_, AppState>) -> Result<Vec<(String, String)>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.get_all_links().map_err(|e| e.to_string())
+pub async fn format_note(
+    path: String,
+    rules: Option<FormatRules>,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<FormatResult, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let rules = rules.unwrap_or_default();
+        let result = format_file(&vault_path, &path, &rules, dry_run.unwrap_or(false))
+            .map_err(SynapseError::from)?;
+
+        if result.changed && !dry_run.unwrap_or(false) {
+            if let Some(db) = db {
+                let _ = db.reindex_note(&vault_path, &path);
+            }
+        }
+
+        Ok(result)
+    })
+    .await
 }
 
-/// Search notes by title for wikilink autocomplete
+/// Format every note in the vault, returning only the notes that changed
 #[tauri::command]
-pub fn search_notes(query: String, state: State<'_, AppState>) -> Result<Vec<CachedNote>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    let all_notes = db.get_all_notes().map_err(|e| e.to_string())?;
+pub async fn format_all(
+    rules: Option<FormatRules>,
+    dry_run: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<FormatResult>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    let rules = rules.unwrap_or_default();
+    let dry_run = dry_run.unwrap_or(false);
+    blocking(move || {
+        let notes = Vault::list_notes(&vault_path).map_err(SynapseError::from)?;
 
-    let query_lower = query.to_lowercase();
-    let mut results: Vec<(usize, CachedNote)> = all_notes
-        .into_iter()
-        .filter_map(|note| {
-            let title_lower = note.title.to_lowercase();
-            if title_lower.contains(&query_lower) {
-                // Score: exact match best, prefix match next, contains last
-                let score = if title_lower == query_lower {
-                    0
-                } else if title_lower.starts_with(&query_lower) {
-                    1
-                } else {
-                    2
-                };
-                Some((score, note))
-            } else {
-                // Fuzzy match: check if all query chars appear in order
-                let mut query_chars = query_lower.chars();
-                let mut current = query_chars.next();
-                for c in title_lower.chars() {
-                    if let Some(qc) = current {
-                        if c == qc {
-                            current = query_chars.next();
-                        }
-                    }
-                }
-                if current.is_none() {
-                    Some((3, note)) // fuzzy match has lowest priority
-                } else {
-                    None
-                }
+        let mut changed = Vec::new();
+        for entry in notes {
+            let result = format_file(&vault_path, &entry.path, &rules, dry_run)
+                .map_err(SynapseError::from)?;
+            if result.changed {
+                changed.push(result);
             }
-        })
-        .collect();
+        }
 
-    results.sort_by_key(|(score, _)| *score);
-    let notes: Vec<CachedNote> = results.into_iter().take(20).map(|(_, n)| n).collect();
+        if !dry_run {
+            if let Some(db) = db {
+                for result in &changed {
+                    let _ = db.reindex_note(&vault_path, &result.path);
+                }
+            }
+        }
 
-    Ok(notes)
+        Ok(changed)
+    })
+    .await
 }
 
-// ─── Tags commands ─────────────────────────────────────────────────
-
-/// Get all tags in the vault with their counts
+/// Fetch a pasted URL's title and OpenGraph metadata so it can become `[Title](url)`
 #[tauri::command]
-pub fn get_all_tags(state: State<'_, AppState>) -> Result<Vec<(String, usize)>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.get_all_tags().map_err(|e| e.to_string())
+pub async fn resolve_url_title(url: String) -> Result<PageMetadata, SynapseError> {
+    web_clip::fetch_page(&url)
+        .await
+        .map(|page| page.metadata)
+        .map_err(SynapseError::from)
 }
 
-/// Get all notes with a specific tag
+/// Archive a web page as a clipped note in `folder`, returning the new note's path
 #[tauri::command]
-pub fn get_notes_by_tag(tag: String, state: State<'_, AppState>) -> Result<Vec<String>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.get_notes_by_tag(&tag).map_err(|e| e.to_string())
+pub async fn clip_url(
+    url: String,
+    folder: String,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let page = web_clip::fetch_page(&url)
+        .await
+        .map_err(SynapseError::from)?;
+    let text = web_clip::readable_text(&page.html);
+    let path = Vault::create_clipped_note(&vault_path, &folder, &page.metadata, &url, &text)
+        .map_err(SynapseError::from)?;
+
+    if let Ok(db) = state.database() {
+        let _ = db.reindex_note(&vault_path, &path);
+    }
+
+    Ok(path)
 }
 
-// ─── Headings / outline commands ──────────────────────────────────
+/// Read and validate a `.canvas` file
+#[tauri::command]
+pub async fn read_canvas(path: String, state: State<'_, AppState>) -> Result<Canvas, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        Canvas::parse(&content).map_err(SynapseError::from)
+    })
+    .await
+}
 
-/// Get headings for a note (for outline view)
+/// Validate and save a `.canvas` file, then index its links
 #[tauri::command]
-pub fn get_headings(
+pub async fn save_canvas(
     path: String,
+    data: Canvas,
     state: State<'_, AppState>,
-) -> Result<Vec<indexer::Heading>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.get_headings(&path).map_err(|e| e.to_string())
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        if !path.ends_with(".canvas") {
+            return Err(SynapseError::invalid(
+                "Canvas files must use the .canvas extension",
+            ));
+        }
+        data.validate().map_err(SynapseError::from)?;
+        let json = data.to_json().map_err(SynapseError::from)?;
+        Vault::write_file_atomic(&vault_path, &path, &json).map_err(SynapseError::from)?;
+
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &path);
+        }
+
+        Ok(())
+    })
+    .await
 }
 
-// ─── Settings commands ────────────────────────────────────────────
+/// Delete a file or folder
+#[tauri::command]
+pub async fn delete_entry(path: String, state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        Vault::delete_entry(&vault_path, &path)
+            .map_err(|e| SynapseError::from(e).with_path(&path))?;
+
+        // Remove from index
+        if let Some(db) = db {
+            let _ = db.delete_note(&path);
+        }
+
+        Ok(())
+    })
+    .await
+}
 
+/// Indexed `.csv` files with their headers and row counts
 #[tauri::command]
-pub fn get_setting(key: String, state: State<'_, AppState>) -> Result<Option<String>, String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.get_setting(&key).map_err(|e| e.to_string())
+pub async fn list_csv_files(state: State<'_, AppState>) -> Result<Vec<CsvSummary>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_csv_summaries().map_err(SynapseError::from)).await
 }
 
+/// Parse a `.csv` file, returning its headers and a window of data rows
+/// (all rows when no range is given)
 #[tauri::command]
-pub fn set_setting(key: String, value: String, state: State<'_, AppState>) -> Result<(), String> {
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.set_setting(&key, &value).map_err(|e| e.to_string())
+pub async fn read_csv(
+    path: String,
+    range: Option<RowRange>,
+    state: State<'_, AppState>,
+) -> Result<CsvPage, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let table = CsvTable::parse(&content).map_err(SynapseError::from)?;
+        let rows = table.rows();
+        let (offset, limit) = range.map_or((0, rows.len()), |r| (r.offset, r.limit));
+        Ok(CsvPage {
+            headers: table.headers().to_vec(),
+            offset,
+            rows: rows.iter().skip(offset).take(limit).cloned().collect(),
+            total_rows: rows.len(),
+        })
+    })
+    .await
 }
 
-// ─── Reindex command ──────────────────────────────────────────────
+/// Replace the contents of a `.csv` file (creating it if needed) with
+/// `rows`, the header row first. An existing file keeps its line endings.
+#[tauri::command]
+pub async fn write_csv(
+    path: String,
+    rows: Vec<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || save_csv(&vault_path, &path, rows, db.as_deref())).await
+}
 
-/// Force reindex the entire vault
+/// Set one cell of a `.csv` file (0-based data row and column) and save it
 #[tauri::command]
-pub fn reindex_vault(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn update_csv_cell(
+    path: String,
+    row: usize,
+    col: usize,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
     let vault_path = get_vault(&state)?;
-    let db_lock = state.db.lock().unwrap();
-    let db = db_lock.as_ref().ok_or("No vault open")?;
-    db.reindex_vault(&vault_path).map_err(|e| e.to_string())
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let mut table = CsvTable::parse(&content).map_err(SynapseError::from)?;
+        table
+            .set_cell(row, col, &value)
+            .map_err(SynapseError::from)?;
+        Vault::write_file_atomic(&vault_path, &path, table.to_csv()).map_err(SynapseError::from)?;
+
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &path);
+        }
+
+        Ok(())
+    })
+    .await
 }
 
-// ─── Helper types & functions ─────────────────────────────────────
+/// Parse a markdown kanban board: `##` headings as columns, list items as cards
+#[tauri::command]
+pub async fn read_board(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoard, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        Ok(kanban::parse(&content))
+    })
+    .await
+}
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct BacklinkResult {
-    pub source_path: String,
-    pub source_title: String,
-    pub context: String,
+/// Move a card (0-based column and card indices) to `position` in a column
+/// of the same board and save it. Returns the updated board.
+#[tauri::command]
+pub async fn move_card(
+    path: String,
+    from_column: usize,
+    card: usize,
+    to_column: usize,
+    position: usize,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoard, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let updated = kanban::move_card(&content, from_column, card, to_column, position)
+            .map_err(|e| SynapseError::from(e).with_path(&path))?;
+        save_board(&vault_path, &path, &updated, db.as_deref())
+    })
+    .await
 }
 
-/// Get the vault path from state, or return error
-fn get_vault(state: &State<'_, AppState>) -> Result<PathBuf, String> {
-    state
-        .vault_path
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or_else(|| "No vault open".to_string())
+/// Add a card to a column of a board (at the end unless a 0-based position
+/// is given) and save it. Returns the updated board.
+#[tauri::command]
+pub async fn add_card(
+    path: String,
+    column: usize,
+    text: String,
+    position: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<KanbanBoard, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let updated = kanban::add_card(&content, column, &text, position)
+            .map_err(|e| SynapseError::from(e).with_path(&path))?;
+        save_board(&vault_path, &path, &updated, db.as_deref())
+    })
+    .await
 }
 
-/// Find context around a wikilink in note content
-fn find_link_context(content: &str, target: &str) -> String {
-    let search_patterns = vec![
-        format!("[[{}]]", target),
-        format!("[[{}|", target),
-        format!("[[{}#", target),
-    ];
+/// Callouts in a note, or across the vault when no path is given,
+/// optionally filtered by type (e.g. every `[!todo]`)
+#[tauri::command]
+pub async fn get_callouts(
+    path: Option<String>,
+    kind: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteCallout>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        db.get_callouts(path.as_deref(), kind.as_deref().map(str::trim))
+            .map_err(SynapseError::from)
+    })
+    .await
+}
 
-    for line in content.lines() {
-        for pattern in &search_patterns {
-            if line.contains(pattern) {
+/// Checklist items across the vault or in one note: open, completed,
+/// overdue or all of them, soonest due first
+#[tauri::command]
+pub async fn get_tasks(
+    filter: Option<TaskFilter>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteTask>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let mut tasks = db
+            .get_tasks(&filter.unwrap_or_default(), &today)
+            .map_err(SynapseError::from)?;
+        tasks.retain(|t| !is_private(&t.path, &hidden));
+        Ok(tasks)
+    })
+    .await
+}
+
+/// Check or uncheck the task on a line (1-based) of a note that may not be
+/// open, stamping it with today's date (`✅ 2024-06-01`) when `add_date` is
+/// set. Only the note's tasks are reindexed. Returns whether it's now done.
+#[tauri::command]
+pub async fn toggle_task(
+    path: String,
+    line: usize,
+    add_date: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<bool, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let date = add_date.unwrap_or(false).then_some(today.as_str());
+        let (updated, done) = editing::toggle_task(&content, line, date)
+            .ok_or_else(|| SynapseError::not_found(format!("No task on line {}", line)))?;
+        Vault::write_file_atomic(&vault_path, &path, &updated).map_err(SynapseError::from)?;
+
+        if let Some(db) = db {
+            let indexed = crypto::index_view(&vault_path, &path, &updated);
+            let tasks = indexer::extract_tasks(&crypto::strip_encrypted(indexed));
+            let _ = db.update_tasks(&path, &tasks);
+        }
+
+        Ok(done)
+    })
+    .await
+}
+
+/// Math in a note: every span with its line range, plus the macros defined
+#[tauri::command]
+pub async fn get_note_math(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<NoteMath, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        let blocks = db.get_math(&path).map_err(SynapseError::from)?;
+        let mut macros: Vec<String> = Vec::new();
+        for m in blocks.iter().flat_map(|b| &b.macros) {
+            if !macros.contains(m) {
+                macros.push(m.clone());
+            }
+        }
+        Ok(NoteMath {
+            count: blocks.len(),
+            blocks,
+            macros,
+        })
+    })
+    .await
+}
+
+/// Mermaid diagrams of a note with their line ranges, from the index
+#[tauri::command]
+pub async fn get_diagrams(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<MermaidBlock>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_diagrams(&path).map_err(SynapseError::from)).await
+}
+
+/// Syntax-check every mermaid diagram in a note
+#[tauri::command]
+pub async fn validate_mermaid(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<DiagramCheck>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        Ok(mermaid::extract_blocks(&content)
+            .iter()
+            .map(mermaid::validate)
+            .collect())
+    })
+    .await
+}
+
+/// Read the scene JSON of an `.excalidraw` or `.excalidraw.md` drawing
+#[tauri::command]
+pub async fn read_excalidraw(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<serde_json::Value, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        if !excalidraw::is_drawing(&path) {
+            return Err(SynapseError::invalid("Not an Excalidraw drawing"));
+        }
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        excalidraw::scene(&path, &content).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Create an `.excalidraw.md` drawing in `folder`, empty or copied from a
+/// template drawing. Returns its vault path.
+#[tauri::command]
+pub async fn create_excalidraw(
+    name: String,
+    folder: String,
+    template: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let relative_path = excalidraw::create(&vault_path, &folder, &name, template.as_deref())
+            .map_err(SynapseError::from)?;
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &relative_path);
+        }
+        Ok(relative_path)
+    })
+    .await
+}
+
+/// Rasterize a drawing to PNG bytes at `scale` times its size (default 2)
+#[tauri::command]
+pub async fn export_excalidraw_png(
+    path: String,
+    scale: Option<f32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        if !excalidraw::is_drawing(&path) {
+            return Err(SynapseError::invalid("Not an Excalidraw drawing"));
+        }
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let scene = excalidraw::scene(&path, &content).map_err(SynapseError::from)?;
+        let png = excalidraw::to_png(&scene, scale.unwrap_or(2.0)).map_err(SynapseError::from)?;
+        Ok(png.data)
+    })
+    .await
+}
+
+/// Search the text elements of every drawing in the vault
+#[tauri::command]
+pub async fn search_drawings(
+    query: String,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DrawingTextMatch>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let mut matches = db
+            .search_drawing_text(query.trim())
+            .map_err(SynapseError::from)?;
+        matches.retain(|m| !is_private(&m.path, &hidden));
+        Ok(matches)
+    })
+    .await
+}
+
+/// Rename/move a file or folder and rewrite the wikilinks that pointed at
+/// the notes moved. Returns the other notes whose links were rewritten.
+#[tauri::command]
+pub async fn rename_entry(
+    old_path: String,
+    new_path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || move_entry(&vault_path, db.as_deref(), &old_path, &new_path)).await
+}
+
+/// Duplicate a file
+#[tauri::command]
+pub async fn duplicate_entry(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let new_path = Vault::duplicate_entry(&vault_path, &path).map_err(SynapseError::from)?;
+
+        // Index the new file
+        if let Some(db) = db {
+            if is_indexed_file(&new_path) {
+                let _ = db.reindex_note(&vault_path, &new_path);
+            }
+        }
+
+        Ok(new_path)
+    })
+    .await
+}
+
+// ─── Archive commands ─────────────────────────────────────────────
+
+/// Move a note into the archive folder, keeping its folder path, and rewrite
+/// links to it. Returns the note's new path.
+#[tauri::command]
+pub async fn archive_note(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let folder = setting_or(&db, ARCHIVE_FOLDER_SETTING, DEFAULT_ARCHIVE_FOLDER)?;
+        let archived = Vault::archive_path(&vault_path, &folder, &path)
+            .map_err(|e| SynapseError::invalid(e.to_string()))?;
+        move_entry(&vault_path, Some(&db), &path, &archived)?;
+        Ok(archived)
+    })
+    .await
+}
+
+/// Archive every note matching `rule` (or, for a dry run, list the notes
+/// that would be archived and where they'd go). A note that can't be moved
+/// is reported in `failed` and the rest are still archived.
+#[tauri::command]
+pub async fn auto_archive(
+    rule: ArchiveRule,
+    state: State<'_, AppState>,
+) -> Result<ArchiveReport, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || archive_matching(&vault_path, &db, &rule)).await
+}
+
+// ─── Note metadata commands ───────────────────────────────────────
+
+/// Get all cached notes (for quick switcher, search, etc.)
+#[tauri::command]
+pub async fn get_all_notes(state: State<'_, AppState>) -> Result<Vec<CachedNote>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_all_notes().map_err(SynapseError::from)).await
+}
+
+/// Toggle star on a note
+#[tauri::command]
+pub async fn toggle_star(path: String, state: State<'_, AppState>) -> Result<bool, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.toggle_star(&path).map_err(SynapseError::from)).await
+}
+
+/// Pin a note to the top of note lists
+#[tauri::command]
+pub async fn pin_note(path: String, state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.pin_note(&path).map_err(SynapseError::from)).await
+}
+
+/// Unpin a note
+#[tauri::command]
+pub async fn unpin_note(path: String, state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.unpin_note(&path).map_err(SynapseError::from)).await
+}
+
+/// Set the manual order of pinned notes
+#[tauri::command]
+pub async fn reorder_pins(
+    paths: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.reorder_pins(&paths).map_err(SynapseError::from)).await
+}
+
+/// Set a frontmatter property (any JSON value, written as YAML), leaving the
+/// rest of the note untouched
+#[tauri::command]
+pub async fn set_frontmatter_property(
+    path: String,
+    key: String,
+    value: serde_json::Value,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let key = frontmatter_key(&key)?;
+    let value = serde_yaml::to_value(value).map_err(|e| SynapseError::invalid(e.to_string()))?;
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let updated = editing::set_frontmatter_property(&content, &key, &value)
+            .map_err(|e| SynapseError::invalid(e.to_string()))?;
+        if updated != content {
+            Vault::write_file_atomic(&vault_path, &path, &updated).map_err(SynapseError::from)?;
+            if let Some(db) = db {
+                let _ = db.reindex_note(&vault_path, &path);
+            }
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Remove a frontmatter property. Returns whether the note had it.
+#[tauri::command]
+pub async fn remove_frontmatter_property(
+    path: String,
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<bool, SynapseError> {
+    let key = frontmatter_key(&key)?;
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let Some(updated) = editing::remove_frontmatter_property(&content, &key)
+            .map_err(|e| SynapseError::invalid(e.to_string()))?
+        else {
+            return Ok(false);
+        };
+        Vault::write_file_atomic(&vault_path, &path, &updated).map_err(SynapseError::from)?;
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &path);
+        }
+        Ok(true)
+    })
+    .await
+}
+
+// ─── Recent notes commands ────────────────────────────────────────
+
+/// Record that a note was opened in the editor
+#[tauri::command]
+pub async fn record_note_opened(
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.record_note_opened(&path).map_err(SynapseError::from)).await?;
+    #[cfg(desktop)]
+    crate::tray::refresh(&app);
+    #[cfg(not(desktop))]
+    let _ = app;
+    Ok(())
+}
+
+/// Get recently opened notes, newest first
+#[tauri::command]
+pub async fn get_recent_notes(
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RecentNote>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        db.get_recent_notes(limit.unwrap_or(20))
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// How often notes were opened, all time and in the last `days` days
+/// (default 30), busiest first; one note when `path` is given
+#[tauri::command]
+pub async fn get_note_open_stats(
+    path: Option<String>,
+    days: Option<u32>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteOpenStats>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        let since = chrono::Utc::now() - chrono::Duration::days(i64::from(days.unwrap_or(30)));
+        let mut stats = db
+            .get_note_open_stats(path.as_deref(), &since.to_rfc3339())
+            .map_err(SynapseError::from)?;
+        stats.truncate(limit.unwrap_or(usize::MAX));
+        Ok(stats)
+    })
+    .await
+}
+
+// ─── Links & backlinks commands ────────────────────────────────────
+
+/// Get backlinks for a note (notes that link TO this note)
+#[tauri::command]
+pub async fn get_backlinks(
+    note_title: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<BacklinkResult>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let source_paths = db.get_backlinks(&note_title).map_err(SynapseError::from)?;
+        let aliases = db
+            .get_aliases_by_title(&note_title)
+            .map_err(SynapseError::from)?;
+        let mut results = Vec::new();
+
+        for source_path in source_paths {
+            // Read source note to get context around the link
+            if let Ok(content) = Vault::read_file(&vault_path, &source_path) {
+                let fm = Vault::parse_frontmatter(&content);
+                let title = fm.title.unwrap_or_else(|| {
+                    std::path::Path::new(&source_path)
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                });
+
+                // Find the context around the link, which may name an alias
+                let context = std::iter::once(&note_title)
+                    .chain(&aliases)
+                    .map(|target| find_link_context(&content, target))
+                    .find(|context| !context.is_empty())
+                    .unwrap_or_default();
+
+                results.push(BacklinkResult {
+                    source_path: source_path.clone(),
+                    source_title: title,
+                    context,
+                });
+            }
+        }
+
+        Ok(results)
+    })
+    .await
+}
+
+/// Get outgoing links from a note
+#[tauri::command]
+pub async fn get_outgoing_links(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_outgoing_links(&path).map_err(SynapseError::from)).await
+}
+
+/// Get all links in the vault (for graph view)
+#[tauri::command]
+pub async fn get_all_links(
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<(String, String)>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let links = db.get_all_links().map_err(SynapseError::from)?;
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        without_private_links(&db, links, &hidden)
+    })
+    .await
+}
+
+/// Degree, PageRank and betweenness of each note, most central first, for
+/// sizing graph nodes and listing hub notes
+#[tauri::command]
+pub async fn get_graph_metrics(
+    limit: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NodeMetrics>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let graph =
+            Graph::load(&db, |path| !is_private(path, &hidden)).map_err(SynapseError::from)?;
+        let mut metrics = graph.metrics();
+        if let Some(limit) = limit {
+            metrics.truncate(limit);
+        }
+        Ok(metrics)
+    })
+    .await
+}
+
+/// A cluster id for every note, grouping notes that link closely to each
+/// other, so the graph view can color them
+#[tauri::command]
+pub async fn get_graph_clusters(
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteCluster>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let graph =
+            Graph::load(&db, |path| !is_private(path, &hidden)).map_err(SynapseError::from)?;
+        Ok(graph.clusters())
+    })
+    .await
+}
+
+/// Notes related to `path` by shared tags, shared links, title and content
+/// that it doesn't link to yet, most related first (default 10)
+#[tauri::command]
+pub async fn get_related_notes(
+    path: String,
+    limit: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<RelatedNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        related::related_notes(&db, &path, limit.unwrap_or(10), |p| !is_private(p, &hidden))
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// The notes within `depth` links (default 1) of a note, for a per-note
+/// mini graph; unresolved links show up as phantom nodes
+#[tauri::command]
+pub async fn get_local_graph(
+    path: String,
+    depth: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<LocalGraph, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let graph =
+            Graph::load(&db, |path| !is_private(path, &hidden)).map_err(SynapseError::from)?;
+        graph
+            .local(&path, depth.unwrap_or(1))
+            .ok_or_else(|| SynapseError::not_found("Note is not indexed").with_path(&path))
+    })
+    .await
+}
+
+/// Wikilinks to notes that don't exist, grouped by target. Links to
+/// attachments and other files in the vault don't count.
+#[tauri::command]
+pub async fn get_unresolved_links(
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<UnresolvedLink>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let mut files = HashSet::new();
+        for entry in Vault::list_entries(&vault_path).map_err(SynapseError::from)? {
+            if !entry.is_dir {
+                let path = entry.path.replace('\\', "/").to_lowercase();
+                let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+                files.insert(name);
+                files.insert(path);
+            }
+        }
+        let mut links = db.get_unresolved_links().map_err(SynapseError::from)?;
+        links.retain_mut(|link| {
+            link.sources.retain(|source| !is_private(source, &hidden));
+            !link.sources.is_empty() && !files.contains(&link.target.trim().to_lowercase())
+        });
+        Ok(links)
+    })
+    .await
+}
+
+/// `[[Note#Heading]]` links whose heading isn't in the note, by source note
+#[tauri::command]
+pub async fn get_broken_heading_links(
+    state: State<'_, AppState>,
+) -> Result<Vec<ResolvedSectionLink>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_broken_heading_links().map_err(SynapseError::from)).await
+}
+
+/// Notes with no links in or out
+#[tauri::command]
+pub async fn get_orphan_notes(
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CachedNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let mut notes = db.get_orphan_notes().map_err(SynapseError::from)?;
+        notes.retain(|note| !is_private(&note.path, &hidden));
+        Ok(notes)
+    })
+    .await
+}
+
+/// Broken links, missing frontmatter, duplicate titles, unreadable files
+/// and cache entries out of step with the files, with suggested fixes
+#[tauri::command]
+pub async fn check_vault_health(state: State<'_, AppState>) -> Result<HealthReport, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || health::check(&vault_path, &db).map_err(SynapseError::from)).await
+}
+
+/// Clusters of notes whose content is at least `min_similarity` (0 to 1,
+/// default 0.8) the same, such as copies left behind by an import
+#[tauri::command]
+pub async fn find_duplicate_notes(
+    min_similarity: Option<f64>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DuplicateCluster>, SynapseError> {
+    let min_similarity = min_similarity.unwrap_or(0.8);
+    if !(0.0..=1.0).contains(&min_similarity) {
+        return Err(SynapseError::invalid(
+            "Minimum similarity must be between 0 and 1",
+        ));
+    }
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        duplicates::find_duplicates(&db, min_similarity, |path| !is_private(path, &hidden))
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Search notes by title or alias for wikilink autocomplete
+#[tauri::command]
+pub async fn search_notes(
+    query: String,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CachedNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let all_notes = db.get_all_notes().map_err(SynapseError::from)?;
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+
+        let query_lower = query.to_lowercase();
+        let mut results: Vec<(usize, CachedNote)> = all_notes
+            .into_iter()
+            .filter(|note| !is_private(&note.path, &hidden))
+            .filter_map(|note| {
+                std::iter::once(&note.title)
+                    .chain(&note.aliases)
+                    .filter_map(|name| {
+                        related::title_match_tier(&name.to_lowercase(), &query_lower)
+                    })
+                    .min()
+                    .map(|score| (score, note))
+            })
+            .collect();
+
+        results.sort_by_key(|(score, _)| *score);
+        let notes: Vec<CachedNote> = results.into_iter().take(20).map(|(_, n)| n).collect();
+
+        Ok(notes)
+    })
+    .await
+}
+
+/// Search note titles and bodies, best matches first, with a highlighted
+/// snippet of each
+#[tauri::command]
+pub async fn search_fulltext(
+    query: String,
+    limit: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<FulltextMatch>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let mut matches = db
+            .search_fulltext(&query, limit.unwrap_or(50))
+            .map_err(SynapseError::from)?;
+        matches.retain(|m| !is_private(&m.path, &hidden));
+        Ok(matches)
+    })
+    .await
+}
+
+/// The `k` notes (default 10) closest in meaning to `query`, by sentence
+/// embeddings from the local model (see `embeddings`). Fails until
+/// `download_embedding_model` has fetched the model.
+#[tauri::command]
+pub async fn semantic_search(
+    query: String,
+    k: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SemanticMatch>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    let slot = state.sentence_model.clone();
+    blocking(move || {
+        let model = load_sentence_model(&slot)?;
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        embeddings::semantic_search(&db, &model, &query, k.unwrap_or(10), |path| {
+            !is_private(path, &hidden)
+        })
+        .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Download the sentence model semantic search runs on, unless it's already
+/// there
+#[tauri::command]
+pub async fn download_embedding_model() -> Result<(), SynapseError> {
+    let dir = embeddings::model_dir().map_err(SynapseError::from)?;
+    if embeddings::model_installed(&dir) {
+        return Ok(());
+    }
+    embeddings::download_model(&dir)
+        .await
+        .map_err(SynapseError::from)
+}
+
+/// The loaded sentence model, loading it from disk on first use
+fn load_sentence_model(
+    slot: &std::sync::Mutex<Option<Arc<SentenceModel>>>,
+) -> Result<Arc<SentenceModel>, SynapseError> {
+    let mut slot = slot.lock_or_recover();
+    if let Some(model) = slot.as_ref() {
+        return Ok(model.clone());
+    }
+    let dir = embeddings::model_dir().map_err(SynapseError::from)?;
+    if !embeddings::model_installed(&dir) {
+        return Err(SynapseError::not_found(
+            "The embedding model isn't downloaded yet. Download it to use semantic search.",
+        ));
+    }
+    let model = Arc::new(SentenceModel::load(&dir).map_err(SynapseError::from)?);
+    *slot = Some(model.clone());
+    Ok(model)
+}
+
+/// Run the ```query blocks of a note and return their matches, keyed by
+/// each block's line range
+#[tauri::command]
+pub async fn evaluate_embedded_queries(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<EmbeddedQueryResult>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        query::evaluate_embedded(&db, &content).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Notes matching a search query such as `tag:#work (created:>2024-01-01
+/// OR starred:true) -path:Archive`, sorted by title
+#[tauri::command]
+pub async fn query_notes(
+    query: String,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CachedNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || run_query(&vault_path, &db, &query, include_private)).await
+}
+
+/// Notes filtered and sorted by their frontmatter properties, e.g.
+/// `status = "active" AND due < today` (see `properties.rs`)
+#[tauri::command]
+pub async fn query_properties(
+    filter: String,
+    sort: Option<PropertySort>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<PropertyMatch>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let mut matches = properties::query(&db, &filter, sort.as_ref())
+            .map_err(|e| SynapseError::invalid(e.to_string()))?;
+        matches.retain(|m| !is_private(&m.note.path, &hidden));
+        Ok(matches)
+    })
+    .await
+}
+
+/// `[[` completions for the note being edited, best first
+#[tauri::command]
+pub async fn suggest_link_targets(
+    query: String,
+    from_path: Option<String>,
+    limit: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CachedNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        related::link_suggestions(
+            &db,
+            &query,
+            from_path.as_deref(),
+            limit.unwrap_or(20),
+            |p| !is_private(p, &hidden),
+        )
+        .map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── Tags commands ─────────────────────────────────────────────────
+
+/// Get all tags in the vault with their counts
+#[tauri::command]
+pub async fn get_all_tags(
+    state: State<'_, AppState>,
+) -> Result<Vec<(String, usize)>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_all_tags().map_err(SynapseError::from)).await
+}
+
+/// Get all notes with a specific tag
+#[tauri::command]
+pub async fn get_notes_by_tag(
+    tag: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_notes_by_tag(&tag).map_err(SynapseError::from)).await
+}
+
+/// Rename a tag, and the tags nested under it, in every note that uses it.
+/// Returns the notes rewritten.
+#[tauri::command]
+pub async fn rename_tag(
+    old: String,
+    new: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    let old = old.trim().trim_start_matches('#').to_string();
+    let new = new.trim().trim_start_matches('#').to_string();
+    if !is_tag_name(&old) || !is_tag_name(&new) {
+        return Err(SynapseError::invalid(
+            "Tags may only contain letters, numbers, '-', '_' and '/'",
+        ));
+    }
+    blocking(move || {
+        let key = format!("#{}", old.to_lowercase());
+        let mut notes = Vec::new();
+        for (tag, _) in db.get_all_tags().map_err(SynapseError::from)? {
+            let tag_lower = tag.to_lowercase();
+            if tag_lower == key || tag_lower.starts_with(&format!("{}/", key)) {
+                notes.extend(db.get_notes_by_tag(&tag).map_err(SynapseError::from)?);
+            }
+        }
+        notes.sort();
+        notes.dedup();
+        let updated =
+            Vault::rename_tag(&vault_path, &notes, &old, &new).map_err(SynapseError::from)?;
+        for path in &updated {
+            let _ = db.reindex_note(&vault_path, path);
+        }
+        Ok(updated)
+    })
+    .await
+}
+
+// ─── Saved search commands ────────────────────────────────────────
+
+/// Save a search query (e.g. `tag:#inbox -path:Archive`) under a name
+#[tauri::command]
+pub async fn save_search(
+    name: String,
+    query: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(SynapseError::invalid("Saved search needs a name"));
+    }
+    Query::parse(&query).map_err(|e| SynapseError::invalid(e.to_string()))?;
+    let db = state.database()?;
+    blocking(move || {
+        db.save_search(&name, query.trim())
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Every saved search, by name
+#[tauri::command]
+pub async fn list_saved_searches(
+    state: State<'_, AppState>,
+) -> Result<Vec<SavedSearch>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.list_saved_searches().map_err(SynapseError::from)).await
+}
+
+/// Notes matching a saved search, sorted by title
+#[tauri::command]
+pub async fn run_saved_search(
+    name: String,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CachedNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let query = db
+            .get_saved_search(&name)
+            .map_err(SynapseError::from)?
+            .ok_or_else(|| SynapseError::not_found("Saved search not found"))?;
+        run_query(&vault_path, &db, &query, include_private)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_saved_search(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.delete_saved_search(&name).map_err(SynapseError::from)).await
+}
+
+// ─── Review commands ──────────────────────────────────────────────
+
+/// A random note, optionally among those matching a search query (e.g.
+/// `tag:#idea`), for stumbling on something forgotten
+#[tauri::command]
+pub async fn get_random_note(
+    filter: Option<String>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Option<CachedNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || random_note(&vault_path, &db, filter.as_deref(), include_private)).await
+}
+
+/// Notes whose file hasn't changed in `days` days, oldest first, optionally
+/// among those matching a search query (`tag:#project`, `path:Research`)
+#[tauri::command]
+pub async fn get_stale_notes(
+    days: u32,
+    filter: Option<String>,
+    limit: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<StaleNote>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let notes = run_query(
+            &vault_path,
+            &db,
+            filter.as_deref().unwrap_or_default(),
+            include_private,
+        )?;
+        let mut stale = stale_notes(&vault_path, notes, days)?;
+        stale.truncate(limit.unwrap_or(usize::MAX));
+        Ok(stale)
+    })
+    .await
+}
+
+// ─── Capture commands ─────────────────────────────────────────────
+
+/// Save text from the quick-note window to the inbox or a new note
+#[tauri::command]
+pub async fn quick_capture(
+    text: String,
+    target: Option<CaptureTarget>,
+    state: State<'_, AppState>,
+) -> Result<AppendResult, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let inbox = setting_or(&db, CAPTURE_INBOX_SETTING, capture::DEFAULT_INBOX)?;
+        let result = capture::capture(&vault_path, &text, &target.unwrap_or_default(), &inbox)
+            .map_err(SynapseError::from)?;
+
+        let _ = db.reindex_note(&vault_path, &result.path);
+        Ok(result)
+    })
+    .await
+}
+
+/// Today's daily note, created if it doesn't exist yet
+#[tauri::command]
+pub async fn open_daily_note(state: State<'_, AppState>) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || create_daily_note(&vault_path, &db)).await
+}
+
+/// Path of today's daily note (`Daily/2024-05-01.md`), creating it if needed
+pub fn daily_note(state: &AppState) -> Result<String, SynapseError> {
+    let vault_path = get_vault(state)?;
+    let db = state.database()?;
+    create_daily_note(&vault_path, &db)
+}
+
+fn create_daily_note(vault_path: &Path, db: &Database) -> Result<String, SynapseError> {
+    let folder = setting_or(db, DAILY_FOLDER_SETTING, DEFAULT_DAILY_FOLDER)?;
+    let title = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let path = Vault::note_relative_path(&folder, &title);
+    if !vault_path.join(&path).exists() {
+        Vault::create_note(vault_path, &folder, &title).map_err(SynapseError::from)?;
+        let _ = db.reindex_note(vault_path, &path);
+    }
+    Ok(path)
+}
+
+/// Intake for the mobile share sheet / Android share intent: text and links
+/// go to the inbox (or a new note), shared images are saved as attachments
+/// and embedded
+#[tauri::command]
+pub async fn receive_shared(
+    items: Vec<SharedItem>,
+    target: Option<CaptureTarget>,
+    state: State<'_, AppState>,
+) -> Result<AppendResult, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let inbox = setting_or(&db, CAPTURE_INBOX_SETTING, capture::DEFAULT_INBOX)?;
+        let attachments = setting_or(&db, ATTACHMENTS_FOLDER_SETTING, DEFAULT_ATTACHMENTS_FOLDER)?;
+
+        let mut embeds = Vec::new();
+        for item in &items {
+            if let SharedItem::Image { path } = item {
+                embeds.push(store_attachment(
+                    &db,
+                    &vault_path,
+                    &attachments,
+                    Path::new(path),
+                )?);
+            }
+        }
+        let text = capture::shared_markdown(&items, &embeds);
+        let result = capture::capture(&vault_path, &text, &target.unwrap_or_default(), &inbox)
+            .map_err(SynapseError::from)?;
+
+        let _ = db.reindex_note(&vault_path, &result.path);
+        Ok(result)
+    })
+    .await
+}
+
+// ─── Private folder commands ──────────────────────────────────────
+
+/// Folders whose notes are left out of search, links and the quick switcher
+/// (from `.private` markers and the private folders setting)
+#[tauri::command]
+pub async fn list_private_folders(state: State<'_, AppState>) -> Result<Vec<String>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || hidden_folders(&vault_path, &db, None)).await
+}
+
+/// Mark or unmark a folder as private with a `.private` marker file
+#[tauri::command]
+pub async fn set_folder_private(
+    folder: String,
+    private: bool,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        Vault::set_folder_private(&vault_path, &folder, private).map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── Attachment commands ──────────────────────────────────────────
+
+/// Choose the folder whose attachments are encrypted on disk (None turns it
+/// off). Files already in the folder are left as they are.
+#[tauri::command]
+pub async fn set_secure_attachments_folder(
+    folder: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(
+        move || match folder.as_deref().map(|f| f.trim_matches('/')) {
+            Some(folder) if !folder.is_empty() => db
+                .set_setting(SECURE_ATTACHMENTS_SETTING, folder)
+                .map_err(SynapseError::from),
+            _ => db
+                .delete_setting(SECURE_ATTACHMENTS_SETTING)
+                .map_err(SynapseError::from),
+        },
+    )
+    .await
+}
+
+/// Copy a file from outside the vault into `folder`, encrypting it if the
+/// folder is the secure attachments folder. Returns the new vault path.
+#[tauri::command]
+pub async fn import_attachment(
+    source: String,
+    folder: String,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || store_attachment(&db, &vault_path, &folder, Path::new(&source))).await
+}
+
+/// Save pasted or dropped file data as an attachment of `target_note`, in
+/// the attachments folder (a `./` folder is relative to the note). Returns
+/// the vault path to embed.
+#[tauri::command]
+pub async fn import_attachment_bytes(
+    data: Vec<u8>,
+    file_name: String,
+    target_note: String,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    if file_name.trim().is_empty() {
+        return Err(SynapseError::invalid("Attachment has no file name"));
+    }
+    let db = state.database()?;
+    blocking(move || {
+        let folder = setting_or(&db, ATTACHMENTS_FOLDER_SETTING, DEFAULT_ATTACHMENTS_FOLDER)?;
+        let mut data = data;
+        if is_secure_attachment(&db, &Vault::attachment_folder(&folder, &target_note))? {
+            let key = crypto::vault_file_key(&vault_path).map_err(SynapseError::from)?;
+            data = crypto::encrypt_bytes(&key, &data).map_err(SynapseError::from)?;
+        }
+        let path = Vault::import_attachment(&vault_path, &folder, &target_note, &file_name, &data)
+            .map_err(|e| SynapseError::from(e).with_path(&file_name))?;
+        db.record_attachment(&path, &target_note)
+            .map_err(SynapseError::from)?;
+        Ok(path)
+    })
+    .await
+}
+
+/// Files imported as attachments of a note
+#[tauri::command]
+pub async fn get_note_attachments(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_attachments(&path).map_err(SynapseError::from)).await
+}
+
+/// Files no note links to or embeds any more, with their sizes
+#[tauri::command]
+pub async fn find_orphaned_attachments(
+    state: State<'_, AppState>,
+) -> Result<Vec<OrphanedAttachment>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || attachments::find_orphans(&vault_path, &db).map_err(SynapseError::from)).await
+}
+
+/// Delete orphaned attachments (the given ones, or all). Returns the paths
+/// deleted; any linked again since they were listed are kept.
+#[tauri::command]
+pub async fn delete_orphaned_attachments(
+    paths: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        attachments::delete_orphans(&vault_path, &db, paths.as_deref()).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Read a file's bytes, decrypting secure attachments transparently
+#[tauri::command]
+pub async fn read_binary(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || load_attachment(&vault_path, &path)).await
+}
+
+// ─── Credential commands ──────────────────────────────────────────
+
+/// Store an integration secret in the OS keychain. Secrets never go in the
+/// settings table and are never sent back to the frontend.
+#[tauri::command]
+pub async fn set_credential(service: String, value: String) -> Result<(), SynapseError> {
+    blocking(move || credentials::set_user_credential(&service, &value).map_err(SynapseError::from))
+        .await
+}
+
+#[tauri::command]
+pub async fn delete_credential(service: String) -> Result<(), SynapseError> {
+    blocking(move || credentials::delete_user_credential(&service).map_err(SynapseError::from))
+        .await
+}
+
+/// Whether a secret is stored for an integration
+#[tauri::command]
+pub async fn has_credential(service: String) -> Result<bool, SynapseError> {
+    blocking(move || {
+        credentials::get_credential(&service)
+            .map(|value| value.is_some())
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── Secret detection commands ────────────────────────────────────
+
+/// Notes containing strings that look like API keys, private keys or
+/// passwords. Scans one note, or the whole vault when `path` is omitted.
+#[tauri::command]
+pub async fn scan_for_secrets(
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<NoteSecrets>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let paths = match path {
+            Some(path) => vec![path],
+            None => Vault::list_notes(&vault_path)
+                .map_err(SynapseError::from)?
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect(),
+        };
+
+        let mut results = Vec::new();
+        for path in paths {
+            let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+            let findings = secrets::scan(&content);
+            if !findings.is_empty() {
+                results.push(NoteSecrets { path, findings });
+            }
+        }
+        Ok(results)
+    })
+    .await
+}
+
+// ─── Export redaction commands ────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_redaction_rules(
+    state: State<'_, AppState>,
+) -> Result<RedactionRules, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        Redactor::load(&db)
+            .map(|r| r.rules().clone())
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Save redaction rules, rejecting invalid patterns
+#[tauri::command]
+pub async fn set_redaction_rules(
+    rules: RedactionRules,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        Redactor::new(rules.clone()).map_err(SynapseError::from)?;
+        let json = serde_json::to_string(&rules).map_err(SynapseError::from)?;
+        db.set_setting(REDACTION_SETTING, &json)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// What a note would look like when exported (`None` if it is left out)
+#[tauri::command]
+pub async fn preview_redaction(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Option<Redaction>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let redactor = Redactor::load(&db).map_err(SynapseError::from)?;
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        Ok(redactor.redact(&path, &content))
+    })
+    .await
+}
+
+/// The link graph with private notes removed and titles redacted
+#[tauri::command]
+pub async fn export_graph(state: State<'_, AppState>) -> Result<GraphExport, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let redactor = Redactor::load(&db).map_err(SynapseError::from)?;
+
+        let mut nodes = Vec::new();
+        let mut hidden_names = HashSet::new();
+        for note in db.get_all_notes().map_err(SynapseError::from)? {
+            let keep = Vault::read_file(&vault_path, &note.path)
+                .ok()
+                .and_then(|content| redactor.redact(&note.path, &content))
+                .is_some();
+            if keep {
+                nodes.push(GraphNode {
+                    title: redactor.redact_text(&note.title).0,
+                    path: note.path,
+                });
+            } else {
+                hidden_names.insert(note.title.to_lowercase());
+                let stem = std::path::Path::new(&note.path)
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_lowercase();
+                hidden_names.insert(stem);
+            }
+        }
+
+        let kept: HashSet<&str> = nodes.iter().map(|n| n.path.as_str()).collect();
+        let edges = db
+            .get_all_links()
+            .map_err(SynapseError::from)?
+            .into_iter()
+            .filter(|(source, target)| {
+                kept.contains(source.as_str()) && !hidden_names.contains(&target.to_lowercase())
+            })
+            .map(|(source, target)| (source, redactor.redact_text(&target).0))
+            .collect();
+
+        Ok(GraphExport { nodes, edges })
+    })
+    .await
+}
+
+/// Render a note to a standalone HTML file, copying the images it shows
+/// beside it
+#[tauri::command]
+pub async fn export_note_html(
+    path: String,
+    options: HtmlExportOptions,
+    state: State<'_, AppState>,
+) -> Result<HtmlExport, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let redactor = Redactor::load(&db).map_err(SynapseError::from)?;
+        export::export_note_html(&vault_path, &db, &redactor, &path, &options)
+            .map_err(|e| SynapseError::from(e).with_path(&path))
+    })
+    .await
+}
+
+/// Print notes to one PDF; folders stand for the notes inside them
+#[tauri::command]
+pub async fn export_pdf(
+    paths: Vec<String>,
+    options: PdfExportOptions,
+    state: State<'_, AppState>,
+) -> Result<PdfExport, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let redactor = Redactor::load(&db).map_err(SynapseError::from)?;
+        pdf::export_pdf(&vault_path, &db, &redactor, &paths, &options).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Write the notes carrying the publish tag to a static HTML site
+#[tauri::command]
+pub async fn publish_site(
+    options: PublishOptions,
+    state: State<'_, AppState>,
+) -> Result<PublishedSite, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let redactor = Redactor::load(&db).map_err(SynapseError::from)?;
+        publish::publish(&vault_path, &db, &redactor, &options).map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── Vault lock commands ──────────────────────────────────────────
+
+/// Set, change or remove (`passphrase: None`) the vault lock passphrase.
+/// Changing or removing an existing one requires the current passphrase.
+#[tauri::command]
+pub async fn set_lock_passphrase(
+    current: Option<String>,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        if let Some(hash) = lock_hash(&vault_path, &db)? {
+            let current = current.ok_or("Current passphrase required")?;
+            if !crypto::verify_passphrase(&current, &hash) {
+                return Err(SynapseError::invalid("Wrong passphrase"));
+            }
+        }
+        match passphrase {
+            Some(p) if p.is_empty() => Err(SynapseError::invalid("Passphrase must not be empty")),
+            Some(p) => {
+                let hash = crypto::hash_passphrase(&p).map_err(SynapseError::from)?;
+                crypto::set_lock_hash(&vault_path, Some(&hash)).map_err(SynapseError::from)
+            }
+            None => crypto::set_lock_hash(&vault_path, None).map_err(SynapseError::from),
+        }
+    })
+    .await
+}
+
+/// Lock the vault now. Requires a passphrase to be set.
+#[tauri::command]
+pub async fn lock_vault(state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    let hash = blocking(move || lock_hash(&vault_path, &db)).await?;
+    if hash.is_none() {
+        return Err(SynapseError::invalid("Set a lock passphrase first"));
+    }
+    state.engage_lock();
+    Ok(())
+}
+
+/// Unlock the vault with its passphrase, returning its last-session layout
+/// (if any) for restore
+#[tauri::command]
+pub async fn unlock_vault(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SynapseError> {
+    // Hashing the passphrase is slow; the vault stays stashed meanwhile
+    let stashed = state.lock.lock_or_recover().stash.clone();
+    let (vault_path, db) = stashed.ok_or("Vault is not locked")?;
+    let verified = blocking({
+        let db = db.clone();
+        move || {
+            Ok(matches!(
+                lock_hash(&vault_path, &db),
+                Ok(Some(hash)) if crypto::verify_passphrase(&passphrase, &hash)
+            ))
+        }
+    })
+    .await?;
+    if !verified {
+        return Err(SynapseError::invalid("Wrong passphrase"));
+    }
+    state.release_lock()?;
+    blocking(move || {
+        db.load_workspace(LAST_SESSION_WORKSPACE)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn is_vault_locked(state: State<'_, AppState>) -> bool {
+    state.is_locked()
+}
+
+/// Auto-lock after this many idle minutes (None or 0 turns it off)
+#[tauri::command]
+pub async fn set_auto_lock(
+    minutes: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        db.set_setting(AUTO_LOCK_SETTING, &minutes.unwrap_or(0).to_string())
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Reset the idle timer. Vault commands already do; this is for input
+/// that doesn't reach the backend.
+#[tauri::command]
+pub fn record_activity(state: State<'_, AppState>) {
+    state.lock.lock_or_recover().last_activity = Instant::now();
+}
+
+// ─── Encryption commands ──────────────────────────────────────────
+
+/// Encrypt a note in place: its `<!-- encrypt -->` sections if it has any,
+/// otherwise the whole note. Returns how many blocks were encrypted.
+#[tauri::command]
+pub async fn encrypt_note(
+    path: String,
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<usize, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let (encrypted, count) =
+            crypto::encrypt_note(&content, &passphrase).map_err(SynapseError::from)?;
+        Vault::write_file_atomic(&vault_path, &path, &encrypted).map_err(SynapseError::from)?;
+
+        if let Some(db) = db {
+            let _ = db.reindex_note(&vault_path, &path);
+        }
+
+        Ok(count)
+    })
+    .await
+}
+
+/// Decrypt a note's encrypted blocks and return the plaintext. With
+/// `persist` the decrypted note is also written back to disk; otherwise the
+/// file stays encrypted and the plaintext lives only in the editor.
+#[tauri::command]
+pub async fn decrypt_note(
+    path: String,
+    passphrase: String,
+    persist: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        let (decrypted, _) =
+            crypto::decrypt_note(&content, &passphrase).map_err(SynapseError::from)?;
+
+        if persist.unwrap_or(false) {
+            Vault::write_file_atomic(&vault_path, &path, &decrypted).map_err(SynapseError::from)?;
+            if let Some(db) = db {
+                let _ = db.reindex_note(&vault_path, &path);
+            }
+        }
+
+        Ok(decrypted)
+    })
+    .await
+}
+
+/// Whether a note contains encrypted blocks (so the editor can prompt for a passphrase)
+#[tauri::command]
+pub async fn is_note_encrypted(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<bool, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        Ok(crypto::is_encrypted(&content))
+    })
+    .await
+}
+
+/// Whether the vault stores its notes encrypted, and whether it's unlocked
+#[tauri::command]
+pub async fn get_vault_encryption(
+    state: State<'_, AppState>,
+) -> Result<VaultEncryption, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || crypto::vault_encryption(&vault_path).map_err(SynapseError::from)).await
+}
+
+/// Encrypt every note on disk with a key derived from `passphrase`, which
+/// unlocks the vault from then on. `index` limits what the cache keeps in
+/// plaintext (frontmatter only by default; `full` puts note bodies in the
+/// cache unencrypted). Returns how many files were encrypted.
+#[tauri::command]
+pub async fn enable_vault_encryption(
+    passphrase: String,
+    index: Option<IndexScope>,
+    state: State<'_, AppState>,
+) -> Result<usize, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let encrypted = crypto::encrypt_vault(&vault_path, &passphrase, index.unwrap_or_default())
+            .map_err(SynapseError::from)?;
+        db.forget_file_states().map_err(SynapseError::from)?;
+        db.reindex_vault(&vault_path).map_err(SynapseError::from)?;
+        Ok(encrypted)
+    })
+    .await
+}
+
+/// Decrypt every note on disk and stop encrypting the vault. Returns how
+/// many files were decrypted.
+#[tauri::command]
+pub async fn disable_vault_encryption(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<usize, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let decrypted =
+            crypto::decrypt_vault(&vault_path, &passphrase).map_err(SynapseError::from)?;
+        db.forget_file_states().map_err(SynapseError::from)?;
+        db.reindex_vault(&vault_path).map_err(SynapseError::from)?;
+        Ok(decrypted)
+    })
+    .await
+}
+
+/// Unlock an encrypted vault with its passphrase and index the notes that
+/// couldn't be read while it was locked
+#[tauri::command]
+pub async fn unlock_vault_encryption(
+    passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database().ok();
+    blocking(move || {
+        crypto::unlock_vault(&vault_path, &passphrase).map_err(SynapseError::from)?;
+        if let Some(db) = db {
+            db.reindex_vault(&vault_path).map_err(SynapseError::from)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Forget an encrypted vault's key. Notes can't be opened or saved until
+/// it is unlocked again; the cache keeps what its index scope allowed.
+#[tauri::command]
+pub fn lock_vault_encryption(state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    crypto::lock_vault(&vault_path);
+    Ok(())
+}
+
+/// Change how much of an unlocked encrypted vault the cache keeps, and
+/// rebuild the cache to match
+#[tauri::command]
+pub async fn set_vault_index_scope(
+    index: IndexScope,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        crypto::set_index_scope(&vault_path, index).map_err(SynapseError::from)?;
+        db.forget_file_states().map_err(SynapseError::from)?;
+        db.reindex_vault(&vault_path).map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── Snippet commands ─────────────────────────────────────────────
+
+/// Turn snippet mode on or off. Reindexes the vault so the snippets table
+/// is filled (or emptied) right away.
+#[tauri::command]
+pub async fn set_snippet_mode(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        db.set_setting(SNIPPET_MODE_SETTING, if enabled { "true" } else { "false" })
+            .map_err(SynapseError::from)?;
+        db.forget_file_states().map_err(SynapseError::from)?;
+        db.reindex_vault(&vault_path).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Search indexed code snippets, optionally restricted to one language
+#[tauri::command]
+pub async fn search_snippets(
+    query: String,
+    language: Option<String>,
+    limit: Option<usize>,
+    include_private: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Snippet>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let hidden = hidden_folders(&vault_path, &db, include_private)?;
+        let language = language.filter(|l| !l.trim().is_empty());
+        let mut snippets = db
+            .search_snippets(query.trim(), language.as_deref(), limit.unwrap_or(100))
+            .map_err(SynapseError::from)?;
+        snippets.retain(|snippet| !is_private(&snippet.path, &hidden));
+        Ok(snippets)
+    })
+    .await
+}
+
+/// Snippet languages with counts, for the language filter
+#[tauri::command]
+pub async fn get_snippet_languages(
+    state: State<'_, AppState>,
+) -> Result<Vec<(String, usize)>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_snippet_languages().map_err(SynapseError::from)).await
+}
+
+// ─── Headings / outline commands ──────────────────────────────────
+
+/// Get headings for a note (for outline view)
+#[tauri::command]
+pub async fn get_headings(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<indexer::Heading>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_headings(&path).map_err(SynapseError::from)).await
+}
+
+/// Resolve a `#slug` (or `#Heading Text`) deep link to the heading it points at
+#[tauri::command]
+pub async fn resolve_anchor(
+    path: String,
+    slug: String,
+    state: State<'_, AppState>,
+) -> Result<Option<indexer::Heading>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.resolve_anchor(&path, &slug).map_err(SynapseError::from)).await
+}
+
+/// Get the nested heading tree for a note, with section line ranges and word counts
+#[tauri::command]
+pub async fn get_outline(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<indexer::OutlineNode>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path).map_err(SynapseError::from)?;
+        Ok(indexer::build_outline(&content))
+    })
+    .await
+}
+
+/// Get block ids for a note
+#[tauri::command]
+pub async fn get_blocks(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<indexer::BlockRef>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_blocks(&path).map_err(SynapseError::from)).await
+}
+
+/// Headings and block ids of a linked note, for `[[Note#` / `[[Note#^` autocomplete
+#[tauri::command]
+pub async fn get_link_targets(
+    note: String,
+    kind: Option<LinkTargetKind>,
+    state: State<'_, AppState>,
+) -> Result<Vec<LinkTarget>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let path = db
+            .resolve_link_target(&note)
+            .map_err(SynapseError::from)?
+            .ok_or_else(|| SynapseError::not_found(format!("Note not found: {}", note)))?;
+
+        let content = Vault::read_file(&vault_path, &path).unwrap_or_default();
+        db.get_link_targets(&path, &content, kind)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Resolve a note's `![[...]]` embeds to the markdown they show (or the
+/// file they name), for rendering transclusions
+#[tauri::command]
+pub async fn resolve_embeds(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ResolvedEmbed>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let content = Vault::read_file(&vault_path, &path)
+            .map_err(|e| SynapseError::from(e).with_path(&path))?;
+        embeds::resolve(&vault_path, &db, &path, &content).map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── OS search commands ───────────────────────────────────────────
+
+/// Turn OS search indexing on or off. When on, every note gets a metadata
+/// stub in `Documents/Synapse Search/<vault>` that Spotlight and Windows
+/// Search pick up, linking back via `synapse://`. Returns the stub count.
+#[tauri::command]
+pub async fn set_os_search_index(
+    enabled: bool,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<usize, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        if let Some(previous) = db
+            .get_setting(OS_SEARCH_FOLDER_SETTING)
+            .map_err(SynapseError::from)?
+        {
+            os_search::clear(Path::new(&previous)).map_err(SynapseError::from)?;
+        }
+        if !enabled {
+            db.delete_setting(OS_SEARCH_FOLDER_SETTING)
+                .map_err(SynapseError::from)?;
+            return Ok(0);
+        }
+
+        let vault_name = vault_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Vault".to_string());
+        let folder = app
+            .path()
+            .document_dir()
+            .map_err(SynapseError::from)?
+            .join("Synapse Search")
+            .join(vault_name);
+        db.set_setting(OS_SEARCH_FOLDER_SETTING, &folder.to_string_lossy())
+            .map_err(SynapseError::from)?;
+        rebuild_search_stubs(&vault_path, &db, &folder)
+    })
+    .await
+}
+
+/// Rewrite all OS search stubs, dropping those of deleted, renamed or
+/// newly private notes
+#[tauri::command]
+pub async fn rebuild_os_search_index(state: State<'_, AppState>) -> Result<usize, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let folder = db
+            .get_setting(OS_SEARCH_FOLDER_SETTING)
+            .map_err(SynapseError::from)?
+            .ok_or("OS search indexing is off")?;
+        rebuild_search_stubs(&vault_path, &db, Path::new(&folder))
+    })
+    .await
+}
+
+/// The `synapse://` link the app was launched with, if any (taken once)
+#[tauri::command]
+pub fn take_launch_link(launch: State<'_, LaunchLink>) -> Option<NoteLink> {
+    launch.0.lock_or_recover().take()
+}
+
+// ─── Settings commands ────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_setting(
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_setting(&key).map_err(SynapseError::from)).await
+}
+
+#[tauri::command]
+pub async fn set_setting(
+    key: String,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.set_setting(&key, &value).map_err(SynapseError::from)).await
+}
+
+// ─── Keybinding commands ──────────────────────────────────────────
+
+/// Every action's effective shortcut (defaults plus customisations)
+#[tauri::command]
+pub async fn get_keybindings(state: State<'_, AppState>) -> Result<Vec<Keybinding>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || keybindings::load(&db).map_err(SynapseError::from)).await
+}
+
+/// Rebind an action. `None` restores the default, an empty string unbinds
+/// it. Global shortcuts are re-registered with the OS straight away.
+#[tauri::command]
+pub async fn set_keybinding(
+    action: String,
+    shortcut: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        let global = keybindings::is_global(&action);
+        match shortcut.as_deref().map(str::trim) {
+            None => db.reset_keybinding(&action),
+            Some(shortcut) => {
+                #[cfg(desktop)]
+                if global && !shortcut.is_empty() {
+                    crate::hotkeys::parse(shortcut)?;
+                }
+                db.set_keybinding(&action, shortcut)
+            }
+        }
+        .map_err(SynapseError::from)?;
+        if global {
+            #[cfg(desktop)]
+            crate::hotkeys::apply(&app);
+        }
+        Ok(())
+    })
+    .await
+}
+
+// ─── Bookmark commands ────────────────────────────────────────────
+
+/// Add a bookmark (note, heading, block, folder, search or group)
+#[tauri::command]
+pub async fn add_bookmark(
+    bookmark: NewBookmark,
+    state: State<'_, AppState>,
+) -> Result<Bookmark, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        bookmark.validate().map_err(SynapseError::from)?;
+        db.add_bookmark(&bookmark).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Get all bookmarks (flat, ordered by parent then position)
+#[tauri::command]
+pub async fn get_bookmarks(state: State<'_, AppState>) -> Result<Vec<Bookmark>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.get_bookmarks().map_err(SynapseError::from)).await
+}
+
+/// Change a bookmark's display title
+#[tauri::command]
+pub async fn rename_bookmark(
+    id: i64,
+    title: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        db.rename_bookmark(id, title.as_deref())
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Reorder a bookmark or move it into another group
+#[tauri::command]
+pub async fn move_bookmark(
+    id: i64,
+    parent_id: Option<i64>,
+    index: usize,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        db.move_bookmark(id, parent_id, index)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Remove a bookmark (groups are removed with their contents)
+#[tauri::command]
+pub async fn delete_bookmark(id: i64, state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.delete_bookmark(id).map_err(SynapseError::from)).await
+}
+
+// ─── Workspace commands ───────────────────────────────────────────
+
+/// Save the current layout under a name
+#[tauri::command]
+pub async fn save_workspace(
+    name: String,
+    layout_json: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        let name = name.trim();
+        if name.is_empty() || name == LAST_SESSION_WORKSPACE {
+            return Err(SynapseError::invalid("Invalid workspace name"));
+        }
+        validate_layout_json(&layout_json)?;
+        db.save_workspace(name, &layout_json)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Load a named layout (None if it doesn't exist)
+#[tauri::command]
+pub async fn load_workspace(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.load_workspace(&name).map_err(SynapseError::from)).await
+}
+
+/// List all named workspaces
+#[tauri::command]
+pub async fn list_workspaces(
+    state: State<'_, AppState>,
+) -> Result<Vec<WorkspaceSummary>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.list_workspaces().map_err(SynapseError::from)).await
+}
+
+/// Delete a named workspace
+#[tauri::command]
+pub async fn delete_workspace(
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || db.delete_workspace(&name).map_err(SynapseError::from)).await
+}
+
+/// Persist the layout to restore next time this vault is opened
+#[tauri::command]
+pub async fn save_last_session(
+    layout_json: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        validate_layout_json(&layout_json)?;
+        db.save_workspace(LAST_SESSION_WORKSPACE, &layout_json)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Get the last-session layout (used on startup, when the vault is opened before the UI)
+#[tauri::command]
+pub async fn load_last_session(state: State<'_, AppState>) -> Result<Option<String>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        db.load_workspace(LAST_SESSION_WORKSPACE)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── Spellcheck commands ──────────────────────────────────────────
+
+/// Spellcheck markdown text against the hunspell dictionary for `lang` (e.g. "en_US")
+#[tauri::command]
+pub async fn check_text(
+    text: String,
+    lang: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<Misspelling>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let spellchecker = state.spellchecker.clone();
+    blocking(move || {
+        let mut checker = spellchecker.lock_or_recover();
+        checker
+            .check_text(&vault_path, &text, &lang)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Add a word to the vault's custom dictionary
+#[tauri::command]
+pub async fn add_to_dictionary(
+    word: String,
+    state: State<'_, AppState>,
+) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let spellchecker = state.spellchecker.clone();
+    blocking(move || {
+        let mut checker = spellchecker.lock_or_recover();
+        checker
+            .add_word(&vault_path, &word)
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+// ─── Import commands ──────────────────────────────────────────────
+
+/// Import the notes from the legacy `~/.synapse/notes` store into `folder`
+/// of the open vault (default `Imported`) and index them
+#[tauri::command]
+pub async fn import_legacy_notes(
+    folder: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<LegacyImport, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let legacy_dir = file_manager::notes_dir().map_err(SynapseError::from)?;
+        let folder = folder.unwrap_or_else(|| file_manager::DEFAULT_IMPORT_FOLDER.to_string());
+        let report = file_manager::import_into_vault(&legacy_dir, &vault_path, &folder)
+            .map_err(SynapseError::from)?;
+        for path in &report.imported {
+            db.reindex_note(&vault_path, path)
+                .map_err(SynapseError::from)?;
+        }
+        Ok(report)
+    })
+    .await
+}
+
+/// Import a Roam or Logseq JSON export (`source` is the file) or a Logseq
+/// graph (`source` is its folder) into `folder` (default `Imported`).
+/// Daily pages go to the daily notes folder.
+#[tauri::command]
+pub async fn import_outliner_graph(
+    source: String,
+    folder: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<OutlinerImport, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        let daily_folder = setting_or(&db, DAILY_FOLDER_SETTING, DEFAULT_DAILY_FOLDER)?;
+        let folder = folder.unwrap_or_else(|| file_manager::DEFAULT_IMPORT_FOLDER.to_string());
+        let source_path = PathBuf::from(&source);
+        let report = if source_path.is_dir() {
+            outliner::import_logseq_graph(&source_path, &vault_path, &folder, &daily_folder)
+        } else {
+            std::fs::read_to_string(&source_path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| outliner::import_json(&json, &vault_path, &folder, &daily_folder))
+        }
+        .map_err(|e| SynapseError::from(e).with_path(&source))?;
+        for path in &report.imported {
+            db.reindex_note(&vault_path, path)
+                .map_err(SynapseError::from)?;
+        }
+        Ok(report)
+    })
+    .await
+}
+
+// ─── Citation commands ────────────────────────────────────────────
+
+/// Read citations from the vault's `.bib` file at `path` (or stop when
+/// `None`). Returns the number of entries found.
+#[tauri::command]
+pub async fn set_bibliography(
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        match &path {
+            Some(path) if !path.ends_with(".bib") => {
+                return Err(SynapseError::invalid("Bibliographies must be .bib files"));
+            }
+            Some(path) => db.set_setting(citations::BIB_FILE_SETTING, path),
+            None => db.delete_setting(citations::BIB_FILE_SETTING),
+        }
+        .map_err(SynapseError::from)?;
+        citations::reindex_bibliography(&vault_path, &db).map_err(|e| {
+            let error = SynapseError::from(e);
+            match path {
+                Some(path) => error.with_path(path),
+                None => error,
+            }
+        })
+    })
+    .await
+}
+
+/// A bibliography entry with its literature note and the notes citing it
+#[tauri::command]
+pub async fn get_citation(
+    citekey: String,
+    state: State<'_, AppState>,
+) -> Result<Citation, SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        citations::get_citation(&db, &citekey)
+            .map_err(SynapseError::from)?
+            .ok_or_else(|| SynapseError::not_found(format!("No citation @{}", citekey)))
+    })
+    .await
+}
+
+/// The literature note for `citekey`, created from the bibliography entry
+/// (and the configured template) if there isn't one yet. Returns its path.
+#[tauri::command]
+pub async fn insert_literature_note(
+    citekey: String,
+    state: State<'_, AppState>,
+) -> Result<String, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        if let Some(path) = db
+            .find_literature_note(&citekey)
+            .map_err(SynapseError::from)?
+        {
+            return Ok(path);
+        }
+        let entry = db
+            .get_bib_entry(&citekey)
+            .map_err(SynapseError::from)?
+            .ok_or_else(|| SynapseError::not_found(format!("No citation @{}", citekey)))?;
+        let folder = setting_or(
+            &db,
+            citations::LITERATURE_FOLDER_SETTING,
+            citations::DEFAULT_LITERATURE_FOLDER,
+        )?;
+        let template = db
+            .get_setting(citations::LITERATURE_TEMPLATE_SETTING)
+            .map_err(SynapseError::from)?;
+        let path =
+            citations::create_literature_note(&vault_path, &entry, &folder, template.as_deref())
+                .map_err(SynapseError::from)?;
+
+        let _ = db.reindex_note(&vault_path, &path);
+        Ok(path)
+    })
+    .await
+}
+
+// ─── Git commands ─────────────────────────────────────────────────
+
+/// Put the vault under git (if it isn't already in a repository). Returns
+/// whether a new repository was created.
+#[cfg(feature = "git")]
+#[tauri::command]
+pub async fn git_init_vault(state: State<'_, AppState>) -> Result<bool, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || git::init_vault(&vault_path).map_err(SynapseError::from)).await
+}
+
+/// Commit every change in the vault. Returns `None` if there was nothing
+/// to commit.
+#[cfg(feature = "git")]
+#[tauri::command]
+pub async fn git_commit_all(
+    message: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<CommitInfo>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let message = message
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| "Update vault".to_string());
+    blocking(move || git::commit(&vault_path, None, &message).map_err(SynapseError::from)).await
+}
+
+/// Commits that changed a note, newest first (default 50)
+#[cfg(feature = "git")]
+#[tauri::command]
+pub async fn git_history_for_note(
+    path: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CommitInfo>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    blocking(move || {
+        git::history_for_note(&vault_path, &path, limit.unwrap_or(50))
+            .map_err(|e| SynapseError::from(e).with_path(path))
+    })
+    .await
+}
+
+// ─── Backup commands ──────────────────────────────────────────────
+
+/// Save `config` as the vault's backup destination (and schedule), then
+/// back up now. The secret access key is the `s3` credential.
+#[tauri::command]
+pub async fn backup_to_s3(
+    config: S3Config,
+    state: State<'_, AppState>,
+) -> Result<BackupSummary, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    let json = serde_json::to_string(&config).map_err(SynapseError::from)?;
+    blocking({
+        let db = db.clone();
+        move || {
+            db.set_setting(BACKUP_S3_SETTING, &json)
+                .map_err(SynapseError::from)
+        }
+    })
+    .await?;
+    run_backup(&vault_path, db, &config).await
+}
+
+/// The saved backup destination, if any
+#[tauri::command]
+pub async fn get_backup_config(
+    state: State<'_, AppState>,
+) -> Result<Option<S3Config>, SynapseError> {
+    let db = state.database()?;
+    blocking(move || backup::load_config(&db).map_err(SynapseError::from)).await
+}
+
+/// Backups in the saved destination, oldest first
+#[tauri::command]
+pub async fn list_remote_backups(
+    state: State<'_, AppState>,
+) -> Result<Vec<RemoteBackup>, SynapseError> {
+    let config = saved_backup_config(&state).await?;
+    backup::list_backups(&config)
+        .await
+        .map_err(SynapseError::from)
+}
+
+/// Download a backup into `destination`, an empty or new folder. Returns
+/// how many files were restored.
+#[tauri::command]
+pub async fn restore_backup(
+    id: String,
+    destination: String,
+    state: State<'_, AppState>,
+) -> Result<usize, SynapseError> {
+    let config = saved_backup_config(&state).await?;
+    backup::restore(&config, &id, Path::new(&destination))
+        .await
+        .map_err(SynapseError::from)
+}
+
+// ─── Log commands ─────────────────────────────────────────────────
+
+/// The open vault's most recent log entries at `level` (default `warn`) or
+/// more severe, oldest first
+#[tauri::command]
+pub async fn get_recent_logs(
+    level: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<LogEntry>, SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let min_level = match level {
+        Some(level) => logs::parse_level(&level)
+            .map_err(|e| SynapseError::invalid(e.to_string()))?
+            .to_level()
+            .ok_or_else(|| SynapseError::invalid("Log level must not be off"))?,
+        None => log::Level::Warn,
+    };
+    blocking(move || {
+        logs::recent(&vault_path, min_level, limit.unwrap_or(logs::DEFAULT_LIMIT))
+            .map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Set how much the open vault logs: `error`, `warn`, `info`, `debug`,
+/// `trace` or `off`
+#[tauri::command]
+pub async fn set_log_level(level: String, state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let db = state.database()?;
+    blocking(move || {
+        let filter = logs::parse_level(&level).map_err(|e| SynapseError::invalid(e.to_string()))?;
+        db.set_setting(logs::LOG_LEVEL_SETTING, &filter.to_string().to_lowercase())
+            .map_err(SynapseError::from)?;
+        log::set_max_level(filter);
+        Ok(())
+    })
+    .await
+}
+
+// ─── Reindex command ──────────────────────────────────────────────
+
+/// Force reindex the entire vault
+#[tauri::command]
+pub async fn reindex_vault(state: State<'_, AppState>) -> Result<(), SynapseError> {
+    let vault_path = get_vault(&state)?;
+    let db = state.database()?;
+    blocking(move || {
+        db.forget_file_states().map_err(SynapseError::from)?;
+        db.reindex_vault(&vault_path).map_err(SynapseError::from)
+    })
+    .await
+}
+
+/// Time indexing and common queries on a generated vault of `note_count`
+/// notes (default 1000). The open vault is not touched.
+#[tauri::command]
+pub async fn benchmark_index(note_count: Option<usize>) -> Result<BenchmarkReport, SynapseError> {
+    let note_count = note_count.unwrap_or(1000);
+    blocking(move || benchmark::run(note_count).map_err(SynapseError::from)).await
+}
+
+// ─── Helper types & functions ─────────────────────────────────────
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BacklinkResult {
+    pub source_path: String,
+    pub source_title: String,
+    pub context: String,
+}
+
+/// Result of `get_note_math`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NoteMath {
+    pub count: usize,
+    pub blocks: Vec<MathBlock>,
+    /// Macros defined anywhere in the note, in first-definition order
+    pub macros: Vec<String>,
+}
+
+/// A note in `export_graph` output
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GraphNode {
+    pub path: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GraphExport {
+    pub nodes: Vec<GraphNode>,
+    /// (source path, link target name)
+    pub edges: Vec<(String, String)>,
+}
+
+/// A note from `get_stale_notes`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StaleNote {
+    #[serde(flatten)]
+    pub note: CachedNote,
+    /// When the file last changed (unix seconds)
+    pub file_modified: i64,
+}
+
+/// Which notes `auto_archive` moves; a note has to meet every criterion given
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ArchiveRule {
+    /// Search query the notes must match (`tag:#done`, `path:Projects`)
+    pub filter: Option<String>,
+    /// Only notes whose file hasn't changed in this many days. This goes by
+    /// the file's modification time, which syncing, restoring a backup or
+    /// touching the file also resets.
+    pub untouched_days: Option<u32>,
+    /// List the notes without moving them
+    pub dry_run: bool,
+}
+
+/// A note archived (or, for a dry run, to be archived) by `auto_archive`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ArchivedNote {
+    pub from: String,
+    pub to: String,
+}
+
+/// Outcome of `auto_archive`
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ArchiveReport {
+    pub archived: Vec<ArchivedNote>,
+    pub failed: Vec<ArchiveFailure>,
+}
+
+/// A note `auto_archive` couldn't move, and why
+#[derive(Debug, serde::Serialize)]
+pub struct ArchiveFailure {
+    pub path: String,
+    pub error: SynapseError,
+}
+
+/// A 1-based inclusive range of lines in a note
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Options for `generate_toc`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TocOptions {
+    /// Shallowest heading level to include
+    pub min_level: u8,
+    /// Deepest heading level to include
+    pub max_level: u8,
+    /// Insert/update the TOC in the note (false = preview only)
+    pub write: bool,
+    /// `[[#Heading]]` wikilinks or `[Heading](#slug)` anchors
+    pub links: editing::TocLinkStyle,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        TocOptions {
+            min_level: 2,
+            max_level: 4,
+            write: true,
+            links: editing::TocLinkStyle::default(),
+        }
+    }
+}
+
+/// Get the vault path from state, or return error. Every vault command
+/// starts here, so it also resets the auto-lock idle timer.
+fn get_vault(state: &AppState) -> Result<PathBuf, SynapseError> {
+    let vault_path = state.vault_path.lock_or_recover().clone();
+    let vault_path = vault_path.ok_or(SynapseError::VaultNotOpen)?;
+    state.lock.lock_or_recover().last_activity = Instant::now();
+    Ok(vault_path)
+}
+
+/// The vault's lock passphrase hash. Older versions kept it in the cache's
+/// settings, where a cache rebuild lost it; it moves to the keychain the
+/// first time it's read.
+fn lock_hash(vault_path: &Path, db: &Database) -> Result<Option<String>, SynapseError> {
+    if let Some(hash) = db
+        .get_setting(LOCK_HASH_SETTING)
+        .map_err(SynapseError::from)?
+    {
+        crypto::set_lock_hash(vault_path, Some(&hash)).map_err(SynapseError::from)?;
+        db.delete_setting(LOCK_HASH_SETTING)
+            .map_err(SynapseError::from)?;
+        return Ok(Some(hash));
+    }
+    crypto::lock_hash(vault_path).map_err(SynapseError::from)
+}
+
+/// A random note among those matching `filter` (all notes when None)
+fn random_note(
+    vault_path: &Path,
+    db: &Database,
+    filter: Option<&str>,
+    include_private: Option<bool>,
+) -> Result<Option<CachedNote>, SynapseError> {
+    let notes = run_query(vault_path, db, filter.unwrap_or_default(), include_private)?;
+    if notes.is_empty() {
+        return Ok(None);
+    }
+    let pick = uuid::Uuid::new_v4().as_u128() % notes.len() as u128;
+    Ok(notes.into_iter().nth(pick as usize))
+}
+
+/// Those of `notes` whose file hasn't changed in `days` days, oldest first
+fn stale_notes(
+    vault_path: &Path,
+    notes: Vec<CachedNote>,
+    days: u32,
+) -> Result<Vec<StaleNote>, SynapseError> {
+    let modified: HashMap<String, i64> = Vault::list_entries(vault_path)
+        .map_err(SynapseError::from)?
+        .into_iter()
+        .map(|entry| (entry.path, entry.modified))
+        .collect();
+    let cutoff = chrono::Utc::now().timestamp() - i64::from(days) * 24 * 60 * 60;
+    let mut stale: Vec<StaleNote> = notes
+        .into_iter()
+        .filter_map(|note| {
+            let file_modified = *modified.get(&note.path)?;
+            (file_modified < cutoff).then_some(StaleNote {
+                note,
+                file_modified,
+            })
+        })
+        .collect();
+    stale.sort_by(|a, b| (a.file_modified, &a.note.path).cmp(&(b.file_modified, &b.note.path)));
+    Ok(stale)
+}
+
+/// Parse and run a search query, leaving out private folders unless asked
+fn run_query(
+    vault_path: &Path,
+    db: &Database,
+    query: &str,
+    include_private: Option<bool>,
+) -> Result<Vec<CachedNote>, SynapseError> {
+    let query = Query::parse(query).map_err(|e| SynapseError::invalid(e.to_string()))?;
+    let hidden = hidden_folders(vault_path, db, include_private)?;
+    let mut notes = query.execute(db).map_err(SynapseError::from)?;
+    notes.retain(|note| !is_private(&note.path, &hidden));
+    Ok(notes)
+}
+
+/// Private folders to hide, or none when the caller asks to include them
+fn hidden_folders(
+    vault_path: &Path,
+    db: &Database,
+    include_private: Option<bool>,
+) -> Result<Vec<String>, SynapseError> {
+    if include_private.unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+    let mut folders = Vault::private_marker_folders(vault_path).map_err(SynapseError::from)?;
+    if let Some(json) = db
+        .get_setting(PRIVATE_FOLDERS_SETTING)
+        .map_err(SynapseError::from)?
+    {
+        let configured: Vec<String> =
+            serde_json::from_str(&json).map_err(|e| format!("Invalid private folders: {}", e))?;
+        for folder in configured {
+            let folder = folder.trim_matches('/').to_string();
+            if !folder.is_empty() && !folders.contains(&folder) {
+                folders.push(folder);
+            }
+        }
+    }
+    Ok(folders)
+}
+
+fn is_private(path: &str, private_folders: &[String]) -> bool {
+    private_folders
+        .iter()
+        .any(|folder| path_in_folder(path, folder))
+}
+
+/// Drop links from private notes and links that name one
+fn without_private_links(
+    db: &Database,
+    mut links: Vec<(String, String)>,
+    private_folders: &[String],
+) -> Result<Vec<(String, String)>, SynapseError> {
+    if private_folders.is_empty() {
+        return Ok(links);
+    }
+    let mut private_names = HashSet::new();
+    for note in db.get_all_notes().map_err(SynapseError::from)? {
+        if is_private(&note.path, private_folders) {
+            let without_ext = note.path.strip_suffix(".md").unwrap_or(&note.path);
+            let stem = without_ext.rsplit('/').next().unwrap_or(without_ext);
+            private_names.insert(stem.to_lowercase());
+            private_names.insert(without_ext.to_lowercase());
+            private_names.insert(note.title.to_lowercase());
+        }
+    }
+    links.retain(|(source, target)| {
+        !is_private(source, private_folders) && !private_names.contains(&target.to_lowercase())
+    });
+    Ok(links)
+}
+
+fn rebuild_search_stubs(
+    vault_path: &Path,
+    db: &Database,
+    folder: &Path,
+) -> Result<usize, SynapseError> {
+    let redactor = Redactor::load(db).map_err(SynapseError::from)?;
+    let private_folders = hidden_folders(vault_path, db, None)?;
+    StubIndex {
+        folder,
+        vault_path,
+        redactor: &redactor,
+        private_folders: &private_folders,
+    }
+    .rebuild()
+    .map_err(SynapseError::from)
+}
+
+/// Refresh a saved note's OS search stub if OS search indexing is on
+fn update_search_stub(
+    vault_path: &Path,
+    db: &Database,
+    path: &str,
+    content: &str,
+) -> Result<(), SynapseError> {
+    let Some(folder) = db
+        .get_setting(OS_SEARCH_FOLDER_SETTING)
+        .map_err(SynapseError::from)?
+    else {
+        return Ok(());
+    };
+    let redactor = Redactor::load(db).map_err(SynapseError::from)?;
+    let private_folders = hidden_folders(vault_path, db, None)?;
+    StubIndex {
+        folder: Path::new(&folder),
+        vault_path,
+        redactor: &redactor,
+        private_folders: &private_folders,
+    }
+    .update(path, content)
+    .map_err(SynapseError::from)?;
+    Ok(())
+}
+
+/// Fill a cache that replaced a corrupt one, then tell the frontend to
+/// refresh. Runs on its own thread so launch isn't held up.
+pub fn reindex_recovered(app: &AppHandle, vault_path: PathBuf, db: Arc<Database>, backup: PathBuf) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = db.reindex_vault(&vault_path) {
+            log::warn!("Failed to reindex recovered cache: {}", e);
+        }
+        let _ = app.emit("cache-recovered", backup.to_string_lossy());
+    });
+}
+
+/// Back up the open vault if a backup schedule is set and the last backup
+/// is older than its interval. Polled from a background task.
+pub async fn run_scheduled_backup(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let (Ok(vault_path), Ok(db)) = (get_vault(&state), state.database()) else {
+        return;
+    };
+    let due = blocking({
+        let db = db.clone();
+        move || {
+            let config = backup::load_config(&db).map_err(SynapseError::from)?;
+            let last_run = db
+                .get_setting(BACKUP_LAST_RUN_SETTING)
+                .map_err(SynapseError::from)?;
+            Ok(config.filter(|c| backup::is_due(c, last_run.as_deref(), chrono::Utc::now())))
+        }
+    })
+    .await;
+    match due {
+        Ok(Some(config)) => {
+            if let Err(e) = run_backup(&vault_path, db, &config).await {
+                log::warn!("Scheduled backup failed: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to check the backup schedule: {}", e),
+    }
+}
+
+/// Back up and record when it finished
+async fn run_backup(
+    vault_path: &Path,
+    db: Arc<Database>,
+    config: &S3Config,
+) -> Result<BackupSummary, SynapseError> {
+    let summary = backup::backup(vault_path, config)
+        .await
+        .map_err(SynapseError::from)?;
+    blocking(move || {
+        db.set_setting(BACKUP_LAST_RUN_SETTING, &chrono::Utc::now().to_rfc3339())
+            .map_err(SynapseError::from)
+    })
+    .await?;
+    Ok(summary)
+}
+
+async fn saved_backup_config(state: &AppState) -> Result<S3Config, SynapseError> {
+    let db = state.database()?;
+    blocking(move || backup::load_config(&db).map_err(SynapseError::from))
+        .await?
+        .ok_or_else(|| SynapseError::invalid("Set up a backup destination first"))
+}
+
+/// Run blocking work (SQLite queries, vault scans) on the blocking thread
+/// pool, off the main thread and without holding up other commands
+async fn blocking<T, F>(work: F) -> Result<T, SynapseError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, SynapseError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(SynapseError::from)?
+}
+
+/// A setting's value, or `default` when unset
+fn setting_or(db: &Database, key: &str, default: &str) -> Result<String, SynapseError> {
+    Ok(db
+        .get_setting(key)
+        .map_err(SynapseError::from)?
+        .unwrap_or_else(|| default.to_string()))
+}
+
+/// Body of `merge_notes`. Links are rewritten before the source goes, so a
+/// failure part way leaves them pointing at a note that exists.
+fn merge_into(
+    vault_path: &Path,
+    db: &Database,
+    source: &str,
+    target: &str,
+    strategy: editing::MergeStrategy,
+    keep_source: bool,
+) -> Result<Vec<String>, SynapseError> {
+    if !source.ends_with(".md") || !target.ends_with(".md") {
+        return Err(SynapseError::invalid("Only markdown notes can be merged"));
+    }
+    if source == target {
+        return Err(SynapseError::invalid("Can't merge a note into itself"));
+    }
+    for path in [source, target] {
+        if !vault_path.join(path).is_file() {
+            return Err(SynapseError::not_found("Note not found").with_path(path));
+        }
+    }
+    let mut updated = Vec::new();
+    if !keep_source {
+        // The source's links to itself are rewritten too, before it is merged
+        let moves = vec![(source.to_string(), target.to_string())];
+        let (renames, referencing) = link_renames(db, &moves).map_err(SynapseError::from)?;
+        updated =
+            Vault::rewrite_links(vault_path, &referencing, &renames).map_err(SynapseError::from)?;
+        for path in &updated {
+            let _ = db.reindex_note(vault_path, path);
+        }
+    }
+
+    Vault::merge_notes(vault_path, source, target, strategy, keep_source)
+        .map_err(|e| SynapseError::from(e).with_path(source))?;
+    if !keep_source {
+        let _ = db.delete_note(source);
+    }
+    let _ = db.reindex_note(vault_path, target);
+    updated.retain(|path| path != source && path != target);
+    Ok(updated)
+}
+
+/// Save an edited kanban board and reindex it, returning the board as saved
+fn save_board(
+    vault_path: &Path,
+    path: &str,
+    content: &str,
+    db: Option<&Database>,
+) -> Result<KanbanBoard, SynapseError> {
+    Vault::write_file_atomic(vault_path, path, content).map_err(SynapseError::from)?;
+    if let Some(db) = db {
+        let _ = db.reindex_note(vault_path, path);
+    }
+    Ok(kanban::parse(content))
+}
+
+/// Replace a `.csv` file's records, keeping an existing file's line
+/// endings, and reindex it
+fn save_csv(
+    vault_path: &Path,
+    path: &str,
+    rows: Vec<Vec<String>>,
+    db: Option<&Database>,
+) -> Result<(), SynapseError> {
+    if !path.ends_with(".csv") {
+        return Err(SynapseError::invalid(
+            "CSV files must use the .csv extension",
+        ));
+    }
+    let content = match Vault::read_file(vault_path, path) {
+        Ok(content) => content,
+        Err(_) if !vault_path.join(path).exists() => String::new(),
+        Err(e) => return Err(SynapseError::from(e).with_path(path)),
+    };
+    // Only the line endings are kept, so a file that doesn't parse is
+    // replaced all the same
+    let mut table = CsvTable::parse(&content)
+        .or_else(|_| CsvTable::parse(""))
+        .map_err(SynapseError::from)?;
+    table.set_records(rows);
+    Vault::write_file_atomic(vault_path, path, table.to_csv()).map_err(SynapseError::from)?;
+    if let Some(db) = db {
+        let _ = db.reindex_note(vault_path, path);
+    }
+    Ok(())
+}
+
+/// Copy an outside file into a vault folder, encrypting it for the secure
+/// attachments folder. Returns the new vault path.
+fn store_attachment(
+    db: &Database,
+    vault_path: &Path,
+    folder: &str,
+    source: &Path,
+) -> Result<String, SynapseError> {
+    let file_name = source
+        .file_name()
+        .ok_or("Source has no file name")?
+        .to_string_lossy()
+        .to_string();
+    let mut data = std::fs::read(source).map_err(SynapseError::from)?;
+    let path = Vault::unique_file_path(vault_path, folder, &file_name);
+    if is_secure_attachment(db, &path)? {
+        let key = crypto::vault_file_key(vault_path).map_err(SynapseError::from)?;
+        data = crypto::encrypt_bytes(&key, &data).map_err(SynapseError::from)?;
+    }
+    Vault::write_file_atomic(vault_path, &path, data).map_err(SynapseError::from)?;
+    Ok(path)
+}
+
+/// Read a file's bytes, decrypting it if it was stored encrypted
+fn load_attachment(vault_path: &Path, path: &str) -> Result<Vec<u8>, SynapseError> {
+    let data = Vault::read_bytes(vault_path, path).map_err(SynapseError::from)?;
+    if !crypto::is_encrypted_bytes(&data) {
+        return Ok(data);
+    }
+    let key = crypto::vault_file_key(vault_path).map_err(SynapseError::from)?;
+    crypto::decrypt_bytes(&key, &data).map_err(SynapseError::from)
+}
+
+/// Whether a vault path is inside the secure attachments folder
+fn is_secure_attachment(db: &Database, path: &str) -> Result<bool, SynapseError> {
+    let folder = db
+        .get_setting(SECURE_ATTACHMENTS_SETTING)
+        .map_err(SynapseError::from)?;
+    Ok(folder.is_some_and(|folder| path_in_folder(path, &folder)))
+}
+
+/// Cached notes moved by renaming `old_path` (a file or a folder) to
+/// `new_path`, as `(old, new)` pairs
+fn note_moves(
+    db: &Database,
+    old_path: &str,
+    new_path: &str,
+) -> anyhow::Result<Vec<(String, String)>> {
+    if is_indexed_file(old_path) {
+        return Ok(vec![(old_path.to_string(), new_path.to_string())]);
+    }
+    let folder = format!(
+        "{}{}",
+        old_path.trim_end_matches(['/', '\\']),
+        std::path::MAIN_SEPARATOR
+    );
+    Ok(db
+        .get_all_notes()?
+        .into_iter()
+        .filter_map(|note| {
+            let rest = note.path.strip_prefix(&folder)?;
+            let moved = Path::new(new_path).join(rest).to_string_lossy().to_string();
+            Some((note.path, moved))
+        })
+        .collect())
+}
+
+/// Point links from other notes at the headings of `path` that a save
+/// renamed (`before` is what the cache had). Links within the note itself
+/// are left to the editor. Returns the notes that changed.
+fn propagate_heading_renames(
+    vault_path: &Path,
+    db: &Database,
+    path: &str,
+    before: &[indexer::Heading],
+) -> anyhow::Result<Vec<String>> {
+    let after = db.get_headings(path)?;
+    let renames = indexer::heading_renames(before, &after);
+    if renames.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut targets = HashSet::new();
+    let mut referencing = Vec::new();
+    for resolved in db.get_resolved_section_links()? {
+        let renamed = renames.iter().any(|(old, _)| {
+            indexer::find_heading(std::slice::from_ref(old), &resolved.link.heading).is_some()
+        });
+        if resolved.path == path && resolved.source != path && renamed {
+            targets.insert(resolved.link.target.trim().to_lowercase());
+            referencing.push(resolved.source);
+        }
+    }
+    referencing.dedup();
+    let updated = Vault::rewrite_section_links(vault_path, &referencing, &targets, &renames)?;
+    for note in &updated {
+        let _ = db.reindex_note(vault_path, note);
+    }
+    Ok(updated)
+}
+
+/// Body of `auto_archive`
+fn archive_matching(
+    vault_path: &Path,
+    db: &Database,
+    rule: &ArchiveRule,
+) -> Result<ArchiveReport, SynapseError> {
+    let filter = rule.filter.as_deref().unwrap_or_default();
+    if filter.trim().is_empty() && rule.untouched_days.is_none() {
+        return Err(SynapseError::invalid(
+            "An archive rule needs a filter or a number of days",
+        ));
+    }
+    let folder = setting_or(db, ARCHIVE_FOLDER_SETTING, DEFAULT_ARCHIVE_FOLDER)?;
+    let mut notes = run_query(vault_path, db, filter, None)?;
+    notes.retain(|note| !path_in_folder(&note.path, &folder));
+    if let Some(days) = rule.untouched_days {
+        let stale = stale_notes(vault_path, notes, days)?;
+        notes = stale.into_iter().map(|stale| stale.note).collect();
+    }
+
+    let mut report = ArchiveReport::default();
+    for note in notes {
+        let moved = Vault::archive_path(vault_path, &folder, &note.path)
+            .map_err(|e| SynapseError::invalid(e.to_string()).with_path(&note.path))
+            .and_then(|to| {
+                if !rule.dry_run {
+                    move_entry(vault_path, Some(db), &note.path, &to)?;
+                }
+                Ok(to)
+            });
+        match moved {
+            Ok(to) => report.archived.push(ArchivedNote {
+                from: note.path,
+                to,
+            }),
+            Err(error) => report.failed.push(ArchiveFailure {
+                path: note.path,
+                error,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// Move a file or folder, carry the moved notes' index state over and
+/// rewrite links to them. Returns the other notes whose links were rewritten.
+fn move_entry(
+    vault_path: &Path,
+    db: Option<&Database>,
+    old_path: &str,
+    new_path: &str,
+) -> Result<Vec<String>, SynapseError> {
+    // Work out which links point at the moved notes while the index still
+    // has them at their old paths
+    let moves = match db {
+        Some(db) => note_moves(db, old_path, new_path).map_err(SynapseError::from)?,
+        None => Vec::new(),
+    };
+    let (renames, referencing) = match db {
+        Some(db) => link_renames(db, &moves).map_err(SynapseError::from)?,
+        None => Default::default(),
+    };
+
+    Vault::rename_entry(vault_path, old_path, new_path)
+        .map_err(|e| SynapseError::from(e).with_path(old_path))?;
+
+    let Some(db) = db else {
+        return Ok(Vec::new());
+    };
+    // Update index: carry over user state, remove old, index new
+    for (from, to) in &moves {
+        let _ = db.move_note_state(from, to);
+        let _ = db.delete_note(from);
+        let _ = db.reindex_note(vault_path, to);
+    }
+
+    // A moved note linking to itself (or its folder mates) is edited at its new path
+    let referencing: Vec<String> = referencing
+        .into_iter()
+        .map(|path| {
+            moves
+                .iter()
+                .find(|(from, _)| *from == path)
+                .map_or(path, |(_, to)| to.clone())
+        })
+        .collect();
+    let updated =
+        Vault::rewrite_links(vault_path, &referencing, &renames).map_err(SynapseError::from)?;
+    for path in &updated {
+        let _ = db.reindex_note(vault_path, path);
+    }
+    Ok(updated)
+}
+
+/// Link targets to rewrite for `moves`, and the notes holding such links.
+/// A target is only rewritten if it resolved to the moved note, so `[[Plan]]`
+/// meaning some other `Plan.md` is left alone.
+fn link_renames(
+    db: &Database,
+    moves: &[(String, String)],
+) -> anyhow::Result<(HashMap<String, String>, Vec<String>)> {
+    let mut renames = HashMap::new();
+    for (from, to) in moves {
+        let (old_stem, new_stem) = (link_name(from, false), link_name(to, false));
+        let (old_full, new_full) = (link_name(from, true), link_name(to, true));
+        for (old, new) in [(old_stem, new_stem), (old_full, new_full)] {
+            if old != new && db.resolve_link_target(&old)?.as_deref() == Some(from.as_str()) {
+                renames.insert(old.to_lowercase(), new);
+            }
+        }
+    }
+    let mut referencing: Vec<String> = db
+        .get_all_links()?
+        .into_iter()
+        .filter(|(_, target)| {
+            let target = target.strip_suffix(".md").unwrap_or(target);
+            renames.contains_key(&target.to_lowercase())
+        })
+        .map(|(source, _)| source)
+        .collect();
+    referencing.sort();
+    referencing.dedup();
+    Ok((renames, referencing))
+}
+
+/// How a wikilink names a note: its file name, or (`full`) its vault path,
+/// both without `.md`
+fn link_name(path: &str, full: bool) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.strip_suffix(".md").unwrap_or(&path);
+    if full {
+        path.to_string()
+    } else {
+        path.rsplit('/').next().unwrap_or(path).to_string()
+    }
+}
+
+/// A frontmatter key from the frontend, trimmed; it has to fit on the
+/// `key:` line
+fn frontmatter_key(key: &str) -> Result<String, SynapseError> {
+    let key = key.trim();
+    if key.is_empty() || key.contains(['\n', '\r']) {
+        return Err(SynapseError::invalid(
+            "Frontmatter keys must be a single non-empty line",
+        ));
+    }
+    Ok(key.to_string())
+}
+
+/// A tag without its `#`: letters, digits, `-`, `_`, and `/` between levels
+fn is_tag_name(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.split('/').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Files the cache tracks: notes, canvases, drawings and CSV tables
+fn is_indexed_file(path: &str) -> bool {
+    [".md", ".canvas", ".excalidraw", ".csv"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+/// Reject layouts that aren't valid JSON so a bad save can't break restore
+fn validate_layout_json(layout_json: &str) -> Result<(), SynapseError> {
+    serde_json::from_str::<serde_json::Value>(layout_json)
+        .map(|_| ())
+        .map_err(|e| SynapseError::invalid(format!("Invalid layout JSON: {}", e)))
+}
+
+/// Format one note on disk, writing it atomically unless `dry_run`
+fn format_file(
+    vault_path: &std::path::Path,
+    path: &str,
+    rules: &FormatRules,
+    dry_run: bool,
+) -> anyhow::Result<FormatResult> {
+    let content = Vault::read_file(vault_path, path)?;
+    let formatted = formatter::format_markdown(&content, rules);
+    let changed = formatted != content;
+    if changed && !dry_run {
+        Vault::write_file_atomic(vault_path, path, &formatted)?;
+    }
+    Ok(FormatResult {
+        path: path.to_string(),
+        changed,
+        diff: formatter::unified_diff(path, &content, &formatted),
+    })
+}
+
+/// Find context around a wikilink in note content
+fn find_link_context(content: &str, target: &str) -> String {
+    let search_patterns = vec![
+        format!("[[{}]]", target),
+        format!("[[{}|", target),
+        format!("[[{}#", target),
+    ];
+
+    for line in content.lines() {
+        for pattern in &search_patterns {
+            if line.contains(pattern) {
                 // Return the whole line as context (trimmed)
                 let trimmed = line.trim();
                 if trimmed.len() > 200 {
@@ -388,3 +4277,403 @@ fn find_link_context(content: &str, target: &str) -> String {
 
     String::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{use_memory_keychain, TestVault};
+
+    fn protect(vault_path: &Path) {
+        let hash = crypto::hash_passphrase("hunter2").unwrap();
+        crypto::set_lock_hash(vault_path, Some(&hash)).unwrap();
+    }
+
+    #[test]
+    fn test_protected_vault_opens_locked_until_released() {
+        use_memory_keychain();
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n")]);
+        let db = vault.index();
+        protect(vault.path());
+        let state = AppState::default();
+
+        state.install_vault(vault.path().to_path_buf(), db);
+        assert!(state.is_locked());
+        assert!(matches!(state.database(), Err(SynapseError::VaultLocked)));
+        assert!(get_vault(&state).is_err());
+
+        state.release_lock().unwrap();
+        assert!(!state.is_locked());
+        assert_eq!(get_vault(&state).unwrap(), vault.path());
+        let db = state.database().unwrap();
+        assert!(db.get_note("Home.md").unwrap().is_some());
+        assert!(state.release_lock().is_err());
+
+        assert!(state.engage_lock());
+        assert!(matches!(state.database(), Err(SynapseError::VaultLocked)));
+        state.close_vault();
+        assert!(!state.is_locked());
+        assert!(matches!(state.database(), Err(SynapseError::VaultNotOpen)));
+        assert!(!state.engage_lock());
+    }
+
+    #[test]
+    fn test_auto_lock_waits_for_idle_protected_vault() {
+        use_memory_keychain();
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n")]);
+        let db = vault.index();
+        db.set_setting(AUTO_LOCK_SETTING, "1").unwrap();
+        let state = AppState::default();
+        state.install_vault(vault.path().to_path_buf(), db);
+        let go_idle = |state: &AppState| {
+            state.lock.lock_or_recover().last_activity = Instant::now() - Duration::from_secs(120);
+        };
+
+        // No passphrase, nothing to lock with
+        go_idle(&state);
+        assert!(!state.auto_lock_if_idle());
+        assert!(!state.is_locked());
+
+        protect(vault.path());
+        state.lock.lock_or_recover().last_activity = Instant::now();
+        assert!(!state.auto_lock_if_idle());
+        go_idle(&state);
+        assert!(state.auto_lock_if_idle());
+        assert!(state.is_locked());
+        // Already locked: nothing left to stash
+        assert!(!state.auto_lock_if_idle());
+
+        state.release_lock().unwrap();
+        assert!(!state.auto_lock_if_idle());
+        // Any vault command counts as activity
+        go_idle(&state);
+        get_vault(&state).unwrap();
+        assert!(!state.auto_lock_if_idle());
+    }
+
+    #[test]
+    fn test_lock_survives_a_cache_rebuild() {
+        use_memory_keychain();
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n")]);
+        let state = AppState::default();
+        // Older versions kept the hash in the cache
+        let db = vault.index();
+        let hash = crypto::hash_passphrase("hunter2").unwrap();
+        db.set_setting(LOCK_HASH_SETTING, &hash).unwrap();
+
+        let db = state.install_vault(vault.path().to_path_buf(), db);
+        assert!(state.is_locked());
+        assert_eq!(db.get_setting(LOCK_HASH_SETTING).unwrap(), None);
+        assert_eq!(crypto::lock_hash(vault.path()).unwrap(), Some(hash));
+
+        drop(db);
+        state.close_vault();
+        let (db, backup) = Database::rebuild_for_vault(vault.path()).unwrap();
+        assert!(backup.is_some());
+        state.install_vault(vault.path().to_path_buf(), db);
+        assert!(state.is_locked());
+    }
+
+    #[test]
+    fn test_secure_attachments_are_encrypted_and_tamper_evident() {
+        use_memory_keychain();
+        let vault = TestVault::new();
+        let db = vault.index();
+        db.set_setting(SECURE_ATTACHMENTS_SETTING, "Secure")
+            .unwrap();
+        let outside = TestVault::with_notes(&[("passport.pdf", "%PDF-1.7 passport")]);
+        let source = outside.path().join("passport.pdf");
+
+        let sealed = store_attachment(&db, vault.path(), "Secure/IDs", &source).unwrap();
+        assert_eq!(sealed, "Secure/IDs/passport.pdf");
+        let on_disk = std::fs::read(vault.path().join(&sealed)).unwrap();
+        assert!(crypto::is_encrypted_bytes(&on_disk));
+        assert!(!String::from_utf8_lossy(&on_disk).contains("passport"));
+        assert_eq!(
+            load_attachment(vault.path(), &sealed).unwrap(),
+            b"%PDF-1.7 passport"
+        );
+
+        // Outside the secure folder files are copied as they are
+        let plain = store_attachment(&db, vault.path(), "Securities", &source).unwrap();
+        assert_eq!(
+            std::fs::read(vault.path().join(&plain)).unwrap(),
+            b"%PDF-1.7 passport"
+        );
+
+        let mut tampered = on_disk.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        std::fs::write(vault.path().join(&sealed), &tampered).unwrap();
+        assert!(load_attachment(vault.path(), &sealed).is_err());
+        std::fs::write(vault.path().join(&sealed), &on_disk[..on_disk.len() / 2]).unwrap();
+        assert!(load_attachment(vault.path(), &sealed).is_err());
+    }
+
+    #[test]
+    fn test_webview_has_no_fs_plugin_access() {
+        // Vault files go through commands, which keep paths inside the open
+        // vault and check the lock; the fs plugin isn't registered at all
+        let capability: serde_json::Value =
+            serde_json::from_str(include_str!("../capabilities/default.json")).unwrap();
+        let permissions = capability["permissions"].as_array().unwrap();
+        // Entries are identifiers, or objects with an identifier and a scope
+        let granted = permissions
+            .iter()
+            .filter_map(|p| p.as_str().or_else(|| p["identifier"].as_str()));
+        assert!(granted.clone().any(|id| id == "core:default"));
+        assert!(granted.clone().all(|id| !id.starts_with("fs:")));
+    }
+
+    #[test]
+    fn test_private_folders_hidden_from_search_links_and_drawings() {
+        let vault = TestVault::with_notes(&[
+            ("Home.md", "# Home\nLighthouse, see [[Diary]] [[Plans]]\n"),
+            ("Journal/Diary.md", "# Diary\nLighthouse [[Home]]\n"),
+            ("Journal/.private", ""),
+            ("Work/Plans.md", "# Plans\nLighthouse budget\n"),
+            (
+                "Work/Map.excalidraw",
+                r#"{"elements":[{"id":"t","type":"text","text":"Lighthouse"}]}"#,
+            ),
+        ]);
+        let db = vault.index();
+        db.set_setting(PRIVATE_FOLDERS_SETTING, r#"["Work/"]"#)
+            .unwrap();
+
+        let hidden = hidden_folders(vault.path(), &db, None).unwrap();
+        assert_eq!(hidden, vec!["Journal", "Work"]);
+        assert!(hidden_folders(vault.path(), &db, Some(true))
+            .unwrap()
+            .is_empty());
+        assert!(is_private("Work/Plans.md", &hidden));
+        assert!(!is_private("Workshop.md", &hidden));
+
+        let mut found = db.search_fulltext("lighthouse", 10).unwrap();
+        assert_eq!(found.len(), 3);
+        found.retain(|m| !is_private(&m.path, &hidden));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, "Home.md");
+
+        let mut drawings = db.search_drawing_text("lighthouse").unwrap();
+        assert_eq!(drawings.len(), 1);
+        drawings.retain(|m| !is_private(&m.path, &hidden));
+        assert!(drawings.is_empty());
+
+        let links = db.get_all_links().unwrap();
+        assert_eq!(links.len(), 3);
+        assert!(without_private_links(&db, links.clone(), &hidden)
+            .unwrap()
+            .is_empty());
+        assert_eq!(without_private_links(&db, links, &[]).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_daily_note_created_once_in_the_daily_folder() {
+        use_memory_keychain();
+        let vault = TestVault::new();
+        let state = AppState::default();
+        assert!(matches!(
+            daily_note(&state),
+            Err(SynapseError::VaultNotOpen)
+        ));
+
+        let db = state.install_vault(vault.path().to_path_buf(), vault.index());
+        db.set_setting(DAILY_FOLDER_SETTING, "Journal/Daily")
+            .unwrap();
+        let path = daily_note(&state).unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(path, format!("Journal/Daily/{}.md", today));
+        assert!(db.get_note(&path).unwrap().is_some());
+
+        // An existing note is opened, not replaced
+        vault.write(&path, "# Today\nkept\n");
+        assert_eq!(daily_note(&state).unwrap(), path);
+        assert_eq!(
+            Vault::read_file(vault.path(), &path).unwrap(),
+            "# Today\nkept\n"
+        );
+    }
+
+    #[test]
+    fn test_blocking_runs_work_off_the_calling_thread() {
+        use std::sync::{Arc, Barrier};
+        let caller = std::thread::current().id();
+        let worker =
+            tauri::async_runtime::block_on(blocking(|| Ok(std::thread::current().id()))).unwrap();
+        assert_ne!(worker, caller);
+
+        // Two commands run side by side: each waits for the other
+        let barrier = Arc::new(Barrier::new(2));
+        let tasks: Vec<_> = (0..2)
+            .map(|_| {
+                let barrier = barrier.clone();
+                tauri::async_runtime::spawn(blocking(move || {
+                    barrier.wait();
+                    Ok(())
+                }))
+            })
+            .collect();
+        for task in tasks {
+            tauri::async_runtime::block_on(task).unwrap().unwrap();
+        }
+
+        let err = tauri::async_runtime::block_on(blocking(|| -> Result<(), SynapseError> {
+            Err(SynapseError::not_found("gone"))
+        }));
+        assert!(matches!(err, Err(SynapseError::NotFound { .. })));
+        let panicked = tauri::async_runtime::block_on(blocking(|| -> Result<(), SynapseError> {
+            panic!("worker panicked")
+        }));
+        assert!(matches!(panicked, Err(SynapseError::Internal { .. })));
+    }
+
+    #[test]
+    fn test_random_and_stale_notes_respect_the_filter() {
+        let vault = TestVault::with_notes(&[
+            ("Old.md", "# Old\n#idea\n"),
+            ("Fresh.md", "# Fresh\n#idea\n"),
+            ("Ancient.md", "# Ancient\n"),
+        ]);
+        let days_ago = |path: &str, days: u64| {
+            let file = std::fs::File::options()
+                .write(true)
+                .open(vault.path().join(path))
+                .unwrap();
+            let when = std::time::SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+            file.set_modified(when).unwrap();
+        };
+        days_ago("Old.md", 40);
+        days_ago("Ancient.md", 100);
+        let db = vault.index();
+
+        for _ in 0..10 {
+            let note = random_note(vault.path(), &db, Some("tag:#idea"), None).unwrap();
+            assert!(["Old.md", "Fresh.md"].contains(&note.unwrap().path.as_str()));
+        }
+        assert!(random_note(vault.path(), &db, Some("tag:#none"), None)
+            .unwrap()
+            .is_none());
+
+        let paths = |stale: Vec<StaleNote>| -> Vec<String> {
+            stale.into_iter().map(|s| s.note.path).collect()
+        };
+        let all = run_query(vault.path(), &db, "", None).unwrap();
+        let stale = stale_notes(vault.path(), all, 30).unwrap();
+        assert_eq!(paths(stale), vec!["Ancient.md", "Old.md"]);
+        let ideas = run_query(vault.path(), &db, "tag:#idea", None).unwrap();
+        let stale = stale_notes(vault.path(), ideas, 30).unwrap();
+        assert_eq!(paths(stale), vec!["Old.md"]);
+    }
+
+    #[test]
+    fn test_save_csv_replaces_rows_and_keeps_line_endings() {
+        let vault = TestVault::with_notes(&[
+            ("Parts.csv", "name,size\r\nbolt,3\r\n"),
+            ("Broken.csv", "a,\"unterminated\n"),
+        ]);
+        let db = vault.index();
+        let rows = |rows: &[&[&str]]| -> Vec<Vec<String>> {
+            rows.iter()
+                .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+                .collect()
+        };
+        let read = |path: &str| Vault::read_file(vault.path(), path).unwrap();
+
+        let parts = rows(&[&["name", "size"], &["nut", "1"], &["washer"]]);
+        save_csv(vault.path(), "Parts.csv", parts, Some(&db)).unwrap();
+        assert_eq!(read("Parts.csv"), "name,size\r\nnut,1\r\nwasher,\r\n");
+        let summaries = db.get_csv_summaries().unwrap();
+        let summary = summaries.iter().find(|s| s.path == "Parts.csv").unwrap();
+        assert_eq!(summary.row_count, 2);
+
+        let new = rows(&[&["x"], &["1"]]);
+        save_csv(vault.path(), "Data/New.csv", new.clone(), None).unwrap();
+        assert_eq!(read("Data/New.csv"), "x\n1\n");
+        save_csv(vault.path(), "Broken.csv", new.clone(), None).unwrap();
+        assert_eq!(read("Broken.csv"), "x\n1\n");
+        assert!(save_csv(vault.path(), "Notes.md", new, None).is_err());
+        assert!(!vault.path().join("Notes.md").exists());
+    }
+
+    #[test]
+    fn test_merge_rewrites_links_then_removes_or_keeps_the_source() {
+        let vault = TestVault::with_notes(&[
+            ("Target.md", "# Target\n\nbody\n"),
+            ("Source.md", "# Source\n\nmore, see [[Source#More]]\n"),
+            ("Other.md", "See [[Source]] and [[Extra]].\n"),
+            ("Extra.md", "# Extra\n\nextra\n"),
+        ]);
+        let db = vault.index();
+        let read = |path: &str| Vault::read_file(vault.path(), path).unwrap();
+        let append = editing::MergeStrategy::Append;
+
+        let updated = merge_into(vault.path(), &db, "Source.md", "Target.md", append, false);
+        assert_eq!(updated.unwrap(), vec!["Other.md"]);
+        assert!(!vault.path().join("Source.md").exists());
+        assert_eq!(read("Other.md"), "See [[Target]] and [[Extra]].\n");
+        assert!(read("Target.md").ends_with("more, see [[Target#More]]\n"));
+        let backlinks = db.get_backlinks("Target").unwrap();
+        assert!(backlinks.contains(&"Other.md".to_string()));
+        let notes = db.get_all_notes().unwrap();
+        assert!(notes.iter().all(|n| n.path != "Source.md"));
+
+        let updated = merge_into(vault.path(), &db, "Extra.md", "Target.md", append, true);
+        assert!(updated.unwrap().is_empty());
+        assert_eq!(read("Extra.md"), "# Extra\n\nextra\n");
+        assert_eq!(read("Other.md"), "See [[Target]] and [[Extra]].\n");
+        assert!(read("Target.md").ends_with("# Extra\n\nextra\n"));
+
+        // Nothing is rewritten towards a target that isn't there
+        let missing = merge_into(vault.path(), &db, "Extra.md", "Gone.md", append, false);
+        assert!(matches!(missing, Err(SynapseError::NotFound { .. })));
+        assert_eq!(read("Other.md"), "See [[Target]] and [[Extra]].\n");
+    }
+
+    #[test]
+    fn test_auto_archive_moves_what_it_can_and_reports_the_rest() {
+        let vault = TestVault::with_notes(&[
+            ("Done.md", "# Done\n#done\n"),
+            ("Projects/Plan.md", "# Plan\n#done\n"),
+            ("Keep.md", "# Keep\nSee [[Old]].\n"),
+            ("Old.md", "# Old\n"),
+            // A file where Plan.md's archive folder should go
+            ("Archive/Projects", "not a folder"),
+        ]);
+        let old = std::fs::File::options()
+            .write(true)
+            .open(vault.path().join("Old.md"))
+            .unwrap();
+        old.set_modified(std::time::SystemTime::now() - Duration::from_secs(100 * 24 * 60 * 60))
+            .unwrap();
+        let db = vault.index();
+        let rule = |filter: Option<&str>, untouched_days, dry_run| ArchiveRule {
+            filter: filter.map(str::to_string),
+            untouched_days,
+            dry_run,
+        };
+        let moved = |report: &ArchiveReport| -> Vec<(String, String)> {
+            let notes = report.archived.iter();
+            notes.map(|n| (n.from.clone(), n.to.clone())).collect()
+        };
+
+        assert!(archive_matching(vault.path(), &db, &rule(None, None, false)).is_err());
+
+        let report = archive_matching(vault.path(), &db, &rule(Some("tag:#done"), None, true));
+        assert_eq!(report.unwrap().archived.len(), 2);
+        assert!(vault.path().join("Done.md").exists());
+
+        let report = archive_matching(vault.path(), &db, &rule(Some("tag:#done"), None, false));
+        let report = report.unwrap();
+        let done = ("Done.md".to_string(), "Archive/Done.md".to_string());
+        assert_eq!(moved(&report), vec![done]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, "Projects/Plan.md");
+        assert!(vault.path().join("Archive/Done.md").exists());
+        assert!(vault.path().join("Projects/Plan.md").exists());
+
+        let report = archive_matching(vault.path(), &db, &rule(None, Some(30), false)).unwrap();
+        let old = ("Old.md".to_string(), "Archive/Old.md".to_string());
+        assert_eq!(moved(&report), vec![old]);
+        let keep = Vault::read_file(vault.path(), "Keep.md").unwrap();
+        assert_eq!(keep, "# Keep\nSee [[Archive/Old]].\n");
+    }
+}
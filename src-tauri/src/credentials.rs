@@ -0,0 +1,119 @@
+use anyhow::{bail, Context, Result};
+use keyring::Entry;
+
+/// Keychain service all Synapse credentials are stored under. Each
+/// integration is a separate account within it.
+const KEYCHAIN_SERVICE: &str = "com.synapse.app";
+
+/// Names the app keeps for itself. Overwriting or deleting a vault's file key
+/// would make its secure attachments unreadable, and its lock hash would let
+/// the webview unlock it, so neither is writable from there.
+const RESERVED_PREFIXES: &[&str] = &[
+    crate::crypto::FILE_KEY_CREDENTIAL,
+    crate::crypto::LOCK_HASH_CREDENTIAL,
+];
+
+/// Store a secret for an integration (`webdav`, `s3`, `readwise`, ...) in the
+/// OS keychain, replacing any previous value
+pub fn set_credential(service: &str, value: &str) -> Result<()> {
+    if value.is_empty() {
+        bail!("Credential must not be empty");
+    }
+    entry(service)?
+        .set_password(value)
+        .with_context(|| format!("Failed to store credential for '{}'", service))
+}
+
+/// `set_credential` for the webview: names the app keeps for itself are refused
+pub fn set_user_credential(service: &str, value: &str) -> Result<()> {
+    check_not_reserved(service)?;
+    set_credential(service, value)
+}
+
+/// `delete_credential` for the webview: names the app keeps for itself are
+/// refused
+pub fn delete_user_credential(service: &str) -> Result<()> {
+    check_not_reserved(service)?;
+    delete_credential(service)
+}
+
+/// Look up an integration's secret. `None` if none is stored.
+pub fn get_credential(service: &str) -> Result<Option<String>> {
+    match entry(service)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read credential for '{}'", service)),
+    }
+}
+
+/// Remove an integration's secret (no-op if none is stored)
+pub fn delete_credential(service: &str) -> Result<()> {
+    match entry(service)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to delete credential for '{}'", service)),
+    }
+}
+
+fn check_not_reserved(service: &str) -> Result<()> {
+    if RESERVED_PREFIXES
+        .iter()
+        .any(|prefix| service.starts_with(prefix))
+    {
+        bail!("'{}' is reserved for Synapse", service);
+    }
+    Ok(())
+}
+
+fn entry(service: &str) -> Result<Entry> {
+    let valid = !service.is_empty()
+        && service
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        bail!("Invalid credential name '{}'", service);
+    }
+    Entry::new(KEYCHAIN_SERVICE, service).context("Failed to open the OS keychain")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::use_memory_keychain;
+
+    #[test]
+    fn test_credentials_round_trip_and_reject_bad_input() {
+        use_memory_keychain();
+        assert_eq!(get_credential("test-readwise").unwrap(), None);
+
+        set_credential("test-readwise", "token-1").unwrap();
+        set_credential("test-readwise", "token-2").unwrap();
+        assert_eq!(
+            get_credential("test-readwise").unwrap().as_deref(),
+            Some("token-2")
+        );
+        delete_credential("test-readwise").unwrap();
+        delete_credential("test-readwise").unwrap();
+        assert_eq!(get_credential("test-readwise").unwrap(), None);
+
+        assert!(set_credential("test-readwise", "").is_err());
+        for name in ["", "s3 key", "../webdav", "ai/openai"] {
+            assert!(set_credential(name, "secret").is_err(), "{:?}", name);
+            assert!(get_credential(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_webview_cannot_touch_reserved_credentials() {
+        use_memory_keychain();
+        let name = format!("{}-0123456789abcdef", crate::crypto::FILE_KEY_CREDENTIAL);
+        set_credential(&name, "file-key").unwrap();
+
+        assert!(set_user_credential(&name, "replaced").is_err());
+        assert!(delete_user_credential(&name).is_err());
+        assert_eq!(get_credential(&name).unwrap().as_deref(), Some("file-key"));
+
+        set_user_credential("test-webdav", "secret").unwrap();
+        delete_user_credential("test-webdav").unwrap();
+        delete_credential(&name).unwrap();
+    }
+}
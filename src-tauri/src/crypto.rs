@@ -0,0 +1,685 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::credentials;
+use crate::sync::LockExt;
+use crate::vault::Vault;
+
+pub const ARMOR_BEGIN: &str = "-----BEGIN SYNAPSE ENCRYPTED-----";
+pub const ARMOR_END: &str = "-----END SYNAPSE ENCRYPTED-----";
+/// Markers around a section to encrypt in place
+pub const SECTION_START: &str = "<!-- encrypt -->";
+pub const SECTION_END: &str = "<!-- /encrypt -->";
+
+/// Header of an encrypted binary file
+const BINARY_MAGIC: &[u8] = b"SYNAPSE-ENC1\n";
+/// Keychain entry prefix for per-vault file keys
+pub const FILE_KEY_CREDENTIAL: &str = "vault-file-key";
+/// Keychain entry prefix for per-vault lock passphrase hashes
+pub const LOCK_HASH_CREDENTIAL: &str = "vault-lock-hash";
+/// Vault-root file with the salt and check value of an encrypted vault. It
+/// syncs with the notes so every device can unlock them.
+pub const VAULT_KEY_FILE: &str = ".synapse-encryption.json";
+/// Encrypted into the key file to tell a right passphrase from a wrong one
+const KEY_CHECK: &[u8] = b"synapse vault key";
+/// Files an encrypted vault stores encrypted: the ones the indexer reads
+const SEALED_EXTENSIONS: &[&str] = &["md", "canvas", "csv", "excalidraw"];
+
+const FORMAT_VERSION: &str = "v1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const ARMOR_WIDTH: usize = 64;
+
+/// Keys of the encrypted vaults unlocked in this session
+static UNLOCKED: Mutex<Vec<UnlockedVault>> = Mutex::new(Vec::new());
+
+struct UnlockedVault {
+    path: PathBuf,
+    key: [u8; 32],
+    index: IndexScope,
+}
+
+/// How much of an encrypted vault's notes the cache may hold in plaintext.
+/// Frontmatter only unless the user asks for more, so note bodies don't sit
+/// unencrypted in `.synapse/cache.db`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexScope {
+    /// Everything, as in an unencrypted vault; note bodies are stored in
+    /// plaintext in the cache
+    Full,
+    /// Frontmatter only (title, tags, aliases and properties); no body
+    /// text, links or tasks
+    #[default]
+    Metadata,
+    /// Nothing but file names
+    Names,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultEncryption {
+    pub enabled: bool,
+    /// The key is in memory, so notes can be read and written
+    pub unlocked: bool,
+    pub index: IndexScope,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultKeyFile {
+    salt: String,
+    /// `KEY_CHECK` encrypted with the derived key
+    check: String,
+    #[serde(default)]
+    index: IndexScope,
+}
+
+/// What an armored block replaced: the whole note or a marked section
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockKind {
+    Note,
+    Section,
+}
+
+impl BlockKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            BlockKind::Note => "note",
+            BlockKind::Section => "section",
+        }
+    }
+}
+
+/// Encrypt a note. If it contains `<!-- encrypt -->` ... `<!-- /encrypt -->`
+/// sections only those are encrypted; otherwise the whole note is.
+/// Returns the new content and how many blocks were written.
+pub fn encrypt_note(content: &str, passphrase: &str) -> Result<(String, usize)> {
+    if passphrase.is_empty() {
+        bail!("Passphrase must not be empty");
+    }
+    let sections = marked_sections(content)?;
+    if sections.is_empty() {
+        if content.trim_start().starts_with(ARMOR_BEGIN) {
+            bail!("Note is already encrypted");
+        }
+        return Ok((armor(content, passphrase, BlockKind::Note)?, 1));
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for section in &sections {
+        let (start, end) = section.outer;
+        let (inner_start, inner_end) = section.inner;
+        out.push_str(&content[last..start]);
+        out.push_str(&armor(
+            &content[inner_start..inner_end],
+            passphrase,
+            BlockKind::Section,
+        )?);
+        last = end;
+    }
+    out.push_str(&content[last..]);
+    Ok((out, sections.len()))
+}
+
+/// Decrypt every armored block in a note. Sections come back wrapped in
+/// their markers so they can be encrypted again. Returns the new content and
+/// how many blocks were decrypted.
+pub fn decrypt_note(content: &str, passphrase: &str) -> Result<(String, usize)> {
+    let blocks = armored_blocks(content);
+    if blocks.is_empty() {
+        bail!("Note has no encrypted content");
+    }
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for &(start, end) in &blocks {
+        out.push_str(&content[last..start]);
+        let (kind, plaintext) = dearmor(&content[start..end], passphrase)?;
+        match kind {
+            BlockKind::Note => out.push_str(&plaintext),
+            BlockKind::Section => {
+                out.push_str(SECTION_START);
+                out.push('\n');
+                out.push_str(&plaintext);
+                out.push_str(SECTION_END);
+                if content[..end].ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+        }
+        last = end;
+    }
+    out.push_str(&content[last..]);
+    Ok((out, blocks.len()))
+}
+
+/// Whether a note holds any encrypted block
+pub fn is_encrypted(content: &str) -> bool {
+    content.contains(ARMOR_BEGIN)
+}
+
+/// The note with encrypted blocks blanked out, so ciphertext never reaches
+/// the index (line numbers are preserved)
+pub fn strip_encrypted(content: &str) -> Cow<'_, str> {
+    let blocks = armored_blocks(content);
+    if blocks.is_empty() {
+        return Cow::Borrowed(content);
+    }
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for (start, end) in blocks {
+        out.push_str(&content[last..start]);
+        out.push_str(&"\n".repeat(content[start..end].matches('\n').count()));
+        last = end;
+    }
+    out.push_str(&content[last..]);
+    Cow::Owned(out)
+}
+
+/// Derive a 256-bit key from a passphrase with Argon2id
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Argon2id hash of a passphrase in PHC string form, for storing
+pub fn hash_passphrase(passphrase: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(&random_bytes::<SALT_LEN>())
+        .map_err(|e| anyhow!("Failed to encode salt: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Failed to hash passphrase: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Check a passphrase against a hash from `hash_passphrase`
+pub fn verify_passphrase(passphrase: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(passphrase.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// Encrypt a binary file with a raw key. Output is the magic header, nonce
+/// and ciphertext.
+pub fn encrypt_bytes(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, data)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    let mut out = Vec::with_capacity(BINARY_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(BINARY_MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decrypt a file written by `encrypt_bytes`
+pub fn decrypt_bytes(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>> {
+    let rest = data
+        .strip_prefix(BINARY_MAGIC)
+        .context("File is not encrypted")?;
+    if rest.len() < NONCE_LEN {
+        bail!("Encrypted file is truncated");
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Wrong key or corrupted encrypted file"))
+}
+
+pub fn is_encrypted_bytes(data: &[u8]) -> bool {
+    data.starts_with(BINARY_MAGIC)
+}
+
+/// The vault's key for encrypted files, kept in the OS keychain and created
+/// on first use. Losing the keychain entry makes those files unreadable.
+pub fn vault_file_key(vault_path: &Path) -> Result<[u8; 32]> {
+    let name = vault_credential(FILE_KEY_CREDENTIAL, vault_path);
+    if let Some(encoded) = credentials::get_credential(&name)? {
+        let bytes = BASE64
+            .decode(encoded)
+            .context("Stored file key is corrupted")?;
+        return bytes
+            .try_into()
+            .map_err(|_| anyhow!("Stored file key has the wrong length"));
+    }
+    let key = random_bytes::<32>();
+    credentials::set_credential(&name, &BASE64.encode(key))?;
+    Ok(key)
+}
+
+/// The vault's lock passphrase hash, if it has one. It is kept in the OS
+/// keychain rather than the cache, so rebuilding or deleting the cache
+/// can't drop the lock.
+pub fn lock_hash(vault_path: &Path) -> Result<Option<String>> {
+    credentials::get_credential(&vault_credential(LOCK_HASH_CREDENTIAL, vault_path))
+}
+
+/// Set or (with `None`) remove the vault's lock passphrase hash
+pub fn set_lock_hash(vault_path: &Path, hash: Option<&str>) -> Result<()> {
+    let name = vault_credential(LOCK_HASH_CREDENTIAL, vault_path);
+    match hash {
+        Some(hash) => credentials::set_credential(&name, hash),
+        None => credentials::delete_credential(&name),
+    }
+}
+
+/// Keychain name of one of the vault's own secrets
+fn vault_credential(prefix: &str, vault_path: &Path) -> String {
+    let vault_id = crate::indexer::content_hash(&vault_path.to_string_lossy());
+    format!("{}-{}", prefix, &vault_id[..16])
+}
+
+/// Random bytes from the OS generator
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Whether the vault is encrypted and whether it is unlocked
+pub fn vault_encryption(vault_path: &Path) -> Result<VaultEncryption> {
+    let Some(key_file) = read_key_file(vault_path)? else {
+        return Ok(VaultEncryption {
+            enabled: false,
+            unlocked: false,
+            index: IndexScope::Full,
+        });
+    };
+    Ok(VaultEncryption {
+        enabled: true,
+        unlocked: unlocked_key(vault_path).is_some(),
+        index: key_file.index,
+    })
+}
+
+/// Turn on vault encryption and encrypt every note still in plaintext,
+/// leaving the vault unlocked. On an already encrypted vault the passphrase
+/// must match; files left in plaintext by an interrupted run are finished
+/// and `index` replaces the index scope. Returns how many files were
+/// encrypted.
+pub fn encrypt_vault(vault_path: &Path, passphrase: &str, index: IndexScope) -> Result<usize> {
+    if passphrase.is_empty() {
+        bail!("Passphrase must not be empty");
+    }
+    let (salt, check, key) = match read_key_file(vault_path)? {
+        Some(key_file) => {
+            let key = check_passphrase(&key_file, passphrase)?;
+            (key_file.salt, key_file.check, key)
+        }
+        None => {
+            let salt = random_bytes::<SALT_LEN>();
+            let key = derive_key(passphrase, &salt)?;
+            let check = BASE64.encode(encrypt_bytes(&key, KEY_CHECK)?);
+            (BASE64.encode(salt), check, key)
+        }
+    };
+    write_key_file(vault_path, &VaultKeyFile { salt, check, index })?;
+    remember(vault_path, key, index);
+
+    let mut encrypted = 0;
+    for path in sealed_files(vault_path)? {
+        let data = fs::read(vault_path.join(&path))
+            .with_context(|| format!("Failed to read file: {}", path))?;
+        if !is_encrypted_bytes(&data) {
+            Vault::write_file_atomic(vault_path, &path, &data)?;
+            encrypted += 1;
+        }
+    }
+    Ok(encrypted)
+}
+
+/// Decrypt every note and turn vault encryption off. Returns how many
+/// files were decrypted.
+pub fn decrypt_vault(vault_path: &Path, passphrase: &str) -> Result<usize> {
+    let key_file = read_key_file(vault_path)?.context("The vault is not encrypted")?;
+    let key = check_passphrase(&key_file, passphrase)?;
+    let mut decrypted = 0;
+    for path in sealed_files(vault_path)? {
+        let data = fs::read(vault_path.join(&path))
+            .with_context(|| format!("Failed to read file: {}", path))?;
+        if is_encrypted_bytes(&data) {
+            let plaintext = decrypt_bytes(&key, &data)
+                .with_context(|| format!("Failed to decrypt {}", path))?;
+            Vault::write_bytes_atomic(vault_path, &path, plaintext)?;
+            decrypted += 1;
+        }
+    }
+    // Only once nothing depends on it, so an interrupted run can be resumed
+    fs::remove_file(vault_path.join(VAULT_KEY_FILE))
+        .context("Failed to remove the vault key file")?;
+    lock_vault(vault_path);
+    Ok(decrypted)
+}
+
+/// Derive the vault key from the passphrase and keep it in memory until
+/// `lock_vault`
+pub fn unlock_vault(vault_path: &Path, passphrase: &str) -> Result<()> {
+    let key_file = read_key_file(vault_path)?.context("The vault is not encrypted")?;
+    let key = check_passphrase(&key_file, passphrase)?;
+    remember(vault_path, key, key_file.index);
+    Ok(())
+}
+
+/// Forget the vault key; notes can't be read or saved until it is unlocked
+pub fn lock_vault(vault_path: &Path) {
+    UNLOCKED
+        .lock_or_recover()
+        .retain(|vault| vault.path != vault_path);
+}
+
+/// Change how much of an unlocked encrypted vault is indexed
+pub fn set_index_scope(vault_path: &Path, index: IndexScope) -> Result<()> {
+    let key_file = read_key_file(vault_path)?.context("The vault is not encrypted")?;
+    let key = unlocked_key(vault_path).context("The vault is locked")?;
+    write_key_file(vault_path, &VaultKeyFile { index, ..key_file })?;
+    remember(vault_path, key, index);
+    Ok(())
+}
+
+/// What the indexer may see of a file read from the vault: all of it
+/// unless the vault is encrypted with a narrower index scope
+pub fn index_view<'a>(vault_path: &Path, relative_path: &str, content: &'a str) -> &'a str {
+    let scope = UNLOCKED
+        .lock_or_recover()
+        .iter()
+        .find(|vault| vault.path == vault_path)
+        .map_or(IndexScope::Full, |vault| vault.index);
+    match scope {
+        IndexScope::Full => content,
+        IndexScope::Metadata
+            if relative_path.ends_with(".md") && !crate::excalidraw::is_drawing(relative_path) =>
+        {
+            crate::editing::split_frontmatter(content).0
+        }
+        _ => "",
+    }
+}
+
+/// Decrypt a file read from disk if the vault stores it encrypted
+pub fn open_file(vault_path: &Path, relative_path: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_sealed(relative_path) || !is_encrypted_bytes(&data) {
+        return Ok(data);
+    }
+    let key = unlocked_key(vault_path).context("The vault is locked")?;
+    decrypt_bytes(&key, &data).with_context(|| format!("Failed to decrypt {}", relative_path))
+}
+
+/// Encrypt a file about to be written if the vault is encrypted. Fails
+/// while an encrypted vault is locked rather than write plaintext.
+pub fn seal_file<'a>(
+    vault_path: &Path,
+    relative_path: &str,
+    data: &'a [u8],
+) -> Result<Cow<'a, [u8]>> {
+    if !is_sealed(relative_path) {
+        return Ok(Cow::Borrowed(data));
+    }
+    match unlocked_key(vault_path) {
+        Some(key) => Ok(Cow::Owned(encrypt_bytes(&key, data)?)),
+        None if vault_path.join(VAULT_KEY_FILE).exists() => bail!("The vault is locked"),
+        None => Ok(Cow::Borrowed(data)),
+    }
+}
+
+fn is_sealed(relative_path: &str) -> bool {
+    Path::new(relative_path)
+        .extension()
+        .is_some_and(|ext| SEALED_EXTENSIONS.iter().any(|sealed| ext == *sealed))
+}
+
+/// Every file an encrypted vault keeps encrypted
+fn sealed_files(vault_path: &Path) -> Result<Vec<String>> {
+    Ok(Vault::list_entries(vault_path)?
+        .into_iter()
+        .filter(|entry| !entry.is_dir && is_sealed(&entry.path))
+        .map(|entry| entry.path)
+        .collect())
+}
+
+fn unlocked_key(vault_path: &Path) -> Option<[u8; 32]> {
+    UNLOCKED
+        .lock_or_recover()
+        .iter()
+        .find(|vault| vault.path == vault_path)
+        .map(|vault| vault.key)
+}
+
+fn remember(vault_path: &Path, key: [u8; 32], index: IndexScope) {
+    let mut unlocked = UNLOCKED.lock_or_recover();
+    unlocked.retain(|vault| vault.path != vault_path);
+    unlocked.push(UnlockedVault {
+        path: vault_path.to_path_buf(),
+        key,
+        index,
+    });
+}
+
+fn read_key_file(vault_path: &Path) -> Result<Option<VaultKeyFile>> {
+    let path = vault_path.join(VAULT_KEY_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = fs::read_to_string(&path).context("Failed to read the vault key file")?;
+    let key_file = serde_json::from_str(&raw).context("The vault key file is corrupted")?;
+    Ok(Some(key_file))
+}
+
+fn write_key_file(vault_path: &Path, key_file: &VaultKeyFile) -> Result<()> {
+    let raw =
+        serde_json::to_string_pretty(key_file).context("Failed to serialize the vault key")?;
+    Vault::write_file_atomic(vault_path, VAULT_KEY_FILE, raw)
+}
+
+/// The vault key if `passphrase` is the one the vault was encrypted with
+fn check_passphrase(key_file: &VaultKeyFile, passphrase: &str) -> Result<[u8; 32]> {
+    let salt = BASE64
+        .decode(&key_file.salt)
+        .context("The vault key file is corrupted")?;
+    let check = BASE64
+        .decode(&key_file.check)
+        .context("The vault key file is corrupted")?;
+    let key = derive_key(passphrase, &salt)?;
+    match decrypt_bytes(&key, &check) {
+        Ok(plaintext) if plaintext == KEY_CHECK => Ok(key),
+        _ => bail!("Wrong passphrase"),
+    }
+}
+
+fn armor(plaintext: &str, passphrase: &str, kind: BlockKind) -> Result<String> {
+    let salt = random_bytes::<SALT_LEN>();
+    let key = derive_key(passphrase, &salt)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let mut payload = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+    let encoded = BASE64.encode(payload);
+
+    let mut out = format!("{}\n{} {}\n", ARMOR_BEGIN, FORMAT_VERSION, kind.as_str());
+    for chunk in encoded.as_bytes().chunks(ARMOR_WIDTH) {
+        // Base64 is ASCII, so chunks are valid UTF-8
+        out.push_str(std::str::from_utf8(chunk).expect("base64 is ASCII"));
+        out.push('\n');
+    }
+    out.push_str(ARMOR_END);
+    out.push('\n');
+    Ok(out)
+}
+
+fn dearmor(block: &str, passphrase: &str) -> Result<(BlockKind, String)> {
+    let mut lines = block.lines().map(str::trim).skip(1);
+    let header = lines.next().context("Encrypted block is truncated")?;
+    let kind = match header.split_once(' ') {
+        Some((FORMAT_VERSION, "note")) => BlockKind::Note,
+        Some((FORMAT_VERSION, "section")) => BlockKind::Section,
+        _ => bail!("Unsupported encrypted block format '{}'", header),
+    };
+    let encoded: String = lines.take_while(|l| *l != ARMOR_END).collect();
+    let payload = BASE64
+        .decode(encoded)
+        .context("Encrypted block is corrupted")?;
+    if payload.len() < SALT_LEN + NONCE_LEN {
+        bail!("Encrypted block is truncated");
+    }
+    let (salt, rest) = payload.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let key = derive_key(passphrase, salt)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Wrong passphrase or corrupted encrypted block"))?;
+    let plaintext = String::from_utf8(plaintext).context("Decrypted text is not UTF-8")?;
+    Ok((kind, plaintext))
+}
+
+/// Byte ranges of armored blocks, each including its END line and newline
+fn armored_blocks(content: &str) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut search = 0;
+    while let Some(pos) = content[search..].find(ARMOR_BEGIN) {
+        let start = search + pos;
+        let Some(end_pos) = content[start..].find(ARMOR_END) else {
+            break;
+        };
+        let mut end = start + end_pos + ARMOR_END.len();
+        if content[end..].starts_with('\n') {
+            end += 1;
+        }
+        blocks.push((start, end));
+        search = end;
+    }
+    blocks
+}
+
+/// A `<!-- encrypt -->` section. `outer` covers both markers and the newline
+/// after each; `inner` is the text between them.
+struct MarkedSection {
+    outer: (usize, usize),
+    inner: (usize, usize),
+}
+
+fn marked_sections(content: &str) -> Result<Vec<MarkedSection>> {
+    let after_marker = |at: usize, marker: &str| {
+        let end = at + marker.len();
+        if content[end..].starts_with('\n') {
+            end + 1
+        } else {
+            end
+        }
+    };
+
+    let mut sections = Vec::new();
+    let mut search = 0;
+    while let Some(pos) = content[search..].find(SECTION_START) {
+        let start = search + pos;
+        let inner_start = after_marker(start, SECTION_START);
+        let close = content[inner_start..]
+            .find(SECTION_END)
+            .map(|p| inner_start + p)
+            .context("Encrypted section is missing its closing marker")?;
+        let end = after_marker(close, SECTION_END);
+        sections.push(MarkedSection {
+            outer: (start, end),
+            inner: (inner_start, close),
+        });
+        search = end;
+    }
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_section_round_trip() {
+        let content =
+            "# Journal\nPublic line\n<!-- encrypt -->\nSecret #tag\n<!-- /encrypt -->\nAfter\n";
+        let (encrypted, count) = encrypt_note(content, "hunter2").unwrap();
+        assert_eq!(count, 1);
+        assert!(!encrypted.contains("Secret"));
+        assert!(encrypted.starts_with("# Journal\nPublic line\n-----BEGIN"));
+        assert!(encrypted.ends_with("-----\nAfter\n"));
+        assert!(!strip_encrypted(&encrypted).contains("v1 section"));
+
+        assert!(decrypt_note(&encrypted, "wrong").is_err());
+        let (decrypted, _) = decrypt_note(&encrypted, "hunter2").unwrap();
+        assert_eq!(decrypted, content);
+
+        let key = random_bytes::<32>();
+        let sealed = encrypt_bytes(&key, b"%PDF-1.7").unwrap();
+        assert!(is_encrypted_bytes(&sealed));
+        assert_eq!(decrypt_bytes(&key, &sealed).unwrap(), b"%PDF-1.7");
+        assert!(decrypt_bytes(&random_bytes::<32>(), &sealed).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_vault_lock_and_index_scope() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Diary.md",
+                "---\ntags: [private]\n---\nMet Ada at the lighthouse\n",
+            ),
+            ("Board.canvas", "{}"),
+            ("photo.png", "not a note"),
+        ]);
+        let path = vault.path();
+        assert_eq!(
+            encrypt_vault(path, "hunter2", IndexScope::default()).unwrap(),
+            2
+        );
+        assert_eq!(vault_encryption(path).unwrap().index, IndexScope::Metadata);
+        let on_disk = fs::read(path.join("Diary.md")).unwrap();
+        assert!(is_encrypted_bytes(&on_disk));
+        assert_eq!(fs::read(path.join("photo.png")).unwrap(), b"not a note");
+        assert!(Vault::read_file(path, "Diary.md")
+            .unwrap()
+            .contains("lighthouse"));
+
+        let db = vault.index();
+        assert_eq!(db.get_all_tags().unwrap(), vec![("#private".to_string(), 1)]);
+        assert!(db.search_fulltext("lighthouse", 10).unwrap().is_empty());
+
+        lock_vault(path);
+        assert!(!vault_encryption(path).unwrap().unlocked);
+        assert!(Vault::read_file(path, "Diary.md").is_err());
+        assert!(Vault::write_file_atomic(path, "New.md", "plaintext").is_err());
+        assert!(unlock_vault(path, "wrong").is_err());
+        unlock_vault(path, "hunter2").unwrap();
+        Vault::write_file_atomic(path, "New.md", "Sealed").unwrap();
+        assert!(is_encrypted_bytes(&fs::read(path.join("New.md")).unwrap()));
+
+        assert_eq!(decrypt_vault(path, "hunter2").unwrap(), 3);
+        assert!(!vault_encryption(path).unwrap().enabled);
+        assert_eq!(fs::read(path.join("New.md")).unwrap(), b"Sealed");
+        Vault::write_file_atomic(path, "Later.md", "Plain").unwrap();
+        assert_eq!(fs::read(path.join("Later.md")).unwrap(), b"Plain");
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Header row and size of a `.csv` file, as cached in the index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvSummary {
+    pub path: String,
+    pub headers: Vec<String>,
+    /// Data rows, not counting the header
+    pub row_count: usize,
+}
+
+/// Which data rows to return from `read_csv` (0-based, header excluded)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowRange {
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// A window of parsed rows from a `.csv` file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvPage {
+    pub headers: Vec<String>,
+    /// Index of the first returned row among the data rows
+    pub offset: usize,
+    pub rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+}
+
+/// A parsed CSV document. The first record is the header row.
+pub struct CsvTable {
+    records: Vec<Vec<String>>,
+    line_ending: &'static str,
+    trailing_newline: bool,
+}
+
+impl CsvTable {
+    /// Parse RFC 4180 CSV: quoted fields may contain commas, `""` and newlines
+    pub fn parse(content: &str) -> Result<CsvTable> {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = content.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    }
+                    '"' => in_quotes = false,
+                    _ => field.push(c),
+                }
+                continue;
+            }
+            match c {
+                '"' if field.is_empty() => in_quotes = true,
+                ',' => record.push(std::mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => field.push(c),
+            }
+        }
+        if in_quotes {
+            bail!("Unterminated quoted field in CSV");
+        }
+        if !field.is_empty() || !record.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+
+        Ok(CsvTable {
+            records,
+            line_ending: if content.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            },
+            trailing_newline: content.is_empty() || content.ends_with('\n'),
+        })
+    }
+
+    pub fn headers(&self) -> &[String] {
+        self.records.first().map_or(&[], |h| h.as_slice())
+    }
+
+    /// Data rows, header excluded
+    pub fn rows(&self) -> &[Vec<String>] {
+        self.records.get(1..).unwrap_or(&[])
+    }
+
+    /// Set one cell of a data row. Short rows are padded up to the header width.
+    pub fn set_cell(&mut self, row: usize, col: usize, value: &str) -> Result<()> {
+        let width = self.headers().len();
+        if col >= width {
+            bail!("Column {} is out of range ({} columns)", col, width);
+        }
+        let total = self.rows().len();
+        let Some(record) = self.records.get_mut(row + 1) else {
+            bail!("Row {} is out of range ({} rows)", row, total);
+        };
+        if record.len() <= col {
+            record.resize(col + 1, String::new());
+        }
+        record[col] = value.to_string();
+        Ok(())
+    }
+
+    /// Replace every record, header first. Rows shorter than the header are
+    /// padded to its width.
+    pub fn set_records(&mut self, records: Vec<Vec<String>>) {
+        let width = records.first().map_or(0, Vec::len);
+        self.records = records;
+        for record in self.records.iter_mut().skip(1) {
+            if record.len() < width {
+                record.resize(width, String::new());
+            }
+        }
+    }
+
+    /// Serialize, quoting only the fields that need it and keeping the
+    /// file's line endings
+    pub fn to_csv(&self) -> String {
+        let mut out = self
+            .records
+            .iter()
+            .map(|record| {
+                record
+                    .iter()
+                    .map(|f| quote_field(f))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join(self.line_ending);
+        if self.trailing_newline && !out.is_empty() {
+            out.push_str(self.line_ending);
+        }
+        out
+    }
+}
+
+fn quote_field(field: &str) -> String {
+    let needs_quotes =
+        field.contains([',', '"', '\n', '\r']) || field.starts_with(' ') || field.ends_with(' ');
+    if needs_quotes {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_round_trip_and_edit() {
+        let content = "name,notes,qty\r\nApple,\"red, sweet\",3\r\n\"Pear \"\"B\"\"\",\"two\r\nlines\",\r\nFig\r\n";
+        let mut table = CsvTable::parse(content).unwrap();
+        assert_eq!(table.headers(), ["name", "notes", "qty"]);
+        assert_eq!(table.rows().len(), 3);
+        assert_eq!(table.rows()[1][0], "Pear \"B\"");
+        assert_eq!(table.rows()[1][1], "two\r\nlines");
+        assert_eq!(table.to_csv(), content);
+
+        table.set_cell(2, 2, "1,5").unwrap();
+        assert!(table.to_csv().ends_with("Fig,,\"1,5\"\r\n"));
+        assert!(table.set_cell(3, 0, "x").is_err());
+        assert!(table.set_cell(0, 3, "x").is_err());
+
+        table.set_records(vec![
+            vec!["a".into(), "b".into()],
+            vec!["1".into()],
+            vec!["x\"y".into(), "2".into()],
+        ]);
+        assert_eq!(table.to_csv(), "a,b\r\n1,\r\n\"x\"\"y\",2\r\n");
+    }
+}
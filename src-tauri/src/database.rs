@@ -1,12 +1,45 @@
 use anyhow::{Context, Result};
-use rusqlite::Connection;
-use std::path::Path;
-use std::sync::Mutex;
+use rusqlite::{Connection, ErrorCode, OpenFlags, TransactionBehavior};
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::migrations;
+use crate::properties::{Property, PropertyType};
+use crate::sync::LockExt;
+
+/// Read-only connections kept open next to the writer
+const READ_POOL_SIZE: usize = 4;
+/// Prepared statements kept per connection; enough for every query the
+/// app runs, so indexing never re-prepares
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+/// Indexing warnings kept for the debug dump
+const MAX_INDEX_WARNINGS: usize = 100;
+/// Rows of each table included in the debug dump
+const DUMP_SAMPLE_ROWS: usize = 5;
+/// Files `reindex_vault` indexes per transaction
+const REINDEX_BATCH: usize = 200;
+/// How long a statement waits on another connection's lock before SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Tries a user-facing write gets when the database stays busy
+const WRITE_ATTEMPTS: u32 = 4;
+/// How far back the note open history goes
+const HISTORY_DAYS: i64 = 365;
 
 /// Wrapper around SQLite connection for thread-safe access.
 /// Now uses vault-local cache database instead of global ~/.synapse/synapse.db
+///
+/// Writes go through a single connection so they stay serialized; reads
+/// take one of a small pool of read-only connections, which WAL mode lets
+/// run alongside the writer.
 pub struct Database {
-    pub conn: Mutex<Connection>,
+    writer: Mutex<Connection>,
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
+    /// Recent indexing problems, oldest first, for `debug_dump`
+    warnings: Mutex<VecDeque<IndexWarning>>,
 }
 
 impl Database {
@@ -19,113 +52,203 @@ impl Database {
             std::fs::create_dir_all(parent).context("Failed to create cache directory")?;
         }
 
-        let conn = Connection::open(&db_path)
+        Self::open(&db_path, OpenFlags::default())
+    }
+
+    /// A private database that lives only as long as this handle, so tests
+    /// can exercise the cache without files. Connections share it through
+    /// SQLite's shared cache.
+    #[cfg(test)]
+    pub fn init_in_memory() -> Result<Self> {
+        let uri = format!(
+            "file:synapse-{}?mode=memory&cache=shared",
+            uuid::Uuid::new_v4()
+        );
+        Self::open(
+            Path::new(&uri),
+            OpenFlags::default() | OpenFlags::SQLITE_OPEN_URI,
+        )
+    }
+
+    fn open(db_path: &Path, flags: OpenFlags) -> Result<Self> {
+        let conn = Connection::open_with_flags(db_path, flags)
             .with_context(|| format!("Failed to open database at {:?}", db_path))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .context("Failed to set busy timeout")?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+
+        // Refuse caches from a newer Synapse before touching them
+        let schema = read_schema_info(&conn)?;
+        if !schema.compatible() {
+            return Err(NewerSchemaError(schema).into());
+        }
 
         // Enable WAL mode for better concurrent read performance
         conn.execute_batch("PRAGMA journal_mode=WAL;")
             .context("Failed to set WAL mode")?;
 
-        // Run migrations
-        conn.execute_batch(
-            "
-            -- Notes metadata cache (mirrors filesystem)
-            CREATE TABLE IF NOT EXISTS notes (
-                path TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                created_at TEXT,
-                modified_at TEXT,
-                word_count INTEGER DEFAULT 0,
-                starred INTEGER DEFAULT 0
-            );
+        // Child rows (links, tags, headings, ...) go with their note through
+        // ON DELETE CASCADE; SQLite only enforces that per connection
+        conn.execute_batch("PRAGMA foreign_keys=ON;")
+            .context("Failed to enable foreign keys")?;
 
-            -- Outgoing links from notes
-            CREATE TABLE IF NOT EXISTS links (
-                source_path TEXT NOT NULL,
-                target_name TEXT NOT NULL,
-                PRIMARY KEY (source_path, target_name),
-                FOREIGN KEY (source_path) REFERENCES notes(path) ON DELETE CASCADE
-            );
+        // Bring older caches up to this build's schema
+        migrations::migrate(&conn, schema.schema_version)?;
+        purge_orphans(&conn)?;
 
-            -- Tags on notes
-            CREATE TABLE IF NOT EXISTS tags (
-                note_path TEXT NOT NULL,
-                tag TEXT NOT NULL,
-                PRIMARY KEY (note_path, tag),
-                FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
-            );
+        conn.execute(
+            "INSERT OR REPLACE INTO schema_info (key, value) VALUES ('app_version', ?1)",
+            [APP_VERSION],
+        )
+        .context("Failed to record app version")?;
 
-            -- Headings in notes (for outline + section links)
-            CREATE TABLE IF NOT EXISTS headings (
-                note_path TEXT NOT NULL,
-                text TEXT NOT NULL,
-                level INTEGER NOT NULL,
-                line_number INTEGER NOT NULL,
-                FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
-            );
+        // Readers open after the schema exists so they see every table
+        let readers = (0..READ_POOL_SIZE)
+            .map(|_| {
+                Connection::open_with_flags(
+                    db_path,
+                    OpenFlags::SQLITE_OPEN_READ_ONLY
+                        | OpenFlags::SQLITE_OPEN_NO_MUTEX
+                        | (flags & OpenFlags::SQLITE_OPEN_URI),
+                )
+                .and_then(|reader| {
+                    reader.busy_timeout(BUSY_TIMEOUT)?;
+                    // Shared-cache readers would otherwise hit table locks
+                    // held by an open write transaction
+                    reader.pragma_update(None, "read_uncommitted", true)?;
+                    reader.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+                    Ok(reader)
+                })
+                .map(Mutex::new)
+                .with_context(|| format!("Failed to open reader for {:?}", db_path))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-            -- Settings key-value store
-            CREATE TABLE IF NOT EXISTS settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            );
+        let db = Database {
+            writer: Mutex::new(conn),
+            readers,
+            next_reader: AtomicUsize::new(0),
+            warnings: Mutex::new(VecDeque::new()),
+        };
+        let tuning = db.tuning().unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid database tuning: {}", e);
+            DbTuning::default()
+        });
+        db.apply_tuning(&tuning)?;
+        Ok(db)
+    }
 
-            -- Indexes for fast lookups
-            CREATE INDEX IF NOT EXISTS idx_links_target ON links(target_name);
-            CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);
-            CREATE INDEX IF NOT EXISTS idx_headings_path ON headings(note_path);
-            ",
-        )
-        .context("Failed to create tables")?;
+    /// The write connection. Holding it blocks other writers, not readers.
+    fn writer(&self) -> MutexGuard<'_, Connection> {
+        self.writer.lock_or_recover()
+    }
 
-        Ok(Database {
-            conn: Mutex::new(conn),
-        })
+    /// Run a write in an immediate transaction, retrying with backoff
+    /// while another connection (a second window, an external tool) keeps
+    /// the database busy past the busy timeout
+    fn write<T>(&self, mut op: impl FnMut(&Connection) -> Result<T>) -> Result<T> {
+        let mut delay = Duration::from_millis(50);
+        for attempt in 1.. {
+            let result = {
+                let mut conn = self.writer();
+                conn.transaction_with_behavior(TransactionBehavior::Immediate)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|tx| {
+                        let value = op(&tx)?;
+                        tx.commit()?;
+                        Ok(value)
+                    })
+            };
+            match result {
+                Err(e) if attempt < WRITE_ATTEMPTS && is_busy(&e) => {
+                    log::debug!("Database busy, retrying write in {:?}", delay);
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+        unreachable!()
     }
 
-    // ─── Note metadata ────────────────────────────────────────────────
+    /// An idle read connection, or the next one in turn if all are busy
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..self.readers.len() {
+            let slot = &self.readers[(start + offset) % self.readers.len()];
+            if let Ok(conn) = slot.try_lock() {
+                return conn;
+            }
+        }
+        self.readers[start % self.readers.len()].lock_or_recover()
+    }
 
-    /// Upsert note metadata into the cache
-    pub fn upsert_note(&self, note: &CachedNote) -> Result<()> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        conn.execute(
-            "INSERT INTO notes (path, title, created_at, modified_at, word_count, starred)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
-             ON CONFLICT(path) DO UPDATE SET
-               title = excluded.title,
-               modified_at = excluded.modified_at,
-               word_count = excluded.word_count",
-            (
-                &note.path,
-                &note.title,
-                &note.created_at,
-                &note.modified_at,
-                note.word_count,
-                note.starred as i32,
-            ),
-        )
-        .context("Failed to upsert note")?;
-        Ok(())
+    /// Schema of a vault's cache without opening it for writing. A vault
+    /// without a cache reports schema 0.
+    pub fn inspect_schema(vault_path: &Path) -> Result<SchemaInfo> {
+        let db_path = crate::vault::Vault::db_path(vault_path);
+        if !db_path.exists() {
+            return Ok(SchemaInfo::unversioned());
+        }
+        let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open database at {:?}", db_path))?;
+        read_schema_info(&conn)
+    }
+
+    /// Set the vault's cache aside and start a fresh one. The old file is
+    /// kept as `cache.db.<timestamp>.bak`, since pins, bookmarks and
+    /// settings live only in the cache. Returns the new database and the
+    /// backup path.
+    pub fn rebuild_for_vault(vault_path: &Path) -> Result<(Self, Option<PathBuf>)> {
+        let db_path = crate::vault::Vault::db_path(vault_path);
+        let mut backup = None;
+        if db_path.exists() {
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            let target = db_path.with_extension(format!("db.{}.bak", stamp));
+            std::fs::rename(&db_path, &target).context("Failed to set the old cache aside")?;
+            backup = Some(target);
+        }
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = db_path.clone().into_os_string();
+            sidecar.push(suffix);
+            let _ = std::fs::remove_file(sidecar);
+        }
+        Ok((Self::init_for_vault(vault_path)?, backup))
+    }
+
+    /// Open the vault's cache, setting it aside and starting an empty one
+    /// if SQLite reports it corrupt (disk full, killed mid-write). The
+    /// backup path is returned when that happened; the caller reindexes.
+    pub fn open_or_recover(vault_path: &Path) -> Result<(Self, Option<PathBuf>)> {
+        match Self::init_for_vault(vault_path) {
+            Ok(db) => Ok((db, None)),
+            Err(e) if is_corruption(&e) => {
+                log::warn!(
+                    "Cache for {} is corrupt, rebuilding: {:#}",
+                    vault_path.display(),
+                    e
+                );
+                Self::rebuild_for_vault(vault_path)
+            }
+            Err(e) => Err(e),
+        }
     }
 
+    // ─── Note metadata ────────────────────────────────────────────────
+
     /// Get all cached notes
     pub fn get_all_notes(&self) -> Result<Vec<CachedNote>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
+        let conn = self.reader();
+        // Pinned notes float to the top in their manual order
         let mut stmt = conn
-            .prepare("SELECT path, title, created_at, modified_at, word_count, starred FROM notes ORDER BY modified_at DESC")
+            .prepare_cached(&format!(
+                "{} ORDER BY p.pin_order IS NULL, p.pin_order, n.modified_at DESC",
+                NOTE_SELECT
+            ))
             .context("Failed to prepare query")?;
 
         let notes = stmt
-            .query_map([], |row| {
-                Ok(CachedNote {
-                    path: row.get(0)?,
-                    title: row.get(1)?,
-                    created_at: row.get(2)?,
-                    modified_at: row.get(3)?,
-                    word_count: row.get(4)?,
-                    starred: row.get::<_, i32>(5)? != 0,
-                })
-            })
+            .query_map([], note_from_row)
             .context("Failed to query notes")?
             .collect::<std::result::Result<Vec<_>, _>>()
             .context("Failed to collect note rows")?;
@@ -133,53 +256,275 @@ impl Database {
         Ok(notes)
     }
 
+    /// Notes matching a SQL condition over `notes n` (built by `query.rs`,
+    /// with every value bound as a parameter), sorted by title
+    pub fn find_notes(&self, filter: &str, params: &[String]) -> Result<Vec<CachedNote>> {
+        self.find_notes_ordered(filter, "n.title COLLATE NOCASE", params)
+    }
+
+    /// `find_notes` with an `ORDER BY` clause of its own, which may bind
+    /// parameters too
+    pub fn find_notes_ordered(
+        &self,
+        filter: &str,
+        order_by: &str,
+        params: &[String],
+    ) -> Result<Vec<CachedNote>> {
+        let conn = self.reader();
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE {} ORDER BY {}",
+                NOTE_SELECT, filter, order_by
+            ))
+            .context("Failed to prepare query")?;
+        let notes = stmt
+            .query_map(rusqlite::params_from_iter(params), note_from_row)
+            .context("Failed to query notes")?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(notes)
+    }
+
+    /// Get a single cached note by path
+    pub fn get_note(&self, path: &str) -> Result<Option<CachedNote>> {
+        let conn = self.reader();
+        let result = conn.query_row(
+            &format!("{} WHERE n.path = ?1", NOTE_SELECT),
+            [path],
+            note_from_row,
+        );
+        match result {
+            Ok(note) => Ok(Some(note)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Resolve wikilink text (`Note`, `folder/Note`, `Note.md`) to a note path.
+    /// Exact paths win over titles, which win over bare file names elsewhere
+    /// in the vault, which win over frontmatter aliases.
+    pub fn resolve_link_target(&self, name: &str) -> Result<Option<String>> {
+        let conn = self.reader();
+        let result = conn.query_row(
+            "SELECT path FROM notes
+             WHERE lower(path) IN (lower(?1), lower(?1) || '.md')
+                OR lower(title) = lower(?1)
+                OR substr(lower(path), -length(?1) - 4) = '/' || lower(?1) || '.md'
+                OR path IN (SELECT note_path FROM aliases WHERE alias = ?1 COLLATE NOCASE)
+             ORDER BY CASE
+                 WHEN lower(path) IN (lower(?1), lower(?1) || '.md') THEN 0
+                 WHEN lower(title) = lower(?1) THEN 1
+                 WHEN substr(lower(path), -length(?1) - 4) = '/' || lower(?1) || '.md' THEN 2
+                 ELSE 3
+             END, length(path)
+             LIMIT 1",
+            [name.trim()],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(path) => Ok(Some(path)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Delete a note and all its related data (links, tags, headings cascade)
     pub fn delete_note(&self, path: &str) -> Result<()> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        // Delete manually since SQLite foreign keys require PRAGMA foreign_keys=ON
-        conn.execute("DELETE FROM links WHERE source_path = ?1", [path])?;
-        conn.execute("DELETE FROM tags WHERE note_path = ?1", [path])?;
-        conn.execute("DELETE FROM headings WHERE note_path = ?1", [path])?;
-        conn.execute("DELETE FROM notes WHERE path = ?1", [path])
-            .context("Failed to delete note")?;
-        Ok(())
+        self.write(|conn| remove_note(conn, path))
     }
 
     /// Toggle starred status
     pub fn toggle_star(&self, path: &str) -> Result<bool> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        conn.execute(
-            "UPDATE notes SET starred = CASE WHEN starred = 0 THEN 1 ELSE 0 END WHERE path = ?1",
-            [path],
-        )?;
-        let starred: bool = conn.query_row(
-            "SELECT starred FROM notes WHERE path = ?1",
-            [path],
-            |row| row.get::<_, i32>(0).map(|v| v != 0),
-        )?;
-        Ok(starred)
+        self.write(|conn| {
+            conn.execute(
+                "UPDATE notes SET starred = CASE WHEN starred = 0 THEN 1 ELSE 0 END WHERE path = ?1",
+                [path],
+            )?;
+            let starred: bool = conn.query_row(
+                "SELECT starred FROM notes WHERE path = ?1",
+                [path],
+                |row| row.get::<_, i32>(0).map(|v| v != 0),
+            )?;
+            Ok(starred)
+        })
     }
 
-    // ─── Links ────────────────────────────────────────────────────────
+    /// Carry user state (recents, etc.) over to a note's new path after a rename
+    pub fn move_note_state(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute(
+                "UPDATE OR REPLACE recent_files SET path = ?2 WHERE path = ?1",
+                [old_path, new_path],
+            )?;
+            conn.execute(
+                "UPDATE history SET path = ?2 WHERE path = ?1",
+                [old_path, new_path],
+            )?;
+            conn.execute(
+                "UPDATE bookmarks SET path = ?2 WHERE path = ?1",
+                [old_path, new_path],
+            )?;
+            conn.execute(
+                "UPDATE OR REPLACE pins SET path = ?2 WHERE path = ?1",
+                [old_path, new_path],
+            )?;
+            conn.execute(
+                "UPDATE OR REPLACE attachments SET note_path = ?2 WHERE note_path = ?1",
+                [old_path, new_path],
+            )?;
+            Ok(())
+        })
+    }
+
+    // ─── Pins ─────────────────────────────────────────────────────────
+
+    /// Pin a note at the end of the pinned list (no-op if already pinned)
+    pub fn pin_note(&self, path: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO pins (path, pin_order)
+                 VALUES (?1, (SELECT COALESCE(MAX(pin_order) + 1, 0) FROM pins))",
+                [path],
+            )
+            .context("Failed to pin note")?;
+            Ok(())
+        })
+    }
+
+    /// Unpin a note
+    pub fn unpin_note(&self, path: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("DELETE FROM pins WHERE path = ?1", [path])
+                .context("Failed to unpin note")?;
+            Ok(())
+        })
+    }
+
+    /// Reorder pinned notes. Pins not mentioned keep their relative order after the listed ones.
+    pub fn reorder_pins(&self, paths: &[String]) -> Result<()> {
+        self.write(|tx| {
+            let existing: Vec<String> = {
+                let mut stmt = tx.prepare_cached("SELECT path FROM pins ORDER BY pin_order")?;
+                let rows = stmt
+                    .query_map([], |row| row.get(0))?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                rows
+            };
+
+            let mut seen = HashSet::new();
+            let mut ordered: Vec<&String> = paths
+                .iter()
+                .filter(|p| existing.contains(p) && seen.insert(*p))
+                .collect();
+            for path in &existing {
+                if !ordered.contains(&path) {
+                    ordered.push(path);
+                }
+            }
+
+            for (order, path) in ordered.iter().enumerate() {
+                tx.execute(
+                    "UPDATE pins SET pin_order = ?2 WHERE path = ?1",
+                    rusqlite::params![path, order as i64],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    // ─── Recent files ─────────────────────────────────────────────────
+
+    /// Record that a note was opened (bumps timestamp and open count, and
+    /// adds to the open history, which keeps `HISTORY_DAYS`)
+    pub fn record_note_opened(&self, path: &str) -> Result<()> {
+        self.write(|conn| {
+            let now = chrono::Utc::now();
+            let opened_at = now.to_rfc3339();
+            conn.execute(
+                "INSERT INTO recent_files (path, last_opened, open_count) VALUES (?1, ?2, 1)
+                 ON CONFLICT(path) DO UPDATE SET
+                   last_opened = excluded.last_opened,
+                   open_count = open_count + 1",
+                [path, &opened_at],
+            )
+            .context("Failed to record note open")?;
+            conn.prepare_cached("INSERT INTO history (path, opened_at) VALUES (?1, ?2)")?
+                .execute([path, &opened_at])?;
+            let cutoff = (now - chrono::Duration::days(HISTORY_DAYS)).to_rfc3339();
+            conn.prepare_cached("DELETE FROM history WHERE opened_at < ?1")?
+                .execute([cutoff])?;
+            Ok(())
+        })
+    }
 
-    /// Replace all outgoing links for a note
-    pub fn update_links(&self, source_path: &str, targets: &[String]) -> Result<()> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        conn.execute("DELETE FROM links WHERE source_path = ?1", [source_path])?;
-        let mut stmt = conn.prepare(
-            "INSERT OR IGNORE INTO links (source_path, target_name) VALUES (?1, ?2)",
+    /// Get the most recently opened notes, newest first
+    pub fn get_recent_notes(&self, limit: usize) -> Result<Vec<RecentNote>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT r.path, n.title, r.last_opened, r.open_count
+             FROM recent_files r JOIN notes n ON n.path = r.path
+             ORDER BY r.last_opened DESC
+             LIMIT ?1",
         )?;
-        for target in targets {
-            stmt.execute(rusqlite::params![source_path, target])?;
-        }
-        Ok(())
+        let recents = stmt
+            .query_map([limit as i64], |row| {
+                Ok(RecentNote {
+                    path: row.get(0)?,
+                    title: row.get(1)?,
+                    last_opened: row.get(2)?,
+                    open_count: row.get(3)?,
+                })
+            })
+            .context("Failed to query recent notes")?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(recents)
+    }
+
+    /// How often notes were opened: all time (as far as the history goes)
+    /// and since `since` (RFC 3339), busiest first. One note, or every note
+    /// in the history.
+    pub fn get_note_open_stats(
+        &self,
+        path: Option<&str>,
+        since: &str,
+    ) -> Result<Vec<NoteOpenStats>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT h.path, n.title, COUNT(*), SUM(h.opened_at >= ?2),
+                    MIN(h.opened_at), MAX(h.opened_at)
+             FROM history h JOIN notes n ON n.path = h.path
+             WHERE ?1 IS NULL OR h.path = ?1
+             GROUP BY h.path
+             ORDER BY 4 DESC, 6 DESC",
+        )?;
+        let stats = stmt
+            .query_map(rusqlite::params![path, since], |row| {
+                Ok(NoteOpenStats {
+                    path: row.get(0)?,
+                    title: row.get(1)?,
+                    opens: row.get(2)?,
+                    recent_opens: row.get(3)?,
+                    first_opened: row.get(4)?,
+                    last_opened: row.get(5)?,
+                })
+            })
+            .context("Failed to query open history")?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(stats)
     }
 
-    /// Get all notes that link TO the given note title
+    // ─── Links ────────────────────────────────────────────────────────
+
+    /// Get all notes that link TO the given note title, or to one of the
+    /// note's aliases
     pub fn get_backlinks(&self, note_title: &str) -> Result<Vec<String>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        let mut stmt = conn.prepare(
-            "SELECT DISTINCT source_path FROM links WHERE target_name = ?1",
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT DISTINCT source_path FROM links
+             WHERE target_name = ?1
+                OR target_name COLLATE NOCASE IN (
+                    SELECT a.alias FROM aliases a JOIN notes n ON n.path = a.note_path
+                    WHERE n.title = ?1
+                )",
         )?;
         let paths = stmt
             .query_map([note_title], |row| row.get(0))
@@ -190,8 +535,8 @@ impl Database {
 
     /// Get all outgoing links from a note
     pub fn get_outgoing_links(&self, source_path: &str) -> Result<Vec<String>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        let mut stmt = conn.prepare(
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
             "SELECT target_name FROM links WHERE source_path = ?1",
         )?;
         let links = stmt
@@ -203,8 +548,8 @@ impl Database {
 
     /// Get all links in the vault (for graph view)
     pub fn get_all_links(&self) -> Result<Vec<(String, String)>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        let mut stmt = conn.prepare("SELECT source_path, target_name FROM links")?;
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT source_path, target_name FROM links")?;
         let links = stmt
             .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
             .context("Failed to query all links")?
@@ -212,24 +557,78 @@ impl Database {
         Ok(links)
     }
 
-    // ─── Tags ─────────────────────────────────────────────────────────
+    /// Wikilink targets that don't resolve to any note, with the notes
+    /// linking to them, sorted by target
+    pub fn get_unresolved_links(&self) -> Result<Vec<UnresolvedLink>> {
+        let resolver = link_resolver(&self.get_all_notes()?);
+        // Targets differing only in case are one missing note
+        let mut by_key: HashMap<String, UnresolvedLink> = HashMap::new();
+        for (source, target) in self.get_all_links()? {
+            let key = target.trim().to_lowercase();
+            if resolver.contains_key(&key) {
+                continue;
+            }
+            by_key
+                .entry(key)
+                .or_insert_with(|| UnresolvedLink {
+                    target,
+                    sources: Vec::new(),
+                })
+                .sources
+                .push(source);
+        }
+        let mut unresolved: Vec<UnresolvedLink> = by_key.into_values().collect();
+        unresolved.sort_by_key(|u| u.target.to_lowercase());
+        for link in &mut unresolved {
+            link.sources.sort();
+            link.sources.dedup();
+        }
+        Ok(unresolved)
+    }
 
-    /// Replace all tags for a note
-    pub fn update_tags(&self, note_path: &str, tags: &[String]) -> Result<()> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        conn.execute("DELETE FROM tags WHERE note_path = ?1", [note_path])?;
-        let mut stmt =
-            conn.prepare("INSERT OR IGNORE INTO tags (note_path, tag) VALUES (?1, ?2)")?;
-        for tag in tags {
-            stmt.execute(rusqlite::params![note_path, tag])?;
+    /// Notes that link nowhere and that no other note links to
+    pub fn get_orphan_notes(&self) -> Result<Vec<CachedNote>> {
+        let notes = self.get_all_notes()?;
+        let resolver = link_resolver(&notes);
+        let mut connected = HashSet::new();
+        for (source, target) in self.get_all_links()? {
+            if let Some(path) = resolver.get(&target.trim().to_lowercase()) {
+                if *path != source {
+                    connected.insert(path.clone());
+                }
+            }
+            connected.insert(source);
         }
-        Ok(())
+        Ok(notes
+            .into_iter()
+            .filter(|note| !connected.contains(&note.path))
+            .collect())
     }
 
+    /// Every link with the note its target resolves to (None when it
+    /// doesn't), by the rules of `resolve_link_target`
+    pub fn get_resolved_links(&self) -> Result<Vec<ResolvedLink>> {
+        let resolver = link_resolver(&self.get_all_notes()?);
+        Ok(self
+            .get_all_links()?
+            .into_iter()
+            .map(|(source, target)| {
+                let path = resolver.get(&target.trim().to_lowercase()).cloned();
+                ResolvedLink {
+                    source,
+                    target,
+                    path,
+                }
+            })
+            .collect())
+    }
+
+    // ─── Tags ─────────────────────────────────────────────────────────
+
     /// Get all unique tags in the vault with their counts
     pub fn get_all_tags(&self) -> Result<Vec<(String, usize)>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        let mut stmt = conn.prepare(
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
             "SELECT tag, COUNT(*) as cnt FROM tags GROUP BY tag ORDER BY cnt DESC",
         )?;
         let tags = stmt
@@ -242,43 +641,70 @@ impl Database {
 
     /// Get all notes with a specific tag
     pub fn get_notes_by_tag(&self, tag: &str) -> Result<Vec<String>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        let mut stmt = conn.prepare("SELECT note_path FROM tags WHERE tag = ?1")?;
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT note_path FROM tags WHERE tag = ?1")?;
         let paths = stmt
             .query_map([tag], |row| row.get(0))?
             .collect::<std::result::Result<Vec<String>, _>>()?;
         Ok(paths)
     }
 
-    // ─── Headings ─────────────────────────────────────────────────────
+    /// Tags of every tagged note, by path
+    pub fn get_all_note_tags(&self) -> Result<HashMap<String, Vec<String>>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT note_path, tag FROM tags ORDER BY tag")?;
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (path, tag): (String, String) = row?;
+            tags.entry(path).or_default().push(tag);
+        }
+        Ok(tags)
+    }
 
-    /// Replace all headings for a note
-    pub fn update_headings(
-        &self,
-        note_path: &str,
-        headings: &[crate::indexer::Heading],
-    ) -> Result<()> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        conn.execute("DELETE FROM headings WHERE note_path = ?1", [note_path])?;
-        let mut stmt = conn.prepare(
-            "INSERT INTO headings (note_path, text, level, line_number) VALUES (?1, ?2, ?3, ?4)",
+    // ─── Properties ───────────────────────────────────────────────────
+
+    /// A note's frontmatter properties, by key
+    pub fn get_properties(&self, note_path: &str) -> Result<Vec<Property>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT key, value, type FROM properties WHERE note_path = ?1 ORDER BY key, rowid",
         )?;
-        for h in headings {
-            stmt.execute(rusqlite::params![
-                note_path,
-                &h.text,
-                h.level as i32,
-                h.line as i32,
-            ])?;
-        }
-        Ok(())
+        let properties = stmt
+            .query_map([note_path], |row| {
+                Ok(Property {
+                    key: row.get(0)?,
+                    value: row.get(1)?,
+                    kind: PropertyType::parse(&row.get::<_, String>(2)?),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(properties)
+    }
+
+    // ─── Aliases ──────────────────────────────────────────────────────
+
+    /// Aliases of the note(s) with this title
+    pub fn get_aliases_by_title(&self, note_title: &str) -> Result<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT DISTINCT a.alias FROM aliases a JOIN notes n ON n.path = a.note_path
+             WHERE n.title = ?1",
+        )?;
+        let aliases = stmt
+            .query_map([note_title], |row| row.get(0))
+            .context("Failed to query aliases")?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(aliases)
     }
 
+    // ─── Headings ─────────────────────────────────────────────────────
+
     /// Get headings for a specific note
     pub fn get_headings(&self, note_path: &str) -> Result<Vec<crate::indexer::Heading>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        let mut stmt = conn.prepare(
-            "SELECT text, level, line_number FROM headings WHERE note_path = ?1 ORDER BY line_number",
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT text, level, line_number, slug FROM headings WHERE note_path = ?1 ORDER BY line_number",
         )?;
         let headings = stmt
             .query_map([note_path], |row| {
@@ -286,124 +712,3253 @@ impl Database {
                     text: row.get(0)?,
                     level: row.get::<_, i32>(1)? as u8,
                     line: row.get::<_, i32>(2)? as usize,
+                    slug: row.get(3)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(headings)
     }
 
-    // ─── Settings ─────────────────────────────────────────────────────
-
-    /// Get a setting value
-    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        let result = conn.query_row(
-            "SELECT value FROM settings WHERE key = ?1",
-            [key],
-            |row| row.get(0),
-        );
-        match result {
-            Ok(value) => Ok(Some(value)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
+    /// Find the heading an anchor points at (see `indexer::find_heading`)
+    pub fn resolve_anchor(
+        &self,
+        note_path: &str,
+        anchor: &str,
+    ) -> Result<Option<crate::indexer::Heading>> {
+        let headings = self.get_headings(note_path)?;
+        Ok(crate::indexer::find_heading(&headings, anchor).cloned())
     }
 
-    /// Set a setting value
-    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
-        let conn = self.conn.lock().expect("Database mutex poisoned");
-        conn.execute(
-            "INSERT INTO settings (key, value) VALUES (?1, ?2)
-             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-            [key, value],
+    /// Every link into a section with the note it resolves to (the source
+    /// itself for `[[#Heading]]`), skipping links to notes that don't exist
+    pub fn get_resolved_section_links(&self) -> Result<Vec<ResolvedSectionLink>> {
+        let resolver = link_resolver(&self.get_all_notes()?);
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT source_path, target_name, heading, line_number FROM section_links
+             ORDER BY source_path, line_number",
         )?;
-        Ok(())
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    crate::indexer::SectionLink {
+                        target: row.get(1)?,
+                        heading: row.get(2)?,
+                        line: row.get::<_, i64>(3)? as usize,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(source, link)| {
+                let path = if link.target.is_empty() {
+                    source.clone()
+                } else {
+                    resolver.get(&link.target.trim().to_lowercase())?.clone()
+                };
+                Some(ResolvedSectionLink { source, path, link })
+            })
+            .collect())
     }
 
-    // ─── Bulk operations ──────────────────────────────────────────────
-
-    /// Reindex the entire vault — scans all .md files and rebuilds cache
-    pub fn reindex_vault(&self, vault_path: &Path) -> Result<()> {
-        let notes = crate::vault::Vault::list_notes(vault_path)?;
-
-        for entry in &notes {
-            let content = match crate::vault::Vault::read_file(vault_path, &entry.path) {
-                Ok(c) => c,
-                Err(_) => continue,
+    /// Links into sections whose heading isn't in the note they point at,
+    /// by source note and line
+    pub fn get_broken_heading_links(&self) -> Result<Vec<ResolvedSectionLink>> {
+        let mut headings: HashMap<String, Vec<crate::indexer::Heading>> = HashMap::new();
+        let mut broken = Vec::new();
+        for resolved in self.get_resolved_section_links()? {
+            let note_headings = match headings.entry(resolved.path.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.get_headings(&resolved.path)?),
             };
+            if crate::indexer::find_heading(note_headings, &resolved.link.heading).is_none() {
+                broken.push(resolved);
+            }
+        }
+        Ok(broken)
+    }
 
-            let fm = crate::vault::Vault::parse_frontmatter(&content);
-            let index = crate::indexer::index_note(
-                &entry.path,
-                &content,
-                &fm.tags,
-            );
-
-            let title = fm
-                .title
-                .unwrap_or_else(|| index.title.clone());
+    // ─── Blocks ───────────────────────────────────────────────────────
 
-            let cached_note = CachedNote {
-                path: entry.path.clone(),
-                title,
-                created_at: fm.created.clone(),
-                modified_at: fm.modified.clone(),
-                word_count: index.word_count as i64,
-                starred: false,
-            };
+    /// Get block ids for a specific note
+    pub fn get_blocks(&self, note_path: &str) -> Result<Vec<crate::indexer::BlockRef>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT block_id, line_number FROM blocks WHERE note_path = ?1 ORDER BY line_number",
+        )?;
+        let blocks = stmt
+            .query_map([note_path], |row| {
+                Ok(crate::indexer::BlockRef {
+                    id: row.get(0)?,
+                    line: row.get::<_, i64>(1)? as usize,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(blocks)
+    }
 
-            self.upsert_note(&cached_note)?;
-            self.update_links(&entry.path, &index.outgoing_links)?;
-            self.update_tags(&entry.path, &index.tags)?;
-            self.update_headings(&entry.path, &index.headings)?;
+    /// Headings and block ids of a note, in line order, for `[[Note#` /
+    /// `[[Note#^` autocomplete. `content` is the note's text, used to preview
+    /// the line each block id marks.
+    pub fn get_link_targets(
+        &self,
+        note_path: &str,
+        content: &str,
+        kind: Option<LinkTargetKind>,
+    ) -> Result<Vec<LinkTarget>> {
+        let mut targets = Vec::new();
+        if kind != Some(LinkTargetKind::Block) {
+            let headings = self.get_headings(note_path)?;
+            targets.extend(headings.into_iter().map(|h| LinkTarget {
+                kind: LinkTargetKind::Heading,
+                text: h.text,
+                level: Some(h.level),
+                line: h.line,
+                preview: None,
+            }));
         }
-
-        // Remove notes that no longer exist on disk
-        let all_cached = self.get_all_notes()?;
-        let disk_paths: std::collections::HashSet<String> =
-            notes.iter().map(|e| e.path.clone()).collect();
-        for cached in &all_cached {
-            if !disk_paths.contains(&cached.path) {
-                self.delete_note(&cached.path)?;
-            }
+        if kind != Some(LinkTargetKind::Heading) {
+            // Block ids alone are meaningless in a picker, so show the line they mark
+            let lines: Vec<&str> = content.lines().collect();
+            let blocks = self.get_blocks(note_path)?;
+            targets.extend(blocks.into_iter().map(|b| {
+                let preview = lines.get(b.line.saturating_sub(1)).map(|line| {
+                    let text = line.trim_end();
+                    let text = text.strip_suffix(&format!("^{}", b.id)).unwrap_or(text);
+                    text.trim().chars().take(120).collect()
+                });
+                LinkTarget {
+                    kind: LinkTargetKind::Block,
+                    text: b.id,
+                    level: None,
+                    line: b.line,
+                    preview,
+                }
+            }));
         }
+        targets.sort_by_key(|t| t.line);
+        Ok(targets)
+    }
 
-        Ok(())
+    // ─── Diagrams ─────────────────────────────────────────────────────
+
+    /// Get the mermaid diagrams of a note, in document order
+    pub fn get_diagrams(&self, note_path: &str) -> Result<Vec<crate::mermaid::MermaidBlock>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT start_line, end_line, diagram_type FROM diagrams
+             WHERE note_path = ?1 ORDER BY start_line",
+        )?;
+        let diagrams = stmt
+            .query_map([note_path], |row| {
+                Ok(crate::mermaid::MermaidBlock {
+                    start_line: row.get::<_, i64>(0)? as usize,
+                    end_line: row.get::<_, i64>(1)? as usize,
+                    diagram_type: row.get(2)?,
+                    closed: true,
+                    source: String::new(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(diagrams)
     }
 
-    /// Reindex a single note (after save or external change)
-    pub fn reindex_note(&self, vault_path: &Path, relative_path: &str) -> Result<()> {
-        let content = crate::vault::Vault::read_file(vault_path, relative_path)?;
-        let fm = crate::vault::Vault::parse_frontmatter(&content);
-        let index = crate::indexer::index_note(relative_path, &content, &fm.tags);
+    // ─── Callouts ─────────────────────────────────────────────────────
 
-        let title = fm.title.unwrap_or_else(|| index.title.clone());
+    /// Callouts in one note or the whole vault, optionally of a single type
+    pub fn get_callouts(
+        &self,
+        note_path: Option<&str>,
+        kind: Option<&str>,
+    ) -> Result<Vec<NoteCallout>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path, kind, title, fold, start_line, end_line FROM callouts
+             WHERE (?1 IS NULL OR note_path = ?1) AND (?2 IS NULL OR kind = ?2)
+             ORDER BY note_path, start_line",
+        )?;
+        let kind = kind.map(str::to_lowercase);
+        let callouts = stmt
+            .query_map(rusqlite::params![note_path, kind], |row| {
+                let fold: Option<String> = row.get(3)?;
+                Ok(NoteCallout {
+                    path: row.get(0)?,
+                    callout: crate::indexer::Callout {
+                        kind: row.get(1)?,
+                        title: row.get(2)?,
+                        fold: fold.and_then(|f| f.chars().next()),
+                        start_line: row.get::<_, i64>(4)? as usize,
+                        end_line: row.get::<_, i64>(5)? as usize,
+                    },
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(callouts)
+    }
 
-        let cached_note = CachedNote {
-            path: relative_path.to_string(),
-            title,
-            created_at: fm.created.clone(),
-            modified_at: fm.modified.clone(),
-            word_count: index.word_count as i64,
-            starred: false,
-        };
+    // ─── Tasks ────────────────────────────────────────────────────────
 
-        self.upsert_note(&cached_note)?;
-        self.update_links(relative_path, &index.outgoing_links)?;
-        self.update_tags(relative_path, &index.tags)?;
-        self.update_headings(relative_path, &index.headings)?;
+    /// Replace all tasks for a note
+    pub fn update_tasks(&self, note_path: &str, tasks: &[crate::indexer::Task]) -> Result<()> {
+        self.write(|conn| store_tasks(conn, note_path, tasks))
+    }
 
-        Ok(())
+    /// Tasks across the vault (or in one note) by due date, undated last.
+    /// `today` (`YYYY-MM-DD`) decides what is overdue.
+    pub fn get_tasks(&self, filter: &TaskFilter, today: &str) -> Result<Vec<NoteTask>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path, line, text, completed, due, heading FROM tasks
+             WHERE (?1 IS NULL OR note_path = ?1)
+               AND CASE ?2
+                   WHEN 'open' THEN completed = 0
+                   WHEN 'completed' THEN completed = 1
+                   WHEN 'overdue' THEN completed = 0 AND due < ?3
+                   ELSE 1
+               END
+             ORDER BY due IS NULL, due, note_path, line",
+        )?;
+        let tasks = stmt
+            .query_map(
+                rusqlite::params![filter.path, filter.status.as_str(), today],
+                |row| {
+                    Ok(NoteTask {
+                        path: row.get(0)?,
+                        task: crate::indexer::Task {
+                            line: row.get::<_, i64>(1)? as usize,
+                            text: row.get(2)?,
+                            completed: row.get(3)?,
+                            due: row.get(4)?,
+                            heading: row.get(5)?,
+                        },
+                    })
+                },
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tasks)
     }
-}
 
-/// Cached note metadata (stored in SQLite, mirrors filesystem)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct CachedNote {
-    pub path: String,
-    pub title: String,
-    pub created_at: Option<String>,
-    pub modified_at: Option<String>,
-    pub word_count: i64,
-    pub starred: bool,
+    // ─── Embeddings ───────────────────────────────────────────────────
+
+    /// Every chunk's lexical vector in the vault with its note's path
+    pub fn get_embeddings(&self) -> Result<Vec<(String, Vec<f32>)>> {
+        let conn = self.reader();
+        let mut stmt = conn
+            .prepare_cached("SELECT note_path, vector FROM embeddings ORDER BY note_path, line")?;
+        let chunks = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    crate::embeddings::from_blob(&row.get::<_, Vec<u8>>(1)?),
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(chunks)
+    }
+
+    /// Up to `limit` chunks that have no sentence embedding yet
+    pub fn get_unembedded_chunks(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<crate::embeddings::PendingChunk>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path, line, text FROM embeddings
+             WHERE semantic IS NULL AND text != ''
+             ORDER BY note_path, line LIMIT ?1",
+        )?;
+        let chunks = stmt
+            .query_map([limit as i64], |row| {
+                Ok(crate::embeddings::PendingChunk {
+                    path: row.get(0)?,
+                    line: row.get::<_, i64>(1)? as usize,
+                    text: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(chunks)
+    }
+
+    /// Save sentence embeddings from `get_unembedded_chunks`. A chunk whose
+    /// note changed in the meantime is left for the next pass.
+    pub fn store_sentence_vectors(
+        &self,
+        vectors: &[(crate::embeddings::PendingChunk, Vec<f32>)],
+    ) -> Result<()> {
+        self.write(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "UPDATE embeddings SET semantic = ?4
+                 WHERE note_path = ?1 AND line = ?2 AND text = ?3",
+            )?;
+            for (chunk, vector) in vectors {
+                stmt.execute(rusqlite::params![
+                    &chunk.path,
+                    chunk.line as i64,
+                    &chunk.text,
+                    crate::embeddings::to_blob(vector)
+                ])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Every chunk's sentence embedding in the vault
+    pub fn get_sentence_vectors(&self) -> Result<Vec<crate::embeddings::SentenceChunk>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path, line, excerpt, semantic FROM embeddings
+             WHERE semantic IS NOT NULL ORDER BY note_path, line",
+        )?;
+        let chunks = stmt
+            .query_map([], |row| {
+                Ok(crate::embeddings::SentenceChunk {
+                    path: row.get(0)?,
+                    line: row.get::<_, i64>(1)? as usize,
+                    excerpt: row.get(2)?,
+                    vector: crate::embeddings::from_blob(&row.get::<_, Vec<u8>>(3)?),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(chunks)
+    }
+
+    // ─── Content signatures ───────────────────────────────────────────
+
+    /// Every note's content signature with its path
+    pub fn get_content_signatures(&self) -> Result<Vec<(String, Vec<u32>)>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path, signature FROM content_signatures ORDER BY note_path",
+        )?;
+        let signatures = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    crate::duplicates::from_blob(&row.get::<_, Vec<u8>>(1)?),
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(signatures)
+    }
+
+    // ─── Math ─────────────────────────────────────────────────────────
+
+    /// Get the math spans of a note, in document order
+    pub fn get_math(&self, note_path: &str) -> Result<Vec<crate::math::MathBlock>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT start_line, end_line, display, macros FROM math_blocks
+             WHERE note_path = ?1 ORDER BY start_line",
+        )?;
+        let blocks = stmt
+            .query_map([note_path], |row| {
+                let macros: String = row.get(3)?;
+                Ok(crate::math::MathBlock {
+                    start_line: row.get::<_, i64>(0)? as usize,
+                    end_line: row.get::<_, i64>(1)? as usize,
+                    display: row.get(2)?,
+                    macros: macros.split_whitespace().map(String::from).collect(),
+                    range: (0, 0),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(blocks)
+    }
+
+    // ─── Snippets ─────────────────────────────────────────────────────
+
+    /// Whether fenced code blocks are indexed as snippets (off by default)
+    pub fn snippet_mode(&self) -> Result<bool> {
+        Ok(self.get_setting(SNIPPET_MODE_SETTING)?.as_deref() == Some("true"))
+    }
+
+    /// Snippets whose code contains `query`, optionally of one language
+    pub fn search_snippets(
+        &self,
+        query: &str,
+        language: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Snippet>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path, line_number, language, content_hash, content FROM snippets
+             WHERE content LIKE '%' || ?1 || '%' AND (?2 IS NULL OR language = ?2)
+             ORDER BY note_path, line_number LIMIT ?3",
+        )?;
+        let language = language.map(str::to_lowercase);
+        let snippets = stmt
+            .query_map(rusqlite::params![query, language, limit as i64], |row| {
+                Ok(Snippet {
+                    path: row.get(0)?,
+                    line: row.get::<_, i64>(1)? as usize,
+                    language: row.get(2)?,
+                    content_hash: row.get(3)?,
+                    content: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(snippets)
+    }
+
+    /// Languages with at least one snippet, and how many each has
+    pub fn get_snippet_languages(&self) -> Result<Vec<(String, usize)>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT language, COUNT(*) FROM snippets GROUP BY language ORDER BY COUNT(*) DESC",
+        )?;
+        let languages = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(languages)
+    }
+
+    // ─── Drawings ─────────────────────────────────────────────────────
+
+    /// Drawings whose text elements contain `query` (case-insensitive)
+    pub fn search_drawing_text(&self, query: &str) -> Result<Vec<DrawingTextMatch>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path, element_id, text FROM drawing_text
+             WHERE text LIKE '%' || ?1 || '%' ORDER BY note_path",
+        )?;
+        let matches = stmt
+            .query_map([query], |row| {
+                Ok(DrawingTextMatch {
+                    path: row.get(0)?,
+                    element_id: row.get(1)?,
+                    text: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(matches)
+    }
+
+    // ─── Full-text search ─────────────────────────────────────────────
+
+    /// Notes matching every word of `query` (the last one as a prefix), best
+    /// first. Title matches weigh more than body matches; the snippet has
+    /// the matched words wrapped in `<mark>` and is otherwise HTML-escaped.
+    pub fn search_fulltext(&self, query: &str, limit: usize) -> Result<Vec<FulltextMatch>> {
+        let Some(query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT path, title,
+                    snippet(fts_content, 2, char(2), char(3), '…', 16),
+                    bm25(fts_content, 0.0, 10.0, 1.0) AS rank
+             FROM fts_content WHERE fts_content MATCH ?1
+             ORDER BY rank LIMIT ?2",
+        )?;
+        let matches = stmt
+            .query_map(rusqlite::params![query, limit as i64], |row| {
+                Ok(FulltextMatch {
+                    path: row.get(0)?,
+                    title: row.get(1)?,
+                    snippet: highlight(&row.get::<_, String>(2)?),
+                    rank: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .context("Failed to search notes")?;
+        Ok(matches)
+    }
+
+    // ─── CSV files ────────────────────────────────────────────────────
+
+    /// Every indexed `.csv` file with its headers and row count
+    pub fn get_csv_summaries(&self) -> Result<Vec<crate::csv_file::CsvSummary>> {
+        let conn = self.reader();
+        let mut stmt =
+            conn.prepare_cached("SELECT path, headers, row_count FROM csv_files ORDER BY path")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows
+            .into_iter()
+            .map(|(path, headers, row_count)| crate::csv_file::CsvSummary {
+                path,
+                headers: serde_json::from_str(&headers).unwrap_or_default(),
+                row_count: row_count as usize,
+            })
+            .collect())
+    }
+
+    // ─── Citations ────────────────────────────────────────────────────
+
+    /// Replace the cached bibliography
+    pub fn replace_bib_entries(&self, entries: &[crate::citations::BibEntry]) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("DELETE FROM bib_entries", [])?;
+            let mut stmt = conn.prepare_cached(
+                "INSERT OR REPLACE INTO bib_entries (citekey, entry) VALUES (?1, ?2)",
+            )?;
+            for entry in entries {
+                stmt.execute(rusqlite::params![
+                    entry.citekey,
+                    serde_json::to_string(entry)?
+                ])?;
+            }
+            Ok(())
+        })
+        .context("Failed to cache bibliography")
+    }
+
+    /// A bibliography entry by citekey
+    pub fn get_bib_entry(&self, citekey: &str) -> Result<Option<crate::citations::BibEntry>> {
+        let conn = self.reader();
+        let result = conn.query_row(
+            "SELECT entry FROM bib_entries WHERE citekey = ?1",
+            [citekey],
+            |row| row.get::<_, String>(0),
+        );
+        match result {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Notes that cite `citekey`
+    pub fn get_citing_notes(&self, citekey: &str) -> Result<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT note_path FROM citations WHERE citekey = ?1 ORDER BY note_path",
+        )?;
+        let paths = stmt
+            .query_map([citekey], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// The note whose frontmatter `citekey` property is `citekey`
+    pub fn find_literature_note(&self, citekey: &str) -> Result<Option<String>> {
+        let conn = self.reader();
+        let result = conn.query_row(
+            "SELECT note_path FROM properties WHERE key = 'citekey' AND value = ?1
+             ORDER BY note_path LIMIT 1",
+            [citekey],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(path) => Ok(Some(path)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // ─── Attachments ──────────────────────────────────────────────────
+
+    /// Record that the file at `path` was attached to `note_path`
+    pub fn record_attachment(&self, path: &str, note_path: &str) -> Result<()> {
+        self.write(|conn| {
+            let now = chrono::Utc::now().to_rfc3339();
+            conn.execute(
+                "INSERT INTO attachments (path, note_path, imported_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(path, note_path) DO UPDATE SET imported_at = excluded.imported_at",
+                [path, note_path, &now],
+            )
+            .context("Failed to record attachment")?;
+            Ok(())
+        })
+    }
+
+    /// Drop the records of an attachment that was deleted
+    pub fn forget_attachment(&self, path: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("DELETE FROM attachments WHERE path = ?1", [path])
+                .context("Failed to forget attachment")?;
+            Ok(())
+        })
+    }
+
+    /// Files attached to a note, oldest first
+    pub fn get_attachments(&self, note_path: &str) -> Result<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT path FROM attachments WHERE note_path = ?1 ORDER BY imported_at, path",
+        )?;
+        let paths = stmt
+            .query_map([note_path], |row| row.get(0))
+            .context("Failed to query attachments")?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    // ─── Settings ─────────────────────────────────────────────────────
+
+    /// Get a setting value
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.reader();
+        let result = conn
+            .prepare_cached("SELECT value FROM settings WHERE key = ?1")?
+            .query_row([key], |row| row.get(0));
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Set a setting value
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                [key, value],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn delete_setting(&self, key: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("DELETE FROM settings WHERE key = ?1", [key])?;
+            Ok(())
+        })
+    }
+
+    // ─── Keybindings ──────────────────────────────────────────────────
+
+    /// Customised shortcuts as (action, shortcut)
+    pub fn get_keybindings(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.reader();
+        let mut stmt =
+            conn.prepare_cached("SELECT action, shortcut FROM keybindings ORDER BY action")?;
+        let bindings = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(bindings)
+    }
+
+    pub fn set_keybinding(&self, action: &str, shortcut: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute(
+                "INSERT INTO keybindings (action, shortcut) VALUES (?1, ?2)
+                 ON CONFLICT(action) DO UPDATE SET shortcut = excluded.shortcut",
+                [action, shortcut],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Go back to the action's default shortcut
+    pub fn reset_keybinding(&self, action: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("DELETE FROM keybindings WHERE action = ?1", [action])?;
+            Ok(())
+        })
+    }
+
+    // ─── Bookmarks ────────────────────────────────────────────────────
+
+    /// Add a bookmark at the end of its parent group
+    pub fn add_bookmark(&self, bookmark: &NewBookmark) -> Result<Bookmark> {
+        self.write(|conn| {
+            if let Some(parent_id) = bookmark.parent_id {
+                ensure_bookmark_group(conn, parent_id)?;
+            }
+            let position: i64 = conn.query_row(
+                "SELECT COALESCE(MAX(position) + 1, 0) FROM bookmarks WHERE parent_id IS ?1",
+                [bookmark.parent_id],
+                |row| row.get(0),
+            )?;
+            let now = chrono::Utc::now().to_rfc3339();
+            conn.execute(
+                "INSERT INTO bookmarks (parent_id, kind, title, path, subpath, query, position, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                rusqlite::params![
+                    bookmark.parent_id,
+                    bookmark.kind.as_str(),
+                    &bookmark.title,
+                    &bookmark.path,
+                    &bookmark.subpath,
+                    &bookmark.query,
+                    position,
+                    &now,
+                ],
+            )
+            .context("Failed to add bookmark")?;
+            let id = conn.last_insert_rowid();
+            Ok(Bookmark {
+                id,
+                parent_id: bookmark.parent_id,
+                kind: bookmark.kind,
+                title: bookmark.title.clone(),
+                path: bookmark.path.clone(),
+                subpath: bookmark.subpath.clone(),
+                query: bookmark.query.clone(),
+                position,
+                created_at: now,
+            })
+        })
+    }
+
+    /// Get all bookmarks, ordered by parent then position
+    pub fn get_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, parent_id, kind, title, path, subpath, query, position, created_at
+             FROM bookmarks ORDER BY parent_id, position",
+        )?;
+        let bookmarks = stmt
+            .query_map([], |row| {
+                let kind: String = row.get(2)?;
+                Ok(Bookmark {
+                    id: row.get(0)?,
+                    parent_id: row.get(1)?,
+                    kind: BookmarkKind::parse(&kind).unwrap_or(BookmarkKind::Note),
+                    title: row.get(3)?,
+                    path: row.get(4)?,
+                    subpath: row.get(5)?,
+                    query: row.get(6)?,
+                    position: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            })
+            .context("Failed to query bookmarks")?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(bookmarks)
+    }
+
+    /// Rename a bookmark's display title
+    pub fn rename_bookmark(&self, id: i64, title: Option<&str>) -> Result<()> {
+        self.write(|conn| {
+            let updated = conn.execute(
+                "UPDATE bookmarks SET title = ?2 WHERE id = ?1",
+                rusqlite::params![id, title],
+            )?;
+            if updated == 0 {
+                anyhow::bail!("Bookmark not found");
+            }
+            Ok(())
+        })
+    }
+
+    /// Move a bookmark into a group (or the root) at the given index among its siblings
+    pub fn move_bookmark(&self, id: i64, parent_id: Option<i64>, index: usize) -> Result<()> {
+        self.write(|tx| {
+            if let Some(parent_id) = parent_id {
+                ensure_bookmark_group(tx, parent_id)?;
+                // Walk up from the new parent to make sure we're not moving into our own subtree
+                let mut cursor = Some(parent_id);
+                while let Some(current) = cursor {
+                    if current == id {
+                        anyhow::bail!("Cannot move a bookmark group into itself");
+                    }
+                    cursor = tx.query_row(
+                        "SELECT parent_id FROM bookmarks WHERE id = ?1",
+                        [current],
+                        |row| row.get(0),
+                    )?;
+                }
+            }
+
+            let mut siblings: Vec<i64> = {
+                let mut stmt = tx.prepare_cached(
+                    "SELECT id FROM bookmarks WHERE parent_id IS ?1 AND id != ?2 ORDER BY position",
+                )?;
+                let ids = stmt
+                    .query_map(rusqlite::params![parent_id, id], |row| row.get(0))?
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                ids
+            };
+            siblings.insert(index.min(siblings.len()), id);
+
+            let updated = tx.execute(
+                "UPDATE bookmarks SET parent_id = ?2 WHERE id = ?1",
+                rusqlite::params![id, parent_id],
+            )?;
+            if updated == 0 {
+                anyhow::bail!("Bookmark not found");
+            }
+            for (position, sibling) in siblings.iter().enumerate() {
+                tx.execute(
+                    "UPDATE bookmarks SET position = ?2 WHERE id = ?1",
+                    rusqlite::params![sibling, position as i64],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Delete a bookmark (and everything nested under it, for groups)
+    pub fn delete_bookmark(&self, id: i64) -> Result<()> {
+        self.write(|conn| {
+            // Nested bookmarks cascade through parent_id
+            conn.execute("DELETE FROM bookmarks WHERE id = ?1", [id])
+                .context("Failed to delete bookmark")?;
+            Ok(())
+        })
+    }
+
+    // ─── Workspaces ───────────────────────────────────────────────────
+
+    /// Save (or overwrite) a named workspace layout
+    pub fn save_workspace(&self, name: &str, layout_json: &str) -> Result<()> {
+        self.write(|conn| {
+            let now = chrono::Utc::now().to_rfc3339();
+            conn.execute(
+                "INSERT INTO workspaces (name, layout, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(name) DO UPDATE SET
+                   layout = excluded.layout,
+                   updated_at = excluded.updated_at",
+                [name, layout_json, &now],
+            )
+            .context("Failed to save workspace")?;
+            Ok(())
+        })
+    }
+
+    /// Load a workspace layout by name
+    pub fn load_workspace(&self, name: &str) -> Result<Option<String>> {
+        let conn = self.reader();
+        let result = conn.query_row(
+            "SELECT layout FROM workspaces WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(layout) => Ok(Some(layout)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// List saved workspaces (excluding the automatic last-session slot)
+    pub fn list_workspaces(&self) -> Result<Vec<WorkspaceSummary>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT name, updated_at FROM workspaces WHERE name != ?1 ORDER BY name COLLATE NOCASE",
+        )?;
+        let workspaces = stmt
+            .query_map([LAST_SESSION_WORKSPACE], |row| {
+                Ok(WorkspaceSummary {
+                    name: row.get(0)?,
+                    updated_at: row.get(1)?,
+                })
+            })
+            .context("Failed to query workspaces")?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(workspaces)
+    }
+
+    /// Delete a named workspace
+    pub fn delete_workspace(&self, name: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("DELETE FROM workspaces WHERE name = ?1", [name])
+                .context("Failed to delete workspace")?;
+            Ok(())
+        })
+    }
+
+    // ─── Saved searches ───────────────────────────────────────────────
+
+    /// Save a query under a name, replacing any search already called that
+    pub fn save_search(&self, name: &str, query: &str) -> Result<()> {
+        self.write(|conn| {
+            let now = chrono::Utc::now().to_rfc3339();
+            conn.execute(
+                "INSERT INTO saved_searches (name, query, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(name) DO UPDATE SET
+                   query = excluded.query,
+                   updated_at = excluded.updated_at",
+                [name, query, &now],
+            )
+            .context("Failed to save search")?;
+            Ok(())
+        })
+    }
+
+    /// The query saved under `name`
+    pub fn get_saved_search(&self, name: &str) -> Result<Option<String>> {
+        let conn = self.reader();
+        let result = conn.query_row(
+            "SELECT query FROM saved_searches WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        );
+        match result {
+            Ok(query) => Ok(Some(query)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every saved search, by name
+    pub fn list_saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT name, query, updated_at FROM saved_searches ORDER BY name COLLATE NOCASE",
+        )?;
+        let searches = stmt
+            .query_map([], |row| {
+                Ok(SavedSearch {
+                    name: row.get(0)?,
+                    query: row.get(1)?,
+                    updated_at: row.get(2)?,
+                })
+            })
+            .context("Failed to query saved searches")?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(searches)
+    }
+
+    pub fn delete_saved_search(&self, name: &str) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("DELETE FROM saved_searches WHERE name = ?1", [name])
+                .context("Failed to delete saved search")?;
+            Ok(())
+        })
+    }
+
+    // ─── Maintenance ──────────────────────────────────────────────────
+
+    /// SQLite tuning saved for this vault, or the defaults
+    pub fn tuning(&self) -> Result<DbTuning> {
+        match self.get_setting(DB_TUNING_SETTING)? {
+            Some(json) => serde_json::from_str(&json).context("Invalid database tuning"),
+            None => Ok(DbTuning::default()),
+        }
+    }
+
+    /// Save and apply new tuning
+    pub fn set_tuning(&self, tuning: &DbTuning) -> Result<()> {
+        tuning.validate()?;
+        self.set_setting(DB_TUNING_SETTING, &serde_json::to_string(tuning)?)?;
+        self.apply_tuning(tuning)
+    }
+
+    fn apply_tuning(&self, tuning: &DbTuning) -> Result<()> {
+        // A negative cache_size is in KiB rather than pages
+        let cache_size = format!("PRAGMA cache_size = -{};", tuning.cache_size_kib);
+        self.writer()
+            .execute_batch(&format!(
+                "PRAGMA wal_autocheckpoint = {}; PRAGMA synchronous = {}; {}",
+                tuning.wal_autocheckpoint,
+                tuning.synchronous.pragma(),
+                cache_size
+            ))
+            .context("Failed to apply database tuning")?;
+        for reader in &self.readers {
+            reader
+                .lock_or_recover()
+                .execute_batch(&cache_size)
+                .context("Failed to apply database tuning")?;
+        }
+        Ok(())
+    }
+
+    /// Copy the WAL back into `cache.db` and truncate it
+    pub fn checkpoint(&self) -> Result<()> {
+        self.writer()
+            .execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+            .context("Failed to checkpoint the WAL")
+    }
+
+    /// Checkpoint the WAL, check integrity, refresh planner statistics and
+    /// compact the file. A cache that fails the integrity check is left as
+    /// is (rebuild it instead).
+    pub fn run_maintenance(&self) -> Result<MaintenanceReport> {
+        let conn = self.writer();
+        let size_before = cache_size(&conn);
+
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+            .context("Failed to checkpoint the WAL")?;
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let integrity_errors = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|line| line != "ok")
+            .collect::<Vec<_>>();
+        drop(stmt);
+
+        if integrity_errors.is_empty() {
+            conn.execute_batch("ANALYZE; VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+                .context("Failed to compact the cache")?;
+        }
+
+        Ok(MaintenanceReport {
+            size_before,
+            size_after: cache_size(&conn),
+            integrity_errors,
+        })
+    }
+
+    // ─── Diagnostics ──────────────────────────────────────────────────
+
+    /// Log an indexing problem and keep it for the debug dump
+    fn index_warning(&self, path: &str, message: String) {
+        log::warn!("{}: {}", path, message);
+        let mut warnings = self.warnings.lock_or_recover();
+        if warnings.len() == MAX_INDEX_WARNINGS {
+            warnings.pop_front();
+        }
+        warnings.push_back(IndexWarning {
+            path: path.to_string(),
+            message,
+            at: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+
+    /// Row counts and a few sample rows from every cache table, plus recent
+    /// indexing warnings, for attaching to bug reports. The lock passphrase
+    /// hash and the full-text index (whole note bodies) are left out.
+    pub fn debug_dump(&self) -> Result<serde_json::Value> {
+        let conn = self.reader();
+        let tables: Vec<String> = {
+            let mut stmt = conn.prepare_cached(
+                "SELECT name FROM sqlite_master
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+                   AND name NOT LIKE 'fts_content%' ORDER BY name",
+            )?;
+            let names = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            names
+        };
+
+        let mut dump = serde_json::Map::new();
+        for table in &tables {
+            let count: i64 =
+                conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| {
+                    row.get(0)
+                })?;
+            let mut stmt = conn.prepare(&format!("SELECT * FROM \"{}\" LIMIT ?1", table))?;
+            let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+            let sample = stmt
+                .query_map([DUMP_SAMPLE_ROWS as i64], |row| {
+                    let mut object = serde_json::Map::new();
+                    for (i, column) in columns.iter().enumerate() {
+                        object.insert(column.clone(), json_value(row.get_ref(i)?));
+                    }
+                    Ok(serde_json::Value::Object(object))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?
+                .into_iter()
+                .filter(|row| {
+                    !(table == "settings" && row["key"].as_str() == Some(LOCK_HASH_SETTING))
+                })
+                .collect::<Vec<_>>();
+            dump.insert(
+                table.clone(),
+                serde_json::json!({ "count": count, "sample": sample }),
+            );
+        }
+
+        let warnings: Vec<IndexWarning> = self.warnings.lock_or_recover().iter().cloned().collect();
+        Ok(serde_json::json!({
+            "app_version": APP_VERSION,
+            "schema": read_schema_info(&conn)?,
+            "generated_at": chrono::Utc::now().to_rfc3339(),
+            "tables": dump,
+            "index_warnings": warnings,
+        }))
+    }
+
+    // ─── Bulk operations ──────────────────────────────────────────────
+
+    /// Reindex the entire vault — scans all indexed files and updates the
+    /// cache. Files whose modification time or content hash matches what
+    /// they were last indexed from are skipped.
+    pub fn reindex_vault(&self, vault_path: &Path) -> Result<()> {
+        let known = self.get_file_states()?;
+        let snippets = self.snippet_mode()?;
+        let now = chrono::Utc::now().timestamp();
+        let notes = crate::vault::Vault::list_notes(vault_path)?;
+        let drawings = crate::vault::Vault::list_drawings(vault_path)?;
+        let canvases = crate::vault::Vault::list_canvases(vault_path)?;
+        let csv_files = crate::vault::Vault::list_csv_files(vault_path)?;
+
+        // Files whose mtime is unchanged since they were indexed are current
+        let stale: Vec<&crate::vault::VaultEntry> = notes
+            .iter()
+            .chain(&drawings)
+            .chain(&canvases)
+            .chain(&csv_files)
+            .filter(|entry| {
+                !known
+                    .get(&entry.path)
+                    .is_some_and(|(_, mtime)| *mtime == Some(entry.modified))
+            })
+            .collect();
+        // A commit per batch instead of per file; the write lock is still
+        // released between batches for saves and other windows
+        for batch in stale.chunks(REINDEX_BATCH) {
+            self.write(|conn| {
+                for entry in batch {
+                    let state = known.get(&entry.path);
+                    self.reindex_entry(conn, vault_path, entry, state, now, snippets)?;
+                }
+                Ok(())
+            })?;
+        }
+
+        // The bibliography isn't a note; it is reread whole
+        if let Err(e) = crate::citations::reindex_bibliography(vault_path, self) {
+            let bib_path = self
+                .get_setting(crate::citations::BIB_FILE_SETTING)?
+                .unwrap_or_default();
+            self.index_warning(&bib_path, format!("Skipping bibliography: {:#}", e));
+        }
+
+        // Remove notes that no longer exist on disk
+        let all_cached = self.get_all_notes()?;
+        let disk_paths: std::collections::HashSet<String> = notes
+            .iter()
+            .chain(&drawings)
+            .chain(&canvases)
+            .chain(&csv_files)
+            .map(|e| e.path.clone())
+            .collect();
+        let gone: Vec<&CachedNote> = all_cached
+            .iter()
+            .filter(|cached| !disk_paths.contains(&cached.path))
+            .collect();
+        if !gone.is_empty() {
+            self.write(|conn| {
+                gone.iter()
+                    .try_for_each(|cached| remove_note(conn, &cached.path))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Bring one file of a `reindex_vault` batch up to date: parse it if
+    /// its content hash changed, then record what it was indexed from
+    fn reindex_entry(
+        &self,
+        conn: &Connection,
+        vault_path: &Path,
+        entry: &crate::vault::VaultEntry,
+        state: Option<&FileState>,
+        now: i64,
+        snippets: bool,
+    ) -> Result<()> {
+        let content = match crate::vault::Vault::read_file(vault_path, &entry.path) {
+            Ok(c) => c,
+            Err(e) => {
+                self.index_warning(&entry.path, format!("Failed to read: {}", e));
+                return Ok(());
+            }
+        };
+        let hash = crate::indexer::content_hash(&content);
+        let content = crate::crypto::index_view(vault_path, &entry.path, &content);
+        let changed = !state.is_some_and(|(known_hash, _)| known_hash.as_deref() == Some(&hash));
+        // A file written this second could change again within it
+        // without its mtime moving, so only its hash counts for now
+        let mtime = (entry.modified < now).then_some(entry.modified);
+
+        let indexed = in_savepoint(conn, || {
+            if changed {
+                self.index_file(conn, &entry.path, content, snippets)?;
+            }
+            store_file_state(conn, &entry.path, &hash, mtime)
+        });
+        // One malformed canvas or CSV file shouldn't stop the rest of the
+        // vault indexing
+        match indexed {
+            Err(e) if entry.path.ends_with(".canvas") => {
+                self.index_warning(&entry.path, format!("Skipping canvas: {}", e));
+                Ok(())
+            }
+            Err(e) if entry.path.ends_with(".csv") => {
+                self.index_warning(&entry.path, format!("Skipping CSV file: {}", e));
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Reindex a single note (after save or external change)
+    pub fn reindex_note(&self, vault_path: &Path, relative_path: &str) -> Result<()> {
+        let content = crate::vault::Vault::read_file(vault_path, relative_path)?;
+        let indexed = crate::crypto::index_view(vault_path, relative_path, &content);
+        let hash = crate::indexer::content_hash(&content);
+        let snippets = self.snippet_mode()?;
+        self.write(|conn| {
+            self.index_file(conn, relative_path, indexed, snippets)?;
+            // The next vault reindex checks the hash and records the mtime
+            store_file_state(conn, relative_path, &hash, None)
+        })
+    }
+
+    /// What each cached file was last indexed from
+    pub fn get_file_states(&self) -> Result<HashMap<String, FileState>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT path, content_hash, fs_mtime FROM notes")?;
+        let states = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()
+            .context("Failed to query file states")?;
+        Ok(states)
+    }
+
+    /// Make the next `reindex_vault` parse every file again, e.g. after a
+    /// setting changed what indexing stores
+    pub fn forget_file_states(&self) -> Result<()> {
+        self.write(|conn| {
+            conn.execute("UPDATE notes SET content_hash = NULL, fs_mtime = NULL", [])
+                .context("Failed to reset file states")?;
+            Ok(())
+        })
+    }
+
+    /// Index `content` as the file at `relative_path` without reading it
+    /// from disk
+    #[cfg(test)]
+    fn index_content(&self, relative_path: &str, content: &str) -> Result<()> {
+        let snippets = self.snippet_mode()?;
+        self.write(|conn| self.index_file(conn, relative_path, content, snippets))
+    }
+
+    /// Parse a file and replace everything cached for it, within the
+    /// caller's transaction so a failure leaves the file's old rows intact.
+    /// `snippets` is the snippet mode setting.
+    fn index_file(
+        &self,
+        conn: &Connection,
+        relative_path: &str,
+        content: &str,
+        snippets: bool,
+    ) -> Result<()> {
+        if relative_path.ends_with(".canvas") {
+            self.index_canvas(conn, relative_path, content)
+        } else if relative_path.ends_with(".csv") {
+            self.index_csv(conn, relative_path, content)
+        } else if crate::excalidraw::is_drawing(relative_path) {
+            self.index_drawing(conn, relative_path, content, snippets)
+        } else {
+            self.index_markdown(conn, relative_path, content, snippets)
+        }
+    }
+
+    /// Cache a canvas like a note so its file nodes and text-node wikilinks
+    /// show up in backlinks and the graph
+    fn index_canvas(&self, conn: &Connection, relative_path: &str, content: &str) -> Result<()> {
+        let canvas = crate::canvas::Canvas::parse(content)?;
+        self.index_file_entry(conn, relative_path, &canvas.link_targets())
+    }
+
+    /// Cache a `.csv` file's headers and row count; it is listed like a note
+    /// so it shows up in the quick switcher
+    fn index_csv(&self, conn: &Connection, relative_path: &str, content: &str) -> Result<()> {
+        let table = crate::csv_file::CsvTable::parse(content)?;
+        self.index_file_entry(conn, relative_path, &[])?;
+        store_csv_summary(
+            conn,
+            &crate::csv_file::CsvSummary {
+                path: relative_path.to_string(),
+                headers: table.headers().to_vec(),
+                row_count: table.rows().len(),
+            },
+        )
+    }
+
+    /// Cache a non-markdown file as a note titled by its file name, with
+    /// the given outgoing links and no markdown structure
+    fn index_file_entry(
+        &self,
+        conn: &Connection,
+        relative_path: &str,
+        links: &[String],
+    ) -> Result<()> {
+        let title = Path::new(relative_path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        store_note(
+            conn,
+            &CachedNote {
+                path: relative_path.to_string(),
+                title: title.clone(),
+                created_at: None,
+                modified_at: None,
+                word_count: 0,
+                starred: false,
+                pinned: false,
+                pin_order: None,
+                aliases: Vec::new(),
+            },
+        )?;
+        store_links(conn, relative_path, links)?;
+        store_tags(conn, relative_path, &[])?;
+        store_aliases(conn, relative_path, &[])?;
+        store_properties(conn, relative_path, &[])?;
+        store_headings(conn, relative_path, &[])?;
+        store_blocks(conn, relative_path, &[])?;
+        store_section_links(conn, relative_path, &[])?;
+        store_diagrams(conn, relative_path, &[])?;
+        store_math(conn, relative_path, &[])?;
+        store_callouts(conn, relative_path, &[])?;
+        store_tasks(conn, relative_path, &[])?;
+        store_embeddings(conn, relative_path, &[])?;
+        store_content_signature(conn, relative_path, &[])?;
+        store_citations(conn, relative_path, &[])?;
+        store_fulltext(conn, relative_path, &title, "")?;
+        Ok(())
+    }
+
+    /// Index an Excalidraw drawing: only the markdown around the scene counts
+    /// as note text, and the scene's text elements are cached separately
+    fn index_drawing(
+        &self,
+        conn: &Connection,
+        relative_path: &str,
+        content: &str,
+        snippets: bool,
+    ) -> Result<()> {
+        let (texts, scene_links) = match crate::excalidraw::scene(relative_path, content) {
+            Ok(scene) => (
+                crate::excalidraw::text_elements(&scene),
+                crate::excalidraw::links(&scene),
+            ),
+            Err(e) => {
+                self.index_warning(relative_path, format!("Skipping drawing text: {}", e));
+                (Vec::new(), Vec::new())
+            }
+        };
+
+        let markdown = crate::excalidraw::markdown_part(relative_path, content);
+        self.index_markdown(conn, relative_path, markdown, snippets)?;
+        // Raw drawings have no markdown, and the plugin's markdown doesn't
+        // always list element links, so add the scene's links to it
+        let mut targets =
+            crate::indexer::extract_wikilinks(&crate::crypto::strip_encrypted(markdown));
+        for target in scene_links {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        store_links(conn, relative_path, &targets)?;
+        store_drawing_text(conn, relative_path, &texts)
+    }
+
+    fn index_markdown(
+        &self,
+        conn: &Connection,
+        relative_path: &str,
+        content: &str,
+        snippets: bool,
+    ) -> Result<()> {
+        // Encrypted blocks are opaque: no words, links or tags from ciphertext
+        let content = &*crate::crypto::strip_encrypted(content);
+        let fm = crate::vault::Vault::parse_frontmatter(content);
+        let index = crate::indexer::index_note(relative_path, content, &fm.tags);
+
+        let title = fm.title.unwrap_or_else(|| index.title.clone());
+
+        let cached_note = CachedNote {
+            path: relative_path.to_string(),
+            title,
+            created_at: fm.created.clone(),
+            modified_at: fm.modified.clone(),
+            word_count: index.word_count as i64,
+            starred: false,
+            pinned: false,
+            pin_order: None,
+            aliases: fm.aliases.clone(),
+        };
+
+        store_note(conn, &cached_note)?;
+        store_links(conn, relative_path, &index.outgoing_links)?;
+        store_tags(conn, relative_path, &index.tags)?;
+        store_aliases(conn, relative_path, &fm.aliases)?;
+        store_properties(
+            conn,
+            relative_path,
+            &crate::properties::from_frontmatter(&fm.extra),
+        )?;
+        store_headings(conn, relative_path, &index.headings)?;
+        store_blocks(conn, relative_path, &index.blocks)?;
+        store_section_links(conn, relative_path, &index.section_links)?;
+        store_diagrams(conn, relative_path, &index.diagrams)?;
+        store_math(conn, relative_path, &index.math)?;
+        store_callouts(conn, relative_path, &index.callouts)?;
+        store_tasks(conn, relative_path, &index.tasks)?;
+        store_embeddings(conn, relative_path, &crate::embeddings::embed_note(content))?;
+        store_content_signature(conn, relative_path, &crate::duplicates::signature(content))?;
+        store_citations(
+            conn,
+            relative_path,
+            &crate::citations::extract_citekeys(content),
+        )?;
+        store_fulltext(
+            conn,
+            relative_path,
+            &cached_note.title,
+            &crate::vault::Vault::strip_frontmatter(content),
+        )?;
+        let code_blocks = if snippets {
+            crate::indexer::extract_code_blocks(content)
+        } else {
+            Vec::new()
+        };
+        store_snippets(conn, relative_path, &code_blocks)?;
+
+        Ok(())
+    }
+}
+
+/// Closing the vault (switching vaults, quitting) folds the WAL back in so
+/// it doesn't linger at its high-water size
+impl Drop for Database {
+    fn drop(&mut self) {
+        if let Err(e) = self.checkpoint() {
+            log::warn!("{:#}", e);
+        }
+    }
+}
+
+// Writers for everything cached about a file. They take the connection so
+// indexing a note runs them all in one transaction.
+
+fn store_note(conn: &Connection, note: &CachedNote) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO notes (path, title, created_at, modified_at, word_count, starred)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(path) DO UPDATE SET
+           title = excluded.title,
+           modified_at = excluded.modified_at,
+           word_count = excluded.word_count",
+    )?;
+    stmt.execute((
+        &note.path,
+        &note.title,
+        &note.created_at,
+        &note.modified_at,
+        note.word_count,
+        note.starred as i32,
+    ))
+    .context("Failed to upsert note")?;
+    Ok(())
+}
+
+fn remove_note(conn: &Connection, path: &str) -> Result<()> {
+    // Indexed rows cascade from `notes`; recents and pins have no
+    // foreign key so they can be moved on rename before the note exists
+    conn.execute("DELETE FROM recent_files WHERE path = ?1", [path])?;
+    conn.execute("DELETE FROM history WHERE path = ?1", [path])?;
+    conn.execute("DELETE FROM pins WHERE path = ?1", [path])?;
+    conn.execute("DELETE FROM attachments WHERE note_path = ?1", [path])?;
+    conn.execute("DELETE FROM fts_content WHERE path = ?1", [path])?;
+    conn.execute("DELETE FROM notes WHERE path = ?1", [path])
+        .context("Failed to delete note")?;
+    Ok(())
+}
+
+fn store_file_state(conn: &Connection, path: &str, hash: &str, mtime: Option<i64>) -> Result<()> {
+    conn.prepare_cached("UPDATE notes SET content_hash = ?2, fs_mtime = ?3 WHERE path = ?1")?
+        .execute(rusqlite::params![path, hash, mtime])
+        .context("Failed to record file state")?;
+    Ok(())
+}
+
+fn store_links(conn: &Connection, source_path: &str, targets: &[String]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM links WHERE source_path = ?1")?
+        .execute([source_path])?;
+    let mut stmt = conn
+        .prepare_cached("INSERT OR IGNORE INTO links (source_path, target_name) VALUES (?1, ?2)")?;
+    for target in targets {
+        stmt.execute(rusqlite::params![source_path, target])?;
+    }
+    Ok(())
+}
+
+fn store_tags(conn: &Connection, note_path: &str, tags: &[String]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM tags WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt =
+        conn.prepare_cached("INSERT OR IGNORE INTO tags (note_path, tag) VALUES (?1, ?2)")?;
+    for tag in tags {
+        stmt.execute(rusqlite::params![note_path, tag])?;
+    }
+    Ok(())
+}
+
+fn store_properties(conn: &Connection, note_path: &str, properties: &[Property]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM properties WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO properties (note_path, key, value, type) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for p in properties {
+        stmt.execute(rusqlite::params![
+            note_path,
+            p.key,
+            p.value,
+            p.kind.as_str()
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_aliases(conn: &Connection, note_path: &str, aliases: &[String]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM aliases WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt =
+        conn.prepare_cached("INSERT OR IGNORE INTO aliases (note_path, alias) VALUES (?1, ?2)")?;
+    for alias in aliases.iter().map(|a| a.trim()).filter(|a| !a.is_empty()) {
+        stmt.execute(rusqlite::params![note_path, alias])?;
+    }
+    Ok(())
+}
+
+fn store_headings(
+    conn: &Connection,
+    note_path: &str,
+    headings: &[crate::indexer::Heading],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM headings WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO headings (note_path, text, level, line_number, slug)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for h in headings {
+        stmt.execute(rusqlite::params![
+            note_path,
+            &h.text,
+            h.level as i32,
+            h.line as i32,
+            &h.slug,
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_section_links(
+    conn: &Connection,
+    note_path: &str,
+    links: &[crate::indexer::SectionLink],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM section_links WHERE source_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO section_links (source_path, target_name, heading, line_number)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for link in links {
+        stmt.execute(rusqlite::params![
+            note_path,
+            &link.target,
+            &link.heading,
+            link.line as i64
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_blocks(
+    conn: &Connection,
+    note_path: &str,
+    blocks: &[crate::indexer::BlockRef],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM blocks WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO blocks (note_path, block_id, line_number) VALUES (?1, ?2, ?3)",
+    )?;
+    for b in blocks {
+        stmt.execute(rusqlite::params![note_path, &b.id, b.line as i64])?;
+    }
+    Ok(())
+}
+
+fn store_diagrams(
+    conn: &Connection,
+    note_path: &str,
+    diagrams: &[crate::mermaid::MermaidBlock],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM diagrams WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO diagrams (note_path, start_line, end_line, diagram_type)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for d in diagrams {
+        stmt.execute(rusqlite::params![
+            note_path,
+            d.start_line as i64,
+            d.end_line as i64,
+            &d.diagram_type
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_callouts(
+    conn: &Connection,
+    note_path: &str,
+    callouts: &[crate::indexer::Callout],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM callouts WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO callouts (note_path, kind, title, fold, start_line, end_line)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for c in callouts {
+        stmt.execute(rusqlite::params![
+            note_path,
+            &c.kind,
+            &c.title,
+            c.fold.map(String::from),
+            c.start_line as i64,
+            c.end_line as i64
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_tasks(conn: &Connection, note_path: &str, tasks: &[crate::indexer::Task]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM tasks WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO tasks (note_path, line, text, completed, due, heading)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for t in tasks {
+        stmt.execute(rusqlite::params![
+            note_path,
+            t.line as i64,
+            &t.text,
+            t.completed,
+            &t.due,
+            &t.heading
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_embeddings(
+    conn: &Connection,
+    note_path: &str,
+    chunks: &[crate::embeddings::ChunkEmbedding],
+) -> Result<()> {
+    // Sentence embeddings are slow to compute; keep those of unchanged chunks
+    let kept: HashMap<String, Vec<u8>> = conn
+        .prepare_cached(
+            "SELECT text, semantic FROM embeddings WHERE note_path = ?1 AND semantic IS NOT NULL",
+        )?
+        .query_map([note_path], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<_, _>>()?;
+    conn.prepare_cached("DELETE FROM embeddings WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO embeddings (note_path, line, excerpt, vector, text, semantic)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for chunk in chunks {
+        stmt.execute(rusqlite::params![
+            note_path,
+            chunk.line as i64,
+            &chunk.excerpt,
+            crate::embeddings::to_blob(&chunk.vector),
+            &chunk.text,
+            kept.get(&chunk.text)
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_content_signature(conn: &Connection, note_path: &str, signature: &[u32]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM content_signatures WHERE note_path = ?1")?
+        .execute([note_path])?;
+    if !signature.is_empty() {
+        conn.prepare_cached(
+            "INSERT INTO content_signatures (note_path, signature) VALUES (?1, ?2)",
+        )?
+        .execute(rusqlite::params![
+            note_path,
+            crate::duplicates::to_blob(signature)
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_math(conn: &Connection, note_path: &str, blocks: &[crate::math::MathBlock]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM math_blocks WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO math_blocks (note_path, start_line, end_line, display, macros)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for b in blocks {
+        stmt.execute(rusqlite::params![
+            note_path,
+            b.start_line as i64,
+            b.end_line as i64,
+            b.display,
+            b.macros.join(" ")
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_snippets(
+    conn: &Connection,
+    note_path: &str,
+    blocks: &[crate::indexer::CodeBlock],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM snippets WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO snippets (note_path, line_number, language, content_hash, content)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for b in blocks.iter().filter(|b| !b.content.trim().is_empty()) {
+        stmt.execute(rusqlite::params![
+            note_path,
+            b.start_line as i64,
+            b.language.to_lowercase(),
+            crate::indexer::content_hash(&b.content),
+            &b.content
+        ])?;
+    }
+    Ok(())
+}
+
+fn store_drawing_text(
+    conn: &Connection,
+    note_path: &str,
+    texts: &[crate::excalidraw::DrawingText],
+) -> Result<()> {
+    conn.prepare_cached("DELETE FROM drawing_text WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO drawing_text (note_path, element_id, text) VALUES (?1, ?2, ?3)",
+    )?;
+    for t in texts {
+        stmt.execute(rusqlite::params![note_path, &t.element_id, &t.text])?;
+    }
+    Ok(())
+}
+
+fn store_fulltext(conn: &Connection, note_path: &str, title: &str, body: &str) -> Result<()> {
+    conn.prepare_cached("DELETE FROM fts_content WHERE path = ?1")?
+        .execute([note_path])?;
+    conn.prepare_cached("INSERT INTO fts_content (path, title, body) VALUES (?1, ?2, ?3)")?
+        .execute([note_path, title, body])?;
+    Ok(())
+}
+
+fn store_csv_summary(conn: &Connection, summary: &crate::csv_file::CsvSummary) -> Result<()> {
+    let headers = serde_json::to_string(&summary.headers)?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO csv_files (path, headers, row_count) VALUES (?1, ?2, ?3)
+         ON CONFLICT(path) DO UPDATE SET headers = excluded.headers, row_count = excluded.row_count",
+    )?;
+    stmt.execute(rusqlite::params![
+        &summary.path,
+        headers,
+        summary.row_count as i64
+    ])
+    .context("Failed to cache CSV summary")?;
+    Ok(())
+}
+
+fn store_citations(conn: &Connection, note_path: &str, citekeys: &[String]) -> Result<()> {
+    conn.prepare_cached("DELETE FROM citations WHERE note_path = ?1")?
+        .execute([note_path])?;
+    let mut stmt = conn
+        .prepare_cached("INSERT OR IGNORE INTO citations (note_path, citekey) VALUES (?1, ?2)")?;
+    for citekey in citekeys {
+        stmt.execute([note_path, citekey])?;
+    }
+    Ok(())
+}
+/// Columns for building a `CachedNote` (see `note_from_row`); aliases come
+/// joined by `ALIAS_SEPARATOR`
+const NOTE_SELECT: &str =
+    "SELECT n.path, n.title, n.created_at, n.modified_at, n.word_count, n.starred, p.pin_order,
+            (SELECT group_concat(alias, char(31)) FROM aliases WHERE note_path = n.path)
+     FROM notes n LEFT JOIN pins p ON p.path = n.path";
+const ALIAS_SEPARATOR: char = '\u{1f}';
+
+/// Content hash and modification time a file was last indexed from
+pub type FileState = (Option<String>, Option<i64>);
+
+/// Every lowercase link target that resolves, mapped to the note it picks,
+/// by the rules of `resolve_link_target`: exact paths, then titles, then
+/// file names (with their folders) anywhere in the vault, then aliases,
+/// shortest path first
+fn link_resolver<'a>(notes: &'a [CachedNote]) -> HashMap<String, String> {
+    let mut best: HashMap<String, (u8, usize, &str)> = HashMap::new();
+    let mut offer = |key: String, rank: u8, path: &'a str| {
+        let candidate = (rank, path.len(), path);
+        best.entry(key)
+            .and_modify(|current| {
+                if candidate < *current {
+                    *current = candidate;
+                }
+            })
+            .or_insert(candidate);
+    };
+    for note in notes {
+        let path = note.path.to_lowercase();
+        offer(path.clone(), 0, &note.path);
+        if let Some(stem) = path.strip_suffix(".md") {
+            offer(stem.to_string(), 0, &note.path);
+            for (i, _) in stem.match_indices('/') {
+                offer(stem[i + 1..].to_string(), 2, &note.path);
+            }
+        }
+        offer(note.title.to_lowercase(), 1, &note.path);
+        for alias in &note.aliases {
+            offer(alias.trim().to_lowercase(), 3, &note.path);
+        }
+    }
+    best.into_iter()
+        .map(|(key, (_, _, path))| (key, path.to_string()))
+        .collect()
+}
+
+/// Run `op` in a savepoint of the open transaction, so a failure undoes
+/// only its own writes and the rest of the transaction can still commit
+fn in_savepoint(conn: &Connection, op: impl FnOnce() -> Result<()>) -> Result<()> {
+    conn.execute_batch("SAVEPOINT op;")?;
+    match op() {
+        Ok(()) => conn.execute_batch("RELEASE op;")?,
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO op; RELEASE op;")?;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Whether an error means another connection holds a conflicting lock
+pub(crate) fn is_busy(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rusqlite::Error>(),
+            Some(rusqlite::Error::SqliteFailure(e, _))
+                if matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+        )
+    })
+}
+
+/// Whether an error came from a damaged database file
+pub(crate) fn is_corruption(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rusqlite::Error>(),
+            Some(rusqlite::Error::SqliteFailure(e, _))
+                if matches!(e.code, ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+        )
+    })
+}
+
+/// An FTS5 query matching each word of free text, the last as a prefix so
+/// results update while typing. Quoting keeps FTS syntax (`-`, `OR`, `:`)
+/// in the input literal.
+fn fts_query(input: &str) -> Option<String> {
+    let words: Vec<String> = input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", words.join(" ")))
+}
+
+/// HTML-escape a snippet and turn FTS5's match markers into `<mark>` tags
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len() + 16);
+    for c in snippet.chars() {
+        match c {
+            '\u{2}' => html.push_str("<mark>"),
+            '\u{3}' => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+/// Delete rows whose parent is gone. Caches written before foreign keys
+/// were enforced can hold links, tags etc. of notes deleted long ago.
+fn purge_orphans(conn: &Connection) -> Result<()> {
+    let orphans = {
+        let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to check foreign keys")?
+    };
+    if orphans.is_empty() {
+        return Ok(());
+    }
+    log::info!("Removing {} orphaned cache rows", orphans.len());
+    let tx = conn.unchecked_transaction()?;
+    for (table, rowid) in &orphans {
+        tx.execute(
+            &format!("DELETE FROM \"{}\" WHERE rowid = ?1", table),
+            [rowid],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Schema version and writer of a cache, from its `schema_info` table
+fn read_schema_info(conn: &Connection) -> Result<SchemaInfo> {
+    let has_table: bool = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_info'",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to inspect cache schema")?;
+    let mut info = SchemaInfo::unversioned();
+    if !has_table {
+        return Ok(info);
+    }
+    let mut stmt = conn.prepare("SELECT key, value FROM schema_info")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        match row? {
+            (key, value) if key == "schema_version" => {
+                info.schema_version = value.parse().context("Invalid cache schema version")?
+            }
+            (key, value) if key == "app_version" => info.app_version = Some(value),
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn note_from_row(row: &rusqlite::Row) -> rusqlite::Result<CachedNote> {
+    let pin_order: Option<i64> = row.get(6)?;
+    Ok(CachedNote {
+        path: row.get(0)?,
+        title: row.get(1)?,
+        created_at: row.get(2)?,
+        modified_at: row.get(3)?,
+        word_count: row.get(4)?,
+        starred: row.get::<_, i32>(5)? != 0,
+        pinned: pin_order.is_some(),
+        pin_order,
+        aliases: row
+            .get::<_, Option<String>>(7)?
+            .map(|joined| joined.split(ALIAS_SEPARATOR).map(str::to_string).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Bytes the cache takes on disk, WAL included
+fn cache_size(conn: &Connection) -> u64 {
+    let Some(path) = conn.path() else {
+        return 0;
+    };
+    ["", "-wal"]
+        .iter()
+        .filter_map(|suffix| std::fs::metadata(format!("{}{}", path, suffix)).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// A SQLite value as JSON; blobs are summarised by size
+fn json_value(value: rusqlite::types::ValueRef<'_>) -> serde_json::Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+        ValueRef::Blob(b) => format!("<{} bytes>", b.len()).into(),
+    }
+}
+
+/// Bail unless `id` refers to an existing bookmark group
+fn ensure_bookmark_group(conn: &Connection, id: i64) -> Result<()> {
+    let kind: Option<String> = conn
+        .query_row("SELECT kind FROM bookmarks WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .map(Some)
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok(None),
+            e => Err(e),
+        })?;
+    match kind.as_deref() {
+        Some("group") => Ok(()),
+        Some(_) => anyhow::bail!("Bookmarks can only be nested inside groups"),
+        None => anyhow::bail!("Parent bookmark not found"),
+    }
+}
+
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Which schema a vault's cache uses and which app version wrote it
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SchemaInfo {
+    /// 0 for caches from before schema versioning (or no cache yet)
+    pub schema_version: i64,
+    pub app_version: Option<String>,
+    /// Newest schema this build understands
+    pub supported_version: i64,
+}
+
+impl SchemaInfo {
+    fn unversioned() -> Self {
+        SchemaInfo {
+            schema_version: 0,
+            app_version: None,
+            supported_version: migrations::SCHEMA_VERSION,
+        }
+    }
+
+    /// Older schemas are upgraded on open; newer ones can't be read
+    pub fn compatible(&self) -> bool {
+        self.schema_version <= self.supported_version
+    }
+}
+
+/// The cache was written by a newer Synapse. The vault opens again after
+/// updating the app or rebuilding the cache.
+#[derive(Debug)]
+pub struct NewerSchemaError(pub SchemaInfo);
+
+impl std::fmt::Display for NewerSchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "This vault's cache was written by a newer version of Synapse{} (schema {}, this version supports {}). Update Synapse or rebuild the cache.",
+            self.0
+                .app_version
+                .as_deref()
+                .map(|v| format!(" ({})", v))
+                .unwrap_or_default(),
+            self.0.schema_version,
+            self.0.supported_version
+        )
+    }
+}
+
+impl std::error::Error for NewerSchemaError {}
+
+/// Reserved workspace name used to restore the previous session on vault open
+pub const LAST_SESSION_WORKSPACE: &str = "__last_session__";
+
+/// Setting holding private folders as a JSON array, alongside `.private` markers
+pub const PRIVATE_FOLDERS_SETTING: &str = "private.folders";
+
+/// Setting naming the note quick captures are appended to
+pub const CAPTURE_INBOX_SETTING: &str = "capture.inbox";
+
+/// Setting naming the folder daily notes live in
+pub const DAILY_FOLDER_SETTING: &str = "daily.folder";
+pub const DEFAULT_DAILY_FOLDER: &str = "Daily";
+
+/// Setting naming the folder archived notes are moved to
+pub const ARCHIVE_FOLDER_SETTING: &str = "archive.folder";
+pub const DEFAULT_ARCHIVE_FOLDER: &str = "Archive";
+
+/// Setting naming the folder new attachments are saved to
+pub const ATTACHMENTS_FOLDER_SETTING: &str = "attachments.folder";
+pub const DEFAULT_ATTACHMENTS_FOLDER: &str = "attachments";
+
+/// Setting naming the folder whose attachments are encrypted on disk
+pub const SECURE_ATTACHMENTS_SETTING: &str = "attachments.secure_folder";
+
+/// Setting that turns on scanning notes for secrets when they are saved
+pub const SECRET_SCAN_SETTING: &str = "secrets.scan_on_save";
+
+/// Setting holding the export redaction rules as JSON
+pub const REDACTION_SETTING: &str = "export.redaction";
+
+/// Setting that held the Argon2 hash of the vault lock passphrase before it
+/// moved to the keychain (see `crypto::lock_hash`). Read once to migrate it.
+pub const LOCK_HASH_SETTING: &str = "lock.passphrase_hash";
+
+/// Setting holding the auto-lock idle timeout in minutes (0 = off)
+pub const AUTO_LOCK_SETTING: &str = "lock.idle_minutes";
+
+/// Setting holding the folder OS search stubs are written to (unset = off)
+pub const OS_SEARCH_FOLDER_SETTING: &str = "search.os_index_folder";
+
+/// Setting holding `DbTuning` as JSON
+pub const DB_TUNING_SETTING: &str = "db.tuning";
+
+/// Setting that turns on indexing fenced code blocks as snippets
+pub const SNIPPET_MODE_SETTING: &str = "snippets.enabled";
+
+/// Setting holding the S3 backup destination (`S3Config`) as JSON
+pub const BACKUP_S3_SETTING: &str = "backup.s3";
+
+/// Setting holding when the last S3 backup finished (RFC 3339)
+pub const BACKUP_LAST_RUN_SETTING: &str = "backup.s3.last_run";
+
+/// Cached note metadata (stored in SQLite, mirrors filesystem)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CachedNote {
+    pub path: String,
+    pub title: String,
+    pub created_at: Option<String>,
+    pub modified_at: Option<String>,
+    pub word_count: i64,
+    pub starred: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Position in the pinned list (None when not pinned)
+    #[serde(default)]
+    pub pin_order: Option<i64>,
+    /// Other names from the frontmatter `aliases`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// A saved workspace layout, without the layout payload
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceSummary {
+    pub name: String,
+    pub updated_at: String,
+}
+
+/// A named query, shown in the sidebar as a folder of the notes it matches
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    pub updated_at: String,
+}
+
+/// A recently opened note with usage stats
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecentNote {
+    pub path: String,
+    pub title: String,
+    pub last_opened: String,
+    pub open_count: i64,
+}
+
+/// How often a note was opened, from the open history
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NoteOpenStats {
+    pub path: String,
+    pub title: String,
+    /// Opens in the whole history
+    pub opens: i64,
+    /// Opens in the window asked for
+    pub recent_opens: i64,
+    pub first_opened: String,
+    pub last_opened: String,
+}
+
+/// A file the indexer skipped or only partly indexed
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IndexWarning {
+    pub path: String,
+    pub message: String,
+    pub at: String,
+}
+
+/// SQLite knobs for the cache, stored as JSON in the `db.tuning` setting
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DbTuning {
+    /// WAL pages written before SQLite checkpoints on its own (0 = only
+    /// on close and maintenance)
+    pub wal_autocheckpoint: u32,
+    /// Page cache per connection, in KiB
+    pub cache_size_kib: u32,
+    pub synchronous: Synchronous,
+}
+
+impl Default for DbTuning {
+    fn default() -> Self {
+        DbTuning {
+            wal_autocheckpoint: 1000,
+            cache_size_kib: 8 * 1024,
+            // The cache can always be rebuilt from the notes, so it doesn't
+            // need to survive power loss
+            synchronous: Synchronous::Normal,
+        }
+    }
+}
+
+impl DbTuning {
+    pub fn validate(&self) -> Result<()> {
+        if !(256..=1024 * 1024).contains(&self.cache_size_kib) {
+            anyhow::bail!("Cache size must be between 256 KiB and 1 GiB");
+        }
+        if self.wal_autocheckpoint > 1_000_000 {
+            anyhow::bail!("WAL checkpoint interval is too large");
+        }
+        Ok(())
+    }
+}
+
+/// `PRAGMA synchronous` level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+}
+
+impl Synchronous {
+    fn pragma(self) -> &'static str {
+        match self {
+            Synchronous::Off => "OFF",
+            Synchronous::Normal => "NORMAL",
+            Synchronous::Full => "FULL",
+        }
+    }
+}
+
+/// Outcome of `run_maintenance`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MaintenanceReport {
+    /// Cache size on disk (database plus WAL) in bytes
+    pub size_before: u64,
+    pub size_after: u64,
+    /// Problems reported by `PRAGMA integrity_check`; empty when sound
+    pub integrity_errors: Vec<String>,
+}
+
+/// A callout and the note it lives in
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NoteCallout {
+    pub path: String,
+    #[serde(flatten)]
+    pub callout: crate::indexer::Callout,
+}
+
+/// A task and the note it lives in
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NoteTask {
+    pub path: String,
+    #[serde(flatten)]
+    pub task: crate::indexer::Task,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    #[default]
+    All,
+    Open,
+    Completed,
+    /// Open with a due date before today
+    Overdue,
+}
+
+impl TaskStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            TaskStatus::All => "all",
+            TaskStatus::Open => "open",
+            TaskStatus::Completed => "completed",
+            TaskStatus::Overdue => "overdue",
+        }
+    }
+}
+
+/// Which tasks `get_tasks` returns
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TaskFilter {
+    pub status: TaskStatus,
+    /// Only tasks in this note
+    pub path: Option<String>,
+}
+
+/// A fenced code block indexed in snippet mode
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Snippet {
+    pub path: String,
+    /// 1-based line of the opening fence
+    pub line: usize,
+    /// Lowercased info string; empty for unlabelled blocks
+    pub language: String,
+    pub content_hash: String,
+    pub content: String,
+}
+
+/// A note matching a full-text search
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FulltextMatch {
+    pub path: String,
+    pub title: String,
+    /// Body excerpt around the match, HTML with `<mark>`ed terms
+    pub snippet: String,
+    /// bm25 score; lower is a better match
+    pub rank: f64,
+}
+
+/// A wikilink and the note it resolves to
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ResolvedLink {
+    pub source: String,
+    /// The target as written
+    pub target: String,
+    pub path: Option<String>,
+}
+
+/// A link into a section and the note it resolves to
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ResolvedSectionLink {
+    pub source: String,
+    pub path: String,
+    pub link: crate::indexer::SectionLink,
+}
+
+/// Which part of a note a `[[Note#...]]` link can point at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkTargetKind {
+    Heading,
+    Block,
+}
+
+/// A heading or block inside a note, offered as a link completion
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LinkTarget {
+    pub kind: LinkTargetKind,
+    /// Heading text, or block id without the `^`
+    pub text: String,
+    /// Heading level (headings only)
+    pub level: Option<u8>,
+    pub line: usize,
+    /// Text of the line a block id marks (blocks only)
+    pub preview: Option<String>,
+}
+
+/// A wikilink target with no note, and the notes linking to it
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UnresolvedLink {
+    pub target: String,
+    pub sources: Vec<String>,
+}
+
+/// A drawing text element matching a search
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DrawingTextMatch {
+    pub path: String,
+    pub element_id: String,
+    pub text: String,
+}
+
+/// What a bookmark points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkKind {
+    /// A whole note (`path`)
+    Note,
+    /// A heading inside a note (`path` + `subpath` heading text)
+    Heading,
+    /// A block inside a note (`path` + `subpath` block id)
+    Block,
+    /// A vault folder (`path`)
+    Folder,
+    /// A saved search (`query`)
+    Search,
+    /// A named container for other bookmarks
+    Group,
+}
+
+impl BookmarkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookmarkKind::Note => "note",
+            BookmarkKind::Heading => "heading",
+            BookmarkKind::Block => "block",
+            BookmarkKind::Folder => "folder",
+            BookmarkKind::Search => "search",
+            BookmarkKind::Group => "group",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "note" => Some(BookmarkKind::Note),
+            "heading" => Some(BookmarkKind::Heading),
+            "block" => Some(BookmarkKind::Block),
+            "folder" => Some(BookmarkKind::Folder),
+            "search" => Some(BookmarkKind::Search),
+            "group" => Some(BookmarkKind::Group),
+            _ => None,
+        }
+    }
+}
+
+/// A stored bookmark
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Bookmark {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub kind: BookmarkKind,
+    pub title: Option<String>,
+    pub path: Option<String>,
+    pub subpath: Option<String>,
+    pub query: Option<String>,
+    pub position: i64,
+    pub created_at: String,
+}
+
+/// Fields for creating a bookmark
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NewBookmark {
+    pub kind: BookmarkKind,
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub subpath: Option<String>,
+    #[serde(default)]
+    pub query: Option<String>,
+}
+
+impl NewBookmark {
+    /// Check that the fields required by this kind are present
+    pub fn validate(&self) -> Result<()> {
+        let has = |v: &Option<String>| v.as_deref().is_some_and(|s| !s.trim().is_empty());
+        match self.kind {
+            BookmarkKind::Note | BookmarkKind::Folder if !has(&self.path) => {
+                anyhow::bail!("Bookmark requires a path")
+            }
+            BookmarkKind::Heading | BookmarkKind::Block
+                if !has(&self.path) || !has(&self.subpath) =>
+            {
+                anyhow::bail!("Bookmark requires a path and a heading or block id")
+            }
+            BookmarkKind::Search if !has(&self.query) => {
+                anyhow::bail!("Search bookmark requires a query")
+            }
+            BookmarkKind::Group if !has(&self.title) => {
+                anyhow::bail!("Bookmark group requires a title")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_reindex_vault_tracks_links_tags_and_deletions() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Home.md",
+                "# Home\nSee [[Projects/Plan]] and [[Ideas]] #start\n",
+            ),
+            (
+                "Projects/Plan.md",
+                "---\ntags: [work]\n---\n# Plan\nBack to [[Home]]\n",
+            ),
+            ("Ideas.md", "# Ideas\n#start #later\n"),
+        ]);
+        let db = vault.index();
+
+        assert_eq!(db.get_backlinks("Ideas").unwrap(), vec!["Home.md"]);
+        assert_eq!(
+            db.get_notes_by_tag("#work").unwrap(),
+            vec!["Projects/Plan.md"]
+        );
+        assert_eq!(
+            db.resolve_link_target("Plan").unwrap().as_deref(),
+            Some("Projects/Plan.md")
+        );
+
+        vault.remove("Ideas.md");
+        db.reindex_vault(vault.path()).unwrap();
+        assert!(db.get_note("Ideas.md").unwrap().is_none());
+        let tags = db.get_all_tags().unwrap();
+        assert!(tags.contains(&("#start".to_string(), 1)));
+        assert!(!tags.iter().any(|(tag, _)| tag == "#later"));
+    }
+
+    #[test]
+    fn test_reindex_vault_skips_unchanged_files() {
+        let vault = TestVault::with_notes(&[
+            ("Home.md", "# Home\n#start\n"),
+            ("Ideas.md", "# Ideas\n#later\n"),
+        ]);
+        let db = vault.index();
+
+        // Unchanged files aren't parsed again, so a tampered cache stays as is
+        db.write(|conn| store_tags(conn, "Home.md", &[])).unwrap();
+        vault.write("Ideas.md", "# Ideas\n#soon\n");
+        db.reindex_vault(vault.path()).unwrap();
+        assert!(db.get_notes_by_tag("#start").unwrap().is_empty());
+        assert_eq!(db.get_notes_by_tag("#soon").unwrap(), vec!["Ideas.md"]);
+
+        db.forget_file_states().unwrap();
+        db.reindex_vault(vault.path()).unwrap();
+        assert_eq!(db.get_notes_by_tag("#start").unwrap(), vec!["Home.md"]);
+    }
+
+    #[test]
+    fn test_reads_proceed_during_a_write_transaction() {
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n#start\n")]);
+        let db = std::sync::Arc::new(vault.index());
+
+        let writer = db.writer();
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+        writer
+            .execute("DELETE FROM tags WHERE note_path = 'Home.md'", [])
+            .unwrap();
+
+        // A read queued behind the writer would never finish here
+        let (tx, rx) = std::sync::mpsc::channel();
+        let reader_db = db.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(reader_db.get_notes_by_tag("#start").unwrap());
+        });
+        let tagged = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(tagged, vec!["Home.md"]);
+
+        writer.execute_batch("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_in_memory_database_cascades_deletes() {
+        let db = Database::init_in_memory().unwrap();
+        db.index_content("a.md", "# A\n[[b]] #tag\n## Section\n")
+            .unwrap();
+        assert_eq!(db.get_headings("a.md").unwrap().len(), 2);
+
+        db.delete_note("a.md").unwrap();
+        assert!(db.get_all_links().unwrap().is_empty());
+        assert!(db.get_all_tags().unwrap().is_empty());
+        assert!(db.get_headings("a.md").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rename_and_delete_leave_no_dangling_rows() {
+        let vault = TestVault::with_notes(&[
+            ("Old.md", "# Old\n[[Other]] #tag\n## Section\n"),
+            ("Other.md", "# Other\n[[Old]] #tag\n"),
+        ]);
+        let db = vault.index();
+
+        // What rename_entry does to the cache once the file has moved
+        vault.write("New.md", "# Old\n[[Other]] #tag\n## Section\n");
+        vault.remove("Old.md");
+        db.move_note_state("Old.md", "New.md").unwrap();
+        db.delete_note("Old.md").unwrap();
+        db.reindex_note(vault.path(), "New.md").unwrap();
+        vault.remove("Other.md");
+        db.reindex_vault(vault.path()).unwrap();
+
+        let conn = db.reader();
+        for (table, column) in [
+            ("links", "source_path"),
+            ("tags", "note_path"),
+            ("headings", "note_path"),
+        ] {
+            let paths: Vec<String> = conn
+                .prepare(&format!("SELECT DISTINCT {} FROM {}", column, table))
+                .unwrap()
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .collect::<rusqlite::Result<_>>()
+                .unwrap();
+            assert_eq!(paths, vec!["New.md"], "{}", table);
+        }
+        let dangling: i64 = conn
+            .query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(dangling, 0);
+    }
+
+    #[test]
+    fn test_unresolved_links_and_orphans() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Home.md",
+                "[[Projects/Plan]] [[plan]] [[Missing]] [[missing#Part]]\n",
+            ),
+            ("Projects/Plan.md", "# Plan\n"),
+            ("Ideas.md", "---\ntitle: Someday\n---\n[[Elsewhere]]\n"),
+            ("Notes/Lonely.md", "# Lonely\n[[Someday]]\n"),
+            ("Scratch.md", "nothing here\n"),
+        ]);
+        let db = vault.index();
+
+        let unresolved = db.get_unresolved_links().unwrap();
+        let targets: Vec<&str> = unresolved.iter().map(|u| u.target.as_str()).collect();
+        assert_eq!(targets, vec!["Elsewhere", "Missing"]);
+        assert_eq!(unresolved[1].sources, vec!["Home.md"]);
+
+        let orphans: Vec<String> = db
+            .get_orphan_notes()
+            .unwrap()
+            .into_iter()
+            .map(|n| n.path)
+            .collect();
+        assert_eq!(orphans, vec!["Scratch.md"]);
+    }
+
+    #[test]
+    fn test_broken_heading_links() {
+        let vault = TestVault::with_notes(&[
+            ("Plan.md", "# Plan\n## Goals\n## Open Questions\n"),
+            (
+                "Home.md",
+                "# Home\n[[Plan#Goals]] [[Plan#open-questions]]\n[[Plan#Budget]] [[Missing#Goals]]\n[[#Home]] [[#Nowhere]] [[Plan#^block]]\n",
+            ),
+        ]);
+        let db = vault.index();
+
+        let broken: Vec<(String, String, String, usize)> = db
+            .get_broken_heading_links()
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.source, b.path, b.link.heading, b.link.line))
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                ("Home.md".into(), "Plan.md".into(), "Budget".into(), 3),
+                ("Home.md".into(), "Home.md".into(), "Nowhere".into(), 4),
+            ]
+        );
+
+        vault.write("Plan.md", "# Plan\n## Goals\n## Budget\n");
+        db.reindex_note(vault.path(), "Plan.md").unwrap();
+        let broken = db.get_broken_heading_links().unwrap();
+        assert_eq!(broken.len(), 2);
+        assert_eq!(broken[0].link.heading, "open-questions");
+    }
+
+    #[test]
+    fn test_aliases_resolve_links_and_backlinks() {
+        let db = Database::init_in_memory().unwrap();
+        db.index_content(
+            "People/Ada Lovelace.md",
+            "---\naliases: [Ada, Countess]\n---\n# Ada Lovelace\n",
+        )
+        .unwrap();
+        db.index_content("Ada.md", "# Ada the language\n").unwrap();
+        db.index_content("Home.md", "Met [[countess|her]] and [[Ada]].\n")
+            .unwrap();
+
+        // A file name wins over an alias
+        assert_eq!(
+            db.resolve_link_target("Ada").unwrap().as_deref(),
+            Some("Ada.md")
+        );
+        assert_eq!(
+            db.resolve_link_target("Countess").unwrap().as_deref(),
+            Some("People/Ada Lovelace.md")
+        );
+        assert_eq!(db.get_backlinks("Ada Lovelace").unwrap(), vec!["Home.md"]);
+        assert!(db.get_unresolved_links().unwrap().is_empty());
+
+        let note = db.get_note("People/Ada Lovelace.md").unwrap().unwrap();
+        assert_eq!(note.aliases.len(), 2);
+        db.index_content("People/Ada Lovelace.md", "# Ada Lovelace\n")
+            .unwrap();
+        assert_eq!(db.resolve_link_target("Countess").unwrap(), None);
+    }
+
+    #[test]
+    fn test_fulltext_search_ranks_and_highlights() {
+        let db = Database::init_in_memory().unwrap();
+        db.index_content("Garden.md", "# Garden\nTomatoes need <full> sun.\n")
+            .unwrap();
+        db.index_content(
+            "Recipes.md",
+            "---\ntitle: Tomato soup\n---\nSimmer the tomatoes.\n",
+        )
+        .unwrap();
+
+        let matches = db.search_fulltext("tomato", 10).unwrap();
+        assert_eq!(matches.len(), 2);
+        // The title match ranks first
+        assert_eq!(matches[0].path, "Recipes.md");
+        assert!(matches[1]
+            .snippet
+            .contains("<mark>Tomatoes</mark> need &lt;full&gt; sun"));
+
+        // FTS syntax in the input is searched for literally
+        assert!(db.search_fulltext("soup OR sun", 10).unwrap().is_empty());
+        assert_eq!(db.search_fulltext("need \"sun", 10).unwrap().len(), 1);
+        assert!(db.search_fulltext("  ", 10).unwrap().is_empty());
+
+        db.delete_note("Garden.md").unwrap();
+        assert_eq!(db.search_fulltext("sun", 10).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_tasks_by_status_and_due_date() {
+        let db = Database::init_in_memory().unwrap();
+        db.index_content(
+            "Plan.md",
+            "# Plan\n- [ ] Draft 📅 2024-06-01\n## Later\n* [x] Outline due: 2024-05-01\n\
+             1. [ ] Publish [due:: 2030-01-01]\n- [ ]no space\n```\n- [ ] in code\n```\n",
+        )
+        .unwrap();
+
+        // Soonest due first; `[ ]no space` and tasks in code don't count
+        let all = db.get_tasks(&TaskFilter::default(), "2024-07-01").unwrap();
+        let lines: Vec<usize> = all.iter().map(|t| t.task.line).collect();
+        assert_eq!(lines, vec![4, 2, 5]);
+        assert_eq!(
+            all[0].task,
+            crate::indexer::Task {
+                text: "Outline due: 2024-05-01".to_string(),
+                completed: true,
+                line: 4,
+                due: Some("2024-05-01".to_string()),
+                heading: Some("Later".to_string()),
+            }
+        );
+        assert_eq!(all[1].task.due.as_deref(), Some("2024-06-01"));
+        assert_eq!(all[2].task.due.as_deref(), Some("2030-01-01"));
+
+        let overdue = TaskFilter {
+            status: TaskStatus::Overdue,
+            path: None,
+        };
+        let overdue = db.get_tasks(&overdue, "2024-07-01").unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].task.line, 2);
+    }
+
+    #[test]
+    fn test_open_history_follows_renames_and_deletes() {
+        let db = Database::init_in_memory().unwrap();
+        db.index_content("Daily.md", "# Daily\n").unwrap();
+        db.index_content("Plan.md", "# Plan\n").unwrap();
+        for path in ["Daily.md", "Plan.md", "Daily.md", "Daily.md"] {
+            db.record_note_opened(path).unwrap();
+        }
+
+        let stats = db.get_note_open_stats(None, "1970-01-01").unwrap();
+        let counts: Vec<(&str, i64)> = stats.iter().map(|s| (s.path.as_str(), s.opens)).collect();
+        assert_eq!(counts, vec![("Daily.md", 3), ("Plan.md", 1)]);
+        // Nothing opened in the window
+        let future = (chrono::Utc::now() + chrono::Duration::days(1)).to_rfc3339();
+        let stats = db.get_note_open_stats(Some("Daily.md"), &future).unwrap();
+        assert_eq!((stats[0].opens, stats[0].recent_opens), (3, 0));
+
+        db.index_content("Daily log.md", "# Daily\n").unwrap();
+        db.move_note_state("Daily.md", "Daily log.md").unwrap();
+        db.delete_note("Daily.md").unwrap();
+        db.delete_note("Plan.md").unwrap();
+        let stats = db.get_note_open_stats(None, "1970-01-01").unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(
+            (stats[0].path.as_str(), stats[0].opens),
+            ("Daily log.md", 3)
+        );
+    }
+
+    #[test]
+    fn test_workspaces_save_load_and_delete() {
+        let db = Database::init_in_memory().unwrap();
+        db.save_workspace("writing", r#"{"panes":1}"#).unwrap();
+        db.save_workspace("Research", r#"{"panes":2}"#).unwrap();
+        db.save_workspace("writing", r#"{"panes":3}"#).unwrap();
+        db.save_workspace(LAST_SESSION_WORKSPACE, r#"{"panes":4}"#)
+            .unwrap();
+
+        assert_eq!(
+            db.load_workspace("writing").unwrap().as_deref(),
+            Some(r#"{"panes":3}"#)
+        );
+        // The last-session slot loads but isn't listed
+        let names: Vec<String> = db
+            .list_workspaces()
+            .unwrap()
+            .into_iter()
+            .map(|w| w.name)
+            .collect();
+        assert_eq!(names, vec!["Research", "writing"]);
+        assert!(db.load_workspace(LAST_SESSION_WORKSPACE).unwrap().is_some());
+
+        db.delete_workspace("writing").unwrap();
+        assert!(db.load_workspace("writing").unwrap().is_none());
+        assert_eq!(db.list_workspaces().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_recent_notes_newest_first_with_counts() {
+        let db = Database::init_in_memory().unwrap();
+        db.index_content("a.md", "# A\n").unwrap();
+        db.index_content("b.md", "# B\n").unwrap();
+        for path in ["a.md", "b.md", "a.md", "gone.md"] {
+            db.record_note_opened(path).unwrap();
+        }
+
+        // Notes no longer in the cache aren't offered
+        let recent = db.get_recent_notes(10).unwrap();
+        let opened: Vec<(&str, i64)> = recent
+            .iter()
+            .map(|r| (r.path.as_str(), r.open_count))
+            .collect();
+        assert_eq!(opened, vec![("a.md", 2), ("b.md", 1)]);
+        assert_eq!(recent[0].title, "A");
+        assert_eq!(db.get_recent_notes(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_bookmarks_round_trip_groups_and_moves() {
+        let db = Database::init_in_memory().unwrap();
+        let new = |kind, parent_id, path: Option<&str>| NewBookmark {
+            kind,
+            parent_id,
+            title: None,
+            path: path.map(str::to_string),
+            subpath: None,
+            query: None,
+        };
+        let group = db
+            .add_bookmark(&NewBookmark {
+                title: Some("Work".into()),
+                ..new(BookmarkKind::Group, None, None)
+            })
+            .unwrap();
+        let plan = db
+            .add_bookmark(&new(BookmarkKind::Note, Some(group.id), Some("Plan.md")))
+            .unwrap();
+        let heading = db
+            .add_bookmark(&NewBookmark {
+                subpath: Some("Goals".into()),
+                ..new(BookmarkKind::Heading, None, Some("Plan.md"))
+            })
+            .unwrap();
+        // Only groups can hold bookmarks
+        assert!(db
+            .add_bookmark(&new(BookmarkKind::Note, Some(plan.id), Some("x.md")))
+            .is_err());
+
+        let stored = db.get_bookmarks().unwrap();
+        assert_eq!(stored.len(), 3);
+        let goals = stored.iter().find(|b| b.id == heading.id).unwrap();
+        assert_eq!(goals.kind, BookmarkKind::Heading);
+        assert_eq!(goals.subpath.as_deref(), Some("Goals"));
+
+        db.rename_bookmark(plan.id, Some("The plan")).unwrap();
+        db.move_bookmark(heading.id, Some(group.id), 0).unwrap();
+        assert!(db.move_bookmark(group.id, Some(group.id), 0).is_err());
+        let children: Vec<(i64, i64)> = db
+            .get_bookmarks()
+            .unwrap()
+            .iter()
+            .filter(|b| b.parent_id == Some(group.id))
+            .map(|b| (b.id, b.position))
+            .collect();
+        assert_eq!(children, vec![(heading.id, 0), (plan.id, 1)]);
+
+        // Deleting a group takes its bookmarks with it
+        db.delete_bookmark(group.id).unwrap();
+        assert!(db.get_bookmarks().unwrap().is_empty());
+        assert!(db.rename_bookmark(plan.id, None).is_err());
+    }
+
+    #[test]
+    fn test_pins_keep_manual_order() {
+        let db = Database::init_in_memory().unwrap();
+        for path in ["a.md", "b.md", "c.md", "d.md"] {
+            db.index_content(path, "# Note\n").unwrap();
+        }
+        for path in ["a.md", "b.md", "c.md", "a.md"] {
+            db.pin_note(path).unwrap();
+        }
+        let pinned = |db: &Database| -> Vec<(String, Option<i64>)> {
+            db.get_all_notes()
+                .unwrap()
+                .into_iter()
+                .filter(|n| n.pinned)
+                .map(|n| (n.path, n.pin_order))
+                .collect()
+        };
+        assert_eq!(pinned(&db).len(), 3);
+
+        // Unlisted pins follow the listed ones; unknown paths are ignored
+        db.reorder_pins(&["c.md".into(), "x.md".into(), "a.md".into()])
+            .unwrap();
+        let order: Vec<String> = pinned(&db).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(order, vec!["c.md", "a.md", "b.md"]);
+        assert!(!db.get_all_notes().unwrap()[3].pinned);
+
+        db.unpin_note("a.md").unwrap();
+        assert_eq!(
+            pinned(&db),
+            vec![("c.md".to_string(), Some(0)), ("b.md".to_string(), Some(2))]
+        );
+
+        // A path listed twice keeps its first position
+        db.reorder_pins(&["b.md".into(), "c.md".into(), "b.md".into()])
+            .unwrap();
+        assert_eq!(
+            pinned(&db),
+            vec![("b.md".to_string(), Some(0)), ("c.md".to_string(), Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_link_targets_list_headings_and_blocks_in_line_order() {
+        let db = Database::init_in_memory().unwrap();
+        let content = "# Plan\nFirst step ^step1\n## Risks\n- Budget overrun ^risk\n";
+        db.index_content("Projects/Plan.md", content).unwrap();
+        let path = db.resolve_link_target("plan").unwrap().unwrap();
+        assert_eq!(path, "Projects/Plan.md");
+
+        let targets = db.get_link_targets(&path, content, None).unwrap();
+        let summary: Vec<(LinkTargetKind, &str, usize)> = targets
+            .iter()
+            .map(|t| (t.kind, t.text.as_str(), t.line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (LinkTargetKind::Heading, "Plan", 1),
+                (LinkTargetKind::Block, "step1", 2),
+                (LinkTargetKind::Heading, "Risks", 3),
+                (LinkTargetKind::Block, "risk", 4),
+            ]
+        );
+        assert_eq!(targets[2].level, Some(2));
+        assert_eq!(targets[3].preview.as_deref(), Some("- Budget overrun"));
+
+        let blocks = db
+            .get_link_targets(&path, content, Some(LinkTargetKind::Block))
+            .unwrap();
+        assert!(blocks.iter().all(|t| t.kind == LinkTargetKind::Block));
+        assert_eq!(blocks.len(), 2);
+        let headings = db
+            .get_link_targets(&path, content, Some(LinkTargetKind::Heading))
+            .unwrap();
+        assert_eq!(headings.len(), 2);
+
+        // Without the file text, blocks are still listed but have no preview
+        let bare = db.get_link_targets(&path, "", None).unwrap();
+        assert_eq!(bare.len(), 4);
+        assert!(bare[1].preview.is_none());
+    }
+
+    #[test]
+    fn test_newer_cache_schema_is_refused_until_rebuilt() {
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n")]);
+        let newer = migrations::SCHEMA_VERSION + 1;
+        {
+            let db = vault.index();
+            db.writer()
+                .execute(
+                    "UPDATE schema_info SET value = ?1 WHERE key = 'schema_version'",
+                    [newer.to_string()],
+                )
+                .unwrap();
+            db.writer()
+                .execute(
+                    "UPDATE schema_info SET value = '99.0.0' WHERE key = 'app_version'",
+                    [],
+                )
+                .unwrap();
+        }
+
+        let err = Database::init_for_vault(vault.path()).err().unwrap();
+        let NewerSchemaError(info) = err.downcast_ref::<NewerSchemaError>().unwrap();
+        assert_eq!(info.schema_version, newer);
+        assert_eq!(info.app_version.as_deref(), Some("99.0.0"));
+        assert!(!info.compatible());
+        let message = err.to_string();
+        assert!(message.contains("newer version of Synapse (99.0.0)"));
+        assert!(matches!(
+            crate::error::SynapseError::from(err),
+            crate::error::SynapseError::NewerSchema(_)
+        ));
+        // Not corruption, so opening doesn't quietly replace the cache
+        assert!(Database::open_or_recover(vault.path()).is_err());
+        let info = Database::inspect_schema(vault.path()).unwrap();
+        assert_eq!(info.schema_version, newer);
+
+        let (db, backup) = Database::rebuild_for_vault(vault.path()).unwrap();
+        assert!(backup.unwrap().is_file());
+        let info = Database::inspect_schema(vault.path()).unwrap();
+        assert_eq!(info.schema_version, migrations::SCHEMA_VERSION);
+        assert_eq!(info.app_version.as_deref(), Some(APP_VERSION));
+        db.reindex_vault(vault.path()).unwrap();
+        assert!(db.get_note("Home.md").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_foreign_keys_enforced_and_old_orphans_purged_on_open() {
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n#start [[Ideas]]\n")]);
+        {
+            let db = vault.index();
+            let writer = db.writer();
+            let enforced: bool = writer
+                .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+                .unwrap();
+            assert!(enforced);
+            assert!(writer
+                .execute(
+                    "INSERT INTO tags (note_path, tag) VALUES ('Gone.md', '#old')",
+                    []
+                )
+                .is_err());
+
+            // What a cache written before foreign keys were enforced can hold
+            writer.execute_batch("PRAGMA foreign_keys=OFF").unwrap();
+            writer
+                .execute_batch(
+                    "INSERT INTO tags (note_path, tag) VALUES ('Gone.md', '#old');
+                     INSERT INTO links (source_path, target_name) VALUES ('Gone.md', 'Home');",
+                )
+                .unwrap();
+        }
+
+        let db = Database::init_for_vault(vault.path()).unwrap();
+        assert_eq!(db.get_all_tags().unwrap(), vec![("#start".to_string(), 1)]);
+        assert_eq!(
+            db.get_all_links().unwrap(),
+            vec![("Home.md".to_string(), "Ideas".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_pool_serves_reads_and_serializes_writes_across_threads() {
+        let db = std::sync::Arc::new(Database::init_in_memory().unwrap());
+        db.index_content("Home.md", "# Home\n").unwrap();
+
+        // With all but one reader checked out, reads use the idle one
+        let held: Vec<_> = (1..READ_POOL_SIZE).map(|_| db.reader()).collect();
+        assert_eq!(db.get_all_notes().unwrap().len(), 1);
+        drop(held);
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let db = db.clone();
+                std::thread::spawn(move || {
+                    db.index_content(&format!("Note {}.md", i), "# Note\n#shared\n")
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+        assert_eq!(db.get_all_notes().unwrap().len(), 9);
+        assert_eq!(db.get_notes_by_tag("#shared").unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_corrupt_cache_is_set_aside_and_recreated() {
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n#start\n")]);
+        let (db, backup) = Database::open_or_recover(vault.path()).unwrap();
+        assert!(backup.is_none());
+        db.reindex_vault(vault.path()).unwrap();
+        drop(db);
+
+        let db_path = crate::vault::Vault::db_path(vault.path());
+        let garbage = vec![0xA5u8; 8192];
+        std::fs::write(&db_path, &garbage).unwrap();
+        assert!(is_corruption(
+            &Database::init_for_vault(vault.path()).err().unwrap()
+        ));
+
+        let (db, backup) = Database::open_or_recover(vault.path()).unwrap();
+        assert_eq!(std::fs::read(backup.unwrap()).unwrap(), garbage);
+        assert!(db.get_all_notes().unwrap().is_empty());
+        db.reindex_vault(vault.path()).unwrap();
+        assert_eq!(db.get_notes_by_tag("#start").unwrap(), vec!["Home.md"]);
+    }
+
+    #[test]
+    fn test_maintenance_compacts_a_sound_cache() {
+        let vault = TestVault::new();
+        let body = "#bulk [[Home]]\n".repeat(200);
+        for i in 0..100 {
+            vault.write(&format!("Note {}.md", i), &body);
+        }
+        vault.write("Home.md", "# Home\n#keep\n");
+        let db = vault.index();
+        for i in 0..100 {
+            db.delete_note(&format!("Note {}.md", i)).unwrap();
+        }
+
+        let report = db.run_maintenance().unwrap();
+        assert!(report.integrity_errors.is_empty());
+        assert!(report.size_after < report.size_before);
+        assert_eq!(db.get_notes_by_tag("#keep").unwrap(), vec!["Home.md"]);
+    }
+
+    #[test]
+    fn test_note_is_indexed_all_or_nothing() {
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n[[Old]]\n#start\n")]);
+        let db = vault.index();
+        vault.write("Home.md", "# Home\n[[New]]\n#later\n");
+
+        // Fail partway through, after the note's links were replaced
+        db.writer()
+            .execute_batch(
+                "CREATE TEMP TRIGGER no_tags BEFORE INSERT ON tags
+                 BEGIN SELECT RAISE(ABORT, 'tags unavailable'); END;",
+            )
+            .unwrap();
+        assert!(db.reindex_note(vault.path(), "Home.md").is_err());
+        assert_eq!(db.get_outgoing_links("Home.md").unwrap(), vec!["Old"]);
+        assert_eq!(db.get_notes_by_tag("#start").unwrap(), vec!["Home.md"]);
+
+        db.writer().execute_batch("DROP TRIGGER no_tags;").unwrap();
+        db.reindex_note(vault.path(), "Home.md").unwrap();
+        assert_eq!(db.get_outgoing_links("Home.md").unwrap(), vec!["New"]);
+    }
+
+    #[test]
+    fn test_write_retries_while_another_connection_holds_the_lock() {
+        let vault = TestVault::new();
+        let db = vault.index();
+        // Give up on each attempt quickly so the retries are what waits
+        db.writer().busy_timeout(Duration::from_millis(20)).unwrap();
+
+        let db_path = crate::vault::Vault::db_path(vault.path());
+        let hold = |ms| {
+            let other = Connection::open(&db_path).unwrap();
+            other.execute_batch("BEGIN IMMEDIATE;").unwrap();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(ms));
+                other.execute_batch("COMMIT;").unwrap();
+            })
+        };
+
+        // Released after a couple of retries
+        let other = hold(150);
+        db.set_setting("theme", "dark").unwrap();
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
+        other.join().unwrap();
+
+        // Held past every attempt
+        let other = hold(2000);
+        let err = db.set_setting("theme", "light").unwrap_err();
+        assert!(is_busy(&err));
+        other.join().unwrap();
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
+    }
+
+    #[test]
+    fn test_debug_dump_counts_tables_and_hides_secrets() {
+        let vault = TestVault::with_notes(&[
+            ("Home.md", "# Home\n[[Ideas]]\n#start\n"),
+            ("Board.canvas", "{ not json"),
+        ]);
+        let db = vault.index();
+        db.set_setting(LOCK_HASH_SETTING, "secret-hash").unwrap();
+        db.set_setting("theme", "dark").unwrap();
+
+        let dump = db.debug_dump().unwrap();
+        assert_eq!(dump["tables"]["notes"]["count"], 1);
+        assert_eq!(dump["tables"]["links"]["sample"][0]["target_name"], "Ideas");
+        let settings = dump["tables"]["settings"]["sample"].as_array().unwrap();
+        assert!(settings.iter().any(|row| row["key"] == "theme"));
+        assert!(!dump.to_string().contains("secret-hash"));
+        let tables = dump["tables"].as_object().unwrap();
+        assert!(!tables.keys().any(|t| t.starts_with("fts_content")));
+        assert_eq!(dump["index_warnings"][0]["path"], "Board.canvas");
+    }
+
+    #[test]
+    fn test_failed_file_in_a_reindex_batch_rolls_back_alone() {
+        let vault = TestVault::with_notes(&[
+            ("Home.md", "# Home\n#start\n"),
+            ("Data.csv", "name,size\nbolt,3\n"),
+            ("Ideas.md", "# Ideas\n#later\n"),
+        ]);
+        let db = Database::init_for_vault(vault.path()).unwrap();
+        db.writer()
+            .execute_batch(
+                "CREATE TEMP TRIGGER no_csv BEFORE INSERT ON csv_files
+                 BEGIN SELECT RAISE(ABORT, 'csv unavailable'); END;",
+            )
+            .unwrap();
+
+        db.reindex_vault(vault.path()).unwrap();
+        let notes = db.get_all_notes().unwrap();
+        let indexed = |path: &str| notes.iter().any(|n| n.path == path);
+        assert!(indexed("Home.md") && indexed("Ideas.md"));
+        // The CSV file's note row went with its failed summary
+        assert!(!indexed("Data.csv"));
+        let warnings = db.warnings.lock_or_recover();
+        assert_eq!(warnings.back().unwrap().path, "Data.csv");
+    }
+
+    #[test]
+    fn test_tuning_is_validated_saved_and_applied() {
+        let vault = TestVault::new();
+        let db = vault.index();
+        let pragma = |db: &Database, name: &str| -> i64 {
+            db.writer()
+                .query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+                .unwrap()
+        };
+
+        let too_small = DbTuning {
+            cache_size_kib: 16,
+            ..DbTuning::default()
+        };
+        assert!(db.set_tuning(&too_small).is_err());
+
+        let tuning = DbTuning {
+            wal_autocheckpoint: 0,
+            cache_size_kib: 4096,
+            synchronous: Synchronous::Full,
+        };
+        db.set_tuning(&tuning).unwrap();
+        assert_eq!(pragma(&db, "cache_size"), -4096);
+        assert_eq!(pragma(&db, "wal_autocheckpoint"), 0);
+        assert_eq!(pragma(&db, "synchronous"), 2);
+        drop(db);
+
+        // Reapplied when the cache is opened again
+        let db = Database::init_for_vault(vault.path()).unwrap();
+        assert_eq!(db.tuning().unwrap().cache_size_kib, 4096);
+        assert_eq!(pragma(&db, "cache_size"), -4096);
+        assert_eq!(pragma(&db, "synchronous"), 2);
+    }
+
+    #[test]
+    fn test_closing_the_cache_checkpoints_the_wal() {
+        let vault = TestVault::with_notes(&[("Home.md", "# Home\n#start\n")]);
+        let db = vault.index();
+        db.set_tuning(&DbTuning {
+            wal_autocheckpoint: 0,
+            ..DbTuning::default()
+        })
+        .unwrap();
+        db.set_setting("theme", "dark").unwrap();
+
+        let mut wal = crate::vault::Vault::db_path(vault.path()).into_os_string();
+        wal.push("-wal");
+        assert!(std::fs::metadata(&wal).unwrap().len() > 0);
+        drop(db);
+        assert_eq!(std::fs::metadata(&wal).map_or(0, |meta| meta.len()), 0);
+    }
 }
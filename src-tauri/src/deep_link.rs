@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use url::Url;
+
+use crate::commands::AppState;
+use crate::sync::LockExt;
+use crate::vault::Vault;
+
+/// Scheme registered with the OS (see `plugins.deep-link` in tauri.conf.json)
+pub const URI_SCHEME: &str = "synapse";
+
+/// A note addressed by a `synapse://open?vault=...&path=...` link or
+/// opened as a file from the OS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteLink {
+    /// Vault the note lives in; `None` means the open vault
+    pub vault: Option<PathBuf>,
+    pub path: String,
+    /// `vault` is just the file's folder, not a Synapse vault yet; the
+    /// frontend asks before opening it as one
+    #[serde(default)]
+    pub new_vault: bool,
+}
+
+/// Link that launched the app, kept until the frontend is ready to take it
+#[derive(Default)]
+pub struct LaunchLink(pub Mutex<Option<NoteLink>>);
+
+/// `synapse://open?vault=<vault>&path=<note>`
+pub fn note_uri(vault_path: &Path, note_path: &str) -> String {
+    let mut url = Url::parse(&format!("{}://open", URI_SCHEME)).expect("valid base URI");
+    url.query_pairs_mut()
+        .append_pair("vault", &vault_path.to_string_lossy())
+        .append_pair("path", note_path);
+    url.into()
+}
+
+pub fn parse_note_uri(url: &Url) -> Option<NoteLink> {
+    if url.scheme() != URI_SCHEME || url.host_str() != Some("open") {
+        return None;
+    }
+    let mut link = NoteLink {
+        vault: None,
+        path: String::new(),
+        new_vault: false,
+    };
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "vault" if !value.is_empty() => link.vault = Some(PathBuf::from(value.as_ref())),
+            "path" => link.path = value.into_owned(),
+            _ => {}
+        }
+    }
+    let valid = !link.path.is_empty()
+        && !Path::new(&link.path).is_absolute()
+        && !link.path.split(['/', '\\']).any(|part| part == "..");
+    valid.then_some(link)
+}
+
+/// The note for a Markdown file opened from the OS: inside the vault it
+/// belongs to, or else in its own folder as a prospective vault
+pub fn file_link(file: &Path) -> Option<NoteLink> {
+    let file = file.canonicalize().ok()?;
+    if let Some(vault) = Vault::find_vault_root(&file) {
+        let path = file
+            .strip_prefix(&vault)
+            .ok()?
+            .to_string_lossy()
+            .to_string();
+        return Some(NoteLink {
+            vault: Some(vault),
+            path,
+            new_vault: false,
+        });
+    }
+    Some(NoteLink {
+        vault: Some(file.parent()?.to_path_buf()),
+        path: file.file_name()?.to_string_lossy().to_string(),
+        new_vault: true,
+    })
+}
+
+/// Markdown files among command-line arguments ("Open with" on Windows
+/// and Linux passes the file path)
+pub fn files_from_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+    args.iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| cwd.join(arg))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+            }) && path.is_file()
+        })
+        .collect()
+}
+
+/// Handle `synapse://` links and (on macOS) `file://` URLs the OS passed in
+pub fn handle_urls<R: Runtime>(app: &AppHandle<R>, urls: &[Url], launching: bool) {
+    let links = urls.iter().filter_map(|url| {
+        let link = match url.scheme() {
+            "file" => url.to_file_path().ok().and_then(|file| file_link(&file)),
+            _ => parse_note_uri(url),
+        };
+        if link.is_none() {
+            log::warn!("Ignoring unrecognised link: {}", url);
+        }
+        link
+    });
+    dispatch(app, links, launching);
+}
+
+/// Open Markdown files passed on the command line
+pub fn handle_files<R: Runtime>(app: &AppHandle<R>, files: &[PathBuf], launching: bool) {
+    dispatch(app, files.iter().filter_map(|f| file_link(f)), launching);
+}
+
+/// Before the frontend has loaded, the first link is parked in `LaunchLink`
+fn dispatch<R: Runtime>(
+    app: &AppHandle<R>,
+    links: impl Iterator<Item = NoteLink>,
+    launching: bool,
+) {
+    for link in links {
+        if launching {
+            *app.state::<LaunchLink>().0.lock_or_recover() = Some(link);
+            return;
+        }
+        if let Err(e) = open_link(app, &link) {
+            log::warn!("Failed to open {}: {}", link.path, e);
+        }
+    }
+}
+
+/// Show the note in the main window. A note in another vault asks the
+/// frontend to switch vaults first.
+pub fn open_link<R: Runtime>(app: &AppHandle<R>, link: &NoteLink) -> tauri::Result<()> {
+    #[cfg(desktop)]
+    crate::tray::show_main(app)?;
+    let current = app.state::<AppState>().vault_path.lock_or_recover().clone();
+    let same_vault = match (&link.vault, &current) {
+        (None, _) => true,
+        (Some(vault), Some(current)) => same_path(vault, current),
+        (Some(_), None) => false,
+    };
+    if same_vault && !link.new_vault {
+        app.emit_to("main", "open-note", &link.path)
+    } else {
+        app.emit_to("main", "open-vault-note", link)
+    }
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_uri_round_trip() {
+        let uri = note_uri(Path::new("/home/me/My Vault"), "Projects/Plan & Notes.md");
+        let link = parse_note_uri(&Url::parse(&uri).unwrap()).unwrap();
+        assert_eq!(link.vault, Some(PathBuf::from("/home/me/My Vault")));
+        assert_eq!(link.path, "Projects/Plan & Notes.md");
+
+        let open_vault = Url::parse("synapse://open?path=Inbox.md").unwrap();
+        assert_eq!(parse_note_uri(&open_vault).unwrap().vault, None);
+        for bad in [
+            "synapse://open?path=../secret.md",
+            "synapse://open",
+            "synapse://settings?path=a.md",
+            "https://open?path=a.md",
+        ] {
+            assert!(
+                parse_note_uri(&Url::parse(bad).unwrap()).is_none(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_opened_files_map_to_their_vault_or_a_new_one() {
+        let vault = crate::test_support::TestVault::with_notes(&[("Projects/Plan.md", "# Plan\n")]);
+        let _db = vault.index();
+        let loose = crate::test_support::TestVault::with_notes(&[("Todo.md", "- [ ] milk\n")]);
+        let root = vault.path().canonicalize().unwrap();
+
+        let link = file_link(&vault.path().join("Projects/Plan.md")).unwrap();
+        assert_eq!(link.vault.as_deref(), Some(root.as_path()));
+        assert_eq!(link.path, "Projects/Plan.md");
+        assert!(!link.new_vault);
+
+        let link = file_link(&loose.path().join("Todo.md")).unwrap();
+        assert_eq!(link.vault, Some(loose.path().canonicalize().unwrap()));
+        assert_eq!(link.path, "Todo.md");
+        assert!(link.new_vault);
+        assert!(file_link(&loose.path().join("Missing.md")).is_none());
+
+        let args = ["synapse", "--flag", "Projects/Plan.md", "a.txt", "Nope.md"].map(String::from);
+        assert_eq!(
+            files_from_args(&args, vault.path()),
+            vec![vault.path().join("Projects/Plan.md")]
+        );
+    }
+}
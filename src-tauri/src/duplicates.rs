@@ -0,0 +1,247 @@
+//! Near-duplicate note detection. Each note's body is cut into overlapping
+//! runs of words (shingles) and summarised by a MinHash signature at index
+//! time; the share of matching signature slots estimates how much of the
+//! two notes' shingles they have in common. Locality-sensitive banding picks
+//! the pairs worth comparing, so a large vault isn't compared pair by pair.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::database::Database;
+use crate::embeddings::fnv1a;
+
+/// Number of hash functions, and so of values in a signature
+pub const SIGNATURE_LEN: usize = 64;
+/// Words per shingle
+const SHINGLE_WORDS: usize = 3;
+/// Notes with fewer shingles than this are too short to call duplicates
+/// (empty notes made from the same template would all match)
+const MIN_SHINGLES: usize = 5;
+/// Signature values per band; two notes become candidates when every value
+/// of some band matches. Bands of 2 catch most pairs down to ~30% similar.
+const BAND_ROWS: usize = 2;
+
+/// A note in a duplicate cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateNote {
+    pub path: String,
+    pub title: String,
+    pub word_count: i64,
+}
+
+/// Notes whose content is largely the same. Each note is at least
+/// `min_similarity` similar to another note of the cluster.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateCluster {
+    pub notes: Vec<DuplicateNote>,
+    /// Estimated share of shingles the closest pair has in common (0 to 1)
+    pub similarity: f64,
+}
+
+/// MinHash signature of a note's body, or empty if it is too short.
+/// Frontmatter is left out and words are compared case-insensitively.
+pub fn signature(content: &str) -> Vec<u32> {
+    let (_, body) = crate::editing::split_frontmatter(content);
+    let words: Vec<String> = body
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let shingles: HashSet<u64> = words
+        .windows(SHINGLE_WORDS)
+        .map(|w| fnv1a(w.join(" ").as_bytes()))
+        .collect();
+    if shingles.len() < MIN_SHINGLES {
+        return Vec::new();
+    }
+
+    (0..SIGNATURE_LEN as u64)
+        .map(|seed| {
+            shingles
+                .iter()
+                .map(|&shingle| mix(shingle ^ seed.wrapping_mul(0x9e3779b97f4a7c15)) as u32)
+                .min()
+                .unwrap_or(u32::MAX)
+        })
+        .collect()
+}
+
+/// Estimated share of shingles two notes have in common (0 to 1)
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f64 / a.len() as f64
+}
+
+/// Clusters of notes `keep` accepts that are at least `min_similarity`
+/// similar (0 to 1), most similar first
+pub fn find_duplicates(
+    db: &Database,
+    min_similarity: f64,
+    keep: impl Fn(&str) -> bool,
+) -> Result<Vec<DuplicateCluster>> {
+    let signatures: Vec<(String, Vec<u32>)> = db
+        .get_content_signatures()?
+        .into_iter()
+        .filter(|(path, signature)| signature.len() == SIGNATURE_LEN && keep(path))
+        .collect();
+
+    let mut candidates = HashSet::new();
+    for band in 0..SIGNATURE_LEN / BAND_ROWS {
+        let rows = band * BAND_ROWS..(band + 1) * BAND_ROWS;
+        let mut buckets: HashMap<&[u32], Vec<usize>> = HashMap::new();
+        for (i, (_, signature)) in signatures.iter().enumerate() {
+            buckets.entry(&signature[rows.clone()]).or_default().push(i);
+        }
+        for bucket in buckets.values() {
+            for (n, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[n + 1..] {
+                    candidates.insert((a, b));
+                }
+            }
+        }
+    }
+
+    // Single-linkage clustering over the pairs that pass the threshold
+    let mut parent: Vec<usize> = (0..signatures.len()).collect();
+    let mut best = vec![0.0f64; signatures.len()];
+    for (a, b) in candidates {
+        let score = similarity(&signatures[a].1, &signatures[b].1);
+        if score < min_similarity {
+            continue;
+        }
+        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+        let root = ra.min(rb);
+        parent[ra.max(rb)] = root;
+        best[root] = best[root].max(best[ra]).max(best[rb]).max(score);
+    }
+
+    let notes: HashMap<String, (String, i64)> = db
+        .get_all_notes()?
+        .into_iter()
+        .map(|note| (note.path, (note.title, note.word_count)))
+        .collect();
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..signatures.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, members)| {
+            let mut notes: Vec<DuplicateNote> = members
+                .into_iter()
+                .map(|i| {
+                    let path = signatures[i].0.clone();
+                    let (title, word_count) = notes.get(&path).cloned().unwrap_or_default();
+                    DuplicateNote {
+                        path,
+                        title,
+                        word_count,
+                    }
+                })
+                .collect();
+            notes.sort_by(|a, b| a.path.cmp(&b.path));
+            DuplicateCluster {
+                notes,
+                similarity: best[root],
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.notes[0].path.cmp(&b.notes[0].path))
+    });
+    Ok(clusters)
+}
+
+/// Signature bytes as stored in the cache (little-endian `u32`s)
+pub fn to_blob(signature: &[u32]) -> Vec<u8> {
+    signature.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn from_blob(blob: &[u8]) -> Vec<u32> {
+    blob.chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// SplitMix64 finaliser, so each seed acts as a separate hash function
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut i = i;
+    while parent[i] != root {
+        let next = parent[i];
+        parent[i] = root;
+        i = next;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    const MEETING: &str = "Discussed the quarterly roadmap with the design team. \
+        We agreed to ship the sync rewrite before the plugin API, move the \
+        mobile beta to next month and hire another backend engineer for the \
+        storage work. Follow up with finance about the hosting budget.";
+
+    #[test]
+    fn test_find_duplicates_clusters_similar_notes() {
+        let vault = TestVault::with_notes(&[
+            ("Meeting.md", &format!("# Meeting\n{}\n", MEETING)),
+            (
+                "Imported/Meeting 1.md",
+                &format!("---\nsource: import\n---\n# Meeting\n{}\n", MEETING),
+            ),
+            (
+                "Meeting copy.md",
+                &format!("# Meeting\n{} Also book the offsite venue.\n", MEETING),
+            ),
+            (
+                "Gardening.md",
+                "# Gardening\nWater the tomatoes every morning, prune the roses in \
+                 spring and keep the slugs off the lettuce with copper tape.\n",
+            ),
+            ("Short.md", "# Meeting\n"),
+            ("Short 2.md", "# Meeting\n"),
+        ]);
+        let db = vault.index();
+
+        let clusters = find_duplicates(&db, 0.8, |_| true).unwrap();
+        assert_eq!(clusters.len(), 1);
+        let paths: Vec<&str> = clusters[0].notes.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["Imported/Meeting 1.md", "Meeting copy.md", "Meeting.md"]
+        );
+        assert_eq!(clusters[0].similarity, 1.0);
+        assert_eq!(clusters[0].notes[2].title, "Meeting");
+
+        let kept = find_duplicates(&db, 0.8, |p| !p.starts_with("Imported/")).unwrap();
+        assert_eq!(kept[0].notes.len(), 2);
+        assert!(kept[0].similarity >= 0.8 && kept[0].similarity < 1.0);
+        assert!(
+            find_duplicates(&db, 0.8, |p| p != "Meeting.md" && p != "Meeting copy.md")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
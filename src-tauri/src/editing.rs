@@ -0,0 +1,1309 @@
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+use crate::indexer;
+
+/// Line range (0-based, end exclusive) of the section under the first heading
+/// whose text matches `heading` (case-insensitive). The range starts at the
+/// heading line and ends before the next heading of the same or higher level.
+pub fn section_range(content: &str, heading: &str) -> Option<(usize, usize)> {
+    let wanted = heading.trim().trim_start_matches('#').trim().to_lowercase();
+    let headings = indexer::extract_headings(content);
+    let idx = headings
+        .iter()
+        .position(|h| h.text.to_lowercase() == wanted)?;
+    let start = headings[idx].line - 1;
+    let end = headings[idx + 1..]
+        .iter()
+        .find(|h| h.level <= headings[idx].level)
+        .map(|h| h.line - 1)
+        .unwrap_or_else(|| content.lines().count());
+    Some((start, end))
+}
+
+/// Append `text` to the end of the note, or to the end of the section under
+/// `heading` (creating that heading at the bottom if it doesn't exist).
+pub fn append_text(content: &str, text: &str, heading: Option<&str>) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let new_lines: Vec<String> = text
+        .trim_end_matches('\n')
+        .lines()
+        .map(str::to_string)
+        .collect();
+
+    let (section_start, section_end) = match heading {
+        Some(h) => match section_range(content, h) {
+            Some(range) => range,
+            None => {
+                // Missing heading: add it at the end of the note
+                trim_trailing_blank(&mut lines);
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.push(format!("## {}", h.trim().trim_start_matches('#').trim()));
+                (lines.len() - 1, lines.len())
+            }
+        },
+        None => (0, lines.len()),
+    };
+
+    // Insert after the last non-blank line of the section
+    let mut insert_at = section_end;
+    while insert_at > section_start && lines[insert_at - 1].trim().is_empty() {
+        insert_at -= 1;
+    }
+
+    let mut block = Vec::new();
+    let previous = insert_at.checked_sub(1).map(|i| lines[i].as_str());
+    let joins_list =
+        previous.is_some_and(is_list_item) && new_lines.first().is_some_and(|l| is_list_item(l));
+    if previous.is_some() && !joins_list {
+        block.push(String::new());
+    }
+    block.extend(new_lines);
+    // Keep a blank line between the inserted text and whatever follows
+    if lines[insert_at..].iter().any(|l| !l.trim().is_empty()) {
+        block.push(String::new());
+    }
+
+    let tail: Vec<String> = lines
+        .split_off(insert_at)
+        .into_iter()
+        .skip_while(|l| l.trim().is_empty())
+        .collect();
+    lines.extend(block);
+    lines.extend(tail);
+
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
+}
+
+/// Where to insert content in a note
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum InsertPosition {
+    /// Directly below the first heading with this text
+    Heading(String),
+    /// Directly below the line carrying this `^block-id`
+    Block(String),
+    /// At this 1-based line number (existing lines shift down)
+    Line(usize),
+}
+
+/// Resolve an insert position to the 0-based line index the new text will start at
+pub fn resolve_insert_line(content: &str, position: &InsertPosition) -> Option<usize> {
+    let line_count = content.lines().count();
+    match position {
+        InsertPosition::Heading(heading) => section_range(content, heading).map(|(start, _)| start + 1),
+        InsertPosition::Block(id) => {
+            let id = id.trim_start_matches('^');
+            indexer::extract_block_ids(content)
+                .into_iter()
+                .find(|b| b.id == id)
+                .map(|b| b.line)
+        }
+        InsertPosition::Line(line) => Some(line.saturating_sub(1).min(line_count)),
+    }
+}
+
+/// Insert `text` so it starts at 0-based line `at`. Returns the new content and
+/// the 1-based inclusive line range the text now occupies.
+pub fn insert_lines(content: &str, at: usize, text: &str) -> (String, usize, usize) {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let new_lines: Vec<&str> = text.trim_end_matches('\n').lines().collect();
+    let at = at.min(lines.len());
+    let inserted = new_lines.len().max(1);
+
+    if new_lines.is_empty() {
+        lines.insert(at, "");
+    } else {
+        lines.splice(at..at, new_lines);
+    }
+
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        result.push('\n');
+    }
+    (result, at + 1, at + inserted)
+}
+
+/// Split content into the raw frontmatter block (both `---` fences included,
+/// with its trailing newline) and the body. The block is empty if there's none.
+pub fn split_frontmatter(content: &str) -> (&str, &str) {
+    if !content.starts_with("---") {
+        return ("", content);
+    }
+    match content[3..].find("\n---") {
+        Some(idx) => {
+            let fence_end = 3 + idx + 4;
+            let end = content[fence_end..]
+                .find('\n')
+                .map(|nl| fence_end + nl + 1)
+                .unwrap_or(content.len());
+            (&content[..end], &content[end..])
+        }
+        None => ("", content),
+    }
+}
+
+/// Rewrite wikilinks in `content`. `rewrite` gets each parsed link and returns
+/// the replacement link, or None to leave it untouched. Returns None if nothing changed.
+pub fn rewrite_wikilinks<F>(content: &str, mut rewrite: F) -> Option<String>
+where
+    F: FnMut(&indexer::WikiLink) -> Option<indexer::WikiLink>,
+{
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut changed = false;
+
+    for link in indexer::parse_wikilinks(content) {
+        if let Some(new_link) = rewrite(&link) {
+            let rendered = new_link.render();
+            if rendered != content[link.start..link.end] {
+                out.push_str(&content[last..link.start]);
+                out.push_str(&rendered);
+                last = link.end;
+                changed = true;
+            }
+        }
+    }
+
+    if !changed {
+        return None;
+    }
+    out.push_str(&content[last..]);
+    Some(out)
+}
+
+/// A link subpath (`Heading`, `parent#heading`) pointed at `after` if its
+/// last part names `before`. Slug-style anchors get the new slug, others
+/// the new heading text. None when the subpath is about another heading.
+pub fn rename_section_subpath(
+    subpath: &str,
+    before: &indexer::Heading,
+    after: &indexer::Heading,
+) -> Option<String> {
+    let (parents, anchor) = match subpath.rsplit_once('#') {
+        Some((parents, anchor)) => (Some(parents), anchor),
+        None => (None, subpath),
+    };
+    indexer::find_heading(std::slice::from_ref(before), anchor)?;
+    let renamed = if anchor.trim() == before.slug && anchor.trim() != before.text {
+        &after.slug
+    } else {
+        &after.text
+    };
+    Some(match parents {
+        Some(parents) => format!("{}#{}", parents, renamed),
+        None => renamed.clone(),
+    })
+}
+
+/// Rename tag `old` to `new` (both without `#`) in the body and in the
+/// frontmatter `tags` list, nested tags included: renaming `project` to
+/// `work` turns `#project/alpha` into `#work/alpha`. Matching ignores case;
+/// code is left alone. Returns None if nothing changed.
+pub fn rename_tag(content: &str, old: &str, new: &str) -> Option<String> {
+    let (frontmatter, body) = split_frontmatter(content);
+    let mut out = String::with_capacity(content.len());
+    for line in frontmatter_tag_lines(frontmatter) {
+        match line {
+            FrontmatterLine::Other(text) => out.push_str(text),
+            FrontmatterLine::Tags { prefix, items } => {
+                out.push_str(prefix);
+                out.push_str(&rename_tag_items(items, old, new));
+            }
+        }
+    }
+
+    let mut in_code_block = false;
+    for line in body.split_inclusive('\n') {
+        if line.trim().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || line.trim().starts_with("```") {
+            out.push_str(line);
+        } else {
+            out.push_str(&rename_line_tags(line, old, new));
+        }
+    }
+    (out != content).then_some(out)
+}
+
+/// `tag` renamed if it is `old` or nested under it
+fn renamed_tag(tag: &str, old: &str, new: &str) -> Option<String> {
+    let prefix = tag.get(..old.len())?;
+    let rest = &tag[old.len()..];
+    (prefix.to_lowercase() == old.to_lowercase() && (rest.is_empty() || rest.starts_with('/')))
+        .then(|| format!("{}{}", new, rest))
+}
+
+/// Rename `#tags` in a line of prose, following the indexer's rules for
+/// what counts as a tag
+fn rename_line_tags(line: &str, old: &str, new: &str) -> String {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut last = 0;
+    let mut in_inline_code = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        if c == '`' {
+            in_inline_code = !in_inline_code;
+        }
+        let boundary = i == 0 || chars[i - 1].1.is_whitespace() || chars[i - 1].1 == ',';
+        let heading = i == 0 && chars.get(1).is_some_and(|&(_, next)| next == ' ');
+        if c != '#' || in_inline_code || !boundary || heading {
+            i += 1;
+            continue;
+        }
+        let start = offset + 1;
+        i += 1;
+        while i < chars.len()
+            && (chars[i].1.is_alphanumeric() || matches!(chars[i].1, '-' | '_' | '/'))
+        {
+            i += 1;
+        }
+        let end = chars.get(i).map_or(line.len(), |&(offset, _)| offset);
+        if let Some(renamed) = renamed_tag(&line[start..end], old, new) {
+            out.push_str(&line[last..start]);
+            out.push_str(&renamed);
+            last = end;
+        }
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+enum FrontmatterLine<'a> {
+    Other(&'a str),
+    /// A line holding tag values: `prefix` is kept as is, `items` rewritten
+    Tags {
+        prefix: &'a str,
+        items: &'a str,
+    },
+}
+
+/// Split a frontmatter block into lines, picking out the values of its
+/// `tags:` key, whether inline (`tags: [a, b]`, `tags: a, b`) or a list
+fn frontmatter_tag_lines(frontmatter: &str) -> Vec<FrontmatterLine<'_>> {
+    let mut lines = Vec::new();
+    let mut in_tags_list = false;
+    for line in frontmatter.split_inclusive('\n') {
+        if let Some(value) = line.strip_prefix("tags:") {
+            in_tags_list = value.trim().is_empty();
+            let prefix = &line[..line.len() - value.len()];
+            lines.push(FrontmatterLine::Tags {
+                prefix,
+                items: value,
+            });
+            continue;
+        }
+        let item = line.trim_start();
+        if in_tags_list && item.starts_with("- ") {
+            let prefix = &line[..line.len() - item.len() + 2];
+            lines.push(FrontmatterLine::Tags {
+                prefix,
+                items: &item[2..],
+            });
+            continue;
+        }
+        if !line.starts_with([' ', '\t']) {
+            in_tags_list = false;
+        }
+        lines.push(FrontmatterLine::Other(line));
+    }
+    lines
+}
+
+/// Rename tags in a comma-separated YAML value, keeping brackets, quotes,
+/// `#` prefixes and spacing
+fn rename_tag_items(items: &str, old: &str, new: &str) -> String {
+    items
+        .split(',')
+        .map(|item| {
+            let start = item
+                .find(|c: char| !c.is_whitespace() && !matches!(c, '[' | '"' | '\'' | '#'))
+                .unwrap_or(item.len());
+            let end = item[start..]
+                .find(|c: char| c.is_whitespace() || matches!(c, ']' | '"' | '\''))
+                .map_or(item.len(), |end| start + end);
+            match renamed_tag(&item[start..end], old, new) {
+                Some(renamed) => format!("{}{}{}", &item[..start], renamed, &item[end..]),
+                None => item.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Set frontmatter `key` to `value`, creating the frontmatter block if the
+/// note has none. Only the key's own lines are rewritten: other keys, their
+/// order and comments stay as they were, and a new key goes last.
+pub fn set_frontmatter_property(
+    content: &str,
+    key: &str,
+    value: &serde_yaml::Value,
+) -> Result<String> {
+    let mut entry = serde_yaml::Mapping::new();
+    entry.insert(serde_yaml::Value::String(key.to_string()), value.clone());
+    let rendered = serde_yaml::to_string(&entry)?;
+
+    let (frontmatter, body) = split_frontmatter(content);
+    if frontmatter.is_empty() {
+        return Ok(format!("---\n{}---\n{}", rendered, content));
+    }
+    let (open, lines, close) = frontmatter_entries(frontmatter)?;
+    let mut out = String::with_capacity(content.len() + rendered.len());
+    out.push_str(open);
+    let mut replaced = false;
+    for (entry_key, text) in &lines {
+        if entry_key.as_deref() == Some(key) {
+            if !replaced {
+                out.push_str(&rendered);
+                replaced = true;
+            }
+        } else {
+            out.push_str(text);
+        }
+    }
+    if !replaced {
+        out.push_str(&rendered);
+    }
+    out.push_str(close);
+    out.push_str(body);
+    Ok(out)
+}
+
+/// Remove frontmatter `key` and its value, leaving the rest of the block as
+/// it was. Returns None if the note has no such key.
+pub fn remove_frontmatter_property(content: &str, key: &str) -> Result<Option<String>> {
+    let (frontmatter, body) = split_frontmatter(content);
+    if frontmatter.is_empty() {
+        return Ok(None);
+    }
+    let (open, lines, close) = frontmatter_entries(frontmatter)?;
+    if !lines.iter().any(|(k, _)| k.as_deref() == Some(key)) {
+        return Ok(None);
+    }
+    let mut out = String::with_capacity(content.len());
+    out.push_str(open);
+    for (_, text) in lines.iter().filter(|(k, _)| k.as_deref() != Some(key)) {
+        out.push_str(text);
+    }
+    out.push_str(close);
+    out.push_str(body);
+    Ok(Some(out))
+}
+
+/// A frontmatter block's fences and the lines between them, each tagged
+/// with the top-level key it belongs to (None for comments and blank lines
+/// that aren't part of an entry)
+type FrontmatterEntries<'a> = (&'a str, Vec<(Option<String>, &'a str)>, &'a str);
+
+/// Split a frontmatter block into entries. Refuses blocks that aren't valid
+/// YAML rather than risk mangling them.
+fn frontmatter_entries(frontmatter: &str) -> Result<FrontmatterEntries<'_>> {
+    let open_end = frontmatter
+        .find('\n')
+        .map_or(frontmatter.len(), |nl| nl + 1);
+    let close_start = frontmatter[..frontmatter.len() - 1]
+        .rfind('\n')
+        .map_or(open_end, |nl| nl + 1)
+        .max(open_end);
+    let inner = &frontmatter[open_end..close_start];
+    if !inner.trim().is_empty() {
+        if let Err(e) = serde_yaml::from_str::<serde_yaml::Mapping>(inner) {
+            bail!("The note's frontmatter isn't valid YAML: {}", e);
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut current = None;
+    for line in inner.split_inclusive('\n') {
+        if let Some(key) = top_level_key(line) {
+            current = Some(key);
+        } else if !line.starts_with([' ', '\t', '-']) && !line.trim().is_empty() {
+            // A comment at the margin belongs to whatever comes after it
+            current = None;
+        }
+        lines.push((current.clone(), line));
+    }
+    Ok((&frontmatter[..open_end], lines, &frontmatter[close_start..]))
+}
+
+/// The key of a `key: value` line at the margin of a YAML mapping
+fn top_level_key(line: &str) -> Option<String> {
+    if line.starts_with([' ', '\t', '-', '#']) {
+        return None;
+    }
+    let line = line.trim_end();
+    if let Some(quote) = line.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+        let end = line[1..].find(quote)? + 1;
+        return line[end + 1..]
+            .starts_with(':')
+            .then(|| line[1..end].to_string());
+    }
+    let colon = line
+        .char_indices()
+        .find(|&(i, c)| {
+            c == ':'
+                && line[i + 1..]
+                    .chars()
+                    .next()
+                    .map_or(true, char::is_whitespace)
+        })?
+        .0;
+    Some(line[..colon].trim_end().to_string())
+}
+
+/// A section of a note that starts at a heading of the split level
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub heading: String,
+    /// Lines under the heading (heading line excluded), trimmed of blank edges
+    pub body: String,
+    /// Headings nested inside this section (for rewriting `[[Note#Sub]]` links)
+    pub subheadings: Vec<String>,
+}
+
+/// Split a note body into sections at headings of exactly `level`. Returns the
+/// sections and the remaining body, in which each section is replaced by a
+/// `- [[Heading]]` line.
+pub fn split_sections(body: &str, level: u8) -> (Vec<Section>, String) {
+    let lines: Vec<&str> = body.lines().collect();
+    let headings = indexer::extract_headings(body);
+    let mut sections = Vec::new();
+    let mut remaining: Vec<String> = Vec::new();
+    let mut line = 0;
+
+    for (i, h) in headings.iter().enumerate() {
+        if h.level != level || h.line - 1 < line {
+            continue;
+        }
+        let start = h.line - 1;
+        let end = headings[i + 1..]
+            .iter()
+            .find(|next| next.level <= level)
+            .map(|next| next.line - 1)
+            .unwrap_or(lines.len());
+
+        remaining.extend(lines[line..start].iter().map(|l| l.to_string()));
+        // Keep the generated link list separated from preceding prose
+        if remaining
+            .last()
+            .is_some_and(|l| !l.trim().is_empty() && !is_list_item(l))
+        {
+            remaining.push(String::new());
+        }
+        remaining.push(format!("- [[{}]]", h.text));
+
+        let subheadings = headings[i + 1..]
+            .iter()
+            .take_while(|next| next.line - 1 < end)
+            .map(|next| next.text.clone())
+            .collect();
+        let body = lines[start + 1..end].join("\n").trim_matches('\n').to_string();
+        sections.push(Section {
+            heading: h.text.clone(),
+            body,
+            subheadings,
+        });
+        line = end;
+    }
+    remaining.extend(lines[line..].iter().map(|l| l.to_string()));
+
+    let mut remaining = remaining.join("\n");
+    while remaining.contains("\n\n\n") {
+        remaining = remaining.replace("\n\n\n", "\n\n");
+    }
+    let mut remaining = remaining.trim_matches('\n').to_string();
+    remaining.push('\n');
+    (sections, remaining)
+}
+
+/// How `merge_note_bodies` combines two notes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// The source's body goes after the target's
+    #[default]
+    Append,
+    /// Each source section goes to the end of the target section with the
+    /// same heading; sections without a match are appended
+    Interleave,
+}
+
+/// Merge `source_body` (a note body, without frontmatter) into the `target`
+/// note. For `Interleave` a lone leading H1 in the source is taken as its
+/// title and dropped, and sections are matched at the source's top level.
+pub fn merge_note_bodies(target: &str, source_body: &str, strategy: MergeStrategy) -> String {
+    if source_body.trim().is_empty() {
+        return target.to_string();
+    }
+    if strategy == MergeStrategy::Append {
+        return append_text(target, source_body, None);
+    }
+
+    let lines: Vec<&str> = source_body.lines().collect();
+    let headings = indexer::extract_headings(source_body);
+    let title = match headings.first() {
+        Some(h) if h.level == 1 && headings.iter().filter(|h| h.level == 1).count() == 1 => {
+            Some(h.line - 1)
+        }
+        _ => None,
+    };
+    let sections: Vec<&indexer::Heading> = headings
+        .iter()
+        .filter(|h| title != Some(h.line - 1))
+        .collect();
+    let Some(level) = sections.iter().map(|h| h.level).min() else {
+        return append_text(target, source_body, None);
+    };
+
+    let first = sections
+        .iter()
+        .find(|h| h.level == level)
+        .map_or(lines.len(), |h| h.line - 1);
+    let preamble: Vec<&str> = (0..first)
+        .filter(|&i| title != Some(i))
+        .map(|i| lines[i])
+        .collect();
+    let mut leftover = vec![preamble.join("\n").trim_matches('\n').to_string()];
+
+    let mut merged = target.to_string();
+    for (i, h) in sections.iter().enumerate() {
+        if h.level != level {
+            continue;
+        }
+        let start = h.line - 1;
+        let end = sections[i + 1..]
+            .iter()
+            .find(|next| next.level <= level)
+            .map_or(lines.len(), |next| next.line - 1);
+        let body = lines[start + 1..end].join("\n");
+        if section_range(&merged, &h.text).is_some() {
+            if !body.trim().is_empty() {
+                merged = append_text(&merged, body.trim_matches('\n'), Some(&h.text));
+            }
+        } else {
+            leftover.push(lines[start..end].join("\n").trim_matches('\n').to_string());
+        }
+    }
+
+    leftover.retain(|text| !text.trim().is_empty());
+    if leftover.is_empty() {
+        merged
+    } else {
+        append_text(&merged, &leftover.join("\n\n"), None)
+    }
+}
+
+/// Opening marker of a generated table of contents
+pub const TOC_START: &str = "<!-- toc -->";
+/// Closing marker of a generated table of contents
+pub const TOC_END: &str = "<!-- /toc -->";
+
+/// How table of contents entries link to their headings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TocLinkStyle {
+    /// `[[#Heading]]`, resolved by heading text
+    #[default]
+    Wikilink,
+    /// `[Heading](#slug)`, which stays unambiguous for repeated headings and
+    /// renders in other markdown viewers
+    Anchor,
+}
+
+/// Build a nested list of links for headings within `min_level..=max_level`
+pub fn build_toc(
+    headings: &[indexer::Heading],
+    min_level: u8,
+    max_level: u8,
+    style: TocLinkStyle,
+) -> String {
+    let included: Vec<&indexer::Heading> = headings
+        .iter()
+        .filter(|h| h.level >= min_level && h.level <= max_level)
+        .collect();
+    let base = included.iter().map(|h| h.level).min().unwrap_or(min_level);
+
+    included
+        .iter()
+        .map(|h| {
+            let indent = "  ".repeat((h.level - base) as usize);
+            match style {
+                TocLinkStyle::Wikilink => format!("{}- [[#{}]]", indent, h.text),
+                TocLinkStyle::Anchor => {
+                    let text = h.text.replace('[', "\\[").replace(']', "\\]");
+                    format!("{}- [{}](#{})", indent, text, h.slug)
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replace the TOC between the markers, or insert a marked TOC below the
+/// first H1 (or at the top of the body) if there are no markers yet
+pub fn upsert_toc(content: &str, toc: &str) -> String {
+    let block = format!("{}\n{}\n{}", TOC_START, toc, TOC_END);
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter().position(|l| l.trim() == TOC_START);
+    let end = start.and_then(|s| {
+        lines[s..]
+            .iter()
+            .position(|l| l.trim() == TOC_END)
+            .map(|e| s + e)
+    });
+
+    let mut result = match (start, end) {
+        (Some(start), Some(end)) => {
+            let mut out: Vec<&str> = lines[..start].to_vec();
+            out.push(&block);
+            out.extend(&lines[end + 1..]);
+            out.join("\n")
+        }
+        _ => {
+            let (fm_block, body) = split_frontmatter(content);
+            let fm_lines = fm_block.lines().count();
+            let insert_at = indexer::extract_headings(body)
+                .iter()
+                .find(|h| h.level == 1)
+                .map(|h| fm_lines + h.line)
+                .unwrap_or(fm_lines);
+            let (before, after) = lines.split_at(insert_at.min(lines.len()));
+            let before = before.join("\n");
+            let after = after.join("\n");
+            let mut out = String::new();
+            if !before.trim().is_empty() {
+                out.push_str(before.trim_end_matches('\n'));
+                out.push_str("\n\n");
+            }
+            out.push_str(&block);
+            if !after.trim().is_empty() {
+                out.push_str("\n\n");
+                out.push_str(after.trim_start_matches('\n'));
+            }
+            out
+        }
+    };
+    result.push('\n');
+    result
+}
+
+/// Promote (negative `delta`) or demote every heading whose 1-based line falls
+/// in `lines` (inclusive; None = whole note), clamping levels to 1–6.
+/// Returns the new content and how many headings changed level.
+pub fn shift_headings(content: &str, lines: Option<(usize, usize)>, delta: i32) -> (String, usize) {
+    let targets: HashMap<usize, u8> = indexer::extract_headings(content)
+        .into_iter()
+        .filter(|h| lines.map_or(true, |(start, end)| h.line >= start && h.line <= end))
+        .map(|h| (h.line, (h.level as i32 + delta).clamp(1, 6) as u8))
+        .collect();
+
+    let mut changed = 0;
+    let mut out = String::with_capacity(content.len());
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let Some(&level) = targets.get(&(i + 1)) else {
+            out.push_str(line);
+            continue;
+        };
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        let old_level = trimmed.chars().take_while(|c| *c == '#').count();
+        if old_level != level as usize {
+            changed += 1;
+        }
+        out.push_str(indent);
+        out.push_str(&"#".repeat(level as usize));
+        out.push_str(&trimmed[old_level..]);
+    }
+    (out, changed)
+}
+
+/// A footnote in a note: where it's referenced and what its definition says
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Footnote {
+    pub label: String,
+    /// 1-based lines of every `[^label]` reference
+    pub references: Vec<usize>,
+    /// Definition text (continuation lines joined with newlines)
+    pub definition: Option<String>,
+    /// 1-based line of the `[^label]:` definition
+    pub definition_line: Option<usize>,
+}
+
+struct FootnoteRef {
+    label: String,
+    line: usize,
+    /// Byte range of the label (inside `[^...]`) in the content
+    span: std::ops::Range<usize>,
+}
+
+struct FootnoteDef {
+    label: String,
+    /// 0-based line range of the definition and its indented continuation lines
+    lines: std::ops::Range<usize>,
+}
+
+/// Footnotes in reference order, followed by definitions nothing references
+pub fn list_footnotes(content: &str) -> Vec<Footnote> {
+    let (refs, defs) = scan_footnotes(content);
+    let lines: Vec<&str> = content.lines().collect();
+    let mut footnotes: Vec<Footnote> = Vec::new();
+
+    for r in &refs {
+        match footnotes.iter_mut().find(|f| f.label == r.label) {
+            Some(f) => f.references.push(r.line + 1),
+            None => footnotes.push(Footnote {
+                label: r.label.clone(),
+                references: vec![r.line + 1],
+                definition: None,
+                definition_line: None,
+            }),
+        }
+    }
+    for def in &defs {
+        let text = lines[def.lines.clone()]
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                if i == 0 {
+                    line[def.label.len() + 4..].trim()
+                } else {
+                    line.trim()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let idx = match footnotes.iter().position(|f| f.label == def.label) {
+            Some(idx) => idx,
+            None => {
+                footnotes.push(Footnote {
+                    label: def.label.clone(),
+                    references: Vec::new(),
+                    definition: None,
+                    definition_line: None,
+                });
+                footnotes.len() - 1
+            }
+        };
+        if footnotes[idx].definition.is_none() {
+            footnotes[idx].definition = Some(text);
+            footnotes[idx].definition_line = Some(def.lines.start + 1);
+        }
+    }
+    footnotes
+}
+
+/// Insert a new numbered footnote reference at byte offset `at` with its
+/// definition at the bottom, then renumber so numbers follow reading order.
+/// Returns the updated content and the new footnote's label.
+pub fn add_footnote(content: &str, at: usize, text: &str) -> Option<(String, String)> {
+    if at > content.len() || !content.is_char_boundary(at) || text.trim().is_empty() {
+        return None;
+    }
+    let (refs, defs) = scan_footnotes(content);
+    let next = refs
+        .iter()
+        .map(|r| &r.label)
+        .chain(defs.iter().map(|d| &d.label))
+        .filter_map(|label| label.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let label = next.to_string();
+
+    let mut definition = format!("[^{}]: ", label);
+    for (i, line) in text.trim().lines().enumerate() {
+        if i > 0 {
+            definition.push_str("\n    ");
+        }
+        definition.push_str(line.trim_end());
+    }
+
+    let mut updated = format!("{}[^{}]{}", &content[..at], label, &content[at..]);
+    let body_end = updated.trim_end().len();
+    updated.truncate(body_end);
+    let last_line = updated.lines().last().unwrap_or_default();
+    updated.push_str(if definition_label(last_line).is_some() || updated.is_empty() {
+        "\n"
+    } else {
+        "\n\n"
+    });
+    updated.push_str(&definition);
+    updated.push('\n');
+
+    let (renumbered, mapping) = renumber(&updated);
+    let label = mapping.get(&label).cloned().unwrap_or(label);
+    Some((renumbered, label))
+}
+
+/// Renumber numeric footnotes 1..n in order of first reference and move all
+/// definitions to the bottom of the note. Named footnotes (`[^note]`) keep
+/// their labels and follow the numbered ones.
+pub fn renumber_footnotes(content: &str) -> String {
+    renumber(content).0
+}
+
+fn renumber(content: &str) -> (String, HashMap<String, String>) {
+    let (refs, defs) = scan_footnotes(content);
+    let is_numeric = |label: &str| label.chars().all(|c| c.is_ascii_digit());
+
+    let mut mapping: HashMap<String, String> = HashMap::new();
+    for label in refs
+        .iter()
+        .map(|r| &r.label)
+        .chain(defs.iter().map(|d| &d.label))
+    {
+        if is_numeric(label) && !mapping.contains_key(label) {
+            let number = mapping.len() + 1;
+            mapping.insert(label.clone(), number.to_string());
+        }
+    }
+    if defs.is_empty() && mapping.iter().all(|(old, new)| old == new) {
+        return (content.to_string(), mapping);
+    }
+    let relabel = |label: &str| mapping.get(label).map_or(label, String::as_str).to_string();
+
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut line_starts = Vec::with_capacity(lines.len());
+    let mut offset = 0;
+    for line in &lines {
+        line_starts.push(offset);
+        offset += line.len();
+    }
+    let def_lines: std::collections::HashSet<usize> =
+        defs.iter().flat_map(|d| d.lines.clone()).collect();
+
+    let mut body = String::new();
+    let mut refs = refs.iter().peekable();
+    let mut last_blank = false;
+    for (i, line) in lines.iter().enumerate() {
+        if def_lines.contains(&i) {
+            continue;
+        }
+        // Don't leave a double blank line where a definition was lifted out
+        let blank = line.trim().is_empty();
+        if blank && last_blank && i > 0 && def_lines.contains(&(i - 1)) {
+            continue;
+        }
+        last_blank = blank;
+        let mut cursor = line_starts[i];
+        while let Some(r) = refs.next_if(|r| r.line == i) {
+            body.push_str(&content[cursor..r.span.start]);
+            body.push_str(&relabel(&r.label));
+            cursor = r.span.end;
+        }
+        body.push_str(&content[cursor..line_starts[i] + line.len()]);
+    }
+    if defs.is_empty() {
+        return (body, mapping);
+    }
+
+    let mut ordered: Vec<&FootnoteDef> = defs.iter().collect();
+    ordered.sort_by_key(|d| match mapping.get(&d.label) {
+        Some(number) => (0, number.parse::<usize>().unwrap_or(0)),
+        None => (1, 0),
+    });
+    let mut out = body.trim_end().to_string();
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    for def in ordered {
+        let block: String = lines[def.lines.clone()].concat();
+        let rest = &block[def.label.len() + 2..];
+        out.push_str(&format!("[^{}{}", relabel(&def.label), rest.trim_end()));
+        out.push('\n');
+    }
+    (out, mapping)
+}
+
+/// Find footnote references and definitions, skipping frontmatter and code
+fn scan_footnotes(content: &str) -> (Vec<FootnoteRef>, Vec<FootnoteDef>) {
+    let mut refs = Vec::new();
+    let mut defs: Vec<FootnoteDef> = Vec::new();
+    let mut offset = 0;
+    let mut in_frontmatter = content.starts_with("---");
+    let mut fence: Option<&str> = None;
+    let mut in_def = false;
+
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim_start();
+        if in_frontmatter {
+            if i > 0 && line.trim_end() == "---" {
+                in_frontmatter = false;
+            }
+            continue;
+        }
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            in_def = false;
+            continue;
+        }
+        if in_def && !line.trim().is_empty() && line.starts_with([' ', '\t']) {
+            if let Some(def) = defs.last_mut() {
+                def.lines.end = i + 1;
+            }
+            continue;
+        }
+        in_def = false;
+        if let Some(label) = definition_label(line) {
+            defs.push(FootnoteDef {
+                label: label.to_string(),
+                lines: i..i + 1,
+            });
+            in_def = true;
+            continue;
+        }
+
+        let mut in_code = false;
+        let mut j = 0;
+        while j < line.len() {
+            let rest = &line[j..];
+            if rest.starts_with('`') {
+                in_code = !in_code;
+            } else if !in_code && rest.starts_with("[^") {
+                if let Some(label) = bracket_label(&rest[2..]) {
+                    let start = line_start + j + 2;
+                    refs.push(FootnoteRef {
+                        label: label.to_string(),
+                        line: i,
+                        span: start..start + label.len(),
+                    });
+                    j += label.len() + 3;
+                    continue;
+                }
+            }
+            j += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    (refs, defs)
+}
+
+/// Label of a `[^label]: text` definition line
+fn definition_label(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("[^")?;
+    let label = bracket_label(rest)?;
+    rest[label.len() + 1..].starts_with(':').then_some(label)
+}
+
+/// `label]...` -> `label`, for labels without whitespace or brackets
+fn bracket_label(s: &str) -> Option<&str> {
+    let end = s.find(']')?;
+    let label = &s[..end];
+    let valid = !label.is_empty()
+        && !label
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '[' | '^'));
+    valid.then_some(label)
+}
+
+/// Check or uncheck the task on `line` (1-based). Checking it appends
+/// `✅ date` when a date is given (ahead of a trailing `^block-id`);
+/// unchecking drops any `✅ date`. Returns the new content and whether the
+/// task is now done, or None if the line isn't a task.
+pub fn toggle_task(content: &str, line: usize, done_date: Option<&str>) -> Option<(String, bool)> {
+    let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let original = *lines.get(line.checked_sub(1)?)?;
+    let text = original.trim_end_matches(['\n', '\r']);
+    let ending = &original[text.len()..];
+    let (completed, rest) = indexer::parse_task_line(text)?;
+    let checkbox = text.len() - rest.len() - 3;
+
+    let mut toggled = format!(
+        "{}[{}]{}",
+        &text[..checkbox],
+        if completed { ' ' } else { 'x' },
+        rest
+    );
+    if completed {
+        toggled = strip_done_dates(&toggled);
+    } else if let Some(date) = done_date {
+        let trimmed = toggled.trim_end();
+        let (before, block) = match indexer::parse_block_id(trimmed) {
+            Some(id) => trimmed.split_at(trimmed.len() - id.len() - 1),
+            None => (trimmed, ""),
+        };
+        let mut dated = format!("{} ✅ {}", before.trim_end(), date);
+        if !block.is_empty() {
+            dated.push(' ');
+            dated.push_str(block);
+        }
+        toggled = dated;
+    }
+    toggled.push_str(ending);
+
+    lines[line - 1] = &toggled;
+    Some((lines.concat(), !completed))
+}
+
+/// Remove ` ✅ YYYY-MM-DD` completion markers from a task line
+fn strip_done_dates(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(at) = rest.find('✅') {
+        let after = &rest[at + '✅'.len_utf8()..];
+        let date_len = after.len() - after.trim_start().len() + 10;
+        let is_date = after.trim_start().get(..10).is_some_and(|date| {
+            date.bytes().enumerate().all(|(i, b)| match i {
+                4 | 7 => b == b'-',
+                _ => b.is_ascii_digit(),
+            })
+        });
+        if is_date {
+            out.push_str(rest[..at].trim_end());
+            rest = &after[date_len..];
+        } else {
+            out.push_str(&rest[..at + '✅'.len_utf8()]);
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Format `text` as a `- HH:MM text` bullet for journal-style captures
+pub fn timestamp_bullet(text: &str, time: &str) -> String {
+    let mut lines = text.trim().lines();
+    let first = lines.next().unwrap_or_default();
+    let mut bullet = format!("- {} {}", time, first);
+    for rest in lines {
+        bullet.push_str("\n  ");
+        bullet.push_str(rest);
+    }
+    bullet
+}
+
+/// Whether a line is a markdown list item (`-`, `*`, `+` or `1.`)
+pub fn is_list_item(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("- ") || trimmed.starts_with("* ") || trimmed.starts_with("+ ") {
+        return true;
+    }
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && trimmed[digits..].starts_with(". ")
+}
+
+fn trim_trailing_blank(lines: &mut Vec<String>) {
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_to_end() {
+        let result = append_text("# Note\n\nSome text\n\n", "More", None);
+        assert_eq!(result, "# Note\n\nSome text\n\nMore\n");
+    }
+
+    #[test]
+    fn test_append_under_heading() {
+        let content = "# Log\n\n## Inbox\n\n- one\n\n## Done\n\n- old\n";
+        let result = append_text(content, "- two", Some("inbox"));
+        assert_eq!(
+            result,
+            "# Log\n\n## Inbox\n\n- one\n- two\n\n## Done\n\n- old\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_at_heading_and_block() {
+        let content = "# A\ntext ^blk\n## B\nend\n";
+        let at = resolve_insert_line(content, &InsertPosition::Heading("B".into())).unwrap();
+        let (result, start, end) = insert_lines(content, at, "x\ny");
+        assert_eq!(result, "# A\ntext ^blk\n## B\nx\ny\nend\n");
+        assert_eq!((start, end), (4, 5));
+
+        let at = resolve_insert_line(content, &InsertPosition::Block("^blk".into())).unwrap();
+        assert_eq!(at, 2);
+    }
+
+    #[test]
+    fn test_split_sections() {
+        let body = "# Big\n\nIntro\n\n## One\n\nfirst\n\n### Deep\n\n## Two\nsecond\n";
+        let (sections, remaining) = split_sections(body, 2);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].heading, "One");
+        assert_eq!(sections[0].body, "first\n\n### Deep");
+        assert_eq!(sections[0].subheadings, vec!["Deep"]);
+        assert_eq!(remaining, "# Big\n\nIntro\n\n- [[One]]\n- [[Two]]\n");
+    }
+
+    #[test]
+    fn test_rewrite_wikilinks() {
+        let content = "See [[Old#Part|here]] and ![[Old]] but not [[Other]]";
+        let result = rewrite_wikilinks(content, |link| {
+            (link.target == "Old").then(|| indexer::WikiLink {
+                target: "New".into(),
+                ..link.clone()
+            })
+        });
+        assert_eq!(
+            result.as_deref(),
+            Some("See [[New#Part|here]] and ![[New]] but not [[Other]]")
+        );
+    }
+
+    #[test]
+    fn test_merge_note_bodies() {
+        let target = "---\ntitle: T\n---\n# T\n\n## Ideas\n- one\n\n## Links\nsee x\n";
+        let source = "# S\nintro\n\n## ideas\n- two\n\n## New\ntext\n";
+        assert_eq!(
+            merge_note_bodies(target, source, MergeStrategy::Append),
+            "---\ntitle: T\n---\n# T\n\n## Ideas\n- one\n\n## Links\nsee x\n\n# S\nintro\n\n## ideas\n- two\n\n## New\ntext\n"
+        );
+        assert_eq!(
+            merge_note_bodies(target, source, MergeStrategy::Interleave),
+            "---\ntitle: T\n---\n# T\n\n## Ideas\n- one\n- two\n\n## Links\nsee x\n\nintro\n\n## New\ntext\n"
+        );
+        assert_eq!(
+            merge_note_bodies(target, "\n", MergeStrategy::Interleave),
+            target
+        );
+    }
+
+    #[test]
+    fn test_toc_insert_then_update() {
+        let content = "---\ntitle: T\n---\n# T\n\n## A\n### B\n";
+        let toc = build_toc(
+            &indexer::extract_headings(content),
+            2,
+            3,
+            TocLinkStyle::Wikilink,
+        );
+        assert_eq!(toc, "- [[#A]]\n  - [[#B]]");
+        let inserted = upsert_toc(content, &toc);
+        assert_eq!(
+            inserted,
+            "---\ntitle: T\n---\n# T\n\n<!-- toc -->\n- [[#A]]\n  - [[#B]]\n<!-- /toc -->\n\n## A\n### B\n"
+        );
+        let updated = upsert_toc(&inserted, "- [[#A]]");
+        assert!(updated.contains("<!-- toc -->\n- [[#A]]\n<!-- /toc -->\n\n## A"));
+    }
+
+    #[test]
+    fn test_toc_anchor_links_use_unique_slugs() {
+        let content = "## Setup\n### Notes\n## Usage\n### Notes\n## [x] Done";
+        let toc = build_toc(
+            &indexer::extract_headings(content),
+            2,
+            3,
+            TocLinkStyle::Anchor,
+        );
+        assert_eq!(
+            toc,
+            "- [Setup](#setup)\n  - [Notes](#notes)\n- [Usage](#usage)\n  - [Notes](#notes-1)\n- [\\[x\\] Done](#x-done)"
+        );
+    }
+
+    #[test]
+    fn test_append_creates_missing_heading() {
+        let result = append_text("# Log\n", "hello", Some("Inbox"));
+        assert_eq!(result, "# Log\n\n## Inbox\n\nhello\n");
+    }
+
+    #[test]
+    fn test_footnotes_add_and_renumber() {
+        let content = "Intro[^2] text.\n\n[^2]: Second\n    more\n[^a]: Named\n\nLater[^1].\n\n[^1]: First\n";
+        let renumbered = renumber_footnotes(content);
+        assert_eq!(
+            renumbered,
+            "Intro[^1] text.\n\nLater[^2].\n\n[^1]: Second\n    more\n[^2]: First\n[^a]: Named\n"
+        );
+
+        let at = renumbered.find(" text").unwrap();
+        let (added, label) = add_footnote(&renumbered, at, "New").unwrap();
+        assert_eq!(label, "2");
+        assert!(added.starts_with("Intro[^1][^2] text.\n\nLater[^3]."));
+        assert!(added.ends_with("[^1]: Second\n    more\n[^2]: New\n[^3]: First\n[^a]: Named\n"));
+
+        let footnotes = list_footnotes(&added);
+        assert_eq!(footnotes[0].definition.as_deref(), Some("Second\nmore"));
+        assert_eq!(footnotes[1].references, vec![1]);
+    }
+
+    #[test]
+    fn test_shift_headings_clamps_and_respects_range() {
+        let content = "# A\n```\n# code\n```\n## B\n###### C\n# D";
+        let (shifted, changed) = shift_headings(content, Some((1, 6)), 1);
+        assert_eq!(shifted, "## A\n```\n# code\n```\n### B\n###### C\n# D");
+        assert_eq!(changed, 2);
+        let (shifted, _) = shift_headings(content, None, -2);
+        assert_eq!(shifted, "# A\n```\n# code\n```\n# B\n#### C\n# D");
+    }
+
+    #[test]
+    fn test_rename_tag_in_body_and_frontmatter() {
+        let content = "---\ntitle: Plan\ntags: [project, \"#project/alpha\", projects]\naliases:\n  - project\n---\n# Plan #project\n\nSee #Project/beta, #projects and `#project`.\n```\n#project\n```\n";
+        let renamed = rename_tag(content, "project", "work").unwrap();
+        assert_eq!(
+            renamed,
+            "---\ntitle: Plan\ntags: [work, \"#work/alpha\", projects]\naliases:\n  - project\n---\n# Plan #work\n\nSee #work/beta, #projects and `#project`.\n```\n#project\n```\n"
+        );
+
+        let list = "---\ntags:\n  - project\n  - '#project/x'\n---\nBody\n";
+        assert_eq!(
+            rename_tag(list, "project", "work").unwrap(),
+            "---\ntags:\n  - work\n  - '#work/x'\n---\nBody\n"
+        );
+        assert_eq!(rename_tag("No tags here #other\n", "project", "work"), None);
+    }
+
+    #[test]
+    fn test_set_and_remove_frontmatter_property() {
+        let content = "---\ntitle: Plan # working title\ntags:\n  - a\n  - b\n# review\ndue: 2024-06-01\n---\nBody\n";
+        let list = serde_yaml::Value::Sequence(vec!["x".into(), "y".into()]);
+
+        let updated = set_frontmatter_property(content, "tags", &"solo".into()).unwrap();
+        assert_eq!(
+            updated,
+            "---\ntitle: Plan # working title\ntags: solo\n# review\ndue: 2024-06-01\n---\nBody\n"
+        );
+        let updated = set_frontmatter_property(&updated, "owners", &list).unwrap();
+        assert!(updated.ends_with("due: 2024-06-01\nowners:\n- x\n- y\n---\nBody\n"));
+
+        let removed = remove_frontmatter_property(&updated, "due")
+            .unwrap()
+            .unwrap();
+        assert!(removed.contains("# review\nowners:"));
+        assert_eq!(remove_frontmatter_property(&removed, "due").unwrap(), None);
+
+        assert_eq!(
+            set_frontmatter_property("Body\n", "done", &true.into()).unwrap(),
+            "---\ndone: true\n---\nBody\n"
+        );
+        assert!(set_frontmatter_property("---\n: [\n---\n", "a", &1.into()).is_err());
+    }
+
+    #[test]
+    fn test_toggle_task_with_done_date() {
+        let content = "# Todo\n- [ ] Ship it ^ship\r\n  * [x] Write ✅ 2024-05-01 later\nplain\n";
+        let (checked, done) = toggle_task(content, 2, Some("2024-06-01")).unwrap();
+        assert!(done);
+        assert_eq!(
+            checked,
+            "# Todo\n- [x] Ship it ✅ 2024-06-01 ^ship\r\n  * [x] Write ✅ 2024-05-01 later\nplain\n"
+        );
+        let (unchecked, done) = toggle_task(&checked, 3, Some("2024-06-01")).unwrap();
+        assert!(!done);
+        assert!(unchecked.ends_with("  * [ ] Write later\nplain\n"));
+        assert_eq!(toggle_task(content, 4, None), None);
+        assert_eq!(toggle_task(content, 9, None), None);
+    }
+}
@@ -0,0 +1,528 @@
+//! Note vectors. Notes are split into chunks of a few paragraphs, and each
+//! chunk gets two unit vectors:
+//!
+//! - A sentence embedding from a local language model (all-MiniLM-L6-v2, run
+//!   with candle), for `semantic_search`. It captures meaning, so "car" finds
+//!   notes about automobiles. The model is downloaded once into the app
+//!   config folder, and chunks are embedded the first time a search needs
+//!   them rather than while indexing, which it would slow down a lot.
+//! - A lexical vector from feature hashing: words, adjacent word pairs and
+//!   character trigrams hashed into `DIMENSIONS` signed buckets. It is cheap
+//!   enough to compute while indexing; related notes compare content with it.
+
+use anyhow::{anyhow, Context, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokenizers::{Encoding, PaddingParams, Tokenizer, TruncationParams};
+
+use crate::database::Database;
+use crate::vault::Vault;
+
+/// Length of every lexical vector
+pub const DIMENSIONS: usize = 256;
+/// Sentence model semantic search uses, from Hugging Face
+const MODEL_REPO: &str = "sentence-transformers/all-MiniLM-L6-v2";
+/// Files the sentence model is loaded from, all in `model_dir`
+const MODEL_FILES: &[&str] = &["config.json", "tokenizer.json", "model.safetensors"];
+/// Longer chunks are cut to this many tokens before embedding
+const MAX_TOKENS: usize = 256;
+/// Chunks embedded per model call
+const EMBED_BATCH: usize = 16;
+/// A chunk closes at the first paragraph break after this many words
+const CHUNK_WORDS: usize = 150;
+/// Characters of a chunk kept to show with a match
+const EXCERPT_CHARS: usize = 200;
+/// Weights of the three kinds of feature
+const WORD_WEIGHT: f32 = 1.0;
+const PAIR_WEIGHT: f32 = 0.5;
+const TRIGRAM_WEIGHT: f32 = 0.3;
+
+/// Words too common to say anything about a note
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "could", "did", "do", "does", "for", "from", "had", "has", "have", "he",
+    "her", "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "me", "more", "my",
+    "no", "not", "of", "on", "or", "our", "out", "she", "so", "some", "than", "that", "the",
+    "their", "them", "then", "there", "these", "they", "this", "to", "up", "us", "was", "we",
+    "were", "what", "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+/// One stretch of a note, with its lexical vector
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkEmbedding {
+    /// Line number (1-based) where the chunk starts
+    pub line: usize,
+    pub excerpt: String,
+    /// The whole chunk, for the sentence model
+    pub text: String,
+    pub vector: Vec<f32>,
+}
+
+/// A chunk waiting for its sentence embedding
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingChunk {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+}
+
+/// A chunk's stored sentence embedding
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentenceChunk {
+    pub path: String,
+    pub line: usize,
+    pub excerpt: String,
+    pub vector: Vec<f32>,
+}
+
+/// A note close in meaning to a `semantic_search` query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticMatch {
+    pub path: String,
+    pub title: String,
+    /// Cosine similarity of the closest chunk (-1 to 1, higher is closer)
+    pub score: f32,
+    /// Line number (1-based) of the closest chunk
+    pub line: usize,
+    pub excerpt: String,
+}
+
+/// Split a note into chunks and embed each one. Frontmatter is left out;
+/// chunks without any words are dropped.
+pub fn embed_note(content: &str) -> Vec<ChunkEmbedding> {
+    let (frontmatter, body) = crate::editing::split_frontmatter(content);
+    let first_line = frontmatter.matches('\n').count() + 1;
+
+    let mut chunks = Vec::new();
+    let mut start = first_line;
+    let mut text = String::new();
+    let mut words = 0;
+    for (i, line) in body.lines().enumerate() {
+        if line.trim().is_empty() && words >= CHUNK_WORDS {
+            push_chunk(&mut chunks, start, &text);
+            text.clear();
+            words = 0;
+        }
+        if text.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            start = first_line + i;
+        }
+        words += line.split_whitespace().count();
+        text.push_str(line);
+        text.push('\n');
+    }
+    push_chunk(&mut chunks, start, &text);
+    chunks
+}
+
+/// Embed a piece of text as a lexical unit vector (all zeros if it has no
+/// words)
+pub fn embed(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0; DIMENSIONS];
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .collect();
+    for word in &words {
+        add_feature(&mut vector, word, WORD_WEIGHT);
+        let padded: Vec<char> = format!("<{}>", word).chars().collect();
+        for trigram in padded.windows(3) {
+            add_feature(
+                &mut vector,
+                &trigram.iter().collect::<String>(),
+                TRIGRAM_WEIGHT,
+            );
+        }
+    }
+    for pair in words.windows(2) {
+        add_feature(
+            &mut vector,
+            &format!("{} {}", pair[0], pair[1]),
+            PAIR_WEIGHT,
+        );
+    }
+    normalize(&mut vector);
+    vector
+}
+
+/// Dot product; the cosine similarity of two unit vectors
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Sentence embeddings from a BERT-style model in `model_dir`
+pub struct SentenceModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+}
+
+impl SentenceModel {
+    pub fn load(dir: &Path) -> Result<Self> {
+        if !model_installed(dir) {
+            anyhow::bail!("The embedding model hasn't been downloaded");
+        }
+        let config = std::fs::read_to_string(dir.join("config.json"))
+            .context("Failed to read the model config")?;
+        let config: Config =
+            serde_json::from_str(&config).context("Failed to parse the model config")?;
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| anyhow!("Failed to load the tokenizer: {}", e))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("Failed to configure the tokenizer: {}", e))?;
+        let weights = std::fs::read(dir.join("model.safetensors"))
+            .context("Failed to read the model weights")?;
+        let vb = VarBuilder::from_buffered_safetensors(weights, DType::F32, &Device::Cpu)?;
+        let model = BertModel::load(vb, &config).context("Failed to load the embedding model")?;
+        Ok(SentenceModel { model, tokenizer })
+    }
+
+    /// One unit vector per text: the mean of its token embeddings
+    pub fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("Failed to tokenize: {}", e))?;
+        let batch = |field: fn(&Encoding) -> &[u32]| -> Result<Tensor> {
+            let rows = encodings
+                .iter()
+                .map(|encoding| Tensor::new(field(encoding), &Device::Cpu))
+                .collect::<candle_core::Result<Vec<_>>>()?;
+            Ok(Tensor::stack(&rows, 0)?)
+        };
+        let ids = batch(Encoding::get_ids)?;
+        let type_ids = batch(Encoding::get_type_ids)?;
+        let mask = batch(Encoding::get_attention_mask)?;
+        let tokens = self.model.forward(&ids, &type_ids, Some(&mask))?;
+
+        // Average over the real tokens, leaving out padding
+        let mask = mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let sums = tokens.broadcast_mul(&mask)?.sum(1)?;
+        let means = sums.broadcast_div(&mask.sum(1)?)?;
+        let mut vectors: Vec<Vec<f32>> = means.to_vec2()?;
+        vectors.iter_mut().for_each(|v| normalize(v));
+        Ok(vectors)
+    }
+}
+
+/// Where the sentence model is kept: shared by every vault
+pub fn model_dir() -> Result<PathBuf> {
+    let name = MODEL_REPO.rsplit('/').next().unwrap_or(MODEL_REPO);
+    Ok(Vault::config_dir()?.join("models").join(name))
+}
+
+pub fn model_installed(dir: &Path) -> bool {
+    MODEL_FILES.iter().all(|file| dir.join(file).is_file())
+}
+
+/// Fetch the sentence model (about 90 MB) from Hugging Face into `dir`. Each
+/// file is renamed into place once complete, so an interrupted download
+/// never looks installed.
+pub async fn download_model(dir: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .context("Failed to create the model folder")?;
+    let client = reqwest::Client::new();
+    for file in MODEL_FILES {
+        let url = format!(
+            "https://huggingface.co/{}/resolve/main/{}",
+            MODEL_REPO, file
+        );
+        let data = client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to download {}", file))?
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to download {}", file))?;
+        let partial = dir.join(format!("{}.part", file));
+        tokio::fs::write(&partial, &data)
+            .await
+            .with_context(|| format!("Failed to save {}", file))?;
+        tokio::fs::rename(&partial, dir.join(file))
+            .await
+            .with_context(|| format!("Failed to save {}", file))?;
+    }
+    Ok(())
+}
+
+/// Compute the sentence embeddings chunks are missing: new and changed notes
+/// since the last pass. Returns how many chunks were embedded.
+pub fn embed_pending(db: &Database, model: &SentenceModel) -> Result<usize> {
+    let mut embedded = 0;
+    loop {
+        let pending = db.get_unembedded_chunks(EMBED_BATCH)?;
+        if pending.is_empty() {
+            return Ok(embedded);
+        }
+        let texts: Vec<&str> = pending.iter().map(|chunk| chunk.text.as_str()).collect();
+        let vectors = model.embed(&texts)?;
+        embedded += pending.len();
+        let stored: Vec<_> = pending.into_iter().zip(vectors).collect();
+        db.store_sentence_vectors(&stored)?;
+    }
+}
+
+/// The notes `keep` accepts closest in meaning to `query`, best first.
+/// Chunks without a sentence embedding are embedded first.
+pub fn semantic_search(
+    db: &Database,
+    model: &SentenceModel,
+    query: &str,
+    k: usize,
+    keep: impl Fn(&str) -> bool,
+) -> Result<Vec<SemanticMatch>> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    embed_pending(db, model)?;
+    let query = model.embed(&[query])?.remove(0);
+
+    let mut best: HashMap<String, (f32, usize, String)> = HashMap::new();
+    for chunk in db.get_sentence_vectors()? {
+        if !keep(&chunk.path) {
+            continue;
+        }
+        let score = similarity(&query, &chunk.vector);
+        match best.get(&chunk.path) {
+            Some((top, _, _)) if *top >= score => {}
+            _ => {
+                best.insert(chunk.path, (score, chunk.line, chunk.excerpt));
+            }
+        }
+    }
+
+    let titles: HashMap<String, String> = db
+        .get_all_notes()?
+        .into_iter()
+        .map(|note| (note.path, note.title))
+        .collect();
+    let mut matches: Vec<SemanticMatch> = best
+        .into_iter()
+        .map(|(path, (score, line, excerpt))| SemanticMatch {
+            title: titles.get(&path).cloned().unwrap_or_default(),
+            path,
+            score,
+            line,
+            excerpt,
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    matches.truncate(k);
+    Ok(matches)
+}
+
+/// One vector per note: the normalized mean of its chunks
+pub fn note_vectors(db: &Database) -> Result<HashMap<String, Vec<f32>>> {
+    let mut vectors: HashMap<String, Vec<f32>> = HashMap::new();
+    for (path, vector) in db.get_embeddings()? {
+        let sum = vectors.entry(path).or_insert_with(|| vec![0.0; DIMENSIONS]);
+        sum.iter_mut().zip(&vector).for_each(|(s, x)| *s += x);
+    }
+    vectors.values_mut().for_each(|v| normalize(v));
+    Ok(vectors)
+}
+
+/// Vector bytes as stored in the cache (little-endian `f32`s)
+pub fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// FNV-1a, so vectors and other cached hashes stay the same across builds
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+fn push_chunk(chunks: &mut Vec<ChunkEmbedding>, line: usize, text: &str) {
+    let vector = embed(text);
+    if vector.iter().all(|x| *x == 0.0) {
+        return;
+    }
+    let excerpt = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    chunks.push(ChunkEmbedding {
+        line,
+        excerpt: excerpt.chars().take(EXCERPT_CHARS).collect(),
+        text: text.to_string(),
+        vector,
+    });
+}
+
+/// Add `weight` to the feature's bucket, with a sign from another bit of the
+/// hash so unrelated features sharing a bucket tend to cancel out
+fn add_feature(vector: &mut [f32], feature: &str, weight: f32) {
+    let hash = fnv1a(feature.as_bytes());
+    let bucket = (hash % DIMENSIONS as u64) as usize;
+    let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    vector[bucket] += sign * weight;
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+    use candle_nn::VarMap;
+    use tokenizers::models::wordpiece::WordPiece;
+    use tokenizers::normalizers::BertNormalizer;
+    use tokenizers::pre_tokenizers::bert::BertPreTokenizer;
+    use tokenizers::processors::bert::BertProcessing;
+
+    /// A tiny randomly initialized BERT in the layout `SentenceModel::load`
+    /// reads, knowing only `words`
+    fn write_test_model(dir: &Path, words: &[&str]) {
+        let vocab: Vec<&str> = ["[PAD]", "[UNK]", "[CLS]", "[SEP]"]
+            .iter()
+            .chain(words)
+            .copied()
+            .collect();
+        let vocab_file = dir.join("vocab.txt");
+        std::fs::write(&vocab_file, vocab.join("\n")).unwrap();
+        let config = serde_json::json!({
+            "vocab_size": vocab.len(),
+            "hidden_size": 8,
+            "num_hidden_layers": 1,
+            "num_attention_heads": 2,
+            "intermediate_size": 16,
+            "hidden_act": "gelu",
+            "hidden_dropout_prob": 0.0,
+            "max_position_embeddings": 512,
+            "type_vocab_size": 2,
+            "initializer_range": 0.02,
+            "layer_norm_eps": 1e-12,
+            "pad_token_id": 0
+        });
+        std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+
+        let wordpiece = WordPiece::from_file(&vocab_file.to_string_lossy())
+            .unk_token("[UNK]".to_string())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(wordpiece);
+        tokenizer
+            .with_normalizer(Some(BertNormalizer::default()))
+            .with_pre_tokenizer(Some(BertPreTokenizer))
+            .with_post_processor(Some(BertProcessing::new(
+                ("[SEP]".to_string(), 3),
+                ("[CLS]".to_string(), 2),
+            )));
+        tokenizer.save(dir.join("tokenizer.json"), false).unwrap();
+
+        let config: Config = serde_json::from_value(config).unwrap();
+        let weights = VarMap::new();
+        BertModel::load(
+            VarBuilder::from_varmap(&weights, DType::F32, &Device::Cpu),
+            &config,
+        )
+        .unwrap();
+        weights.save(dir.join("model.safetensors")).unwrap();
+    }
+
+    #[test]
+    fn test_semantic_search_embeds_chunks_once_and_ranks_by_meaning() {
+        let orchard = "apples grow on trees ".repeat(40);
+        let vault = TestVault::with_notes(&[
+            (
+                "Orchard.md",
+                &format!("{}\n\nthe orchard is quiet\n", orchard),
+            ),
+            ("Harbor.md", "boats sail in the harbor\n"),
+            ("Empty.md", "---\ntitle: Empty\n---\n"),
+        ]);
+        let db = vault.index();
+        let models = TestVault::new();
+        assert!(!model_installed(models.path()));
+        write_test_model(
+            models.path(),
+            &[
+                "apples", "grow", "on", "trees", "the", "orchard", "is", "quiet", "loud", "boats",
+                "sail", "in", "harbor",
+            ],
+        );
+        assert!(model_installed(models.path()));
+        let model = SentenceModel::load(models.path()).unwrap();
+
+        assert_eq!(embed_pending(&db, &model).unwrap(), 3);
+        assert_eq!(embed_pending(&db, &model).unwrap(), 0);
+
+        let found = semantic_search(&db, &model, "boats sail in the harbor", 5, |_| true).unwrap();
+        assert_eq!(found[0].path, "Harbor.md");
+        assert_eq!(found[0].title, "Harbor");
+        assert!(found[0].score > 0.999);
+        assert!(found.iter().all(|m| m.path != "Empty.md"));
+        let found = semantic_search(&db, &model, "the orchard is quiet", 1, |_| true).unwrap();
+        assert_eq!(
+            (found.len(), found[0].path.as_str(), found[0].line),
+            (1, "Orchard.md", 3)
+        );
+        assert!(
+            semantic_search(&db, &model, "harbor", 5, |p| p != "Harbor.md")
+                .unwrap()
+                .iter()
+                .all(|m| m.path != "Harbor.md")
+        );
+        assert!(semantic_search(&db, &model, "  ", 5, |_| true)
+            .unwrap()
+            .is_empty());
+
+        // Only the chunk that changed is embedded again
+        vault.write(
+            "Orchard.md",
+            &format!("{}\n\nthe orchard is loud\n", orchard),
+        );
+        db.reindex_vault(vault.path()).unwrap();
+        assert_eq!(embed_pending(&db, &model).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_lexical_vectors_skip_frontmatter() {
+        let chunks = embed_note("---\na: 1\n---\n\nFirst words\n");
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(
+            (
+                chunks[0].line,
+                chunks[0].excerpt.as_str(),
+                chunks[0].text.as_str()
+            ),
+            (5, "First words", "First words\n")
+        );
+        assert!((similarity(&chunks[0].vector, &chunks[0].vector) - 1.0).abs() < 1e-5);
+        assert!(similarity(&embed("reindexing notes"), &embed("the index")) > 0.0);
+    }
+}
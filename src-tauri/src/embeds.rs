@@ -0,0 +1,206 @@
+//! Transclusions: resolve the `![[...]]` embeds of a note in one pass so the
+//! editor can render them without a round trip per embed. Notes come back
+//! as markdown (the whole body, a heading's section or a block's line);
+//! images and other files come back as vault paths for `read_binary`.
+//! Embeds inside the embedded content are left for the editor to resolve.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::database::Database;
+use crate::editing;
+use crate::excalidraw;
+use crate::indexer;
+use crate::vault::{Vault, VaultEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedKind {
+    /// A note, or a section or block of one; `content` holds its markdown
+    Note,
+    /// A drawing or any other file; render it from `path`
+    File,
+    /// The target (or its heading or block) doesn't exist
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedEmbed {
+    /// Byte span of the `![[...]]` in the embedding note
+    pub start: usize,
+    pub end: usize,
+    pub target: String,
+    /// `Heading` or `^block-id`
+    pub subpath: Option<String>,
+    pub kind: EmbedKind,
+    /// Vault path the embed resolved to
+    pub path: Option<String>,
+    pub content: Option<String>,
+}
+
+/// Resolve every embed in `content`, the text of the note at `note_path`
+pub fn resolve(
+    vault_path: &Path,
+    db: &Database,
+    note_path: &str,
+    content: &str,
+) -> Result<Vec<ResolvedEmbed>> {
+    // Listed on the first file embed only
+    let mut entries: Option<Vec<VaultEntry>> = None;
+    let mut embeds = Vec::new();
+    for link in indexer::parse_wikilinks(content)
+        .into_iter()
+        .filter(|l| l.embed)
+    {
+        let mut embed = ResolvedEmbed {
+            start: link.start,
+            end: link.end,
+            target: link.target.clone(),
+            subpath: link.subpath.clone(),
+            kind: EmbedKind::Missing,
+            path: None,
+            content: None,
+        };
+
+        if is_file_target(&link.target) {
+            if entries.is_none() {
+                entries = Some(Vault::list_entries(vault_path)?);
+            }
+            let entries = entries.as_deref().unwrap_or_default();
+            embed.path = find_file(entries, &link.target).or_else(|| {
+                // `![[Sketch.excalidraw]]` shows `Sketch.excalidraw.md`
+                link.target
+                    .ends_with(".excalidraw")
+                    .then(|| find_file(entries, &format!("{}.md", link.target)))
+                    .flatten()
+            });
+            if embed.path.is_some() {
+                embed.kind = EmbedKind::File;
+            }
+        } else {
+            // `![[#Heading]]` embeds part of the same note
+            embed.path = if link.target.is_empty() {
+                Some(note_path.to_string())
+            } else {
+                db.resolve_link_target(&link.target)?
+            };
+            embed.content = match &embed.path {
+                // Drawings are shown as pictures, not as their markdown
+                Some(path) if excalidraw::is_drawing(path) => {
+                    embed.kind = EmbedKind::File;
+                    None
+                }
+                Some(path) if path == note_path => excerpt(content, link.subpath.as_deref()),
+                Some(path) => Vault::read_file(vault_path, path)
+                    .ok()
+                    .and_then(|text| excerpt(&text, link.subpath.as_deref())),
+                None => None,
+            };
+            if embed.content.is_some() {
+                embed.kind = EmbedKind::Note;
+            }
+        }
+        embeds.push(embed);
+    }
+    Ok(embeds)
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// Targets with an extension other than `.md` name a file, not a note
+fn is_file_target(target: &str) -> bool {
+    Path::new(target)
+        .extension()
+        .is_some_and(|ext| !ext.eq_ignore_ascii_case("md"))
+}
+
+/// The file a target names: its exact vault path, or else the file with
+/// that name (and trailing folders) closest to the vault root
+fn find_file(entries: &[VaultEntry], target: &str) -> Option<String> {
+    let target = target.trim_start_matches('/').to_lowercase();
+    let suffix = format!("/{}", target);
+    entries
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .filter_map(|entry| {
+            let path = entry.path.replace('\\', "/").to_lowercase();
+            if path == target {
+                Some((0, entry))
+            } else if path.ends_with(&suffix) {
+                Some((entry.path.len(), entry))
+            } else {
+                None
+            }
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, entry)| entry.path.clone())
+}
+
+/// The part of a note an embed shows: the body without frontmatter, the
+/// section under a heading, or the line carrying a block id
+fn excerpt(text: &str, subpath: Option<&str>) -> Option<String> {
+    let Some(subpath) = subpath else {
+        return Some(Vault::strip_frontmatter(text).trim().to_string());
+    };
+    if let Some(id) = subpath.strip_prefix('^') {
+        let block = indexer::extract_block_ids(text)
+            .into_iter()
+            .find(|b| b.id == id)?;
+        let line = text.lines().nth(block.line.checked_sub(1)?)?.trim_end();
+        let line = line.strip_suffix(&format!("^{}", id)).unwrap_or(line);
+        return Some(line.trim().to_string());
+    }
+    // `Heading#Subheading`: the innermost heading names the section
+    let heading = subpath.rsplit('#').next().unwrap_or(subpath);
+    let (start, end) = editing::section_range(text, heading)?;
+    let lines: Vec<&str> = text.lines().collect();
+    Some(lines[start..end].join("\n").trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_resolve_note_section_block_and_file_embeds() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Home.md",
+                "![[Plan]]\n![[Plan#Goals]]\n![[Plan^key]]\n![[shot.png]]\n![[Nowhere]]\n![[#Local]]\n\n## Local\nHere\n",
+            ),
+            (
+                "Projects/Plan.md",
+                "---\ntags: [work]\n---\n# Plan\nIntro\n\n## Goals\nShip it ^key\n\n## Later\nMaybe\n",
+            ),
+            ("assets/shot.png", "png"),
+        ]);
+        let db = vault.index();
+        let content = Vault::read_file(vault.path(), "Home.md").unwrap();
+
+        let embeds = resolve(vault.path(), &db, "Home.md", &content).unwrap();
+        let kinds: Vec<EmbedKind> = embeds.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EmbedKind::Note,
+                EmbedKind::Note,
+                EmbedKind::Note,
+                EmbedKind::File,
+                EmbedKind::Missing,
+                EmbedKind::Note,
+            ]
+        );
+        assert!(embeds[0]
+            .content
+            .as_deref()
+            .unwrap()
+            .starts_with("# Plan\nIntro"));
+        assert_eq!(embeds[1].content.as_deref(), Some("## Goals\nShip it ^key"));
+        assert_eq!(embeds[2].content.as_deref(), Some("Ship it"));
+        assert_eq!(embeds[3].path.as_deref(), Some("assets/shot.png"));
+        assert_eq!(embeds[5].content.as_deref(), Some("## Local\nHere"));
+        assert_eq!(&content[embeds[0].start..embeds[0].end], "![[Plan]]");
+    }
+}
@@ -0,0 +1,273 @@
+//! The error commands return to the frontend. It serializes as
+//! `{ code, message, path?, schema?, retryable }` so the UI can branch on
+//! `code` (offer to open a vault, retry a busy write, rebuild a cache)
+//! instead of matching message text.
+
+use serde::{Serialize, Serializer};
+use std::io;
+
+use crate::database::{self, NewerSchemaError, SchemaInfo};
+
+#[derive(Debug)]
+pub enum SynapseError {
+    VaultNotOpen,
+    /// The vault is open but locked behind its passphrase
+    VaultLocked,
+    NotFound {
+        message: String,
+        path: Option<String>,
+    },
+    PermissionDenied {
+        message: String,
+        path: Option<String>,
+    },
+    AlreadyExists {
+        message: String,
+        path: Option<String>,
+    },
+    /// The request itself was wrong: a bad name, position or passphrase
+    InvalidInput {
+        message: String,
+    },
+    /// Another connection held the cache for longer than the busy timeout;
+    /// trying again later usually works
+    Busy {
+        message: String,
+    },
+    /// The cache was written by a newer version of Synapse
+    NewerSchema(SchemaInfo),
+    /// The cache file is damaged and needs rebuilding
+    CacheCorrupt {
+        message: String,
+    },
+    Io {
+        message: String,
+        path: Option<String>,
+    },
+    Internal {
+        message: String,
+    },
+}
+
+impl SynapseError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        SynapseError::NotFound {
+            message: message.into(),
+            path: None,
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        SynapseError::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    /// Attach the vault path the error is about, where the variant has one
+    pub fn with_path(mut self, file: impl Into<String>) -> Self {
+        match &mut self {
+            SynapseError::NotFound { path, .. }
+            | SynapseError::PermissionDenied { path, .. }
+            | SynapseError::AlreadyExists { path, .. }
+            | SynapseError::Io { path, .. } => *path = Some(file.into()),
+            _ => {}
+        }
+        self
+    }
+
+    /// Stable identifier the frontend matches on
+    pub fn code(&self) -> &'static str {
+        match self {
+            SynapseError::VaultNotOpen => "vault_not_open",
+            SynapseError::VaultLocked => "vault_locked",
+            SynapseError::NotFound { .. } => "not_found",
+            SynapseError::PermissionDenied { .. } => "permission_denied",
+            SynapseError::AlreadyExists { .. } => "already_exists",
+            SynapseError::InvalidInput { .. } => "invalid_input",
+            SynapseError::Busy { .. } => "busy",
+            SynapseError::NewerSchema(_) => "newer_schema",
+            SynapseError::CacheCorrupt { .. } => "cache_corrupt",
+            SynapseError::Io { .. } => "io",
+            SynapseError::Internal { .. } => "internal",
+        }
+    }
+
+    /// Whether the same call may succeed if made again unchanged
+    pub fn retryable(&self) -> bool {
+        matches!(self, SynapseError::Busy { .. })
+    }
+
+    fn path(&self) -> Option<&str> {
+        match self {
+            SynapseError::NotFound { path, .. }
+            | SynapseError::PermissionDenied { path, .. }
+            | SynapseError::AlreadyExists { path, .. }
+            | SynapseError::Io { path, .. } => path.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SynapseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SynapseError::VaultNotOpen => write!(f, "No vault open"),
+            SynapseError::VaultLocked => write!(f, "Vault is locked"),
+            SynapseError::NewerSchema(schema) => {
+                write!(f, "{}", NewerSchemaError(schema.clone()))
+            }
+            SynapseError::NotFound { message, .. }
+            | SynapseError::PermissionDenied { message, .. }
+            | SynapseError::AlreadyExists { message, .. }
+            | SynapseError::InvalidInput { message }
+            | SynapseError::Busy { message }
+            | SynapseError::CacheCorrupt { message }
+            | SynapseError::Io { message, .. }
+            | SynapseError::Internal { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SynapseError {}
+
+#[derive(Serialize)]
+struct Wire<'a> {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema: Option<&'a SchemaInfo>,
+    retryable: bool,
+}
+
+impl Serialize for SynapseError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Wire {
+            code: self.code(),
+            message: self.to_string(),
+            path: self.path(),
+            schema: match self {
+                SynapseError::NewerSchema(schema) => Some(schema),
+                _ => None,
+            },
+            retryable: self.retryable(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Classify by the first cause we recognise; the message stays the outermost
+/// context, as the frontend showed before
+impl From<anyhow::Error> for SynapseError {
+    fn from(error: anyhow::Error) -> Self {
+        let message = error.to_string();
+        if let Some(newer) = error
+            .chain()
+            .find_map(|c| c.downcast_ref::<NewerSchemaError>())
+        {
+            return SynapseError::NewerSchema(newer.0.clone());
+        }
+        if database::is_busy(&error) {
+            return SynapseError::Busy { message };
+        }
+        if database::is_corruption(&error) {
+            return SynapseError::CacheCorrupt { message };
+        }
+        if let Some(io) = error.chain().find_map(|c| c.downcast_ref::<io::Error>()) {
+            return from_io_kind(io.kind(), message);
+        }
+        SynapseError::Internal { message }
+    }
+}
+
+impl From<io::Error> for SynapseError {
+    fn from(error: io::Error) -> Self {
+        from_io_kind(error.kind(), error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for SynapseError {
+    fn from(error: serde_json::Error) -> Self {
+        SynapseError::invalid(error.to_string())
+    }
+}
+
+impl From<tauri::Error> for SynapseError {
+    fn from(error: tauri::Error) -> Self {
+        SynapseError::Internal {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Validation helpers across the crate report problems as plain strings
+impl From<String> for SynapseError {
+    fn from(message: String) -> Self {
+        SynapseError::InvalidInput { message }
+    }
+}
+
+impl From<&str> for SynapseError {
+    fn from(message: &str) -> Self {
+        SynapseError::invalid(message)
+    }
+}
+
+fn from_io_kind(kind: io::ErrorKind, message: String) -> SynapseError {
+    match kind {
+        io::ErrorKind::NotFound => SynapseError::NotFound {
+            message,
+            path: None,
+        },
+        io::ErrorKind::PermissionDenied => SynapseError::PermissionDenied {
+            message,
+            path: None,
+        },
+        io::ErrorKind::AlreadyExists => SynapseError::AlreadyExists {
+            message,
+            path: None,
+        },
+        _ => SynapseError::Io {
+            message,
+            path: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_codes_and_wire_format() {
+        let missing = std::fs::read("/nonexistent/synapse/note.md")
+            .context("Failed to read file: note.md")
+            .unwrap_err();
+        let error = SynapseError::from(missing).with_path("note.md");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "not_found",
+                "message": "Failed to read file: note.md",
+                "path": "note.md",
+                "retryable": false,
+            })
+        );
+
+        let busy = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            None,
+        );
+        let error = SynapseError::from(anyhow::Error::from(busy).context("Failed to save"));
+        assert_eq!(error.code(), "busy");
+        assert!(error.retryable());
+
+        assert_eq!(SynapseError::VaultNotOpen.to_string(), "No vault open");
+        assert_eq!(
+            SynapseError::from("Wrong passphrase").code(),
+            "invalid_input"
+        );
+    }
+}
@@ -0,0 +1,647 @@
+use anyhow::{bail, Context, Result};
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::f64::consts::FRAC_PI_2;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use crate::vault::Vault;
+
+/// Space left around the drawn elements in rendered images
+const PADDING: f64 = 10.0;
+/// Largest image rendered, in pixels (256 MB of RGBA)
+const MAX_PIXELS: u64 = 64_000_000;
+
+/// Scene of a new, empty drawing
+const EMPTY_SCENE: &str = "{\"type\":\"excalidraw\",\"version\":2,\"source\":\"synapse\",\
+\"elements\":[],\"appState\":{\"gridSize\":null,\"viewBackgroundColor\":\"#ffffff\"},\"files\":{}}";
+
+/// A text element inside an Excalidraw drawing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrawingText {
+    pub element_id: String,
+    pub text: String,
+}
+
+/// Whether a vault path is an Excalidraw drawing (raw JSON or the
+/// Obsidian plugin's markdown wrapper)
+pub fn is_drawing(path: &str) -> bool {
+    path.ends_with(".excalidraw") || path.ends_with(".excalidraw.md")
+}
+
+/// The part of a drawing worth indexing as markdown: for `.excalidraw.md`
+/// everything above the drawing payload, for raw `.excalidraw` nothing
+pub fn markdown_part<'a>(path: &str, content: &'a str) -> &'a str {
+    if path.ends_with(".excalidraw") {
+        return "";
+    }
+    match drawing_section_start(content) {
+        Some(start) => &content[..start],
+        None => content,
+    }
+}
+
+/// The Excalidraw scene JSON of a drawing file
+pub fn scene(path: &str, content: &str) -> Result<serde_json::Value> {
+    if path.ends_with(".excalidraw") {
+        return serde_json::from_str(content).context("Invalid Excalidraw JSON");
+    }
+
+    let start = drawing_section_start(content).context("No drawing found in note")?;
+    let section = &content[start..];
+    let (json, compressed) = if let Some(block) = fenced_block(section, "compressed-json") {
+        (block, true)
+    } else if let Some(block) = fenced_block(section, "json") {
+        (block, false)
+    } else {
+        bail!("No drawing found in note");
+    };
+
+    let json = if compressed {
+        // The plugin wraps the LZ-string base64 payload across lines
+        let packed: String = json.split_whitespace().collect();
+        let utf16 =
+            lz_str::decompress_from_base64(&packed).context("Failed to decompress drawing")?;
+        String::from_utf16(&utf16).context("Drawing is not valid text")?
+    } else {
+        json.to_string()
+    };
+    serde_json::from_str(&json).context("Invalid Excalidraw JSON")
+}
+
+/// A drawing rendered to PNG
+pub struct DrawingPng {
+    pub data: Vec<u8>,
+    /// Width at scale 1, in CSS pixels
+    pub width: u32,
+}
+
+/// Create a `.excalidraw.md` drawing called `name` in `folder`, starting
+/// from the drawing at `template` (a vault path) if given. Template
+/// placeholders are filled in. Returns the new drawing's vault path.
+pub fn create(
+    vault_path: &Path,
+    folder: &str,
+    name: &str,
+    template: Option<&str>,
+) -> Result<String> {
+    let name = name.trim();
+    let name = name.strip_suffix(".md").unwrap_or(name);
+    let name = name.strip_suffix(".excalidraw").unwrap_or(name);
+    if name.is_empty() {
+        bail!("The drawing needs a name");
+    }
+    let relative_path = Vault::note_relative_path(folder, &format!("{}.excalidraw", name));
+    if vault_path.join(&relative_path).exists() {
+        bail!("A drawing with this name already exists");
+    }
+
+    let content = match template {
+        Some(template) => {
+            if !is_drawing(template) {
+                bail!("Templates must be Excalidraw drawings");
+            }
+            let content = Vault::read_file(vault_path, template)
+                .with_context(|| format!("Failed to read template: {}", template))?;
+            let content = if template.ends_with(".excalidraw") {
+                // Wrap a raw drawing's JSON in the markdown format
+                let scene = scene(template, &content)?;
+                markdown_drawing(&serde_json::to_string_pretty(&scene)?)
+            } else {
+                scene(template, &content)?;
+                content
+            };
+            Vault::render_template(&content, name)
+        }
+        None => markdown_drawing(EMPTY_SCENE),
+    };
+    Vault::write_file_atomic(vault_path, &relative_path, content)?;
+    Ok(relative_path)
+}
+
+/// Live (non-deleted) text elements of a scene
+pub fn text_elements(scene: &Value) -> Vec<DrawingText> {
+    live_elements(scene)
+        .filter(|el| el.get("type").and_then(|t| t.as_str()) == Some("text"))
+        .filter_map(|el| {
+            let id = el.get("id")?.as_str()?;
+            let text = el
+                .get("originalText")
+                .or_else(|| el.get("text"))?
+                .as_str()?
+                .trim();
+            (!text.is_empty()).then(|| DrawingText {
+                element_id: id.to_string(),
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Wikilink targets in a scene: those written in text elements and those
+/// set as an element's link
+pub fn links(scene: &Value) -> Vec<String> {
+    let element_links =
+        live_elements(scene).filter_map(|el| el.get("link")?.as_str().map(String::from));
+    let mut targets = Vec::new();
+    for text in text_elements(scene)
+        .into_iter()
+        .map(|t| t.text)
+        .chain(element_links)
+    {
+        for target in crate::indexer::extract_wikilinks(&text) {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+    targets
+}
+
+/// Rasterize a scene, `scale` times its size in the drawing. Shapes, lines,
+/// freehand strokes, text and embedded images are drawn; hachure fills are
+/// drawn as a light tint of their colour.
+pub fn to_png(scene: &Value, scale: f32) -> Result<DrawingPng> {
+    if !(scale > 0.0 && scale <= 8.0) {
+        bail!("Scale must be above 0 and at most 8");
+    }
+    let options = usvg::Options {
+        fontdb: fonts(),
+        ..Default::default()
+    };
+    let tree =
+        usvg::Tree::from_str(&to_svg(scene), &options).context("Failed to lay out drawing")?;
+    let size = tree.size().to_int_size();
+    let (width, height) = (
+        (size.width() as f32 * scale).ceil() as u32,
+        (size.height() as f32 * scale).ceil() as u32,
+    );
+    if width as u64 * height as u64 > MAX_PIXELS {
+        bail!("Drawing is too large to render at this scale");
+    }
+    let mut pixmap =
+        tiny_skia::Pixmap::new(width, height).context("Drawing is too large to render")?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    Ok(DrawingPng {
+        data: pixmap.encode_png().context("Failed to encode PNG")?,
+        width: size.width(),
+    })
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+fn markdown_drawing(scene_json: &str) -> String {
+    format!(
+        "---\nexcalidraw-plugin: parsed\ntags: [excalidraw]\n---\n\
+         ==⚠  Switch to EXCALIDRAW VIEW in the MORE OPTIONS menu of this document. ⚠==\n\n\n\
+         # Excalidraw Data\n## Text Elements\n%%\n## Drawing\n```json\n{}\n```\n%%\n",
+        scene_json
+    )
+}
+
+fn live_elements(scene: &Value) -> impl Iterator<Item = &Value> {
+    scene
+        .get("elements")
+        .and_then(|e| e.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|el| {
+            !el.get("isDeleted")
+                .and_then(|d| d.as_bool())
+                .unwrap_or(false)
+        })
+}
+
+/// System fonts, loaded once for every render
+fn fonts() -> Arc<usvg::fontdb::Database> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut db = usvg::fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
+
+/// The scene as an SVG document framing its elements
+fn to_svg(scene: &Value) -> String {
+    let elements: Vec<&Value> = live_elements(scene).collect();
+    let (mut left, mut top, mut right, mut bottom) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for el in &elements {
+        let (x0, y0, x1, y1) = bounds(el);
+        left = left.min(x0);
+        top = top.min(y0);
+        right = right.max(x1);
+        bottom = bottom.max(y1);
+    }
+    if elements.is_empty() {
+        (left, top, right, bottom) = (0.0, 0.0, 0.0, 0.0);
+    }
+    let (x, y) = (left - PADDING, top - PADDING);
+    let (width, height) = (right - left + 2.0 * PADDING, bottom - top + 2.0 * PADDING);
+    let background = scene
+        .pointer("/appState/viewBackgroundColor")
+        .and_then(Value::as_str)
+        .unwrap_or("#ffffff");
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+         width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n\
+         <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+        width.ceil(),
+        height.ceil(),
+        x,
+        y,
+        width.ceil(),
+        height.ceil(),
+        x,
+        y,
+        width.ceil(),
+        height.ceil(),
+        escape(background)
+    );
+    for el in elements {
+        element_svg(&mut svg, el, scene);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Left, top, right and bottom of an element, ignoring rotation
+fn bounds(el: &Value) -> (f64, f64, f64, f64) {
+    let (x, y) = (number(el, "x", 0.0), number(el, "y", 0.0));
+    let points = points(el);
+    if points.is_empty() {
+        return (
+            x,
+            y,
+            x + number(el, "width", 0.0),
+            y + number(el, "height", 0.0),
+        );
+    }
+    points
+        .iter()
+        .fold((f64::MAX, f64::MAX, f64::MIN, f64::MIN), |b, (px, py)| {
+            (
+                b.0.min(x + px),
+                b.1.min(y + py),
+                b.2.max(x + px),
+                b.3.max(y + py),
+            )
+        })
+}
+
+fn element_svg(svg: &mut String, el: &Value, scene: &Value) {
+    let (x, y) = (number(el, "x", 0.0), number(el, "y", 0.0));
+    let (w, h) = (number(el, "width", 0.0), number(el, "height", 0.0));
+    let stroke = escape(text(el, "strokeColor", "#1e1e1e"));
+    let stroke_width = number(el, "strokeWidth", 2.0);
+    let kind = text(el, "type", "");
+    let points: Vec<(f64, f64)> = points(el)
+        .into_iter()
+        .map(|(px, py)| (x + px, y + py))
+        .collect();
+    // Of the lines, only closed ones are filled
+    let open_line = match kind {
+        "arrow" | "freedraw" => true,
+        "line" => points.len() < 3 || points.first() != points.last(),
+        _ => false,
+    };
+    let fill = match text(el, "backgroundColor", "transparent") {
+        "transparent" => "none".to_string(),
+        _ if open_line => "none".to_string(),
+        color => escape(color),
+    };
+    let fill_opacity = if text(el, "fillStyle", "solid") == "solid" {
+        1.0
+    } else {
+        0.4
+    };
+    let dash = match text(el, "strokeStyle", "solid") {
+        "dashed" => format!(
+            " stroke-dasharray=\"{} {}\"",
+            4.0 * stroke_width,
+            3.0 * stroke_width
+        ),
+        "dotted" => format!(" stroke-dasharray=\"1 {}\"", 2.0 * stroke_width + 2.0),
+        _ => String::new(),
+    };
+    let paint = format!(
+        "stroke=\"{}\" stroke-width=\"{}\" fill=\"{}\" fill-opacity=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"{}",
+        stroke, stroke_width, fill, fill_opacity, dash
+    );
+
+    let _ = write!(
+        svg,
+        "<g opacity=\"{}\" transform=\"rotate({} {} {})\">",
+        number(el, "opacity", 100.0) / 100.0,
+        number(el, "angle", 0.0).to_degrees(),
+        x + w / 2.0,
+        y + h / 2.0
+    );
+    match kind {
+        "rectangle" => {
+            let radius = if el.get("roundness").is_some_and(|r| !r.is_null()) {
+                (w.min(h) * 0.25).min(32.0)
+            } else {
+                0.0
+            };
+            let _ = write!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" {}/>",
+                x, y, w, h, radius, paint
+            );
+        }
+        "ellipse" => {
+            let _ = write!(
+                svg,
+                "<ellipse cx=\"{}\" cy=\"{}\" rx=\"{}\" ry=\"{}\" {}/>",
+                x + w / 2.0,
+                y + h / 2.0,
+                w / 2.0,
+                h / 2.0,
+                paint
+            );
+        }
+        "diamond" => {
+            let _ = write!(
+                svg,
+                "<polygon points=\"{},{} {},{} {},{} {},{}\" {}/>",
+                x + w / 2.0,
+                y,
+                x + w,
+                y + h / 2.0,
+                x + w / 2.0,
+                y + h,
+                x,
+                y + h / 2.0,
+                paint
+            );
+        }
+        "line" | "arrow" | "freedraw" if !points.is_empty() => {
+            let path: Vec<String> = points
+                .iter()
+                .map(|(px, py)| format!("{},{}", px, py))
+                .collect();
+            let _ = write!(svg, "<polyline points=\"{}\" {}/>", path.join(" "), paint);
+            if kind == "arrow" && points.len() > 1 {
+                let n = points.len();
+                if let Some(head) = el.get("endArrowhead").and_then(Value::as_str) {
+                    arrowhead(
+                        svg,
+                        head,
+                        points[n - 2],
+                        points[n - 1],
+                        &stroke,
+                        stroke_width,
+                    );
+                }
+                if let Some(head) = el.get("startArrowhead").and_then(Value::as_str) {
+                    arrowhead(svg, head, points[1], points[0], &stroke, stroke_width);
+                }
+            }
+        }
+        "text" => {
+            let size = number(el, "fontSize", 20.0);
+            let line_height = size * number(el, "lineHeight", 1.25);
+            let family = match el.get("fontFamily").and_then(Value::as_i64) {
+                Some(1) | Some(5) => "Virgil, Excalifont, 'Comic Sans MS', cursive",
+                Some(3) | Some(8) => "Cascadia, Consolas, 'DejaVu Sans Mono', monospace",
+                _ => "Helvetica, Arial, 'DejaVu Sans', sans-serif",
+            };
+            let (anchor, tx) = match text(el, "textAlign", "left") {
+                "center" => ("middle", x + w / 2.0),
+                "right" => ("end", x + w),
+                _ => ("start", x),
+            };
+            for (i, line) in text(el, "text", "").lines().enumerate() {
+                // Baseline of each line, roughly where a browser puts it
+                let baseline = y + i as f64 * line_height + (line_height + size * 0.7) / 2.0;
+                let _ = write!(
+                    svg,
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"{}\" text-anchor=\"{}\" fill=\"{}\" xml:space=\"preserve\">{}</text>",
+                    tx,
+                    baseline,
+                    size,
+                    family,
+                    anchor,
+                    stroke,
+                    escape(line)
+                );
+            }
+        }
+        "image" => {
+            let data_url = el
+                .get("fileId")
+                .and_then(Value::as_str)
+                .and_then(|id| scene.get("files")?.get(id)?.get("dataURL")?.as_str());
+            if let Some(url) = data_url {
+                let _ = write!(
+                    svg,
+                    "<image x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" xlink:href=\"{}\"/>",
+                    x,
+                    y,
+                    w,
+                    h,
+                    escape(url)
+                );
+            }
+        }
+        "frame" | "magicframe" => {
+            let _ = write!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"8\" stroke=\"#bbb\" fill=\"none\"/>",
+                x, y, w, h
+            );
+        }
+        _ => {}
+    }
+    svg.push_str("</g>\n");
+}
+
+/// An arrowhead at `tip`, pointing away from `from`
+fn arrowhead(
+    svg: &mut String,
+    kind: &str,
+    from: (f64, f64),
+    tip: (f64, f64),
+    stroke: &str,
+    stroke_width: f64,
+) {
+    let angle = (tip.1 - from.1).atan2(tip.0 - from.0);
+    let length = 12.0 + 3.0 * stroke_width;
+    // A point `distance` back from the tip, turned `turn` radians off the line
+    let back = |distance: f64, turn: f64| {
+        (
+            tip.0 - distance * (angle + turn).cos(),
+            tip.1 - distance * (angle + turn).sin(),
+        )
+    };
+    let paint = format!(
+        "stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"",
+        stroke, stroke_width
+    );
+    let (a, b) = (back(length, 0.45), back(length, -0.45));
+    let _ = match kind {
+        "triangle" => write!(
+            svg,
+            "<polygon points=\"{},{} {},{} {},{}\" fill=\"{}\" {}/>",
+            tip.0, tip.1, a.0, a.1, b.0, b.1, stroke, paint
+        ),
+        "bar" => {
+            let (a, b) = (
+                back(length / 2.0, FRAC_PI_2),
+                back(length / 2.0, -FRAC_PI_2),
+            );
+            write!(
+                svg,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+                a.0, a.1, b.0, b.1, paint
+            )
+        }
+        "dot" | "circle" => write!(
+            svg,
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" {}/>",
+            tip.0,
+            tip.1,
+            length / 4.0,
+            stroke,
+            paint
+        ),
+        _ => write!(
+            svg,
+            "<polyline points=\"{},{} {},{} {},{}\" fill=\"none\" {}/>",
+            a.0, a.1, tip.0, tip.1, b.0, b.1, paint
+        ),
+    };
+}
+
+/// Points of a line, arrow or freehand stroke, relative to the element
+fn points(el: &Value) -> Vec<(f64, f64)> {
+    el.get("points")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|p| Some((p.get(0)?.as_f64()?, p.get(1)?.as_f64()?)))
+        .collect()
+}
+
+fn number(el: &Value, key: &str, default: f64) -> f64 {
+    el.get(key).and_then(Value::as_f64).unwrap_or(default)
+}
+
+fn text<'a>(el: &'a Value, key: &str, default: &'a str) -> &'a str {
+    el.get(key).and_then(Value::as_str).unwrap_or(default)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Byte offset where the drawing payload starts: the `# Drawing` /
+/// `## Drawing` heading, or the `%%` comment line wrapping it
+fn drawing_section_start(content: &str) -> Option<usize> {
+    let mut offset = 0;
+    let mut comment_start: Option<usize> = None;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed == "%%" {
+            comment_start = Some(offset);
+        } else if matches!(crate::indexer::parse_heading_line(trimmed), Some((1 | 2, ref text)) if text == "Drawing")
+        {
+            return Some(comment_start.unwrap_or(offset));
+        } else if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            comment_start = None;
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Contents of the first ```lang fenced block in `section`
+fn fenced_block<'a>(section: &'a str, lang: &str) -> Option<&'a str> {
+    let open = format!("```{}\n", lang);
+    let start = section.find(&open)? + open.len();
+    let end = section[start..]
+        .find("\n```")
+        .map_or(section.len(), |e| start + e);
+    Some(&section[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excalidraw_markdown_drawing() {
+        let content = "---\nexcalidraw-plugin: parsed\n---\n# Text Elements\nHello [[Note]] ^abc\n\n%%\n# Drawing\n```json\n{\"elements\":[{\"id\":\"abc\",\"type\":\"text\",\"text\":\"Hello\",\"originalText\":\"Hello [[Note]]\"},{\"id\":\"x\",\"type\":\"text\",\"text\":\"gone\",\"isDeleted\":true},{\"id\":\"r\",\"type\":\"rectangle\"}]}\n```\n%%";
+        let markdown = markdown_part("a.excalidraw.md", content);
+        assert!(markdown.ends_with("Hello [[Note]] ^abc\n\n"));
+
+        let scene = scene("a.excalidraw.md", content).unwrap();
+        let texts = text_elements(&scene);
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].text, "Hello [[Note]]");
+    }
+
+    #[test]
+    fn test_create_index_and_render_drawing() {
+        let vault = crate::test_support::TestVault::with_notes(&[(
+            "Templates/Flow.excalidraw",
+            r##"{"elements":[
+                {"id":"r","type":"rectangle","x":0,"y":0,"width":100,"height":50,"backgroundColor":"#a5d8ff","fillStyle":"hachure","roundness":{"type":3},"link":"[[Spec]]"},
+                {"id":"a","type":"arrow","x":100,"y":25,"width":80,"height":0,"points":[[0,0],[80,0]],"endArrowhead":"arrow"},
+                {"id":"t","type":"text","x":190,"y":10,"width":60,"height":25,"text":"{{title}} & [[Notes]]","fontSize":20,"fontFamily":1},
+                {"id":"d","type":"ellipse","x":500,"y":500,"width":10,"height":10,"isDeleted":true}
+            ],"appState":{"viewBackgroundColor":"#ffffff"},"files":{}}"##,
+        )]);
+
+        let path = create(vault.path(), "Sketches", "Blank", None).unwrap();
+        assert_eq!(path, "Sketches/Blank.excalidraw.md");
+        let content = Vault::read_file(vault.path(), &path).unwrap();
+        assert!(scene(&path, &content).unwrap()["elements"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+        assert!(create(vault.path(), "Sketches", "Blank.excalidraw", None).is_err());
+
+        let path = create(
+            vault.path(),
+            "",
+            "Login flow",
+            Some("Templates/Flow.excalidraw"),
+        )
+        .unwrap();
+        let content = Vault::read_file(vault.path(), &path).unwrap();
+        let drawing = scene(&path, &content).unwrap();
+        assert_eq!(links(&drawing), ["Notes", "Spec"]);
+        assert_eq!(text_elements(&drawing)[0].text, "Login flow & [[Notes]]");
+
+        let db = vault.index();
+        let targets = db.get_outgoing_links(&path).unwrap();
+        assert!(targets.contains(&"Spec".to_string()));
+
+        let svg = to_svg(&drawing);
+        assert!(svg.contains("fill=\"#a5d8ff\" fill-opacity=\"0.4\""));
+        assert!(svg.contains("Login flow &amp; [[Notes]]</text>"));
+        assert!(!svg.contains("<ellipse"));
+
+        // Elements span 250 x 50, plus the padding on each side
+        let png = to_png(&drawing, 2.0).unwrap();
+        assert_eq!(png.width, 270);
+        assert!(png.data.starts_with(b"\x89PNG"));
+        let size = |at: usize| u32::from_be_bytes(png.data[at..at + 4].try_into().unwrap());
+        assert_eq!((size(16), size(20)), (540, 140));
+        assert!(to_png(&drawing, 0.0).is_err());
+    }
+}
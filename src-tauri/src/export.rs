@@ -0,0 +1,636 @@
+//! Standalone HTML export. Notes go through the redactor and then
+//! pulldown-cmark; wikilinks become anchors (or plain text when their note
+//! has no page in the export), `![[...]]` note embeds are rendered in
+//! place, and every image shown is copied into a `<name>_files` folder next
+//! to the HTML file.
+
+use anyhow::{bail, Context, Result};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::crypto;
+use crate::database::Database;
+use crate::embeds::{self, EmbedKind, ResolvedEmbed};
+use crate::excalidraw;
+use crate::indexer::{self, Slugger, WikiLink};
+use crate::redact::Redactor;
+use crate::vault::Vault;
+
+/// Note embeds nested deeper than this are rendered as links
+const MAX_EMBED_DEPTH: usize = 4;
+
+/// Drawings are rasterized at this multiple of their size, for sharp
+/// images on high-density screens and in print
+const DRAWING_SCALE: f32 = 2.0;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "avif"];
+
+const STYLE: &str = "\
+body{margin:0;background:#fff;color:#222;font:16px/1.6 -apple-system,BlinkMacSystemFont,\"Segoe UI\",Helvetica,Arial,sans-serif}
+article{max-width:46rem;margin:0 auto;padding:2rem 1.5rem}
+h1,h2,h3,h4,h5,h6{line-height:1.25;margin:1.6em 0 .6em}
+a{color:#5b4fc4}
+img{max-width:100%}
+pre{background:#f5f5f7;padding:.8em 1em;overflow-x:auto;border-radius:4px}
+code{font-family:ui-monospace,SFMono-Regular,Menlo,Consolas,monospace;font-size:.9em}
+blockquote{margin:0;padding:0 1em;border-left:3px solid #ddd;color:#555}
+table{border-collapse:collapse}
+th,td{border:1px solid #ddd;padding:.3em .6em}
+.embed{border-left:3px solid #5b4fc4;padding:0 1em;margin:1em 0}
+.internal-link{color:#5b4fc4}
+.missing{color:#999}
+.page-break{break-before:page}
+";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HtmlExportOptions {
+    /// The `.html` file to write
+    pub output: PathBuf,
+    /// Render `![[Note]]` embeds in place rather than as links
+    #[serde(default = "default_inline_embeds")]
+    pub inline_embeds: bool,
+}
+
+fn default_inline_embeds() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HtmlExport {
+    /// The HTML file written
+    pub output: String,
+    /// Vault paths of the files copied beside it
+    pub assets: Vec<String>,
+}
+
+/// Write the note at `note_path` to `options.output` as a standalone page
+pub fn export_note_html(
+    vault_path: &Path,
+    db: &Database,
+    redactor: &Redactor,
+    note_path: &str,
+    options: &HtmlExportOptions,
+) -> Result<HtmlExport> {
+    let content = Vault::read_file(vault_path, note_path)?;
+    let Some(redaction) = redactor.redact(note_path, &content) else {
+        bail!("{} is excluded from exports", note_path);
+    };
+    let stem = options
+        .output
+        .file_stem()
+        .context("The export needs a file name")?
+        .to_string_lossy()
+        .to_string();
+
+    let mut renderer = Renderer::new(vault_path, db, redactor, &format!("{}_files", stem));
+    renderer.inline_embeds = options.inline_embeds;
+    renderer.pages.insert(note_path.to_string(), String::new());
+    let body = renderer.render(note_path, &redaction.content)?;
+
+    let html = page(&redactor.redact_text(&note_title(db, note_path)?).0, &body);
+
+    let out_dir = options.output.parent().unwrap_or(Path::new(""));
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    renderer.copy_assets(&out_dir.join(format!("{}_files", stem)))?;
+    std::fs::write(&options.output, html)
+        .with_context(|| format!("Failed to write {}", options.output.display()))?;
+
+    Ok(HtmlExport {
+        output: options.output.to_string_lossy().to_string(),
+        assets: renderer.assets.into_iter().map(|(path, _)| path).collect(),
+    })
+}
+
+/// The indexed title of a note, or its file name
+pub fn note_title(db: &Database, note_path: &str) -> Result<String> {
+    Ok(match db.get_note(note_path)? {
+        Some(note) => note.title,
+        None => Path::new(note_path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+    })
+}
+
+/// A complete HTML page around rendered notes, with the export stylesheet inlined
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<article>\n{}</article>\n</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+/// Renders notes to HTML fragments, collecting the files they show
+pub struct Renderer<'a> {
+    vault_path: &'a Path,
+    db: &'a Database,
+    redactor: &'a Redactor,
+    /// Render note embeds in place rather than as links
+    pub inline_embeds: bool,
+    /// href of each note that has a page in the export, by vault path.
+    /// Links to other notes are left as text.
+    pub pages: HashMap<String, String>,
+    /// Folder, relative to the page, that assets are copied into
+    asset_dir: String,
+    /// Vault path and copied file name of each asset, in order of first use
+    assets: Vec<(String, String)>,
+    /// PNG of each drawing shown, by vault path, written in its place
+    drawings: HashMap<String, Vec<u8>>,
+    /// Notes being rendered, innermost last
+    stack: Vec<String>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(
+        vault_path: &'a Path,
+        db: &'a Database,
+        redactor: &'a Redactor,
+        asset_dir: &str,
+    ) -> Renderer<'a> {
+        Renderer {
+            vault_path,
+            db,
+            redactor,
+            inline_embeds: true,
+            pages: HashMap::new(),
+            asset_dir: asset_dir.to_string(),
+            assets: Vec::new(),
+            drawings: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Render `content`, the already redacted text of the note at `note_path`
+    pub fn render(&mut self, note_path: &str, content: &str) -> Result<String> {
+        let body = Vault::strip_frontmatter(&crypto::strip_encrypted(content));
+        let embeds: HashMap<String, ResolvedEmbed> =
+            embeds::resolve(self.vault_path, self.db, note_path, &body)?
+                .into_iter()
+                .map(|embed| (body[embed.start..embed.end].to_string(), embed))
+                .collect();
+
+        self.stack.push(note_path.to_string());
+        let events = self.events(note_path, &body, &embeds);
+        self.stack.pop();
+
+        let mut html = String::new();
+        html::push_html(&mut html, events?.into_iter());
+        Ok(html)
+    }
+
+    /// Copy every asset referenced so far into `dir`, decrypting secure attachments
+    pub fn copy_assets(&self, dir: &Path) -> Result<()> {
+        if self.assets.is_empty() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let mut key = None;
+        for (path, name) in &self.assets {
+            if let Some(png) = self.drawings.get(path) {
+                std::fs::write(dir.join(name), png)
+                    .with_context(|| format!("Failed to write {}", name))?;
+                continue;
+            }
+            let mut data = Vault::read_bytes(self.vault_path, path)?;
+            if crypto::is_encrypted_bytes(&data) {
+                if key.is_none() {
+                    key = Some(crypto::vault_file_key(self.vault_path)?);
+                }
+                if let Some(key) = &key {
+                    data = crypto::decrypt_bytes(key, &data)?;
+                }
+            }
+            std::fs::write(dir.join(name), data)
+                .with_context(|| format!("Failed to copy {}", path))?;
+        }
+        Ok(())
+    }
+
+    fn events<'e>(
+        &mut self,
+        note_path: &str,
+        body: &'e str,
+        embeds: &HashMap<String, ResolvedEmbed>,
+    ) -> Result<Vec<Event<'e>>> {
+        let note_dir = Path::new(note_path).parent().unwrap_or(Path::new(""));
+        let mut events = Vec::new();
+        // Adjacent text events, joined so wikilinks split across them are found
+        let mut text = String::new();
+        let mut in_code_block = false;
+
+        for event in Parser::new_ext(body, markdown_options()) {
+            if let Event::Text(t) = &event {
+                if !in_code_block {
+                    text.push_str(t);
+                    continue;
+                }
+            }
+            self.push_text(&mut events, &mut text, embeds)?;
+            let event = match event {
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    in_code_block = true;
+                    Event::Start(Tag::CodeBlock(kind))
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    in_code_block = false;
+                    Event::End(TagEnd::CodeBlock)
+                }
+                Event::Start(Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) => {
+                    let dest_url = match self.local_file(note_dir, &dest_url) {
+                        Some(path) => CowStr::from(self.asset_href(&path)),
+                        None => dest_url,
+                    };
+                    Event::Start(Tag::Image {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    })
+                }
+                other => other,
+            };
+            events.push(event);
+        }
+        self.push_text(&mut events, &mut text, embeds)?;
+
+        set_heading_ids(&mut events);
+        Ok(events)
+    }
+
+    /// Emit pending text, with its wikilinks replaced by HTML
+    fn push_text<'e>(
+        &mut self,
+        events: &mut Vec<Event<'e>>,
+        text: &mut String,
+        embeds: &HashMap<String, ResolvedEmbed>,
+    ) -> Result<()> {
+        let text = std::mem::take(text);
+        let mut last = 0;
+        for link in indexer::parse_wikilinks(&text) {
+            if link.start > last {
+                events.push(Event::Text(text[last..link.start].to_string().into()));
+            }
+            let html = match embeds.get(&text[link.start..link.end]) {
+                Some(embed) if link.embed => self.embed_html(&link, embed)?,
+                _ => self.link_html(&link)?,
+            };
+            events.push(Event::InlineHtml(html.into()));
+            last = link.end;
+        }
+        if last < text.len() {
+            events.push(Event::Text(text[last..].to_string().into()));
+        }
+        Ok(())
+    }
+
+    fn embed_html(&mut self, link: &WikiLink, embed: &ResolvedEmbed) -> Result<String> {
+        let label = escape(&link_label(link));
+        let Some(path) = &embed.path else {
+            return Ok(format!("<span class=\"embed missing\">{}</span>", label));
+        };
+        match embed.kind {
+            EmbedKind::File if excalidraw::is_drawing(path) => self.drawing_html(link, path),
+            EmbedKind::File if is_image(path) => {
+                // `![[shot.png|300]]` sets the width rather than the alt text
+                let alias = link.alias.as_deref().unwrap_or_default();
+                let attribute = match alias.trim().parse::<u32>() {
+                    Ok(width) => format!("alt=\"\" width=\"{}\"", width),
+                    Err(_) => format!("alt=\"{}\"", escape(alias)),
+                };
+                Ok(format!(
+                    "<img src=\"{}\" {}>",
+                    escape(&self.asset_href(path)),
+                    attribute
+                ))
+            }
+            EmbedKind::File => Ok(format!(
+                "<a class=\"attachment\" href=\"{}\">{}</a>",
+                escape(&self.asset_href(path)),
+                label
+            )),
+            EmbedKind::Note if self.inline_embeds && self.can_embed(link, path) => {
+                match self.redacted_excerpt(path, embed)? {
+                    Some(excerpt) => Ok(format!(
+                        "<div class=\"embed\">\n{}</div>",
+                        self.render(path, &excerpt)?
+                    )),
+                    None => Ok(format!("<span class=\"embed missing\">{}</span>", label)),
+                }
+            }
+            EmbedKind::Note => self.link_html(link),
+            EmbedKind::Missing => Ok(format!("<span class=\"embed missing\">{}</span>", label)),
+        }
+    }
+
+    /// A drawing as an image, rasterized unless the drawing is left out of
+    /// exports or can't be read
+    fn drawing_html(&mut self, link: &WikiLink, path: &str) -> Result<String> {
+        let content = Vault::read_file(self.vault_path, path)?;
+        let png = match self.redactor.redact(path, &content) {
+            Some(_) => excalidraw::scene(path, &content)
+                .and_then(|scene| excalidraw::to_png(&scene, DRAWING_SCALE))
+                .map_err(|e| log::warn!("Skipping drawing {} in export: {:#}", path, e))
+                .ok(),
+            None => None,
+        };
+        let Some(png) = png else {
+            return Ok(format!(
+                "<span class=\"embed missing\">{}</span>",
+                escape(&link_label(link))
+            ));
+        };
+
+        // `![[sketch.excalidraw|300]]` sets the width, as for images
+        let alias = link.alias.as_deref().unwrap_or_default();
+        let width = alias.trim().parse::<u32>().unwrap_or(png.width);
+        let src = self.asset_href(path);
+        self.drawings.insert(path.to_string(), png.data);
+        Ok(format!(
+            "<img src=\"{}\" alt=\"\" width=\"{}\">",
+            escape(&src),
+            width
+        ))
+    }
+
+    /// Whether a note embed can be rendered without recursing forever.
+    /// Sections of the same note are bounded by the depth alone.
+    fn can_embed(&self, link: &WikiLink, path: &str) -> bool {
+        self.stack.len() < MAX_EMBED_DEPTH
+            && (link.target.is_empty() || !self.stack.iter().any(|p| p == path))
+    }
+
+    /// The embedded part of a note after redaction, or `None` if the note is
+    /// left out of exports
+    fn redacted_excerpt(&self, path: &str, embed: &ResolvedEmbed) -> Result<Option<String>> {
+        let content = Vault::read_file(self.vault_path, path)?;
+        if self.redactor.redact(path, &content).is_none() {
+            return Ok(None);
+        }
+        let excerpt = embed.content.as_deref().unwrap_or_default();
+        Ok(self.redactor.redact(path, excerpt).map(|r| r.content))
+    }
+
+    fn link_html(&self, link: &WikiLink) -> Result<String> {
+        let label = escape(&link_label(link));
+        Ok(match self.link_href(link)? {
+            Some(href) => format!(
+                "<a class=\"internal-link\" href=\"{}\">{}</a>",
+                escape(&href),
+                label
+            ),
+            None => format!("<span class=\"internal-link\">{}</span>", label),
+        })
+    }
+
+    /// The page a wikilink points at, with the heading anchor if it names one
+    fn link_href(&self, link: &WikiLink) -> Result<Option<String>> {
+        let page = if link.target.is_empty() {
+            Some(String::new())
+        } else {
+            self.db
+                .resolve_link_target(&link.target)?
+                .and_then(|path| self.pages.get(&path).cloned())
+        };
+        // Pages that are themselves anchors (notes in one document) can't
+        // take a heading anchor as well
+        Ok(page.map(|page| match link.subpath.as_deref() {
+            Some(sub) if !sub.starts_with('^') && !page.contains('#') => {
+                let heading = sub.rsplit('#').next().unwrap_or(sub);
+                format!("{}#{}", page, indexer::heading_slug(heading))
+            }
+            _ if page.is_empty() => "#".to_string(),
+            _ => page,
+        }))
+    }
+
+    /// The vault path a Markdown image points at, relative to the note or
+    /// else to the vault root, if that file exists
+    fn local_file(&self, note_dir: &Path, dest: &str) -> Option<String> {
+        if dest.is_empty() || dest.starts_with('#') || dest.contains(':') {
+            return None;
+        }
+        let dest = indexer::percent_decode(dest);
+        [note_dir.join(&dest), PathBuf::from(&dest)]
+            .iter()
+            .filter_map(|path| vault_relative(path))
+            .find(|path| self.vault_path.join(path).is_file())
+    }
+
+    /// Where the page links a copied file from, claiming it a unique name
+    fn asset_href(&mut self, path: &str) -> String {
+        let name = match self.assets.iter().find(|(p, _)| p == path) {
+            Some((_, name)) => name.clone(),
+            None => {
+                let mut file_name = Path::new(path)
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                if excalidraw::is_drawing(path) {
+                    // Drawings are copied as the PNGs rendered from them
+                    let stem = file_name
+                        .trim_end_matches(".md")
+                        .trim_end_matches(".excalidraw");
+                    file_name = format!("{}.png", stem);
+                }
+                let mut name = file_name.clone();
+                let mut n = 1;
+                while self
+                    .assets
+                    .iter()
+                    .any(|(_, taken)| taken.eq_ignore_ascii_case(&name))
+                {
+                    name = match file_name.rsplit_once('.') {
+                        Some((stem, ext)) => format!("{}-{}.{}", stem, n, ext),
+                        None => format!("{}-{}", file_name, n),
+                    };
+                    n += 1;
+                }
+                self.assets.push((path.to_string(), name.clone()));
+                name
+            }
+        };
+        format!("{}/{}", encode_path(&self.asset_dir), encode_path(&name))
+    }
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+fn markdown_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// Give headings the slugs the index uses, so `[[#Heading]]` anchors land
+fn set_heading_ids(events: &mut [Event]) {
+    let mut slugger = Slugger::default();
+    for i in 0..events.len() {
+        if !matches!(events[i], Event::Start(Tag::Heading { id: None, .. })) {
+            continue;
+        }
+        let mut text = String::new();
+        for event in &events[i + 1..] {
+            match event {
+                Event::End(TagEnd::Heading(_)) => break,
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                _ => {}
+            }
+        }
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+            *id = Some(slugger.slug(&text).into());
+        }
+    }
+}
+
+/// `Alias`, `Note > Heading`, or the target as written
+fn link_label(link: &WikiLink) -> String {
+    if let Some(alias) = &link.alias {
+        return alias.clone();
+    }
+    match &link.subpath {
+        Some(sub) if link.target.is_empty() => sub.clone(),
+        Some(sub) => format!("{} > {}", link.target, sub),
+        None => link.target.clone(),
+    }
+}
+
+fn is_image(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// `/`-separated with `.` and `..` resolved; `None` if it leaves the vault
+fn vault_relative(path: &Path) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::CurDir | Component::RootDir => {}
+            Component::Prefix(_) => return None,
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Escape the characters a relative URL can't carry as they are
+fn encode_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F")
+}
+
+/// Escape text for HTML content and attribute values
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::RedactionRules;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_export_note_html() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Notes/Home.md",
+                "---\ntags: [home]\n---\n# Home\nSee [[#Goals]], [[Plan|the plan]] and [[Nowhere]].\n\n\
+                 ![[Plan#Goals]]\n\n![[Secret]]\n\n![[shot.png|300]]\n\n![diagram](../assets/d%20one.svg)\n\n\
+                 ```\n[[Plan]]\n```\n\n## Goals\nShip\n",
+            ),
+            ("Projects/Plan.md", "# Plan\n\n## Goals\nShip it\n"),
+            ("Secret.md", "#private\nHidden\n"),
+            ("attachments/shot.png", "png"),
+            ("assets/d one.svg", "<svg/>"),
+        ]);
+        let db = vault.index();
+        let redactor = Redactor::new(RedactionRules::default()).unwrap();
+        let output = vault.path().join("out/Home.html");
+        let options = HtmlExportOptions {
+            output: output.clone(),
+            inline_embeds: true,
+        };
+
+        let export =
+            export_note_html(vault.path(), &db, &redactor, "Notes/Home.md", &options).unwrap();
+        assert_eq!(
+            export.assets,
+            vec!["attachments/shot.png", "assets/d one.svg"]
+        );
+        let html = std::fs::read_to_string(&output).unwrap();
+        assert!(html.contains("<title>Home</title>"));
+        assert!(html.contains("<h2 id=\"goals\">Goals</h2>"));
+        assert!(html.contains("<a class=\"internal-link\" href=\"#goals\">Goals</a>"));
+        assert!(html.contains("<span class=\"internal-link\">the plan</span>"));
+        assert!(html.contains(
+            "<div class=\"embed\">\n<h2 id=\"goals\">Goals</h2>\n<p>Ship it</p>\n</div>"
+        ));
+        assert!(html.contains("<span class=\"embed missing\">Secret</span>"));
+        assert!(!html.contains("Hidden"));
+        assert!(html.contains("<img src=\"Home_files/shot.png\" alt=\"\" width=\"300\">"));
+        assert!(html.contains("src=\"Home_files/d%20one.svg\""));
+        assert!(html.contains("<code>[[Plan]]\n</code>"));
+        assert!(!html.contains("tags: [home]"));
+        assert!(vault.path().join("out/Home_files/d one.svg").exists());
+
+        assert!(export_note_html(vault.path(), &db, &redactor, "Secret.md", &options).is_err());
+    }
+
+    #[test]
+    fn test_export_rasterizes_drawings() {
+        let drawing = "---\nexcalidraw-plugin: parsed\n---\n%%\n## Drawing\n```json\n\
+            {\"elements\":[{\"id\":\"r\",\"type\":\"rectangle\",\"x\":0,\"y\":0,\"width\":80,\"height\":40}]}\n```\n%%\n";
+        let vault = TestVault::with_notes(&[
+            (
+                "Home.md",
+                "![[Sketch.excalidraw|120]]\n\n![[Drawings/Sketch.excalidraw.md]]\n",
+            ),
+            ("Drawings/Sketch.excalidraw.md", drawing),
+        ]);
+        let db = vault.index();
+        let redactor = Redactor::new(RedactionRules::default()).unwrap();
+        let output = vault.path().join("out/Home.html");
+        let options = HtmlExportOptions {
+            output: output.clone(),
+            inline_embeds: true,
+        };
+
+        let export = export_note_html(vault.path(), &db, &redactor, "Home.md", &options).unwrap();
+        assert_eq!(export.assets, vec!["Drawings/Sketch.excalidraw.md"]);
+        let html = std::fs::read_to_string(&output).unwrap();
+        assert!(html.contains("<img src=\"Home_files/Sketch.png\" alt=\"\" width=\"120\">"));
+        assert!(
+            html.contains("<img src=\"Home_files/Sketch.png\" alt=\"\" width=\"100\">"),
+            "{}",
+            html
+        );
+        let png = std::fs::read(vault.path().join("out/Home_files/Sketch.png")).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+}
@@ -1,9 +1,16 @@
+//! The legacy `~/.synapse/notes` store: UUID-named notes with timestamp
+//! frontmatter, from before vaults existed. Only read now, to import them
+//! into a vault.
+
 use anyhow::{Context, Result};
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use uuid::Uuid;
+use std::path::{Path, PathBuf};
+
+use crate::vault::Vault;
+
+/// Vault folder imported notes go to unless the caller picks another
+pub const DEFAULT_IMPORT_FOLDER: &str = "Imported";
 
 /// Represents a note with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +22,17 @@ pub struct Note {
     pub modified_at: i64,
 }
 
+/// Outcome of `import_into_vault`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegacyImport {
+    /// Vault-relative paths of the notes written
+    pub imported: Vec<String>,
+    /// Notes whose id is already in the vault (imported before)
+    pub skipped: usize,
+    /// Legacy files that couldn't be read, with the reason
+    pub failed: Vec<String>,
+}
+
 /// Returns the path to the Synapse notes directory (~/.synapse/notes/)
 pub fn notes_dir() -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not determine home directory")?;
@@ -22,136 +40,109 @@ pub fn notes_dir() -> Result<PathBuf> {
     Ok(notes_path)
 }
 
-/// Returns the path to the Synapse data directory (~/.synapse/)
-pub fn synapse_dir() -> Result<PathBuf> {
-    let home = dirs::home_dir().context("Could not determine home directory")?;
-    Ok(home.join(".synapse"))
-}
-
-/// Ensures the notes directory exists, creating it if necessary
-pub fn ensure_notes_dir() -> Result<PathBuf> {
-    let dir = notes_dir()?;
-    fs::create_dir_all(&dir).context("Failed to create notes directory")?;
-    Ok(dir)
-}
-
-/// Creates a new note with the given title, writing a Markdown file with YAML frontmatter
-pub fn create_note(title: &str) -> Result<Note> {
-    let dir = ensure_notes_dir()?;
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
-
-    let file_path = dir.join(format!("{}.md", id));
-    let file_path_str = file_path.to_string_lossy().to_string();
-
-    let frontmatter = format!(
-        "---\nid: {}\ntitle: {}\ncreated: {}\nmodified: {}\n---\n",
-        id, title, now, now
-    );
-
-    fs::write(&file_path, &frontmatter).context("Failed to write note file")?;
-
-    Ok(Note {
-        id,
-        title: title.to_string(),
-        file_path: file_path_str,
-        created_at: now,
-        modified_at: now,
-    })
-}
-
-/// Reads all notes from the notes directory by parsing their YAML frontmatter
-pub fn get_all_notes() -> Result<Vec<Note>> {
-    let dir = ensure_notes_dir()?;
+/// Reads all notes from the notes directory by parsing their YAML
+/// frontmatter. Unreadable files are returned as errors.
+pub fn get_all_notes(dir: &Path) -> Result<Vec<Result<Note>>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
     let mut notes = Vec::new();
 
-    let entries = fs::read_dir(&dir).context("Failed to read notes directory")?;
+    let entries = fs::read_dir(dir).context("Failed to read notes directory")?;
 
     for entry in entries {
         let entry = entry.context("Failed to read directory entry")?;
         let path = entry.path();
 
-        if path.extension().map_or(false, |ext| ext == "md") {
-            match parse_note_from_file(&path) {
-                Ok(note) => notes.push(note),
-                Err(e) => {
-                    log::warn!("Skipping file {:?}: {}", path, e);
-                }
-            }
+        if path.extension().is_some_and(|ext| ext == "md") {
+            notes.push(parse_note_from_file(&path).with_context(|| format!("{}", path.display())));
         }
     }
 
-    // Sort by modified_at descending (most recent first)
-    notes.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    // Oldest first, so a later note with the same title gets the suffix
+    notes.sort_by_key(|n| n.as_ref().map(|n| n.created_at).unwrap_or_default());
 
     Ok(notes)
 }
 
-/// Returns the content of a note (body only, without frontmatter)
-pub fn get_note_content(id: &str) -> Result<String> {
-    let dir = notes_dir()?;
-    let file_path = dir.join(format!("{}.md", id));
-
-    let raw = fs::read_to_string(&file_path)
-        .with_context(|| format!("Failed to read note file: {}", id))?;
-
-    Ok(strip_frontmatter(&raw))
+/// Copy the notes in `legacy_dir` into `folder` of the vault, converting
+/// their frontmatter to the vault's (`title`, dates, `tags`) and keeping
+/// their UUID as `id`. Notes whose id the vault already has are skipped, so
+/// running it twice is harmless. The legacy files are left in place.
+pub fn import_into_vault(
+    legacy_dir: &Path,
+    vault_path: &Path,
+    folder: &str,
+) -> Result<LegacyImport> {
+    import_notes(get_all_notes(legacy_dir)?, vault_path, folder)
 }
 
-/// Saves the content of an existing note, updating the modified timestamp
-pub fn save_note(id: &str, content: &str) -> Result<()> {
-    let dir = notes_dir()?;
-    let file_path = dir.join(format!("{}.md", id));
-
-    let raw = fs::read_to_string(&file_path)
-        .with_context(|| format!("Failed to read note file for saving: {}", id))?;
-
-    let (mut frontmatter_map, _) = parse_frontmatter_raw(&raw)?;
-    let now = Utc::now().timestamp();
-    frontmatter_map.insert("modified".to_string(), now.to_string());
+/// `import_into_vault` for notes already read from the legacy folder. A note
+/// that can't be read or written is reported in `failed` and the rest are
+/// still imported.
+fn import_notes(notes: Vec<Result<Note>>, vault_path: &Path, folder: &str) -> Result<LegacyImport> {
+    let existing = Vault::note_ids(vault_path)?;
+    let mut report = LegacyImport::default();
+
+    for note in notes {
+        let note = match note {
+            Ok(note) => note,
+            Err(e) => {
+                log::warn!("Skipping legacy note: {:#}", e);
+                report.failed.push(format!("{:#}", e));
+                continue;
+            }
+        };
+        if existing.contains_key(&note.id) {
+            report.skipped += 1;
+            continue;
+        }
 
-    let new_content = rebuild_file(&frontmatter_map, content);
-    fs::write(&file_path, new_content).context("Failed to save note file")?;
+        match import_note(&note, vault_path, folder) {
+            Ok(relative_path) => report.imported.push(relative_path),
+            Err(e) => {
+                log::warn!("Skipping legacy note {}: {:#}", note.id, e);
+                report.failed.push(format!("{}: {:#}", note.id, e));
+            }
+        }
+    }
 
-    Ok(())
+    Ok(report)
 }
 
-/// Deletes a note by its ID
-pub fn delete_note(id: &str) -> Result<()> {
-    let dir = notes_dir()?;
-    let file_path = dir.join(format!("{}.md", id));
-
-    fs::remove_file(&file_path)
-        .with_context(|| format!("Failed to delete note file: {}", id))?;
-
-    Ok(())
+/// Write one legacy note into `folder`, returning its vault-relative path
+fn import_note(note: &Note, vault_path: &Path, folder: &str) -> Result<String> {
+    let raw = fs::read_to_string(&note.file_path).context("Failed to read note file")?;
+    let body = strip_frontmatter(&raw);
+    let relative_path = Vault::unique_file_path(vault_path, folder, &format!("{}.md", note.title));
+    Vault::write_file_atomic(vault_path, &relative_path, &convert(note, &body)?)?;
+    Ok(relative_path)
 }
 
-/// Renames a note by updating its title in the frontmatter
-pub fn rename_note(id: &str, new_title: &str) -> Result<()> {
-    let dir = notes_dir()?;
-    let file_path = dir.join(format!("{}.md", id));
-
-    let raw = fs::read_to_string(&file_path)
-        .with_context(|| format!("Failed to read note file for renaming: {}", id))?;
-
-    let (mut frontmatter_map, body) = parse_frontmatter_raw(&raw)?;
-    let now = Utc::now().timestamp();
-    frontmatter_map.insert("title".to_string(), new_title.to_string());
-    frontmatter_map.insert("modified".to_string(), now.to_string());
-
-    let new_content = rebuild_file(&frontmatter_map, &body);
-    fs::write(&file_path, new_content).context("Failed to save renamed note")?;
-
-    Ok(())
+/// The note in the vault's format
+fn convert(note: &Note, body: &str) -> Result<String> {
+    let date = |timestamp: i64| {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+    let mut props = serde_yaml::Mapping::new();
+    props.insert("id".into(), note.id.clone().into());
+    props.insert("title".into(), note.title.clone().into());
+    props.insert("created".into(), date(note.created_at).into());
+    props.insert("modified".into(), date(note.modified_at).into());
+    props.insert("tags".into(), serde_yaml::Sequence::new().into());
+    let yaml = serde_yaml::to_string(&props).context("Failed to build frontmatter")?;
+    Ok(format!("---\n{}---\n\n{}", yaml, body))
 }
 
 // ─── Internal helpers ───────────────────────────────────────────────────────
 
 /// Parses a Note struct from a markdown file by reading its YAML frontmatter
 fn parse_note_from_file(path: &PathBuf) -> Result<Note> {
-    let raw = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read file: {:?}", path))?;
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("Failed to read file: {:?}", path))?;
 
     let (fm, _) = parse_frontmatter_raw(&raw)?;
 
@@ -241,21 +232,59 @@ fn strip_frontmatter(raw: &str) -> String {
     }
 }
 
-/// Rebuilds a markdown file from frontmatter map and body content
-fn rebuild_file(fm: &std::collections::HashMap<String, String>, body: &str) -> String {
-    // Preserve consistent key ordering
-    let keys = ["id", "title", "created", "modified"];
-    let mut frontmatter = String::from("---\n");
-    for key in &keys {
-        if let Some(value) = fm.get(*key) {
-            frontmatter.push_str(&format!("{}: {}\n", key, value));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_import_legacy_notes() {
+        let legacy = TestVault::with_notes(&[
+            (
+                "0b7e.md",
+                "---\nid: 0b7e\ntitle: Groceries: weekly\ncreated: 1700000000\nmodified: 1700086400\n---\nMilk\n[[Recipes]]\n",
+            ),
+            ("broken.md", "no frontmatter here"),
+        ]);
+        let vault = TestVault::new();
+
+        let report = import_into_vault(legacy.path(), vault.path(), DEFAULT_IMPORT_FOLDER).unwrap();
+        assert_eq!(report.imported, vec!["Imported/Groceries_ weekly.md"]);
+        assert_eq!(report.failed.len(), 1);
+
+        let content = Vault::read_file(vault.path(), &report.imported[0]).unwrap();
+        let fm = Vault::parse_frontmatter(&content);
+        assert_eq!(fm.id.as_deref(), Some("0b7e"));
+        assert_eq!(fm.title.as_deref(), Some("Groceries: weekly"));
+        assert_eq!(fm.created.as_deref(), Some("2023-11-14"));
+        assert_eq!(Vault::strip_frontmatter(&content), "Milk\n[[Recipes]]\n");
+
+        // Already imported notes are recognised by id
+        let again = import_into_vault(legacy.path(), vault.path(), DEFAULT_IMPORT_FOLDER).unwrap();
+        assert!(again.imported.is_empty());
+        assert_eq!(again.skipped, 1);
     }
-    frontmatter.push_str("---\n");
 
-    if body.is_empty() {
-        frontmatter
-    } else {
-        format!("{}{}", frontmatter, body)
+    #[test]
+    fn test_import_continues_past_an_unreadable_note() {
+        let legacy = TestVault::with_notes(&[(
+            "1a2b.md",
+            "---\nid: 1a2b\ntitle: Kept\ncreated: 1700000000\nmodified: 1700000000\n---\nBody\n",
+        )]);
+        let vault = TestVault::new();
+        let mut notes = get_all_notes(legacy.path()).unwrap();
+        let missing = Note {
+            id: "9f9f".to_string(),
+            title: "Gone".to_string(),
+            file_path: legacy.path().join("9f9f.md").to_string_lossy().to_string(),
+            created_at: 1600000000,
+            modified_at: 1600000000,
+        };
+        notes.insert(0, Ok(missing));
+
+        let report = import_notes(notes, vault.path(), DEFAULT_IMPORT_FOLDER).unwrap();
+        assert_eq!(report.imported, vec!["Imported/Kept.md"]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].starts_with("9f9f: "));
     }
 }
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use crate::editing;
+
+/// Which normalizations `format_markdown` applies. Everything is on by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatRules {
+    /// One space after the `#`s, and a blank line around every heading
+    pub heading_spacing: bool,
+    /// Bullet character for unordered lists (`-`, `*` or `+`); None leaves markers alone
+    pub list_marker: Option<char>,
+    /// Strip trailing whitespace, keeping two-space hard line breaks
+    pub trailing_whitespace: bool,
+    /// Tidy the YAML block: no blank or whitespace-padded lines inside,
+    /// one blank line between it and the body
+    pub frontmatter: bool,
+    /// Collapse runs of blank lines to at most this many
+    pub max_blank_lines: Option<usize>,
+    /// End the file with exactly one newline
+    pub final_newline: bool,
+}
+
+impl Default for FormatRules {
+    fn default() -> Self {
+        FormatRules {
+            heading_spacing: true,
+            list_marker: Some('-'),
+            trailing_whitespace: true,
+            frontmatter: true,
+            max_blank_lines: Some(1),
+            final_newline: true,
+        }
+    }
+}
+
+/// Outcome of formatting one note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatResult {
+    pub path: String,
+    pub changed: bool,
+    /// Unified diff of the change (empty when unchanged)
+    pub diff: String,
+}
+
+#[derive(PartialEq)]
+enum LineKind {
+    Text,
+    Blank,
+    Heading,
+    /// Inside (or fencing) a code block; never touched
+    Code,
+}
+
+/// Apply `rules` to a markdown document. Fenced code blocks are left verbatim.
+pub fn format_markdown(content: &str, rules: &FormatRules) -> String {
+    let (frontmatter, body) = editing::split_frontmatter(content);
+
+    let mut lines: Vec<(String, LineKind)> = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            lines.push((line.to_string(), LineKind::Code));
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            lines.push((line.to_string(), LineKind::Code));
+            continue;
+        }
+
+        let mut line = line.to_string();
+        if rules.trailing_whitespace {
+            line = strip_trailing_whitespace(&line);
+        }
+        if let Some(marker) = rules.list_marker {
+            line = normalize_list_marker(&line, marker);
+        }
+        let kind = if line.trim().is_empty() {
+            LineKind::Blank
+        } else if line.starts_with('#') && crate::indexer::parse_heading_line(&line).is_some() {
+            if rules.heading_spacing {
+                line = normalize_heading(&line);
+            }
+            LineKind::Heading
+        } else {
+            LineKind::Text
+        };
+        lines.push((line, kind));
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut blank_run = 0;
+    for (i, (line, kind)) in lines.iter().enumerate() {
+        let prev_blank = out.last().map_or(true, |l| l.trim().is_empty());
+        if rules.heading_spacing && *kind == LineKind::Heading && !prev_blank {
+            out.push(String::new());
+        }
+
+        if *kind == LineKind::Blank {
+            blank_run += 1;
+            if rules.max_blank_lines.is_some_and(|max| blank_run > max) {
+                continue;
+            }
+            // Blank lines at the very top of the body are handled with the frontmatter
+            if rules.frontmatter && out.is_empty() && !frontmatter.is_empty() {
+                continue;
+            }
+            out.push(String::new());
+        } else {
+            blank_run = 0;
+            out.push(line.clone());
+        }
+
+        let next_blank = lines
+            .get(i + 1)
+            .map_or(true, |(_, next)| *next == LineKind::Blank);
+        if rules.heading_spacing && *kind == LineKind::Heading && !next_blank {
+            out.push(String::new());
+            blank_run = 1;
+        }
+    }
+
+    let has_content = out.iter().any(|l| !l.trim().is_empty());
+    if rules.final_newline && has_content {
+        while out.last().is_some_and(|l| l.trim().is_empty()) {
+            out.pop();
+        }
+    }
+
+    let mut result = if rules.frontmatter {
+        format_frontmatter(frontmatter)
+    } else {
+        frontmatter.to_string()
+    };
+    if !has_content {
+        // Nothing but whitespace (e.g. a brand-new note): leave the body alone
+        result.push_str(body);
+        return result;
+    }
+    if rules.frontmatter && !frontmatter.is_empty() {
+        result.push('\n');
+    }
+    result.push_str(&out.join("\n"));
+    if rules.final_newline || body.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Unified diff between two versions of a note (empty when identical)
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(2)
+        .header(path, path)
+        .to_string()
+}
+
+fn format_frontmatter(block: &str) -> String {
+    let mut out = String::new();
+    for line in block.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn strip_trailing_whitespace(line: &str) -> String {
+    let stripped = line.trim_end();
+    if !stripped.is_empty() && &line[stripped.len()..] == "  " {
+        line.to_string()
+    } else {
+        stripped.to_string()
+    }
+}
+
+/// `##   Title` -> `## Title`. The text after the hashes is kept verbatim so
+/// headings like `## C#` survive.
+fn normalize_heading(line: &str) -> String {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    format!("{} {}", &line[..level], line[level..].trim_start())
+}
+
+fn normalize_list_marker(line: &str, marker: char) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let mut chars = trimmed.chars();
+    let (Some(first), Some(' ')) = (chars.next(), chars.next()) else {
+        return line.to_string();
+    };
+    if !matches!(first, '-' | '*' | '+') || first == marker {
+        return line.to_string();
+    }
+    // `* * *` is a thematic break, not a list
+    if trimmed.chars().all(|c| c == first || c == ' ') {
+        return line.to_string();
+    }
+    format!("{}{}{}", indent, marker, &trimmed[1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_markdown() {
+        let content = "---\ntitle: x  \n\ntags: []\n---\n\n\n#  Title\nText   \n* one\n+ two  \n\n\n\n## C#\n```\n*  keep   \n```\n* * *\n\n";
+        let formatted = format_markdown(content, &FormatRules::default());
+        assert_eq!(
+            formatted,
+            "---\ntitle: x\ntags: []\n---\n\n# Title\n\nText\n- one\n- two  \n\n## C#\n\n```\n*  keep   \n```\n* * *\n"
+        );
+        assert_eq!(
+            format_markdown(&formatted, &FormatRules::default()),
+            formatted
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_new_note_layout() {
+        let content = "---\ntitle: x\ntags: []\n---\n\n";
+        assert_eq!(format_markdown(content, &FormatRules::default()), content);
+    }
+}
@@ -0,0 +1,220 @@
+//! Git history for the vault: set up a repository, commit changes and list
+//! the commits that touched a note. The vault may also sit inside a larger
+//! repository; only its own files are committed. Pushing and pulling are
+//! left to the user's git setup.
+
+use anyhow::{Context, Result};
+use git2::{IndexAddOption, Repository, Signature};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Setting that commits each note when it is saved ("true"/"false")
+pub const AUTO_COMMIT_SETTING: &str = "git.auto_commit";
+
+/// Kept out of history: the cache, logs and other per-machine state
+const IGNORED: &str = ".synapse/";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitInfo {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    /// RFC 3339
+    pub time: String,
+}
+
+/// Make the vault a git repository (unless it already is in one) and keep
+/// `.synapse/` out of it. Returns whether a repository was created.
+pub fn init_vault(vault_path: &Path) -> Result<bool> {
+    let created = if Repository::discover(vault_path).is_ok() {
+        false
+    } else {
+        Repository::init(vault_path).context("Failed to create git repository")?;
+        true
+    };
+
+    let gitignore = vault_path.join(".gitignore");
+    let existing = fs::read_to_string(&gitignore).unwrap_or_default();
+    if !existing.lines().any(|line| line.trim() == IGNORED) {
+        let separator = if existing.is_empty() || existing.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        fs::write(
+            &gitignore,
+            format!("{}{}{}\n", existing, separator, IGNORED),
+        )
+        .context("Failed to update .gitignore")?;
+    }
+    Ok(created)
+}
+
+/// Commit the vault's changes, or only `paths` (vault-relative) when given.
+/// Returns `None` when there was nothing to commit.
+pub fn commit(
+    vault_path: &Path,
+    paths: Option<&[String]>,
+    message: &str,
+) -> Result<Option<CommitInfo>> {
+    let (repo, prefix) = open(vault_path)?;
+    let mut index = repo.index().context("Failed to read git index")?;
+    let specs: Vec<String> = match paths {
+        Some(paths) => paths.iter().map(|p| repo_path(&prefix, p)).collect(),
+        None if prefix.is_empty() => vec!["*".to_string()],
+        None => vec![format!("{}/*", prefix)],
+    };
+    // add_all picks up new and changed files, update_all deletions
+    index
+        .add_all(&specs, IndexAddOption::DEFAULT, None)
+        .context("Failed to stage changes")?;
+    index
+        .update_all(&specs, None)
+        .context("Failed to stage deletions")?;
+    index.write().context("Failed to write git index")?;
+    let tree_id = index.write_tree().context("Failed to write git tree")?;
+
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(None);
+    }
+    let tree = repo.find_tree(tree_id)?;
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("Synapse", "synapse@localhost"))?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let id = repo
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .context("Failed to commit")?;
+    let info = commit_info(&repo.find_commit(id)?);
+    Ok(Some(info))
+}
+
+/// Commits that changed the note at `relative_path`, newest first
+pub fn history_for_note(
+    vault_path: &Path,
+    relative_path: &str,
+    limit: usize,
+) -> Result<Vec<CommitInfo>> {
+    let (repo, prefix) = open(vault_path)?;
+    let path = PathBuf::from(repo_path(&prefix, relative_path));
+    if repo.head().is_err() {
+        // No commits yet
+        return Ok(Vec::new());
+    }
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.set_sorting(git2::Sort::TIME)?;
+
+    let mut history = Vec::new();
+    for id in walk {
+        if history.len() == limit {
+            break;
+        }
+        let commit = repo.find_commit(id?)?;
+        let blob = |commit: &git2::Commit| {
+            commit
+                .tree()
+                .ok()
+                .and_then(|tree| tree.get_path(&path).ok())
+                .map(|entry| entry.id())
+        };
+        let current = blob(&commit);
+        let previous = commit.parent(0).ok().and_then(|parent| blob(&parent));
+        if current.is_some() && current != previous {
+            history.push(commit_info(&commit));
+        }
+    }
+    Ok(history)
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// The repository holding the vault, and the vault's folder within it
+/// (empty when the vault is the repository root)
+fn open(vault_path: &Path) -> Result<(Repository, String)> {
+    let repo = Repository::discover(vault_path).context("The vault is not in a git repository")?;
+    let workdir = repo
+        .workdir()
+        .context("The vault's git repository has no working tree")?
+        .canonicalize()?;
+    let vault = vault_path.canonicalize()?;
+    let prefix = vault
+        .strip_prefix(&workdir)
+        .unwrap_or(Path::new(""))
+        .to_string_lossy()
+        .replace('\\', "/");
+    Ok((repo, prefix))
+}
+
+fn repo_path(prefix: &str, relative_path: &str) -> String {
+    let relative_path = relative_path.replace('\\', "/");
+    if prefix.is_empty() {
+        relative_path
+    } else {
+        format!("{}/{}", prefix, relative_path)
+    }
+}
+
+fn commit_info(commit: &git2::Commit) -> CommitInfo {
+    let time = chrono::DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_default();
+    CommitInfo {
+        id: commit.id().to_string(),
+        summary: commit.summary().unwrap_or_default().to_string(),
+        author: commit.author().name().unwrap_or_default().to_string(),
+        time: time.to_rfc3339(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_commit_and_note_history() {
+        let vault = TestVault::with_notes(&[
+            ("Home.md", "# Home\n"),
+            ("Other.md", "# Other\n"),
+            (".synapse/cache.db", "not for history"),
+        ]);
+        assert!(init_vault(vault.path()).unwrap());
+        assert!(!init_vault(vault.path()).unwrap());
+
+        let first = commit(vault.path(), None, "Initial").unwrap().unwrap();
+        assert_eq!(first.summary, "Initial");
+        assert!(commit(vault.path(), None, "Again").unwrap().is_none());
+
+        vault.write("Home.md", "# Home\nEdited\n");
+        vault.write("Other.md", "# Other\nEdited\n");
+        commit(
+            vault.path(),
+            Some(&["Home.md".to_string()]),
+            "Update Home.md",
+        )
+        .unwrap()
+        .unwrap();
+
+        let history = history_for_note(vault.path(), "Home.md", 10).unwrap();
+        let summaries: Vec<&str> = history.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Update Home.md", "Initial"]);
+        assert_eq!(
+            history_for_note(vault.path(), "Other.md", 10)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let repo = Repository::open(vault.path()).unwrap();
+        let tree = repo.head().unwrap().peel_to_tree().unwrap();
+        assert!(tree.get_path(Path::new(".synapse/cache.db")).is_err());
+    }
+}
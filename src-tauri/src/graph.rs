@@ -0,0 +1,481 @@
+//! Link graph analytics. Notes are the nodes and resolved wikilinks the
+//! edges; a note linking to another several times, or to itself, adds no
+//! weight. Metrics let the graph view size nodes and list the most central
+//! notes, clusters color groups of related notes, and local graphs show
+//! one note's neighbourhood.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::database::Database;
+
+/// PageRank damping: the chance of following a link rather than jumping
+const DAMPING: f64 = 0.85;
+const PAGERANK_ITERATIONS: usize = 100;
+const PAGERANK_TOLERANCE: f64 = 1e-10;
+/// Betweenness is exact up to this many notes, then estimated from
+/// shortest paths out of this many evenly spread ones
+const BETWEENNESS_SOURCES: usize = 200;
+/// Label propagation rounds before settling for what it has
+const CLUSTER_ROUNDS: usize = 100;
+
+/// The link graph between notes
+pub struct Graph {
+    /// (path, title) by node index
+    nodes: Vec<(String, String)>,
+    /// Linked notes by node, each list sorted and deduplicated
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+    /// Link targets without a note, by node, as first written
+    unresolved: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeMetrics {
+    pub path: String,
+    pub title: String,
+    /// Notes linking here
+    pub in_degree: usize,
+    /// Notes linked from here
+    pub out_degree: usize,
+    /// Share of time a reader following links at random spends here; sums
+    /// to 1 over the vault
+    pub pagerank: f64,
+    /// Share of shortest link paths between other notes passing through
+    /// here (0 to 1)
+    pub betweenness: f64,
+}
+
+/// A note's group of closely linked notes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteCluster {
+    pub path: String,
+    /// 0 for the largest cluster, 1 for the next, ...
+    pub cluster: usize,
+    /// Notes in the cluster
+    pub size: usize,
+}
+
+/// The notes within a few links of one note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalGraph {
+    pub nodes: Vec<LocalNode>,
+    /// (source id, target id)
+    pub edges: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalNode {
+    /// The note's path, or the link target for a phantom
+    pub id: String,
+    pub title: String,
+    /// Links away from the center note
+    pub depth: usize,
+    /// A link target with no note behind it
+    pub phantom: bool,
+}
+
+impl Graph {
+    /// The graph of the notes `keep` accepts
+    pub fn load(db: &Database, keep: impl Fn(&str) -> bool) -> Result<Graph> {
+        let mut nodes = Vec::new();
+        let mut index = HashMap::new();
+        for note in db.get_all_notes()? {
+            if keep(&note.path) {
+                index.insert(note.path.clone(), nodes.len());
+                nodes.push((note.path, note.title));
+            }
+        }
+        let mut outgoing = vec![Vec::new(); nodes.len()];
+        let mut incoming = vec![Vec::new(); nodes.len()];
+        let mut unresolved: Vec<Vec<String>> = vec![Vec::new(); nodes.len()];
+        for link in db.get_resolved_links()? {
+            let Some(&from) = index.get(&link.source) else {
+                continue;
+            };
+            match link.path.as_ref().map(|path| index.get(path)) {
+                Some(Some(&to)) if from != to => {
+                    outgoing[from].push(to);
+                    incoming[to].push(from);
+                }
+                None => {
+                    let target = link.target.trim();
+                    if !unresolved[from]
+                        .iter()
+                        .any(|t| t.eq_ignore_ascii_case(target))
+                    {
+                        unresolved[from].push(target.to_string());
+                    }
+                }
+                // Links to self or to a note left out
+                Some(_) => {}
+            }
+        }
+        for edges in outgoing.iter_mut().chain(incoming.iter_mut()) {
+            edges.sort_unstable();
+            edges.dedup();
+        }
+        Ok(Graph {
+            nodes,
+            outgoing,
+            incoming,
+            unresolved,
+        })
+    }
+
+    /// Notes up to `depth` links away from `path`, following links either
+    /// way, with the links between them. Unresolved targets of the notes
+    /// inside the radius appear as phantom nodes one step further out.
+    /// Nodes come nearest first. None if `path` isn't in the graph.
+    pub fn local(&self, path: &str, depth: usize) -> Option<LocalGraph> {
+        let center = self.nodes.iter().position(|(p, _)| p == path)?;
+        let mut depths: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        depths[center] = Some(0);
+        let mut order = vec![center];
+        let mut queue = VecDeque::from([center]);
+        while let Some(v) = queue.pop_front() {
+            let d = depths[v].unwrap_or_default();
+            if d == depth {
+                continue;
+            }
+            for &w in self.outgoing[v].iter().chain(&self.incoming[v]) {
+                if depths[w].is_none() {
+                    depths[w] = Some(d + 1);
+                    order.push(w);
+                    queue.push_back(w);
+                }
+            }
+        }
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut phantoms: HashMap<String, usize> = HashMap::new();
+        for &v in &order {
+            let (path, title) = &self.nodes[v];
+            let d = depths[v].unwrap_or_default();
+            nodes.push(LocalNode {
+                id: path.clone(),
+                title: title.clone(),
+                depth: d,
+                phantom: false,
+            });
+            for &w in &self.outgoing[v] {
+                if depths[w].is_some() {
+                    edges.push((path.clone(), self.nodes[w].0.clone()));
+                }
+            }
+            if d == depth {
+                continue;
+            }
+            for target in &self.unresolved[v] {
+                let id = match phantoms.get(&target.to_lowercase()) {
+                    Some(&i) => nodes[i].id.clone(),
+                    None => {
+                        phantoms.insert(target.to_lowercase(), nodes.len());
+                        nodes.push(LocalNode {
+                            id: target.clone(),
+                            title: target.clone(),
+                            depth: d + 1,
+                            phantom: true,
+                        });
+                        target.clone()
+                    }
+                };
+                edges.push((path.clone(), id));
+            }
+        }
+        nodes.sort_by(|a, b| (a.depth, a.phantom, &a.id).cmp(&(b.depth, b.phantom, &b.id)));
+        edges.sort();
+        Some(LocalGraph { nodes, edges })
+    }
+
+    /// Degree, PageRank and betweenness of every note, most central
+    /// (by PageRank) first
+    pub fn metrics(&self) -> Vec<NodeMetrics> {
+        let pagerank = self.pagerank();
+        let betweenness = self.betweenness();
+        let mut metrics: Vec<NodeMetrics> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (path, title))| NodeMetrics {
+                path: path.clone(),
+                title: title.clone(),
+                in_degree: self.incoming[i].len(),
+                out_degree: self.outgoing[i].len(),
+                pagerank: pagerank[i],
+                betweenness: betweenness[i],
+            })
+            .collect();
+        metrics.sort_by(|a, b| {
+            b.pagerank
+                .total_cmp(&a.pagerank)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        metrics
+    }
+
+    /// Group notes by label propagation, ignoring link direction: each note
+    /// takes the label most of its neighbours have until no label changes.
+    /// Notes without links are clusters of their own. Sorted by cluster,
+    /// then path.
+    pub fn clusters(&self) -> Vec<NoteCluster> {
+        let n = self.nodes.len();
+        // Visit and label by path so the result doesn't depend on note order
+        let mut by_path: Vec<usize> = (0..n).collect();
+        by_path.sort_by(|&a, &b| self.nodes[a].0.cmp(&self.nodes[b].0));
+        let mut labels = vec![0; n];
+        for (label, &v) in by_path.iter().enumerate() {
+            labels[v] = label;
+        }
+
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for _ in 0..CLUSTER_ROUNDS {
+            let mut changed = false;
+            for &v in &by_path {
+                counts.clear();
+                for &w in self.outgoing[v].iter().chain(&self.incoming[v]) {
+                    *counts.entry(labels[w]).or_default() += 1;
+                }
+                let Some(&most) = counts.values().max() else {
+                    continue;
+                };
+                // Keep the current label on a tie, else take the smallest
+                if counts.get(&labels[v]) == Some(&most) {
+                    continue;
+                }
+                let best = counts
+                    .iter()
+                    .filter(|&(_, &count)| count == most)
+                    .map(|(&label, _)| label)
+                    .min()
+                    .unwrap_or(labels[v]);
+                labels[v] = best;
+                changed = true;
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // Number clusters by size, largest first
+        let mut sizes: HashMap<usize, usize> = HashMap::new();
+        for &label in &labels {
+            *sizes.entry(label).or_default() += 1;
+        }
+        let mut ranked: Vec<(usize, usize)> = sizes.iter().map(|(&l, &s)| (l, s)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let ids: HashMap<usize, usize> = ranked
+            .iter()
+            .enumerate()
+            .map(|(id, &(label, _))| (label, id))
+            .collect();
+
+        let mut clusters: Vec<NoteCluster> = (0..n)
+            .map(|v| NoteCluster {
+                path: self.nodes[v].0.clone(),
+                cluster: ids[&labels[v]],
+                size: sizes[&labels[v]],
+            })
+            .collect();
+        clusters.sort_by(|a, b| (a.cluster, &a.path).cmp(&(b.cluster, &b.path)));
+        clusters
+    }
+
+    /// Power iteration; notes without outgoing links spread their rank
+    /// over every note
+    fn pagerank(&self) -> Vec<f64> {
+        let n = self.nodes.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let mut rank = vec![1.0 / n as f64; n];
+        for _ in 0..PAGERANK_ITERATIONS {
+            let dangling: f64 = (0..n)
+                .filter(|&i| self.outgoing[i].is_empty())
+                .map(|i| rank[i])
+                .sum();
+            let base = (1.0 - DAMPING) / n as f64 + DAMPING * dangling / n as f64;
+            let next: Vec<f64> = (0..n)
+                .map(|i| {
+                    base + DAMPING
+                        * self.incoming[i]
+                            .iter()
+                            .map(|&j| rank[j] / self.outgoing[j].len() as f64)
+                            .sum::<f64>()
+                })
+                .collect();
+            let change: f64 = next.iter().zip(&rank).map(|(a, b)| (a - b).abs()).sum();
+            rank = next;
+            if change < PAGERANK_TOLERANCE {
+                break;
+            }
+        }
+        rank
+    }
+
+    /// Brandes' algorithm over link direction, from every note or a sample
+    /// of them, normalized by the number of (source, target) pairs
+    fn betweenness(&self) -> Vec<f64> {
+        let n = self.nodes.len();
+        let mut centrality = vec![0.0; n];
+        if n < 3 {
+            return centrality;
+        }
+        let step = n.div_ceil(BETWEENNESS_SOURCES);
+        let mut stack = Vec::with_capacity(n);
+        let mut queue = VecDeque::with_capacity(n);
+        for source in (0..n).step_by(step) {
+            let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+            let mut paths = vec![0.0; n];
+            let mut distance: Vec<Option<usize>> = vec![None; n];
+            paths[source] = 1.0;
+            distance[source] = Some(0);
+            queue.push_back(source);
+            while let Some(v) = queue.pop_front() {
+                stack.push(v);
+                let next = distance[v].map(|d| d + 1);
+                for &w in &self.outgoing[v] {
+                    if distance[w].is_none() {
+                        distance[w] = next;
+                        queue.push_back(w);
+                    }
+                    if distance[w] == next {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+            let mut dependency = vec![0.0; n];
+            while let Some(w) = stack.pop() {
+                for &v in &predecessors[w] {
+                    dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                }
+                if w != source {
+                    centrality[w] += dependency[w];
+                }
+            }
+        }
+        let scale = step as f64 / ((n - 1) * (n - 2)) as f64;
+        centrality.iter_mut().for_each(|c| *c *= scale);
+        centrality
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_metrics_rank_hubs() {
+        let vault = TestVault::with_notes(&[
+            ("A.md", "[[Hub]]"),
+            ("B.md", "[[Hub]] [[Hub]]"),
+            ("C.md", "[[Hub]] [[C]] [[Nowhere]]"),
+            ("Hub.md", "[[D]]"),
+            ("D.md", "End"),
+            ("Private/E.md", "[[Hub]]"),
+        ]);
+        let db = vault.index();
+        let graph = Graph::load(&db, |path| !path.starts_with("Private/")).unwrap();
+        let metrics = graph.metrics();
+
+        let paths: Vec<&str> = metrics.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(paths, vec!["D.md", "Hub.md", "A.md", "B.md", "C.md"]);
+        let total: f64 = metrics.iter().map(|m| m.pagerank).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let hub = &metrics[1];
+        assert_eq!((hub.in_degree, hub.out_degree), (3, 1));
+        // On the paths from A, B and C to D: 3 of the 4 * 3 ordered pairs
+        assert!((hub.betweenness - 0.25).abs() < 1e-12);
+        assert!(metrics
+            .iter()
+            .filter(|m| m.path != "Hub.md")
+            .all(|m| m.betweenness == 0.0));
+    }
+
+    #[test]
+    fn test_local_graph_within_depth() {
+        let vault = TestVault::with_notes(&[
+            ("Center.md", "[[Near]] [[Ghost]]"),
+            ("Near.md", "[[Far]] [[ghost]] [[Center]]"),
+            ("Back.md", "[[Center]]"),
+            ("Far.md", "[[Farther]] [[Lost]]"),
+            ("Farther.md", ""),
+        ]);
+        let db = vault.index();
+        let graph = Graph::load(&db, |_| true).unwrap();
+
+        let local = graph.local("Center.md", 1).unwrap();
+        let nodes: Vec<(&str, usize, bool)> = local
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), n.depth, n.phantom))
+            .collect();
+        assert_eq!(
+            nodes,
+            vec![
+                ("Center.md", 0, false),
+                ("Back.md", 1, false),
+                ("Near.md", 1, false),
+                ("Ghost", 1, true),
+            ]
+        );
+        let edge = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            local.edges,
+            vec![
+                edge("Back.md", "Center.md"),
+                edge("Center.md", "Ghost"),
+                edge("Center.md", "Near.md"),
+                edge("Near.md", "Center.md"),
+            ]
+        );
+
+        let wider = graph.local("Center.md", 2).unwrap();
+        assert!(wider.nodes.iter().any(|n| n.id == "Far.md" && n.depth == 2));
+        assert!(wider.edges.contains(&edge("Near.md", "Ghost")));
+        assert!(!wider
+            .nodes
+            .iter()
+            .any(|n| n.id == "Farther.md" || n.id == "Lost"));
+        assert!(graph.local("Missing.md", 1).is_none());
+    }
+
+    #[test]
+    fn test_clusters_split_loosely_linked_groups() {
+        let vault = TestVault::with_notes(&[
+            ("a1.md", "[[a2]] [[a3]]"),
+            ("a2.md", "[[a3]] [[a4]]"),
+            ("a3.md", "[[a4]]"),
+            ("a4.md", "[[a1]] [[b1]]"),
+            ("b1.md", "[[b2]] [[b3]]"),
+            ("b2.md", "[[b3]]"),
+            ("b3.md", "[[b1]]"),
+            ("Alone.md", ""),
+        ]);
+        let db = vault.index();
+        let clusters = Graph::load(&db, |_| true).unwrap().clusters();
+
+        let found: Vec<(&str, usize, usize)> = clusters
+            .iter()
+            .map(|c| (c.path.as_str(), c.cluster, c.size))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("a1.md", 0, 4),
+                ("a2.md", 0, 4),
+                ("a3.md", 0, 4),
+                ("a4.md", 0, 4),
+                ("b1.md", 1, 3),
+                ("b2.md", 1, 3),
+                ("b3.md", 1, 3),
+                ("Alone.md", 2, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Vault health check. Reports problems in the notes themselves (broken
+//! links, missing frontmatter, clashing titles, files that aren't UTF-8)
+//! and places where the cache has drifted from the files on disk, each with
+//! a suggested fix for the frontend to offer.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::database::Database;
+use crate::excalidraw;
+use crate::indexer;
+use crate::vault::Vault;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// A wikilink to a note that doesn't exist
+    BrokenLink,
+    /// A `[[Note#Heading]]` link to a heading the note doesn't have
+    BrokenHeadingLink,
+    /// A note without a frontmatter block
+    MissingFrontmatter,
+    /// Notes sharing a title, so links to it pick one of them
+    DuplicateTitle,
+    /// A file that can't be read as UTF-8, so it isn't indexed
+    InvalidUtf8,
+    /// A file on disk the cache doesn't know
+    NotIndexed,
+    /// A cache entry for a file that is gone
+    StaleIndex,
+    /// A cache entry built from an older version of the file
+    OutdatedIndex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthIssue {
+    pub kind: IssueKind,
+    /// Vault path of the file with the problem
+    pub path: String,
+    pub detail: String,
+    /// What would fix it
+    pub suggestion: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Files looked at
+    pub files_checked: usize,
+    /// By kind, then path
+    pub issues: Vec<HealthIssue>,
+}
+
+/// Check every indexable file in the vault against its cache entry
+pub fn check(vault_path: &Path, db: &Database) -> Result<HealthReport> {
+    let mut issues = Vec::new();
+    let mut issue = |kind, path: &str, detail: String, suggestion: String| {
+        issues.push(HealthIssue {
+            kind,
+            path: path.to_string(),
+            detail,
+            suggestion,
+        })
+    };
+
+    let cached = db.get_file_states()?;
+    let mut on_disk = HashSet::new();
+    let mut files = Vault::list_notes(vault_path)?;
+    files.extend(Vault::list_drawings(vault_path)?);
+    files.extend(Vault::list_canvases(vault_path)?);
+    files.extend(Vault::list_csv_files(vault_path)?);
+    for entry in &files {
+        on_disk.insert(entry.path.as_str());
+        let bytes = Vault::read_bytes(vault_path, &entry.path)?;
+        let Ok(content) = String::from_utf8(bytes) else {
+            issue(
+                IssueKind::InvalidUtf8,
+                &entry.path,
+                "The file isn't valid UTF-8 text".to_string(),
+                "Re-save it as UTF-8 so it can be indexed".to_string(),
+            );
+            continue;
+        };
+        match cached.get(&entry.path) {
+            None => issue(
+                IssueKind::NotIndexed,
+                &entry.path,
+                "The file isn't in the cache".to_string(),
+                "Reindex the vault".to_string(),
+            ),
+            Some((Some(hash), _)) if *hash != indexer::content_hash(&content) => issue(
+                IssueKind::OutdatedIndex,
+                &entry.path,
+                "The cache was built from an older version of the file".to_string(),
+                "Reindex the vault".to_string(),
+            ),
+            _ => {}
+        }
+        if entry.path.ends_with(".md")
+            && !excalidraw::is_drawing(&entry.path)
+            && !has_frontmatter(&content)
+        {
+            issue(
+                IssueKind::MissingFrontmatter,
+                &entry.path,
+                "The note has no frontmatter".to_string(),
+                "Add a frontmatter block with its title and tags".to_string(),
+            );
+        }
+    }
+
+    let mut stale: Vec<&String> = cached
+        .keys()
+        .filter(|path| !on_disk.contains(path.as_str()))
+        .collect();
+    stale.sort();
+    for path in stale {
+        issue(
+            IssueKind::StaleIndex,
+            path,
+            "The cache lists a file that no longer exists".to_string(),
+            "Reindex the vault".to_string(),
+        );
+    }
+
+    for link in db.get_unresolved_links()? {
+        for source in &link.sources {
+            issue(
+                IssueKind::BrokenLink,
+                source,
+                format!("[[{}]] doesn't match any note", link.target),
+                format!(
+                    "Create a note called \"{}\" or point the link elsewhere",
+                    link.target
+                ),
+            );
+        }
+    }
+
+    for broken in db.get_broken_heading_links()? {
+        let link = &broken.link;
+        issue(
+            IssueKind::BrokenHeadingLink,
+            &broken.source,
+            format!(
+                "[[{}#{}]] on line {} doesn't match a heading in {}",
+                link.target, link.heading, link.line, broken.path
+            ),
+            "Point the link at an existing heading".to_string(),
+        );
+    }
+
+    let mut by_title: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for note in db.get_all_notes()? {
+        if note.path.ends_with(".md") && !excalidraw::is_drawing(&note.path) {
+            by_title
+                .entry(note.title.to_lowercase())
+                .or_default()
+                .push(note.path);
+        }
+    }
+    for paths in by_title.values().filter(|paths| paths.len() > 1) {
+        for path in paths {
+            let others: Vec<&str> = paths
+                .iter()
+                .filter(|other| *other != path)
+                .map(String::as_str)
+                .collect();
+            issue(
+                IssueKind::DuplicateTitle,
+                path,
+                format!("Shares its title with {}", others.join(", ")),
+                "Rename one of the notes or link to it by path".to_string(),
+            );
+        }
+    }
+
+    issues.sort_by(|a, b| (a.kind, &a.path).cmp(&(b.kind, &b.path)));
+    Ok(HealthReport {
+        files_checked: files.len(),
+        issues,
+    })
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// A `---` block opening the file and closed again
+fn has_frontmatter(content: &str) -> bool {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return false;
+    };
+    rest.starts_with("---") || rest.contains("\n---")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_check_reports_note_and_cache_problems() {
+        let vault = TestVault::with_notes(&[
+            ("Home.md", "---\ntags: [start]\n---\n[[Plan]] [[Nowhere]]\n"),
+            ("Projects/Plan.md", "---\ntitle: Plan\n---\nBody\n"),
+            ("Archive/Plan.md", "Old notes\n"),
+            ("Gone.md", "---\n---\n"),
+        ]);
+        let db = vault.index();
+        std::fs::write(vault.path().join("Binary.md"), [0xff, 0xfe, 0x00]).unwrap();
+        vault.write("Late.md", "---\n---\nNew\n");
+        vault.write(
+            "Home.md",
+            "---\ntags: [start]\n---\n[[Plan]] [[Nowhere]] edited\n",
+        );
+        vault.remove("Gone.md");
+
+        let report = check(vault.path(), &db).unwrap();
+        assert_eq!(report.files_checked, 5);
+        let found: Vec<(IssueKind, &str)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.kind, issue.path.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (IssueKind::BrokenLink, "Home.md"),
+                (IssueKind::MissingFrontmatter, "Archive/Plan.md"),
+                (IssueKind::DuplicateTitle, "Archive/Plan.md"),
+                (IssueKind::DuplicateTitle, "Projects/Plan.md"),
+                (IssueKind::InvalidUtf8, "Binary.md"),
+                (IssueKind::NotIndexed, "Late.md"),
+                (IssueKind::StaleIndex, "Gone.md"),
+                (IssueKind::OutdatedIndex, "Home.md"),
+            ]
+        );
+        assert_eq!(
+            report.issues[0].detail,
+            "[[Nowhere]] doesn't match any note"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_global_shortcut::{
+    Builder, GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState,
+};
+
+use crate::commands::AppState;
+use crate::keybindings::{self, Keybinding};
+
+/// Install the global shortcut plugin and register the open vault's
+/// global bindings
+pub fn init<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    app.plugin(Builder::new().with_handler(handle_shortcut).build())?;
+    apply(app);
+    Ok(())
+}
+
+/// Re-register global shortcuts after the bindings or the vault change
+pub fn apply<R: Runtime>(app: &AppHandle<R>) {
+    let shortcuts = app.global_shortcut();
+    if let Err(e) = shortcuts.unregister_all() {
+        log::warn!("Failed to clear global shortcuts: {}", e);
+    }
+    for binding in global_bindings(app) {
+        let Some(text) = &binding.shortcut else {
+            continue;
+        };
+        let result = parse(text).and_then(|s| shortcuts.register(s).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::warn!(
+                "Failed to register global shortcut '{}' for {}: {}",
+                text,
+                binding.action,
+                e
+            );
+        }
+    }
+}
+
+/// Parse an accelerator such as `CommandOrControl+Shift+Space`
+pub fn parse(shortcut: &str) -> Result<Shortcut, String> {
+    shortcut
+        .parse()
+        .map_err(|e| format!("Invalid shortcut '{}': {}", shortcut, e))
+}
+
+fn global_bindings<R: Runtime>(app: &AppHandle<R>) -> Vec<Keybinding> {
+    let Ok(db) = app.state::<AppState>().database() else {
+        return Vec::new();
+    };
+    match keybindings::load(&db) {
+        Ok(bindings) => bindings.into_iter().filter(|b| b.global).collect(),
+        Err(e) => {
+            log::warn!("Failed to load keybindings: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn handle_shortcut<R: Runtime>(app: &AppHandle<R>, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state() != ShortcutState::Pressed {
+        return;
+    }
+    let action = global_bindings(app).into_iter().find(|b| {
+        b.shortcut
+            .as_deref()
+            .and_then(|s| parse(s).ok())
+            .is_some_and(|s| s == *shortcut)
+    });
+    let result = match action.as_ref().map(|b| b.action.as_str()) {
+        Some(keybindings::QUICK_CAPTURE) => crate::tray::open_quick_capture(app),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        log::warn!("Global shortcut action failed: {}", e);
+    }
+}
@@ -1,5 +1,4 @@
-use pulldown_cmark::{Event, Parser, Tag, TagEnd};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Extracted metadata from a markdown file
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -14,16 +13,109 @@ pub struct NoteIndex {
     pub tags: Vec<String>,
     /// All headings in the note (for outline + section links)
     pub headings: Vec<Heading>,
+    /// Block ids (`^block-id`) in the note
+    pub blocks: Vec<BlockRef>,
+    /// ```mermaid diagrams in the note
+    pub diagrams: Vec<crate::mermaid::MermaidBlock>,
+    /// `$...$` / `$$...$$` math spans
+    pub math: Vec<crate::math::MathBlock>,
+    /// `> [!type]` callouts
+    pub callouts: Vec<Callout>,
+    /// `- [ ]` / `- [x]` checklist items
+    pub tasks: Vec<Task>,
+    /// `[[Note#Heading]]` links into sections
+    pub section_links: Vec<SectionLink>,
     /// Word count of the body
     pub word_count: usize,
 }
 
+/// A fenced code block. Lines are 1-based and include the fences.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CodeBlock {
+    /// Info string after the fence (`rust`, `mermaid`, ...); empty when unlabelled
+    pub language: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub content: String,
+    /// False when the file ends before the closing fence
+    pub closed: bool,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Heading {
     pub text: String,
     pub level: u8,
     /// Line number (1-based) where this heading appears
     pub line: usize,
+    /// GitHub-style anchor, unique within the note (`intro`, `intro-1`, ...)
+    #[serde(default)]
+    pub slug: String,
+}
+
+/// A heading with its nested subheadings (see `build_outline`)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OutlineNode {
+    pub text: String,
+    pub level: u8,
+    /// Line number (1-based) where this heading appears
+    pub line: usize,
+    /// Last line (1-based) of the section: just before the next heading of the
+    /// same or a higher level, or the last line of the note
+    pub end_line: usize,
+    /// Anchor for the heading, as in `Heading::slug`
+    pub slug: String,
+    /// Words between this heading and the next heading of any level
+    pub word_count: usize,
+    /// Words in the whole section, subsections included
+    pub total_word_count: usize,
+    pub children: Vec<OutlineNode>,
+}
+
+/// A wikilink into a section of a note (`[[Note#Heading]]`, `[[#Heading]]`)
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SectionLink {
+    /// Target note as written; empty for a link within the same note
+    pub target: String,
+    /// The heading named: the last part of a nested `#A#B` subpath
+    pub heading: String,
+    /// Line number (1-based)
+    pub line: usize,
+}
+
+/// A block id marker (`^block-id` at the end of a line)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BlockRef {
+    pub id: String,
+    /// Line number (1-based) of the block's last line
+    pub line: usize,
+}
+
+/// A callout (`> [!type] Title`) and the blockquote lines it spans
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Callout {
+    /// Lowercased type: `note`, `warning`, `todo`, ...
+    pub kind: String,
+    /// Title text after the marker (empty when the default title is used)
+    pub title: String,
+    /// `+` (expanded) or `-` (collapsed) for foldable callouts
+    pub fold: Option<char>,
+    /// 1-based, inclusive
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// A checklist item (`- [ ] text` or `- [x] text`)
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Task {
+    /// Text after the checkbox
+    pub text: String,
+    pub completed: bool,
+    /// Line number (1-based)
+    pub line: usize,
+    /// `YYYY-MM-DD` from a `📅 2024-06-01` or `due: 2024-06-01` annotation
+    pub due: Option<String>,
+    /// The heading the task sits under
+    pub heading: Option<String>,
 }
 
 /// Extract all wikilinks from markdown content
@@ -33,45 +125,43 @@ pub fn extract_wikilinks(content: &str) -> Vec<String> {
     let mut chars = content.chars().peekable();
 
     while let Some(c) = chars.next() {
-        if c == '[' {
-            if chars.peek() == Some(&'[') {
-                chars.next(); // consume second [
-                let mut link_text = String::new();
-                let mut depth = 1;
-
-                for c2 in chars.by_ref() {
-                    if c2 == ']' {
-                        depth -= 1;
-                        if depth == 0 {
-                            // Consume the second ]
-                            chars.next();
-                            break;
-                        }
-                    } else if c2 == '[' {
-                        depth += 1;
-                    } else {
-                        link_text.push(c2);
+        if c == '[' && chars.peek() == Some(&'[') {
+            chars.next(); // consume second [
+            let mut link_text = String::new();
+            let mut depth = 1;
+
+            for c2 in chars.by_ref() {
+                if c2 == ']' {
+                    depth -= 1;
+                    if depth == 0 {
+                        // Consume the second ]
+                        chars.next();
+                        break;
                     }
+                } else if c2 == '[' {
+                    depth += 1;
+                } else {
+                    link_text.push(c2);
                 }
+            }
 
-                if !link_text.is_empty() {
-                    // Extract just the note name (before | # or ^)
-                    let note_name = link_text
-                        .split('|')
-                        .next()
-                        .unwrap_or(&link_text)
-                        .split('#')
-                        .next()
-                        .unwrap_or(&link_text)
-                        .split('^')
-                        .next()
-                        .unwrap_or(&link_text)
-                        .trim()
-                        .to_string();
-
-                    if !note_name.is_empty() {
-                        links.push(note_name);
-                    }
+            if !link_text.is_empty() {
+                // Extract just the note name (before | # or ^)
+                let note_name = link_text
+                    .split('|')
+                    .next()
+                    .unwrap_or(&link_text)
+                    .split('#')
+                    .next()
+                    .unwrap_or(&link_text)
+                    .split('^')
+                    .next()
+                    .unwrap_or(&link_text)
+                    .trim()
+                    .to_string();
+
+                if !note_name.is_empty() {
+                    links.push(note_name);
                 }
             }
         }
@@ -80,6 +170,168 @@ pub fn extract_wikilinks(content: &str) -> Vec<String> {
     links
 }
 
+/// A wikilink occurrence with its byte span in the content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    /// Byte offset of the opening `[[` (or `!` for embeds)
+    pub start: usize,
+    /// Byte offset just past the closing `]]`
+    pub end: usize,
+    /// `![[...]]` embed rather than a plain link
+    pub embed: bool,
+    /// Target note name (may be empty for same-note links like `[[#Heading]]`)
+    pub target: String,
+    /// Text after `#` (a heading, or `^block-id`), or a bare `^block-id`
+    pub subpath: Option<String>,
+    /// Display text after `|`
+    pub alias: Option<String>,
+}
+
+impl WikiLink {
+    /// Render back to `[[target#subpath|alias]]` form
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.embed {
+            out.push('!');
+        }
+        out.push_str("[[");
+        out.push_str(&self.target);
+        if let Some(sub) = &self.subpath {
+            if !sub.starts_with('^') || !self.target.is_empty() {
+                out.push('#');
+            }
+            out.push_str(sub);
+        }
+        if let Some(alias) = &self.alias {
+            out.push('|');
+            out.push_str(alias);
+        }
+        out.push_str("]]");
+        out
+    }
+}
+
+/// Find every `[[...]]` / `![[...]]` in the content, with byte spans
+pub fn parse_wikilinks(content: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut search_from = 0;
+
+    while let Some(rel) = content[search_from..].find("[[") {
+        let open = search_from + rel;
+        let inner_start = open + 2;
+        let Some(close_rel) = content[inner_start..].find("]]") else {
+            break;
+        };
+        let inner = &content[inner_start..inner_start + close_rel];
+        let end = inner_start + close_rel + 2;
+        search_from = end;
+        if inner.is_empty() || inner.contains('\n') || inner.contains("[[") {
+            search_from = inner_start;
+            continue;
+        }
+
+        let embed = open > 0 && content.as_bytes()[open - 1] == b'!';
+        let (target_part, alias) = match inner.split_once('|') {
+            Some((t, a)) => (t, Some(a.to_string())),
+            None => (inner, None),
+        };
+        let (target, subpath) = match target_part.split_once('#') {
+            Some((t, sub)) => (t, Some(sub.to_string())),
+            None => match target_part.split_once('^') {
+                Some((t, block)) => (t, Some(format!("^{}", block))),
+                None => (target_part, None),
+            },
+        };
+
+        links.push(WikiLink {
+            start: if embed { open - 1 } else { open },
+            end,
+            embed,
+            target: target.trim().to_string(),
+            subpath,
+            alias,
+        });
+    }
+
+    links
+}
+
+/// Wikilinks that point at a heading, with the line they are on. Block
+/// references (`[[Note#^id]]`) aren't included.
+pub fn extract_section_links(content: &str) -> Vec<SectionLink> {
+    let mut line = 1;
+    let mut counted = 0;
+    let mut links = Vec::new();
+    for link in parse_wikilinks(content) {
+        line += content[counted..link.start].matches('\n').count();
+        counted = link.start;
+        let Some(subpath) = &link.subpath else {
+            continue;
+        };
+        let heading = subpath.rsplit('#').next().unwrap_or(subpath).trim();
+        if heading.is_empty() || heading.starts_with('^') {
+            continue;
+        }
+        links.push(SectionLink {
+            target: link.target.clone(),
+            heading: heading.to_string(),
+            line,
+        });
+    }
+    links
+}
+
+/// Local destinations of Markdown links and images (`[text](dest)`,
+/// `![alt](<dest with spaces>)`), percent-decoded and without any
+/// `#fragment`. URLs and in-note anchors are skipped.
+pub fn extract_markdown_links(content: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("](") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find([')', '\n']) else {
+            break;
+        };
+        let raw = rest[..end].trim();
+        rest = &rest[end..];
+        let dest = match raw.strip_prefix('<') {
+            Some(bracketed) => bracketed.split('>').next().unwrap_or_default(),
+            // A title may follow the destination: `(image.png "Title")`
+            None => raw.split_whitespace().next().unwrap_or_default(),
+        };
+        if dest.is_empty() || dest.starts_with('#') || dest.contains(':') {
+            continue;
+        }
+        let dest = dest.split('#').next().unwrap_or(dest);
+        links.push(percent_decode(dest));
+    }
+    links
+}
+
+/// Decode `%XX` escapes in a link destination
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
 /// Extract all tags from markdown content (#tag, #nested/tag)
 pub fn extract_tags(content: &str) -> Vec<String> {
     let mut tags = HashSet::new();
@@ -158,30 +410,467 @@ pub fn extract_tags(content: &str) -> Vec<String> {
 }
 
 /// Extract all headings from markdown content
+/// (ATX headings only; skips frontmatter and fenced code blocks)
 pub fn extract_headings(content: &str) -> Vec<Heading> {
     let mut headings = Vec::new();
+    let mut in_frontmatter = content.starts_with("---");
+    let mut in_code_block = false;
+    let mut slugger = Slugger::default();
 
     // Count line numbers
     for (line_num, line) in content.lines().enumerate() {
         let trimmed = line.trim_start();
-        if trimmed.starts_with('#') {
-            let level = trimmed.chars().take_while(|c| *c == '#').count();
-            if level >= 1 && level <= 6 {
-                let text = trimmed[level..].trim().to_string();
-                if !text.is_empty() {
-                    headings.push(Heading {
-                        text,
-                        level: level as u8,
-                        line: line_num + 1,
-                    });
-                }
+        if in_frontmatter {
+            if line_num > 0 && line.trim_end() == "---" {
+                in_frontmatter = false;
             }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if let Some((level, text)) = parse_heading_line(line) {
+            headings.push(Heading {
+                slug: slugger.slug(&text),
+                text,
+                level,
+                line: line_num + 1,
+            });
         }
     }
 
     headings
 }
 
+/// Find the heading an anchor points at: an exact slug first, then the
+/// anchor slugified (`#My Heading`), then a case-insensitive text match
+pub fn find_heading<'a>(headings: &'a [Heading], anchor: &str) -> Option<&'a Heading> {
+    let anchor = anchor.trim().trim_start_matches('#');
+    let slugged = heading_slug(anchor);
+    let lower = anchor.to_lowercase();
+    headings
+        .iter()
+        .find(|h| h.slug == anchor)
+        .or_else(|| headings.iter().find(|h| h.slug == slugged))
+        .or_else(|| headings.iter().find(|h| h.text.to_lowercase() == lower))
+}
+
+/// Headings whose text changed between two versions of a note, as
+/// `(before, after)` pairs. Only an edit that keeps the same headings at
+/// the same levels counts as renaming; adding or removing one doesn't.
+pub fn heading_renames(before: &[Heading], after: &[Heading]) -> Vec<(Heading, Heading)> {
+    if before.len() != after.len() || before.iter().zip(after).any(|(b, a)| b.level != a.level) {
+        return Vec::new();
+    }
+    before
+        .iter()
+        .zip(after)
+        .filter(|(b, a)| b.text != a.text && !a.text.trim().is_empty())
+        .map(|(b, a)| (b.clone(), a.clone()))
+        .collect()
+}
+
+/// GitHub-compatible anchor for heading text: lowercased, punctuation dropped,
+/// spaces turned into hyphens
+pub fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Hands out unique slugs within one document, suffixing repeats with `-1`, `-2`, ...
+#[derive(Default)]
+pub struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn slug(&mut self, text: &str) -> String {
+        let base = heading_slug(text);
+        let mut slug = base.clone();
+        while self.seen.contains_key(&slug) {
+            let count = self.seen.get_mut(&base).expect("base slug seen first");
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+/// Build the nested heading tree for a note. A heading becomes a child of the
+/// closest preceding heading with a lower level, so skipped levels (H1 → H3)
+/// still nest and a document starting at H2 simply has H2 roots.
+pub fn build_outline(content: &str) -> Vec<OutlineNode> {
+    let headings = extract_headings(content);
+    let line_words = words_per_line(content);
+    let words_between = |from: usize, to: usize| -> usize {
+        line_words[from.min(line_words.len())..to.min(line_words.len())]
+            .iter()
+            .sum()
+    };
+
+    let mut roots: Vec<OutlineNode> = Vec::new();
+    let mut stack: Vec<OutlineNode> = Vec::new();
+    fn attach(node: OutlineNode, stack: &mut [OutlineNode], roots: &mut Vec<OutlineNode>) {
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+
+    for (i, heading) in headings.iter().enumerate() {
+        // Lines are 1-based; the section body runs up to the next heading of any level
+        let next_line = headings
+            .get(i + 1)
+            .map_or(line_words.len() + 1, |h| h.line);
+        // ...while the whole section runs up to the next heading that doesn't nest under it
+        let end_line = headings[i + 1..]
+            .iter()
+            .find(|h| h.level <= heading.level)
+            .map_or(line_words.len(), |h| h.line - 1);
+        let node = OutlineNode {
+            text: heading.text.clone(),
+            level: heading.level,
+            line: heading.line,
+            end_line,
+            slug: heading.slug.clone(),
+            word_count: words_between(heading.line, next_line - 1),
+            total_word_count: 0,
+            children: Vec::new(),
+        };
+        while stack.last().is_some_and(|top| top.level >= node.level) {
+            let done = stack.pop().expect("non-empty stack");
+            attach(done, &mut stack, &mut roots);
+        }
+        stack.push(node);
+    }
+    while let Some(done) = stack.pop() {
+        attach(done, &mut stack, &mut roots);
+    }
+
+    fn fill_totals(node: &mut OutlineNode) -> usize {
+        let children: usize = node.children.iter_mut().map(fill_totals).sum();
+        node.total_word_count = node.word_count + children;
+        node.total_word_count
+    }
+    roots.iter_mut().for_each(|node| {
+        fill_totals(node);
+    });
+    roots
+}
+
+/// Word count of every line (0-based index), with frontmatter, code and heading lines counting 0
+fn words_per_line(content: &str) -> Vec<usize> {
+    let mut in_frontmatter = content.starts_with("---");
+    let mut in_code_block = false;
+    content
+        .lines()
+        .enumerate()
+        .map(|(line_num, line)| {
+            let trimmed = line.trim();
+            if in_frontmatter {
+                if line_num > 0 && trimmed == "---" {
+                    in_frontmatter = false;
+                }
+                return 0;
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+                return 0;
+            }
+            if in_code_block || parse_heading_line(line).is_some() {
+                return 0;
+            }
+            trimmed.split_whitespace().count()
+        })
+        .collect()
+}
+
+/// Parse a single ATX heading line (`## Text`), returning its level and text
+pub fn parse_heading_line(line: &str) -> Option<(u8, String)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    // `#tag` is a tag, not a heading
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let text = rest.trim().trim_end_matches('#').trim_end().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some((level as u8, text))
+    }
+}
+
+/// Extract callouts, including nested ones (`> > [!tip]`), skipping
+/// frontmatter and fenced code
+pub fn extract_callouts(content: &str) -> Vec<Callout> {
+    let mut callouts: Vec<Callout> = Vec::new();
+    // Indexes into `callouts` of the open callouts, with their quote depth
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut in_frontmatter = false;
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if i == 0 && trimmed == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            in_frontmatter = trimmed != "---";
+            continue;
+        }
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            open.clear();
+            continue;
+        }
+
+        let (depth, rest) = quote_depth(trimmed);
+        open.retain(|&(_, d)| d <= depth);
+        for &(idx, _) in &open {
+            callouts[idx].end_line = i + 1;
+        }
+        // Only the first line of a quote level can start a callout
+        let starts_level = depth > 0 && open.last().map_or(true, |&(_, d)| d < depth);
+        if let Some((kind, fold, title)) = parse_callout_marker(rest).filter(|_| starts_level) {
+            open.push((callouts.len(), depth));
+            callouts.push(Callout {
+                kind,
+                title,
+                fold,
+                start_line: i + 1,
+                end_line: i + 1,
+            });
+        }
+    }
+    callouts
+}
+
+/// Number of leading `>` markers and the text after them
+fn quote_depth(line: &str) -> (usize, &str) {
+    let mut depth = 0;
+    let mut rest = line;
+    while let Some(r) = rest.strip_prefix('>') {
+        depth += 1;
+        rest = r.trim_start();
+    }
+    (depth, rest)
+}
+
+/// Parse `[!type]`, `[!type]-` or `[!type]+ Title` at the start of quoted text
+fn parse_callout_marker(text: &str) -> Option<(String, Option<char>, String)> {
+    let inner = text.strip_prefix("[!")?;
+    let close = inner.find(']')?;
+    let kind = inner[..close].trim().to_lowercase();
+    if kind.is_empty() || kind.contains(char::is_whitespace) {
+        return None;
+    }
+    let mut rest = &inner[close + 1..];
+    let fold = rest.chars().next().filter(|c| *c == '+' || *c == '-');
+    if fold.is_some() {
+        rest = &rest[1..];
+    }
+    Some((kind, fold, rest.trim().to_string()))
+}
+
+/// Extract block ids (` ^block-id` at the end of a line), skipping code blocks
+pub fn extract_block_ids(content: &str) -> Vec<BlockRef> {
+    let mut blocks = Vec::new();
+    let mut in_code_block = false;
+
+    for (line_num, line) in content.lines().enumerate() {
+        let trimmed = line.trim_end();
+        if trimmed.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if let Some(id) = parse_block_id(trimmed) {
+            blocks.push(BlockRef {
+                id: id.to_string(),
+                line: line_num + 1,
+            });
+        }
+    }
+
+    blocks
+}
+
+/// Get the trailing `^block-id` from a line, if present
+pub fn parse_block_id(line: &str) -> Option<&str> {
+    let trimmed = line.trim_end();
+    let caret = trimmed.rfind('^')?;
+    let id = &trimmed[caret + 1..];
+    let preceded_ok = caret == 0 || trimmed[..caret].ends_with(char::is_whitespace);
+    if preceded_ok
+        && !id.is_empty()
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// Extract checklist items with their due dates and headings, skipping
+/// frontmatter and fenced code
+pub fn extract_tasks(content: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut heading = None;
+    let mut in_frontmatter = false;
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if i == 0 && trimmed == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            in_frontmatter = trimmed != "---";
+            continue;
+        }
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        if let Some((_, text)) = parse_heading_line(line) {
+            heading = Some(text);
+        } else if let Some((completed, text)) = parse_task_line(line) {
+            tasks.push(Task {
+                text: text.trim().to_string(),
+                completed,
+                line: i + 1,
+                due: task_due_date(text),
+                heading: heading.clone(),
+            });
+        }
+    }
+    tasks
+}
+
+/// Whether a line is a checklist item, whether it's checked, and the text
+/// after its checkbox
+pub fn parse_task_line(line: &str) -> Option<(bool, &str)> {
+    let trimmed = line.trim_start();
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = if digits > 0 {
+        trimmed[digits..].strip_prefix(". ")?
+    } else {
+        trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .or_else(|| trimmed.strip_prefix("+ "))?
+    };
+    let completed = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = &rest[3..];
+    (text.is_empty() || text.starts_with(char::is_whitespace)).then_some((completed, text))
+}
+
+/// The date after `📅` or `due:` in a task's text
+fn task_due_date(text: &str) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+    let after = if let Some(at) = text.find('📅') {
+        &text[at + '📅'.len_utf8()..]
+    } else {
+        // `due:` at a word boundary; `due::` is the inline-field spelling
+        let at = lower
+            .match_indices("due:")
+            .map(|(at, _)| at)
+            .find(|&at| at == 0 || !lower[..at].ends_with(char::is_alphanumeric))?;
+        text[at + 4..].trim_start_matches(':')
+    };
+    let date = after.trim_start().get(..10)?;
+    let is_date = date.bytes().enumerate().all(|(i, b)| match i {
+        4 | 7 => b == b'-',
+        _ => b.is_ascii_digit(),
+    });
+    is_date.then(|| date.to_string())
+}
+
+/// Extract fenced (``` or ~~~) code blocks
+pub fn extract_code_blocks(content: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut lines = content.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let trimmed = line.trim_start();
+        let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) else {
+            continue;
+        };
+        let language = trimmed[3..]
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_string();
+
+        let mut body = Vec::new();
+        let mut end_line = i + 1;
+        let mut closed = false;
+        for (j, line) in lines.by_ref() {
+            end_line = j + 1;
+            if line.trim_start().starts_with(marker) {
+                closed = true;
+                break;
+            }
+            body.push(line);
+        }
+        blocks.push(CodeBlock {
+            language,
+            start_line: i + 1,
+            end_line,
+            content: body.join("\n"),
+            closed,
+        });
+    }
+    blocks
+}
+
+/// Hex SHA-256 of some text, for spotting identical content across notes
+pub fn content_hash(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
 /// Count words in markdown content (excluding frontmatter and code blocks)
 pub fn count_words(content: &str) -> usize {
     let body = crate::vault::Vault::strip_frontmatter(content);
@@ -205,7 +894,11 @@ pub fn count_words(content: &str) -> usize {
 
 /// Build a complete index for a note
 pub fn index_note(path: &str, content: &str, frontmatter_tags: &[String]) -> NoteIndex {
-    let mut tags = extract_tags(content);
+    // `#` and words inside math are not tags or prose
+    let math = crate::math::extract_math(content);
+    let prose = crate::math::mask_math(content, &math);
+
+    let mut tags = extract_tags(&prose);
     // Merge frontmatter tags (add # prefix if not present)
     for ft in frontmatter_tags {
         let tag = if ft.starts_with('#') {
@@ -238,7 +931,13 @@ pub fn index_note(path: &str, content: &str, frontmatter_tags: &[String]) -> Not
         outgoing_links: extract_wikilinks(content),
         tags,
         headings,
-        word_count: count_words(content),
+        blocks: extract_block_ids(content),
+        diagrams: crate::mermaid::extract_blocks(content),
+        word_count: count_words(&prose),
+        math,
+        callouts: extract_callouts(content),
+        tasks: extract_tasks(content),
+        section_links: extract_section_links(content),
     }
 }
 
@@ -270,4 +969,105 @@ mod tests {
         assert_eq!(headings[0].level, 1);
         assert_eq!(headings[0].text, "Title");
     }
+
+    #[test]
+    fn test_extract_headings_skips_tags_and_code() {
+        let content = "---\ntitle: x\n# yaml comment\n---\n#tag line\n```\n# not a heading\n```\n## Real";
+        let headings = extract_headings(content);
+        assert_eq!(headings.len(), 1);
+        assert_eq!(headings[0].text, "Real");
+        assert_eq!(headings[0].line, 9);
+    }
+
+    #[test]
+    fn test_build_outline_nests_skipped_levels() {
+        let content = "## Intro\none two\n# Top\nthree\n### Deep\nfour five\n```\nnot counted\n```\n## Mid\nsix";
+        let outline = build_outline(content);
+        assert_eq!(outline.len(), 2);
+        assert_eq!(outline[0].text, "Intro");
+        assert_eq!(outline[0].word_count, 2);
+        let top = &outline[1];
+        assert_eq!(top.children.len(), 2);
+        assert_eq!(top.children[0].text, "Deep");
+        assert_eq!(top.children[0].word_count, 2);
+        assert_eq!(top.children[1].text, "Mid");
+        assert_eq!(top.word_count, 1);
+        assert_eq!(top.total_word_count, 4);
+        assert_eq!((outline[0].line, outline[0].end_line), (1, 2));
+        assert_eq!((top.line, top.end_line), (3, 11));
+        assert_eq!((top.children[0].line, top.children[0].end_line), (5, 9));
+        assert_eq!(top.children[1].slug, "mid");
+    }
+
+    #[test]
+    fn test_heading_slugs_are_github_compatible_and_unique() {
+        let content = "# Hello, World!\n## Hello World\n## Hello World\n## Hello World 1\n## C++ & Rust_2";
+        let slugs: Vec<String> = extract_headings(content).into_iter().map(|h| h.slug).collect();
+        assert_eq!(
+            slugs,
+            vec!["hello-world", "hello-world-1", "hello-world-2", "hello-world-1-1", "c--rust_2"]
+        );
+    }
+
+    #[test]
+    fn test_extract_code_blocks() {
+        let content = "Intro\n```rust title=x\nfn main() {}\n```\n~~~\n```not a fence end\n~~~\n```py\nopen";
+        let blocks = extract_code_blocks(content);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].language, "rust");
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (2, 4));
+        assert_eq!(blocks[1].content, "```not a fence end");
+        assert!(blocks[1].closed && !blocks[2].closed);
+    }
+
+    #[test]
+    fn test_extract_callouts() {
+        let content = "> [!todo]- Ship it\n> step one\n> > [!Question] Why?\n> > because\n> [!tip] just text\n> back out\nplain\n> [!not a callout]\n>[!warning]\n";
+        let callouts = extract_callouts(content);
+        assert_eq!(callouts.len(), 3);
+        assert_eq!(
+            (callouts[0].kind.as_str(), callouts[0].fold, callouts[0].title.as_str()),
+            ("todo", Some('-'), "Ship it")
+        );
+        assert_eq!((callouts[0].start_line, callouts[0].end_line), (1, 6));
+        assert_eq!(callouts[1].kind, "question");
+        assert_eq!((callouts[1].start_line, callouts[1].end_line), (3, 4));
+        assert_eq!((callouts[2].kind.as_str(), callouts[2].start_line), ("warning", 9));
+    }
+
+    #[test]
+    fn test_extract_section_links() {
+        let content =
+            "[[Plan#Goals]] [[Plan]]\n[[#Intro|intro]] ![[Plan#^quote]]\n\n[[A#B#Deep]]\n";
+        let links = extract_section_links(content);
+        let found: Vec<(&str, &str, usize)> = links
+            .iter()
+            .map(|l| (l.target.as_str(), l.heading.as_str(), l.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("Plan", "Goals", 1), ("", "Intro", 2), ("A", "Deep", 4)]
+        );
+    }
+
+    #[test]
+    fn test_heading_renames_and_find_heading() {
+        let before = extract_headings("# Plan\n## Goals\n## Risks\n");
+        let after = extract_headings("# Plan\n## Objectives\n## Risks\n");
+        let renames = heading_renames(&before, &after);
+        assert_eq!(renames.len(), 1);
+        assert_eq!(
+            (renames[0].0.text.as_str(), renames[0].1.text.as_str()),
+            ("Goals", "Objectives")
+        );
+        // Adding a heading or changing a level isn't a rename
+        assert!(heading_renames(&before, &extract_headings("# Plan\n## Goals\n")).is_empty());
+        assert!(
+            heading_renames(&before, &extract_headings("# Plan\n### Aims\n## Risks\n")).is_empty()
+        );
+
+        assert_eq!(find_heading(&after, "objectives").unwrap().line, 2);
+        assert_eq!(find_heading(&after, "#RISKS").unwrap().line, 3);
+        assert!(find_heading(&after, "Goals").is_none());
+    }
 }
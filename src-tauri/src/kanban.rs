@@ -0,0 +1,326 @@
+//! Kanban boards stored as markdown, in the layout the Obsidian Kanban
+//! plugin writes: each `##` heading is a column and each top-level list
+//! item under it is a card, with indented lines continuing the card.
+//! Edits move whole card lines and leave everything else in the file
+//! (frontmatter, settings block, blank lines) as it was.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::indexer;
+
+/// A board parsed from a markdown file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KanbanBoard {
+    pub columns: Vec<KanbanColumn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KanbanColumn {
+    pub title: String,
+    /// Line number (1-based) of the column's heading
+    pub line: usize,
+    pub cards: Vec<KanbanCard>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KanbanCard {
+    /// The card's text without its bullet or checkbox; continuation lines
+    /// are joined with newlines
+    pub text: String,
+    /// Whether its checkbox is ticked, or None for a plain list item
+    pub checked: Option<bool>,
+    /// Line number (1-based) where the card starts
+    pub line: usize,
+}
+
+/// Where a column and its cards sit among the file's lines (0-based)
+struct ColumnSpan {
+    heading: usize,
+    /// One past the column's last non-blank line
+    end: usize,
+    /// Start and end (exclusive) of each card
+    cards: Vec<(usize, usize)>,
+}
+
+/// Parse the columns and cards of a board
+pub fn parse(content: &str) -> KanbanBoard {
+    let lines: Vec<&str> = content.lines().collect();
+    let columns = spans(&lines)
+        .into_iter()
+        .map(|span| KanbanColumn {
+            title: heading_title(lines[span.heading])
+                .unwrap_or_default()
+                .to_string(),
+            line: span.heading + 1,
+            cards: span
+                .cards
+                .iter()
+                .map(|&(start, end)| card(&lines[start..end], start))
+                .collect(),
+        })
+        .collect();
+    KanbanBoard { columns }
+}
+
+/// Move a card (0-based indices) to `position` in another column, or
+/// elsewhere in its own. Positions past the end put it last.
+pub fn move_card(
+    content: &str,
+    from_column: usize,
+    card: usize,
+    to_column: usize,
+    position: usize,
+) -> Result<String> {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let columns = spans(&as_strs(&lines));
+    let Some(column) = columns.get(from_column) else {
+        bail!(
+            "Column {} is out of range ({} columns)",
+            from_column,
+            columns.len()
+        );
+    };
+    if to_column >= columns.len() {
+        bail!(
+            "Column {} is out of range ({} columns)",
+            to_column,
+            columns.len()
+        );
+    }
+    let Some(&(start, end)) = column.cards.get(card) else {
+        bail!(
+            "Card {} is out of range ({} cards)",
+            card,
+            column.cards.len()
+        );
+    };
+
+    let moved: Vec<String> = lines.drain(start..end).collect();
+    let columns = spans(&as_strs(&lines));
+    insert_card(&mut lines, &columns[to_column], position, moved);
+    Ok(join(content, &lines))
+}
+
+/// Add a card with an empty checkbox to a column, last unless a position
+/// is given. Lines after the first of `text` are indented under it.
+pub fn add_card(
+    content: &str,
+    column: usize,
+    text: &str,
+    position: Option<usize>,
+) -> Result<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("A card needs some text");
+    }
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let columns = spans(&as_strs(&lines));
+    let Some(span) = columns.get(column) else {
+        bail!(
+            "Column {} is out of range ({} columns)",
+            column,
+            columns.len()
+        );
+    };
+
+    let mut card = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        card.push(if i == 0 {
+            format!("- [ ] {}", line)
+        } else {
+            format!("  {}", line.trim_start())
+        });
+    }
+    insert_card(&mut lines, span, position.unwrap_or(usize::MAX), card);
+    Ok(join(content, &lines))
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+fn spans(lines: &[&str]) -> Vec<ColumnSpan> {
+    let mut columns: Vec<ColumnSpan> = Vec::new();
+    let mut in_fence = false;
+    let mut i = body_start(lines);
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            if let Some(column) = columns.last_mut() {
+                column.end = i + 1;
+            }
+            i += 1;
+            continue;
+        }
+        if heading_title(line).is_some() {
+            columns.push(ColumnSpan {
+                heading: i,
+                end: i + 1,
+                cards: Vec::new(),
+            });
+            i += 1;
+            continue;
+        }
+        if ends_board(line) {
+            break;
+        }
+        let Some(column) = columns.last_mut() else {
+            i += 1;
+            continue;
+        };
+        if is_card(line) {
+            let start = i;
+            i += 1;
+            while i < lines.len() && is_continuation(lines[i]) {
+                i += 1;
+            }
+            column.cards.push((start, i));
+            column.end = i;
+            continue;
+        }
+        if !line.trim().is_empty() {
+            column.end = i + 1;
+        }
+        i += 1;
+    }
+    columns
+}
+
+/// Index of the first line after any frontmatter
+fn body_start(lines: &[&str]) -> usize {
+    if lines.first().map(|l| l.trim_end()) != Some("---") {
+        return 0;
+    }
+    lines
+        .iter()
+        .skip(1)
+        .position(|l| l.trim_end() == "---")
+        .map_or(0, |end| end + 2)
+}
+
+/// The title of a `##` column heading
+fn heading_title(line: &str) -> Option<&str> {
+    let title = line.strip_prefix("## ")?.trim();
+    (!title.is_empty()).then(|| title.trim_end_matches('#').trim_end())
+}
+
+/// The plugin's settings block and archive divider close off the columns
+fn ends_board(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with("%% kanban:settings") || trimmed == "***"
+}
+
+fn is_card(line: &str) -> bool {
+    ["- ", "* ", "+ "]
+        .iter()
+        .any(|bullet| line.starts_with(bullet))
+        || line.trim() == "-"
+}
+
+fn is_continuation(line: &str) -> bool {
+    line.starts_with([' ', '\t']) && !line.trim().is_empty()
+}
+
+fn card(lines: &[&str], start: usize) -> KanbanCard {
+    let first = lines[0];
+    let (checked, text) = match indexer::parse_task_line(first) {
+        Some((done, rest)) => (Some(done), rest.trim()),
+        None => (None, first.get(2..).unwrap_or("").trim()),
+    };
+    let mut text = text.to_string();
+    for line in &lines[1..] {
+        text.push('\n');
+        text.push_str(line.trim());
+    }
+    KanbanCard {
+        text,
+        checked,
+        line: start + 1,
+    }
+}
+
+/// Put a card's lines at `position` in a column. An empty column gets it
+/// after its heading (and any text under it), set off by a blank line.
+fn insert_card(lines: &mut Vec<String>, column: &ColumnSpan, position: usize, card: Vec<String>) {
+    let at = match column.cards.get(position) {
+        Some(&(start, _)) => start,
+        None => column.cards.last().map_or(column.end, |&(_, end)| end),
+    };
+    let mut card = card;
+    if at == column.heading + 1 {
+        card.insert(0, String::new());
+        if lines.get(at).is_some_and(|l| !l.trim().is_empty()) {
+            card.push(String::new());
+        }
+    }
+    lines.splice(at..at, card);
+}
+
+fn as_strs(lines: &[String]) -> Vec<&str> {
+    lines.iter().map(String::as_str).collect()
+}
+
+/// Join edited lines back up with the original's line ending
+fn join(original: &str, lines: &[String]) -> String {
+    let ending = if original.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut out = lines.join(ending);
+    if original.ends_with('\n') || original.is_empty() {
+        out.push_str(ending);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = "---\nkanban-plugin: basic\n---\n\n## To do\n\n- [ ] Write docs\n  with examples\n- [ ] Fix sync\n\n## Doing\n\n\n## Done\n\n**Complete**\n- [x] Ship beta\n\n%% kanban:settings\n```\n{\"kanban-plugin\":\"basic\"}\n```\n%%\n";
+
+    #[test]
+    fn test_parse_board() {
+        let board = parse(BOARD);
+        let titles: Vec<&str> = board.columns.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["To do", "Doing", "Done"]);
+        let todo = &board.columns[0].cards;
+        assert_eq!(todo.len(), 2);
+        assert_eq!(todo[0].text, "Write docs\nwith examples");
+        assert_eq!((todo[0].checked, todo[0].line), (Some(false), 7));
+        assert!(board.columns[1].cards.is_empty());
+        assert_eq!(board.columns[2].cards[0].checked, Some(true));
+    }
+
+    #[test]
+    fn test_move_and_add_cards() {
+        let moved = move_card(BOARD, 0, 0, 1, 0).unwrap();
+        assert!(moved.contains(
+            "- [ ] Fix sync\n\n## Doing\n\n- [ ] Write docs\n  with examples\n\n\n## Done"
+        ));
+        assert!(moved.ends_with("```\n%%\n"));
+
+        let moved = move_card(&moved, 2, 0, 0, 0).unwrap();
+        let board = parse(&moved);
+        assert_eq!(board.columns[0].cards[0].text, "Ship beta");
+        assert_eq!(board.columns[0].cards[1].text, "Fix sync");
+        assert!(board.columns[2].cards.is_empty());
+        assert!(moved.contains("**Complete**\n\n%% kanban:settings"));
+
+        let reordered = move_card(BOARD, 0, 0, 0, 9).unwrap();
+        assert!(reordered.contains("- [ ] Fix sync\n- [ ] Write docs\n  with examples\n\n## Doing"));
+
+        let added = add_card(BOARD, 2, "Release\nnotes", None).unwrap();
+        assert!(added.contains("- [x] Ship beta\n- [ ] Release\n  notes\n\n%%"));
+        let added = add_card(BOARD, 0, "Triage", Some(1)).unwrap();
+        assert_eq!(parse(&added).columns[0].cards[1].text, "Triage");
+
+        assert!(move_card(BOARD, 1, 0, 0, 0).is_err());
+        assert!(move_card(BOARD, 0, 0, 3, 0).is_err());
+        assert!(add_card(BOARD, 0, "  ", None).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::database::Database;
+
+/// Summon the quick-capture window
+pub const QUICK_CAPTURE: &str = "quick-capture";
+
+/// Actions with a built-in shortcut, as (action, default, global). Global
+/// shortcuts are registered with the OS and fire while Synapse is unfocused;
+/// the rest are handled by the frontend, which may store bindings for any
+/// action of its own.
+const DEFAULTS: &[(&str, &str, bool)] = &[(QUICK_CAPTURE, "CommandOrControl+Shift+Space", true)];
+
+/// An action's effective shortcut
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keybinding {
+    pub action: String,
+    /// `None` when the user unbound it
+    pub shortcut: Option<String>,
+    pub default: Option<String>,
+    pub global: bool,
+}
+
+/// Whether the action's shortcut is registered with the OS
+pub fn is_global(action: &str) -> bool {
+    DEFAULTS
+        .iter()
+        .any(|(name, _, global)| *name == action && *global)
+}
+
+/// Built-in defaults overlaid with the vault's customised shortcuts
+pub fn load(db: &Database) -> Result<Vec<Keybinding>> {
+    Ok(merge(db.get_keybindings()?))
+}
+
+fn merge(overrides: Vec<(String, String)>) -> Vec<Keybinding> {
+    let mut overrides: HashMap<String, String> = overrides.into_iter().collect();
+    let mut bindings: Vec<Keybinding> = DEFAULTS
+        .iter()
+        .map(|(action, default, global)| {
+            let shortcut = overrides
+                .remove(*action)
+                .unwrap_or_else(|| default.to_string());
+            Keybinding {
+                action: action.to_string(),
+                shortcut: Some(shortcut).filter(|s| !s.is_empty()),
+                default: Some(default.to_string()),
+                global: *global,
+            }
+        })
+        .collect();
+    let mut custom: Vec<Keybinding> = overrides
+        .into_iter()
+        .map(|(action, shortcut)| Keybinding {
+            action,
+            shortcut: Some(shortcut).filter(|s| !s.is_empty()),
+            default: None,
+            global: false,
+        })
+        .collect();
+    custom.sort_by(|a, b| a.action.cmp(&b.action));
+    bindings.extend(custom);
+    bindings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keybindings() {
+        let bindings = merge(vec![
+            ("toggle-sidebar".to_string(), "Mod+\\".to_string()),
+            (QUICK_CAPTURE.to_string(), String::new()),
+        ]);
+        assert_eq!(bindings[0].action, QUICK_CAPTURE);
+        assert_eq!(bindings[0].shortcut, None);
+        assert!(bindings[0].global);
+        assert_eq!(bindings[1].shortcut.as_deref(), Some("Mod+\\"));
+        assert_eq!(bindings[1].default, None);
+
+        let defaults = merge(Vec::new());
+        assert_eq!(
+            defaults[0].shortcut.as_deref(),
+            Some("CommandOrControl+Shift+Space")
+        );
+        assert!(!is_global("toggle-sidebar"));
+    }
+}
@@ -1,86 +1,392 @@
+mod attachments;
+mod backup;
+mod benchmark;
+mod canvas;
+mod capture;
+mod citations;
 mod commands;
+mod credentials;
+mod crypto;
+mod csv_file;
 mod database;
+mod deep_link;
+mod duplicates;
+mod editing;
+mod embeddings;
+mod embeds;
+mod error;
+mod excalidraw;
+mod export;
 mod file_manager;
+mod formatter;
+#[cfg(feature = "git")]
+mod git;
+mod graph;
+mod health;
+#[cfg(desktop)]
+mod hotkeys;
 mod indexer;
+mod kanban;
+mod keybindings;
+mod logs;
+mod math;
+mod migrations;
+mod mermaid;
+mod os_search;
+mod outliner;
+mod pdf;
+mod properties;
+mod publish;
+mod query;
+mod redact;
+mod related;
+mod secrets;
+mod spellcheck;
+mod sync;
+#[cfg(test)]
+mod test_support;
+#[cfg(desktop)]
+mod tray;
 mod vault;
+mod watcher;
+mod web_clip;
 
 use commands::AppState;
 use database::Database;
+use deep_link::LaunchLink;
+use tauri::{Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
 use vault::Vault;
 
+/// How often the auto-lock timer checks for idleness
+const AUTO_LOCK_POLL_SECS: u64 = 15;
+/// How often the backup schedule is checked
+const BACKUP_POLL_SECS: u64 = 300;
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Check if a vault is already configured
-    let (vault_path, db) = match Vault::get_vault_path() {
-        Ok(Some(path)) => {
-            // Try to initialize database for existing vault
-            match Database::init_for_vault(&path) {
-                Ok(db) => {
-                    // Reindex on startup to catch any external changes
-                    let _ = db.reindex_vault(&path);
-                    (Some(path), Some(db))
-                }
-                Err(e) => {
-                    log::warn!("Failed to initialize database for vault: {}", e);
-                    (None, None)
-                }
-            }
-        }
-        _ => (None, None),
-    };
+    let app_state = AppState::default();
 
-    let app_state = AppState {
-        vault_path: std::sync::Mutex::new(vault_path),
-        db: std::sync::Mutex::new(db),
-    };
+    let mut builder = tauri::Builder::default();
+    // Must come first: a second launch (a `synapse://` link or "Open with"
+    // on Windows/Linux) hands its arguments to this instance and exits
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            let _ = tray::show_main(app);
+            let files = deep_link::files_from_args(&argv, std::path::Path::new(&cwd));
+            deep_link::handle_files(app, &files, false);
+        }));
+    }
 
-    tauri::Builder::default()
+    builder
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_fs::init())
         .manage(app_state)
+        .manage(LaunchLink::default())
         .setup(|app| {
+            // Log to the open vault's `.synapse/logs` (and the terminal in
+            // dev builds). The level is set per vault once one is open.
+            let mut logger = tauri_plugin_log::Builder::default()
+                .clear_targets()
+                .target(logs::target())
+                .format(logs::format)
+                .level(log::LevelFilter::Trace);
             if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
+                logger = logger.target(tauri_plugin_log::Target::new(
+                    tauri_plugin_log::TargetKind::Stdout,
+                ));
+            }
+            app.handle().plugin(logger.build())?;
+            log::set_max_level(logs::DEFAULT_LEVEL);
+
+            // Mobile has no home directory; keep config in the app's own dir
+            #[cfg(mobile)]
+            Vault::set_config_dir(app.path().app_config_dir()?);
+
+            // Open the configured vault, reindexing to catch external changes
+            // (or rebuilding its cache if it is corrupt).
+            // A vault with a lock passphrase starts locked.
+            if let Ok(Some(path)) = Vault::get_vault_path() {
+                match Database::open_or_recover(&path) {
+                    Ok((db, recovered)) => {
+                        if recovered.is_none() {
+                            let _ = db.reindex_vault(&path);
+                        }
+                        let state = app.state::<AppState>();
+                        let db = state.install_vault(path.clone(), db);
+                        state.watch_vault(app.handle(), &path, db.clone());
+                        // A corrupt cache was replaced by an empty one
+                        if let Some(backup) = recovered {
+                            commands::reindex_recovered(app.handle(), path, db, backup);
+                        }
+                    }
+                    Err(e) => log::warn!("Failed to initialize database for vault: {}", e),
+                }
+            }
+
+            #[cfg(desktop)]
+            {
+                tray::init(app.handle())?;
+                hotkeys::init(app.handle())?;
             }
+
+            // `synapse://open?...` links from OS search stubs. The bundler
+            // registers the scheme; dev builds on Windows/Linux do it here.
+            #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+            if let Err(e) = app.deep_link().register_all() {
+                log::warn!("Failed to register URI scheme: {}", e);
+            }
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                deep_link::handle_urls(app.handle(), &urls, true);
+            }
+            // A Markdown file we were launched to open (macOS delivers these
+            // as `file://` URLs through the deep-link handler instead)
+            #[cfg(any(windows, target_os = "linux"))]
+            {
+                let args: Vec<String> = std::env::args().collect();
+                let files = deep_link::files_from_args(&args, &std::env::current_dir()?);
+                deep_link::handle_files(app.handle(), &files, true);
+            }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                deep_link::handle_urls(&handle, &event.urls(), false);
+            });
+
+            // Auto-lock the vault after the configured idle time
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(std::time::Duration::from_secs(AUTO_LOCK_POLL_SECS));
+                if handle.state::<AppState>().auto_lock_if_idle() {
+                    let _ = handle.emit("vault-locked", ());
+                }
+            });
+
+            // Back up to S3 when a schedule is set and a backup is due
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(BACKUP_POLL_SECS)).await;
+                    commands::run_scheduled_backup(&handle).await;
+                }
+            });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             // Vault
             commands::get_vault_path,
+            commands::default_vault_location,
             commands::create_vault,
             commands::open_vault,
+            commands::get_vault_cache_info,
+            commands::rebuild_vault_cache,
+            commands::run_db_maintenance,
+            commands::dump_index_debug,
+            commands::get_db_tuning,
+            commands::set_db_tuning,
             // File explorer
             commands::list_vault_entries,
             commands::create_note,
             commands::create_folder,
             commands::read_note,
             commands::save_note,
+            commands::append_to_note,
+            commands::insert_at,
+            commands::extract_to_note,
+            commands::split_note,
+            commands::merge_notes,
+            commands::generate_toc,
+            commands::shift_headings,
+            commands::list_footnotes,
+            commands::add_footnote,
+            commands::renumber_footnotes,
+            commands::format_note,
+            commands::format_all,
+            commands::resolve_url_title,
+            commands::clip_url,
+            commands::read_canvas,
+            commands::save_canvas,
+            commands::list_csv_files,
+            commands::read_csv,
+            commands::write_csv,
+            commands::update_csv_cell,
+            commands::read_board,
+            commands::move_card,
+            commands::add_card,
+            commands::get_callouts,
+            commands::get_tasks,
+            commands::toggle_task,
+            commands::get_note_math,
+            commands::get_diagrams,
+            commands::validate_mermaid,
+            commands::read_excalidraw,
+            commands::create_excalidraw,
+            commands::export_excalidraw_png,
+            commands::search_drawings,
             commands::delete_entry,
             commands::rename_entry,
             commands::duplicate_entry,
+            // Archive
+            commands::archive_note,
+            commands::auto_archive,
             // Notes metadata
             commands::get_all_notes,
             commands::toggle_star,
+            commands::pin_note,
+            commands::unpin_note,
+            commands::reorder_pins,
+            commands::set_frontmatter_property,
+            commands::remove_frontmatter_property,
+            // Recent notes
+            commands::record_note_opened,
+            commands::get_recent_notes,
+            commands::get_note_open_stats,
             // Links & backlinks
             commands::get_backlinks,
             commands::get_outgoing_links,
             commands::get_all_links,
+            commands::get_graph_metrics,
+            commands::get_graph_clusters,
+            commands::get_local_graph,
+            commands::get_related_notes,
+            commands::get_unresolved_links,
+            commands::get_broken_heading_links,
+            commands::get_orphan_notes,
+            commands::check_vault_health,
+            commands::find_duplicate_notes,
             commands::search_notes,
+            commands::search_fulltext,
+            commands::semantic_search,
+            commands::download_embedding_model,
+            commands::evaluate_embedded_queries,
+            commands::query_notes,
+            commands::query_properties,
+            commands::suggest_link_targets,
             // Tags
             commands::get_all_tags,
             commands::get_notes_by_tag,
-            // Headings
+            commands::rename_tag,
+            commands::save_search,
+            commands::list_saved_searches,
+            commands::run_saved_search,
+            commands::delete_saved_search,
+            commands::get_random_note,
+            commands::get_stale_notes,
+            // Capture
+            commands::quick_capture,
+            commands::open_daily_note,
+            commands::receive_shared,
+            // Private folders
+            commands::list_private_folders,
+            commands::set_folder_private,
+            // Attachments
+            commands::set_secure_attachments_folder,
+            commands::import_attachment,
+            commands::import_attachment_bytes,
+            commands::get_note_attachments,
+            commands::find_orphaned_attachments,
+            commands::delete_orphaned_attachments,
+            commands::read_binary,
+            // Credentials
+            commands::set_credential,
+            commands::delete_credential,
+            commands::has_credential,
+            // Secret detection
+            commands::scan_for_secrets,
+            // Export redaction
+            commands::get_redaction_rules,
+            commands::set_redaction_rules,
+            commands::preview_redaction,
+            commands::export_graph,
+            commands::export_note_html,
+            commands::export_pdf,
+            commands::publish_site,
+            // Vault lock
+            commands::set_lock_passphrase,
+            commands::lock_vault,
+            commands::unlock_vault,
+            commands::is_vault_locked,
+            commands::set_auto_lock,
+            commands::record_activity,
+            // Encryption
+            commands::encrypt_note,
+            commands::decrypt_note,
+            commands::is_note_encrypted,
+            commands::get_vault_encryption,
+            commands::enable_vault_encryption,
+            commands::disable_vault_encryption,
+            commands::unlock_vault_encryption,
+            commands::lock_vault_encryption,
+            commands::set_vault_index_scope,
+            // Snippets
+            commands::set_snippet_mode,
+            commands::search_snippets,
+            commands::get_snippet_languages,
+            // Headings & blocks
             commands::get_headings,
+            commands::get_outline,
+            commands::resolve_anchor,
+            commands::get_blocks,
+            commands::get_link_targets,
+            commands::resolve_embeds,
+            // OS search
+            commands::set_os_search_index,
+            commands::rebuild_os_search_index,
+            commands::take_launch_link,
             // Settings
             commands::get_setting,
             commands::set_setting,
+            // Keybindings
+            commands::get_keybindings,
+            commands::set_keybinding,
+            // Bookmarks
+            commands::add_bookmark,
+            commands::get_bookmarks,
+            commands::rename_bookmark,
+            commands::move_bookmark,
+            commands::delete_bookmark,
+            // Workspaces
+            commands::save_workspace,
+            commands::load_workspace,
+            commands::list_workspaces,
+            commands::delete_workspace,
+            commands::save_last_session,
+            commands::load_last_session,
+            // Spellcheck
+            commands::check_text,
+            commands::add_to_dictionary,
+            // Import
+            commands::import_legacy_notes,
+            commands::import_outliner_graph,
+            // Citations
+            commands::set_bibliography,
+            commands::get_citation,
+            commands::insert_literature_note,
+            // Git
+            #[cfg(feature = "git")]
+            commands::git_init_vault,
+            #[cfg(feature = "git")]
+            commands::git_commit_all,
+            #[cfg(feature = "git")]
+            commands::git_history_for_note,
+            // Backup
+            commands::backup_to_s3,
+            commands::get_backup_config,
+            commands::list_remote_backups,
+            commands::restore_backup,
+            // Logs
+            commands::get_recent_logs,
+            commands::set_log_level,
             // Reindex
             commands::reindex_vault,
+            commands::benchmark_index,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Checkpoint the cache's WAL before quitting
+            if let tauri::RunEvent::Exit = event {
+                app.state::<AppState>().close_vault();
+            }
+        });
 }
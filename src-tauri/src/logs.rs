@@ -0,0 +1,234 @@
+//! Log lines also go to `.synapse/logs/synapse.log` in the open vault, so
+//! indexer warnings and failed saves can be shown in the app or attached to
+//! a bug report. The file rotates at `MAX_LOG_BYTES`, keeping a few older
+//! ones as `synapse.1.log`, `synapse.2.log`...
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::sync::LockExt;
+use crate::vault::Vault;
+
+/// Setting holding the vault's log level (`error` ... `trace`, or `off`)
+pub const LOG_LEVEL_SETTING: &str = "log.level";
+/// Level used until a vault says otherwise
+pub const DEFAULT_LEVEL: log::LevelFilter = log::LevelFilter::Info;
+/// Lines `recent` returns unless asked for another number
+pub const DEFAULT_LIMIT: usize = 200;
+
+const LOG_FILE: &str = "synapse.log";
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const KEEP_ROTATED: usize = 3;
+
+/// The open vault's log file; `None` while no vault is open
+static CURRENT: Mutex<Option<VaultLog>> = Mutex::new(None);
+
+struct VaultLog {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+}
+
+/// One logged line (plus any continuation lines of its message)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Log plugin target writing to the open vault's log
+pub fn target() -> tauri_plugin_log::Target {
+    let dispatch = tauri_plugin_log::fern::Dispatch::new().chain(
+        tauri_plugin_log::fern::Output::call(|record| append(&record.args().to_string())),
+    );
+    tauri_plugin_log::Target::new(tauri_plugin_log::TargetKind::Dispatch(dispatch))
+}
+
+/// Line format for every target, and what `recent` parses back
+pub fn format(
+    out: tauri_plugin_log::fern::FormatCallback,
+    message: &std::fmt::Arguments,
+    record: &log::Record,
+) {
+    out.finish(format_args!(
+        "{} {} {}: {}",
+        chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+        record.level(),
+        record.target(),
+        message
+    ))
+}
+
+/// Start logging to `vault_path`'s log at the given level
+pub fn open_for_vault(vault_path: &Path, level: log::LevelFilter) -> Result<()> {
+    let dir = log_dir(vault_path);
+    let log = VaultLog::open(dir)?;
+    *CURRENT.lock_or_recover() = Some(log);
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Stop writing to the vault's log, e.g. when the vault is closed
+pub fn close() {
+    *CURRENT.lock_or_recover() = None;
+    log::set_max_level(DEFAULT_LEVEL);
+}
+
+pub fn parse_level(level: &str) -> Result<log::LevelFilter> {
+    log::LevelFilter::from_str(level.trim())
+        .with_context(|| format!("Unknown log level: {}", level))
+}
+
+/// The last `limit` entries at `min_level` or more severe, oldest first,
+/// reading back through the rotated files as needed
+pub fn recent(vault_path: &Path, min_level: log::Level, limit: usize) -> Result<Vec<LogEntry>> {
+    let dir = log_dir(vault_path);
+    let mut entries = Vec::new();
+    // Oldest file first, so entries come out in order
+    for n in (0..=KEEP_ROTATED).rev() {
+        let path = dir.join(file_name(n));
+        if !path.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read log: {}", path.display()))?;
+        entries.extend(parse(&content));
+    }
+    entries.retain(|entry: &LogEntry| {
+        log::Level::from_str(&entry.level).is_ok_and(|level| level <= min_level)
+    });
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.split_off(skip))
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+impl VaultLog {
+    fn open(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir).context("Failed to create log folder")?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))
+            .context("Failed to open log file")?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(VaultLog { dir, file, size })
+    }
+
+    /// Shift `synapse.log` to `synapse.1.log` and so on, dropping the oldest
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for n in (1..=KEEP_ROTATED).rev() {
+            let from = self.dir.join(file_name(n - 1));
+            if from.exists() {
+                fs::rename(from, self.dir.join(file_name(n)))?;
+            }
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(LOG_FILE))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn log_dir(vault_path: &Path) -> PathBuf {
+    Vault::db_path(vault_path).with_file_name("logs")
+}
+
+fn file_name(n: usize) -> String {
+    match n {
+        0 => LOG_FILE.to_string(),
+        n => format!("synapse.{}.log", n),
+    }
+}
+
+/// Write a formatted line to the open vault's log. Failures are dropped:
+/// logging them would only come back here.
+fn append(line: &str) {
+    let mut current = CURRENT.lock_or_recover();
+    let Some(log) = current.as_mut() else {
+        return;
+    };
+    let len = line.len() as u64 + 1;
+    if log.size > 0 && log.size + len > MAX_LOG_BYTES && log.rotate().is_err() {
+        return;
+    }
+    if writeln!(log.file, "{}", line).is_ok() {
+        log.size += len;
+    }
+}
+
+/// Split a log file into entries; lines that don't start with a timestamp
+/// continue the previous entry's message
+fn parse(content: &str) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for line in content.lines() {
+        match parse_line(line) {
+            Some(entry) => entries.push(entry),
+            None => {
+                if let Some(last) = entries.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+    entries
+}
+
+fn parse_line(line: &str) -> Option<LogEntry> {
+    let mut parts = line.splitn(3, ' ');
+    let timestamp = parts.next()?;
+    chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+    let level = parts.next()?;
+    let (target, message) = parts.next()?.split_once(": ")?;
+    Some(LogEntry {
+        timestamp: timestamp.to_string(),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_recent_logs_filter_by_level() {
+        let vault = TestVault::new();
+        vault.write(
+            ".synapse/logs/synapse.1.log",
+            "2026-01-02T10:00:00.000+00:00 ERROR synapse::indexer: Failed to index a.md\n",
+        );
+        vault.write(
+            ".synapse/logs/synapse.log",
+            "2026-01-02T11:00:00.000+00:00 INFO synapse: Opened vault\n\
+             2026-01-02T11:00:01.000+00:00 WARN synapse::indexer: Bad frontmatter in b.md:\n\
+             mapping values are not allowed here\n",
+        );
+
+        let warnings = recent(vault.path(), log::Level::Warn, DEFAULT_LIMIT).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].level, "ERROR");
+        assert_eq!(warnings[1].target, "synapse::indexer");
+        assert_eq!(
+            warnings[1].message,
+            "Bad frontmatter in b.md:\nmapping values are not allowed here"
+        );
+
+        let last = recent(vault.path(), log::Level::Trace, 1).unwrap();
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].level, "WARN");
+        assert!(parse_level("verbose").is_err());
+    }
+}
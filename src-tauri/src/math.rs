@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Commands whose first argument names a new macro
+const MACRO_DEFINERS: &[&str] = &[
+    "\\newcommand",
+    "\\renewcommand",
+    "\\providecommand",
+    "\\DeclareMathOperator",
+    "\\def",
+];
+
+/// A `$...$` or `$$...$$` span in a note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MathBlock {
+    /// `$$` display math rather than inline `$`
+    pub display: bool,
+    /// 1-based, inclusive
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Macros defined inside the block (`\R` for `\newcommand{\R}{...}`)
+    pub macros: Vec<String>,
+    /// Byte range of the span, delimiters included
+    #[serde(skip)]
+    pub range: (usize, usize),
+}
+
+/// Find math spans, skipping frontmatter, fenced code, inline code and
+/// escaped `\$`. Inline math follows the pandoc rule: no space just inside
+/// the dollars and no digit right after the closing one, so "$5 and $10"
+/// stays prose. Unclosed `$$` is treated as text.
+pub fn extract_math(content: &str) -> Vec<MathBlock> {
+    let mut blocks = Vec::new();
+    let mut offset = 0;
+    let mut in_frontmatter = false;
+    let mut fence: Option<&str> = None;
+    // Start byte and line of an open `$$` block
+    let mut display: Option<(usize, usize)> = None;
+
+    for (line_idx, line) in content.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len();
+        let line_no = line_idx + 1;
+        let trimmed = line.trim();
+
+        if line_idx == 0 && trimmed == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            in_frontmatter = trimmed != "---";
+            continue;
+        }
+        if display.is_none() {
+            if let Some(marker) = fence {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                continue;
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = Some(&trimmed[..3]);
+                continue;
+            }
+        }
+
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if let Some((start, start_line)) = display {
+                match find_unescaped(line, i, "$$") {
+                    Some(close) => {
+                        let end = line_start + close + 2;
+                        blocks.push(block(content, true, (start, end), start_line, line_no));
+                        display = None;
+                        i = close + 2;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'`' => {
+                    let ticks = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                    let run = &line[i..i + ticks];
+                    i = line[i + ticks..]
+                        .find(run)
+                        .map_or(i + ticks, |p| i + ticks + p + ticks);
+                }
+                b'$' if bytes.get(i + 1) == Some(&b'$') => {
+                    display = Some((line_start + i, line_no));
+                    i += 2;
+                }
+                b'$' => match inline_close(line, i) {
+                    Some(close) => {
+                        let range = (line_start + i, line_start + close + 1);
+                        blocks.push(block(content, false, range, line_no, line_no));
+                        i = close + 1;
+                    }
+                    None => i += 1,
+                },
+                _ => i += 1,
+            }
+        }
+    }
+    blocks
+}
+
+/// Replace math spans with spaces (keeping newlines) so word counts and tag
+/// extraction only see prose
+pub fn mask_math<'a>(content: &'a str, blocks: &[MathBlock]) -> Cow<'a, str> {
+    if blocks.is_empty() {
+        return Cow::Borrowed(content);
+    }
+    let mut bytes = content.as_bytes().to_vec();
+    for b in blocks {
+        for byte in &mut bytes[b.range.0..b.range.1] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    }
+    // Whole spans are replaced, so multi-byte characters are never split
+    Cow::Owned(String::from_utf8(bytes).expect("masking keeps UTF-8 valid"))
+}
+
+fn block(
+    content: &str,
+    display: bool,
+    range: (usize, usize),
+    start_line: usize,
+    end_line: usize,
+) -> MathBlock {
+    MathBlock {
+        display,
+        start_line,
+        end_line,
+        macros: defined_macros(&content[range.0..range.1]),
+        range,
+    }
+}
+
+/// Byte index (within `line`) of the closing `$` for inline math opened at `open`
+fn inline_close(line: &str, open: usize) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let first = *bytes.get(open + 1)?;
+    if first.is_ascii_whitespace() {
+        return None;
+    }
+    let mut i = open + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            // Inline code can't sit inside math; the `$` was literal
+            b'`' => return None,
+            b'$' => {
+                let valid = !bytes[i - 1].is_ascii_whitespace()
+                    && !bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+                return valid.then_some(i);
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn find_unescaped(line: &str, from: usize, pat: &str) -> Option<usize> {
+    let mut i = from;
+    while let Some(pos) = line[i..].find(pat) {
+        let at = i + pos;
+        let backslashes = line[..at].bytes().rev().take_while(|b| *b == b'\\').count();
+        if backslashes % 2 == 0 {
+            return Some(at);
+        }
+        i = at + pat.len();
+    }
+    None
+}
+
+/// Names of the macros a TeX snippet defines
+fn defined_macros(tex: &str) -> Vec<String> {
+    let mut macros = Vec::new();
+    for definer in MACRO_DEFINERS {
+        let mut rest = tex;
+        while let Some(pos) = rest.find(definer) {
+            rest = &rest[pos + definer.len()..];
+            // `\def` must not match `\default`, and so on
+            if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                continue;
+            }
+            let arg = rest
+                .trim_start_matches('*')
+                .trim_start()
+                .trim_start_matches('{');
+            let Some(name) = arg.strip_prefix('\\') else {
+                continue;
+            };
+            let len = name
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(name.len());
+            if len > 0 {
+                let name = format!("\\{}", &name[..len]);
+                if !macros.contains(&name) {
+                    macros.push(name);
+                }
+            }
+        }
+    }
+    macros
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_and_mask_math() {
+        let content = "Costs $5 and $10, see `$x$` and \\$y$.\nInline $a_{#1} + b$ here #real\n$$\n\\newcommand{\\R}{\\mathbb{R}}\n\\def\\eps{\\varepsilon}\n$$\n```\n$$ code $$\n```\n";
+        let blocks = extract_math(content);
+        assert_eq!(blocks.len(), 2);
+        assert!(!blocks[0].display);
+        assert_eq!(blocks[0].start_line, 2);
+        assert!(blocks[1].display);
+        assert_eq!((blocks[1].start_line, blocks[1].end_line), (3, 6));
+        assert_eq!(blocks[1].macros, vec!["\\R", "\\eps"]);
+
+        let masked = mask_math(content, &blocks);
+        assert!(masked.starts_with("Costs $5 and $10"));
+        assert!(!masked.contains("#1") && masked.contains("#real"));
+        assert_eq!(masked.lines().count(), content.lines().count());
+    }
+}
@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+
+/// Diagram declarations mermaid understands (the first keyword of a diagram)
+const DIAGRAM_TYPES: &[&str] = &[
+    "graph",
+    "flowchart",
+    "flowchart-elk",
+    "sequenceDiagram",
+    "classDiagram",
+    "classDiagram-v2",
+    "stateDiagram",
+    "stateDiagram-v2",
+    "erDiagram",
+    "journey",
+    "gantt",
+    "pie",
+    "quadrantChart",
+    "requirementDiagram",
+    "gitGraph",
+    "mindmap",
+    "timeline",
+    "zenuml",
+    "sankey-beta",
+    "xychart-beta",
+    "block-beta",
+    "packet-beta",
+    "architecture-beta",
+    "kanban",
+    "radar-beta",
+    "C4Context",
+    "C4Container",
+    "C4Component",
+    "C4Dynamic",
+    "C4Deployment",
+];
+const FLOWCHART_DIRECTIONS: &[&str] = &["TB", "TD", "BT", "RL", "LR"];
+/// Sequence diagram statements closed by `end`
+const SEQUENCE_BLOCKS: &[&str] = &[
+    "loop", "alt", "opt", "par", "critical", "break", "rect", "box",
+];
+
+/// A ```mermaid block in a note. Lines are 1-based and include the fences.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MermaidBlock {
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Declared diagram type (`flowchart`, `sequenceDiagram`, ...)
+    pub diagram_type: Option<String>,
+    #[serde(skip)]
+    pub closed: bool,
+    #[serde(skip)]
+    pub source: String,
+}
+
+/// A syntax problem found in a diagram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagramError {
+    /// 1-based line in the note
+    pub line: usize,
+    pub message: String,
+}
+
+/// Validation outcome for one diagram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagramCheck {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub diagram_type: Option<String>,
+    pub errors: Vec<DiagramError>,
+}
+
+/// Find every ```mermaid (or ~~~mermaid) block in a note
+pub fn extract_blocks(content: &str) -> Vec<MermaidBlock> {
+    crate::indexer::extract_code_blocks(content)
+        .into_iter()
+        .filter(|block| block.language.eq_ignore_ascii_case("mermaid"))
+        .map(|block| MermaidBlock {
+            start_line: block.start_line,
+            end_line: block.end_line,
+            diagram_type: declaration(&block.content).map(|(_, kind)| kind.to_string()),
+            closed: block.closed,
+            source: block.content,
+        })
+        .collect()
+}
+
+/// Lightweight syntax check: a known diagram declaration, balanced flowchart
+/// node brackets and quotes, and matching `end`s. Not a full parser — it catches the
+/// typos that would make mermaid refuse to render.
+pub fn validate(block: &MermaidBlock) -> DiagramCheck {
+    let mut errors = Vec::new();
+    // Source lines start right after the opening fence
+    let first = block.start_line + 1;
+    let mut error = |offset: usize, message: String| {
+        errors.push(DiagramError {
+            line: first + offset,
+            message,
+        })
+    };
+
+    if !block.closed {
+        error(0, "Diagram code block is never closed".to_string());
+    }
+
+    match declaration(&block.source) {
+        None => error(0, "Diagram is empty".to_string()),
+        Some((offset, keyword)) if !DIAGRAM_TYPES.contains(&keyword) => {
+            error(offset, format!("Unknown diagram type '{}'", keyword))
+        }
+        Some((offset, keyword)) => {
+            let lines: Vec<&str> = block.source.lines().collect();
+            let header: Vec<&str> = lines[offset].split_whitespace().collect();
+            if matches!(keyword, "graph" | "flowchart") {
+                if let Some(dir) = header.get(1) {
+                    if !FLOWCHART_DIRECTIONS.contains(dir) {
+                        error(offset, format!("Unknown flowchart direction '{}'", dir));
+                    }
+                }
+            }
+
+            let flowchart = matches!(keyword, "graph" | "flowchart" | "flowchart-elk");
+            let openers: &[&str] = match keyword {
+                _ if flowchart => &["subgraph"],
+                "sequenceDiagram" => SEQUENCE_BLOCKS,
+                _ => &[],
+            };
+            let mut open: Vec<(usize, &str)> = Vec::new();
+            for (i, line) in lines.iter().enumerate().skip(offset + 1) {
+                let trimmed = line.trim();
+                if trimmed.is_empty() || trimmed.starts_with("%%") {
+                    continue;
+                }
+                // Node shapes must balance on their line; other diagram types
+                // allow free text or multi-line `{ }` bodies
+                if flowchart {
+                    if let Some(message) = check_brackets(trimmed) {
+                        error(i, message);
+                    }
+                }
+                if openers.is_empty() {
+                    continue;
+                }
+                let word = trimmed.split_whitespace().next().unwrap_or("");
+                if openers.contains(&word) {
+                    open.push((i, word));
+                } else if word == "end" && open.pop().is_none() {
+                    error(i, "'end' without a matching block".to_string());
+                }
+            }
+            for (i, word) in open {
+                error(i, format!("'{}' is never closed with 'end'", word));
+            }
+        }
+    }
+
+    DiagramCheck {
+        start_line: block.start_line,
+        end_line: block.end_line,
+        diagram_type: block.diagram_type.clone(),
+        errors,
+    }
+}
+
+/// Line offset and keyword of the diagram declaration, skipping blank lines,
+/// `%%` comments/directives and a `---` config block
+fn declaration(source: &str) -> Option<(usize, &str)> {
+    let mut in_config = false;
+    for (i, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed == "---" {
+            in_config = !in_config;
+            continue;
+        }
+        if in_config || trimmed.is_empty() || trimmed.starts_with("%%") {
+            continue;
+        }
+        return trimmed.split_whitespace().next().map(|kw| (i, kw));
+    }
+    None
+}
+
+/// Report the first unbalanced bracket or unterminated string on a line
+fn check_brackets(line: &str) -> Option<String> {
+    let mut stack: Vec<char> = Vec::new();
+    let mut in_quote = false;
+    for c in line.chars() {
+        if c == '"' {
+            in_quote = !in_quote;
+            continue;
+        }
+        if in_quote {
+            continue;
+        }
+        match c {
+            '(' | '[' | '{' => stack.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if stack.pop() != Some(expected) {
+                    return Some(format!("Unexpected '{}'", c));
+                }
+            }
+            _ => {}
+        }
+    }
+    if in_quote {
+        Some("Unterminated string".to_string())
+    } else {
+        stack.last().map(|c| format!("Unclosed '{}'", c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_and_validate() {
+        let content = "# Diagrams\n```mermaid\n%% comment\nflowchart LR\n  A[Start] --> B(End\")\n  subgraph one\n  C --> D\n```\n\n```rust\nlet x = 1;\n```\n~~~mermaid\nsequenceDiagram\n  loop Every minute\n    A->>B: hi (again\n  end\n  end\n~~~\n";
+        let blocks = extract_blocks(content);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (2, 8));
+        assert_eq!(blocks[1].diagram_type.as_deref(), Some("sequenceDiagram"));
+
+        let errors: Vec<(usize, String)> = validate(&blocks[0])
+            .errors
+            .into_iter()
+            .map(|e| (e.line, e.message))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (5, "Unterminated string".to_string()),
+                (6, "'subgraph' is never closed with 'end'".to_string())
+            ]
+        );
+
+        let lines: Vec<usize> = validate(&blocks[1]).errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![18]);
+    }
+}
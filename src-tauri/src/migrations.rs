@@ -0,0 +1,543 @@
+//! Cache schema upgrades. Migrations run in order from the cache's
+//! recorded `schema_version`, each in its own transaction that also records
+//! the version it reached, so an interrupted upgrade resumes where it
+//! stopped. A new cache runs them all.
+//!
+//! To change the schema, append a migration; never edit one that has
+//! shipped. Steps that might meet caches already carrying their change
+//! (from before versioning) check before altering.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+/// Cache schema this build reads and writes
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+struct Migration {
+    description: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// Migration `i` takes the cache from version `i` to `i + 1`
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "create the cache tables",
+        up: create_tables,
+    },
+    Migration {
+        description: "track file hashes and modification times",
+        up: add_file_state,
+    },
+    Migration {
+        description: "add saved searches",
+        up: add_saved_searches,
+    },
+    Migration {
+        description: "cache frontmatter properties",
+        up: add_properties,
+    },
+    Migration {
+        description: "index tasks",
+        up: add_tasks,
+    },
+    Migration {
+        description: "keep a history of note opens",
+        up: add_history,
+    },
+    Migration {
+        description: "store note embeddings",
+        up: add_embeddings,
+    },
+    Migration {
+        description: "store content signatures for duplicate detection",
+        up: add_content_signatures,
+    },
+    Migration {
+        description: "index bibliography entries and citations",
+        up: add_citations,
+    },
+    Migration {
+        description: "index links into note sections",
+        up: add_section_links,
+    },
+    Migration {
+        description: "keep note chunk text for the sentence model",
+        up: add_chunk_text,
+    },
+];
+
+/// Upgrade a cache at schema `from` to `SCHEMA_VERSION`
+pub fn migrate(conn: &Connection, from: i64) -> Result<()> {
+    for (version, migration) in (1..).zip(MIGRATIONS).skip(from.max(0) as usize) {
+        if from > 0 {
+            log::info!(
+                "Upgrading cache to schema {}: {}",
+                version,
+                migration.description
+            );
+        }
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx).with_context(|| format!("Failed to {}", migration.description))?;
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_info (key, value) VALUES ('schema_version', ?1)",
+            [version.to_string()],
+        )
+        .context("Failed to record schema version")?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+// ─── Migrations ─────────────────────────────────────────────────────────────
+
+fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- Notes metadata cache (mirrors filesystem)
+        CREATE TABLE IF NOT EXISTS notes (
+            path TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            created_at TEXT,
+            modified_at TEXT,
+            word_count INTEGER DEFAULT 0,
+            starred INTEGER DEFAULT 0
+        );
+
+        -- Outgoing links from notes
+        CREATE TABLE IF NOT EXISTS links (
+            source_path TEXT NOT NULL,
+            target_name TEXT NOT NULL,
+            PRIMARY KEY (source_path, target_name),
+            FOREIGN KEY (source_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Tags on notes
+        CREATE TABLE IF NOT EXISTS tags (
+            note_path TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (note_path, tag),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Headings in notes (for outline + section links)
+        CREATE TABLE IF NOT EXISTS headings (
+            note_path TEXT NOT NULL,
+            text TEXT NOT NULL,
+            level INTEGER NOT NULL,
+            line_number INTEGER NOT NULL,
+            slug TEXT NOT NULL DEFAULT '',
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Alternative names from frontmatter `aliases`; `[[Alias]]` links
+        -- to the note
+        CREATE TABLE IF NOT EXISTS aliases (
+            note_path TEXT NOT NULL,
+            alias TEXT NOT NULL,
+            PRIMARY KEY (note_path, alias),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Block ids (`^block-id`) in notes, for block links and embeds
+        CREATE TABLE IF NOT EXISTS blocks (
+            note_path TEXT NOT NULL,
+            block_id TEXT NOT NULL,
+            line_number INTEGER NOT NULL,
+            PRIMARY KEY (note_path, block_id),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Mermaid diagrams in notes, by line range
+        CREATE TABLE IF NOT EXISTS diagrams (
+            note_path TEXT NOT NULL,
+            start_line INTEGER NOT NULL,
+            end_line INTEGER NOT NULL,
+            diagram_type TEXT,
+            PRIMARY KEY (note_path, start_line),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Callouts (`> [!type] Title`) in notes
+        CREATE TABLE IF NOT EXISTS callouts (
+            note_path TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            title TEXT NOT NULL,
+            fold TEXT,
+            start_line INTEGER NOT NULL,
+            end_line INTEGER NOT NULL,
+            PRIMARY KEY (note_path, start_line),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- LaTeX math spans in notes and the macros they define
+        CREATE TABLE IF NOT EXISTS math_blocks (
+            note_path TEXT NOT NULL,
+            start_line INTEGER NOT NULL,
+            end_line INTEGER NOT NULL,
+            display INTEGER NOT NULL,
+            macros TEXT NOT NULL DEFAULT '',
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Fenced code blocks, when snippet indexing is enabled
+        CREATE TABLE IF NOT EXISTS snippets (
+            note_path TEXT NOT NULL,
+            line_number INTEGER NOT NULL,
+            language TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            content TEXT NOT NULL,
+            PRIMARY KEY (note_path, line_number),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Text elements of Excalidraw drawings, searchable apart from note prose
+        CREATE TABLE IF NOT EXISTS drawing_text (
+            note_path TEXT NOT NULL,
+            element_id TEXT NOT NULL,
+            text TEXT NOT NULL,
+            PRIMARY KEY (note_path, element_id),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Headers and row counts of .csv files
+        CREATE TABLE IF NOT EXISTS csv_files (
+            path TEXT PRIMARY KEY,
+            headers TEXT NOT NULL,
+            row_count INTEGER NOT NULL,
+            FOREIGN KEY (path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Files imported as attachments and the note they were added to.
+        -- No foreign key: a file may be attached to a note that hasn't
+        -- been indexed yet, and rows move with the note on rename.
+        CREATE TABLE IF NOT EXISTS attachments (
+            path TEXT NOT NULL,
+            note_path TEXT NOT NULL,
+            imported_at TEXT NOT NULL,
+            PRIMARY KEY (path, note_path)
+        );
+
+        -- Note titles and bodies for full-text search. Virtual tables
+        -- can't cascade, so delete_note clears a note's row itself.
+        CREATE VIRTUAL TABLE IF NOT EXISTS fts_content USING fts5(
+            path UNINDEXED,
+            title,
+            body,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        -- Schema version and the app version that last wrote the cache
+        CREATE TABLE IF NOT EXISTS schema_info (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        -- Settings key-value store
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        -- Keyboard shortcuts changed from their defaults ('' = unbound)
+        CREATE TABLE IF NOT EXISTS keybindings (
+            action TEXT PRIMARY KEY,
+            shortcut TEXT NOT NULL
+        );
+
+        -- Recently opened notes (for quick switcher ranking)
+        CREATE TABLE IF NOT EXISTS recent_files (
+            path TEXT PRIMARY KEY,
+            last_opened TEXT NOT NULL,
+            open_count INTEGER NOT NULL DEFAULT 0
+        );
+
+        -- Pinned notes with manual ordering
+        CREATE TABLE IF NOT EXISTS pins (
+            path TEXT PRIMARY KEY,
+            pin_order INTEGER NOT NULL
+        );
+
+        -- Bookmarks (notes, headings/blocks, folders, searches, groups)
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            parent_id INTEGER,
+            kind TEXT NOT NULL,
+            title TEXT,
+            path TEXT,
+            subpath TEXT,
+            query TEXT,
+            position INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (parent_id) REFERENCES bookmarks(id) ON DELETE CASCADE
+        );
+
+        -- Named workspace layouts (open tabs, sidebar state, scroll positions)
+        CREATE TABLE IF NOT EXISTS workspaces (
+            name TEXT PRIMARY KEY,
+            layout TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        -- Indexes for fast lookups
+        CREATE INDEX IF NOT EXISTS idx_links_target ON links(target_name);
+        CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);
+        CREATE INDEX IF NOT EXISTS idx_aliases_alias ON aliases(alias COLLATE NOCASE);
+        CREATE INDEX IF NOT EXISTS idx_headings_path ON headings(note_path);
+        CREATE INDEX IF NOT EXISTS idx_bookmarks_parent ON bookmarks(parent_id);
+        CREATE INDEX IF NOT EXISTS idx_callouts_kind ON callouts(kind);
+        CREATE INDEX IF NOT EXISTS idx_math_path ON math_blocks(note_path);
+        CREATE INDEX IF NOT EXISTS idx_snippets_language ON snippets(language);
+        CREATE INDEX IF NOT EXISTS idx_attachments_note ON attachments(note_path);
+        ",
+    )?;
+
+    // Caches created before heading anchors existed lack the slug column;
+    // the startup reindex fills it in
+    if !has_column(conn, "headings", "slug")? {
+        conn.execute_batch("ALTER TABLE headings ADD COLUMN slug TEXT NOT NULL DEFAULT '';")?;
+    }
+    Ok(())
+}
+
+/// What each note's cache entry was built from, so unchanged files are
+/// skipped on reindex
+fn add_file_state(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "notes", "content_hash")? {
+        conn.execute_batch(
+            "ALTER TABLE notes ADD COLUMN content_hash TEXT;
+             ALTER TABLE notes ADD COLUMN fs_mtime INTEGER;",
+        )?;
+    }
+    Ok(())
+}
+
+fn add_saved_searches(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- Named queries shown as smart folders
+        CREATE TABLE IF NOT EXISTS saved_searches (
+            name TEXT PRIMARY KEY,
+            query TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_properties(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- Custom frontmatter fields, one row per value (see `properties.rs`)
+        CREATE TABLE IF NOT EXISTS properties (
+            note_path TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            type TEXT NOT NULL,
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_properties_key ON properties(key COLLATE NOCASE);
+
+        -- Unchanged notes are skipped on reindex; forget what they were
+        -- built from so the next one fills the new table
+        UPDATE notes SET content_hash = NULL, fs_mtime = NULL;
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_tasks(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- Checklist items in notes
+        CREATE TABLE IF NOT EXISTS tasks (
+            note_path TEXT NOT NULL,
+            line INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            completed INTEGER NOT NULL DEFAULT 0,
+            due TEXT,
+            heading TEXT,
+            PRIMARY KEY (note_path, line),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_tasks_due ON tasks(due);
+
+        -- Reindex every note to fill it
+        UPDATE notes SET content_hash = NULL, fs_mtime = NULL;
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_history(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- Every note open, for usage stats; by path without a foreign key,
+        -- like recent_files
+        CREATE TABLE IF NOT EXISTS history (
+            path TEXT NOT NULL,
+            opened_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_history_path ON history(path, opened_at);
+        CREATE INDEX IF NOT EXISTS idx_history_opened ON history(opened_at);
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_embeddings(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- One vector per chunk of a note, for similar-wording search
+        CREATE TABLE IF NOT EXISTS embeddings (
+            note_path TEXT NOT NULL,
+            line INTEGER NOT NULL,
+            excerpt TEXT NOT NULL,
+            vector BLOB NOT NULL,
+            PRIMARY KEY (note_path, line),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Reindex every note to fill it
+        UPDATE notes SET content_hash = NULL, fs_mtime = NULL;
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_content_signatures(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- MinHash signature of each note's body, for near-duplicate detection
+        CREATE TABLE IF NOT EXISTS content_signatures (
+            note_path TEXT PRIMARY KEY,
+            signature BLOB NOT NULL,
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+
+        -- Reindex every note to fill it
+        UPDATE notes SET content_hash = NULL, fs_mtime = NULL;
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_citations(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- Entries of the configured `.bib` file, as JSON by citekey
+        CREATE TABLE IF NOT EXISTS bib_entries (
+            citekey TEXT PRIMARY KEY,
+            entry TEXT NOT NULL
+        );
+
+        -- `[@citekey]` references in notes
+        CREATE TABLE IF NOT EXISTS citations (
+            note_path TEXT NOT NULL,
+            citekey TEXT NOT NULL,
+            PRIMARY KEY (note_path, citekey),
+            FOREIGN KEY (note_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_citations_citekey ON citations(citekey);
+
+        -- Reindex every note to fill it
+        UPDATE notes SET content_hash = NULL, fs_mtime = NULL;
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_section_links(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "
+        -- `[[Note#Heading]]` links, for checking the heading exists
+        CREATE TABLE IF NOT EXISTS section_links (
+            source_path TEXT NOT NULL,
+            target_name TEXT NOT NULL,
+            heading TEXT NOT NULL,
+            line_number INTEGER NOT NULL,
+            FOREIGN KEY (source_path) REFERENCES notes(path) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_section_links_source ON section_links(source_path);
+
+        -- Reindex every note to fill it
+        UPDATE notes SET content_hash = NULL, fs_mtime = NULL;
+        ",
+    )?;
+    Ok(())
+}
+
+fn add_chunk_text(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "embeddings", "text")? {
+        conn.execute_batch(
+            "
+            -- The chunk itself, and its sentence embedding once semantic
+            -- search has computed it
+            ALTER TABLE embeddings ADD COLUMN text TEXT NOT NULL DEFAULT '';
+            ALTER TABLE embeddings ADD COLUMN semantic BLOB;
+
+            -- Reindex every note to fill it
+            UPDATE notes SET content_hash = NULL, fs_mtime = NULL;
+            ",
+        )?;
+    }
+    Ok(())
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema_version(conn: &Connection) -> i64 {
+        conn.query_row(
+            "SELECT value FROM schema_info WHERE key = 'schema_version'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .unwrap()
+        .parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_migrate_upgrades_unversioned_cache() {
+        // A cache from before versioning, without heading slugs or file state
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (path TEXT PRIMARY KEY, title TEXT NOT NULL, created_at TEXT,
+                 modified_at TEXT, word_count INTEGER DEFAULT 0, starred INTEGER DEFAULT 0);
+             CREATE TABLE headings (note_path TEXT NOT NULL, text TEXT NOT NULL,
+                 level INTEGER NOT NULL, line_number INTEGER NOT NULL);
+             INSERT INTO notes (path, title) VALUES ('a.md', 'A');",
+        )
+        .unwrap();
+
+        migrate(&conn, 0).unwrap();
+        assert_eq!(schema_version(&conn), SCHEMA_VERSION);
+        assert!(has_column(&conn, "headings", "slug").unwrap());
+        assert!(has_column(&conn, "notes", "content_hash").unwrap());
+        let title: String = conn
+            .query_row("SELECT title FROM notes WHERE path = 'a.md'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(title, "A");
+
+        // Caches already at a version skip the steps before it
+        conn.execute_batch("ALTER TABLE notes DROP COLUMN fs_mtime;")
+            .unwrap();
+        migrate(&conn, SCHEMA_VERSION).unwrap();
+        assert!(!has_column(&conn, "notes", "fs_mtime").unwrap());
+    }
+}
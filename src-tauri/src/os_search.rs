@@ -0,0 +1,199 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::deep_link;
+use crate::redact::Redactor;
+use crate::vault::Vault;
+
+/// Written into the stub folder so it is only ever cleared when it is ours
+const STUB_MARKER: &str = ".synapse-search";
+/// Characters of body text kept as the stub's description
+const SUMMARY_CHARS: usize = 300;
+
+/// Mirrors notes as small HTML files that Spotlight and Windows Search
+/// index (title, tags, summary). Opening one redirects to the note's
+/// `synapse://` link. Private folders, redacted notes and encrypted text
+/// never reach the stubs, and an encrypted vault's stubs hold no more than
+/// its index scope allows.
+pub struct StubIndex<'a> {
+    pub folder: &'a Path,
+    pub vault_path: &'a Path,
+    pub redactor: &'a Redactor,
+    pub private_folders: &'a [String],
+}
+
+impl StubIndex<'_> {
+    /// Rewrite every stub, dropping ones for deleted or hidden notes
+    pub fn rebuild(&self) -> Result<usize> {
+        clear(self.folder)?;
+        fs::create_dir_all(self.folder).context("Failed to create search folder")?;
+        fs::write(self.folder.join(STUB_MARKER), "").context("Failed to mark search folder")?;
+
+        let mut written = 0;
+        for entry in Vault::list_notes(self.vault_path)? {
+            let content = Vault::read_file(self.vault_path, &entry.path)?;
+            if self.update(&entry.path, &content)? {
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Write (or remove) the stub for one note. Returns whether it has one.
+    pub fn update(&self, path: &str, content: &str) -> Result<bool> {
+        let stub_path = self.stub_path(path);
+        let content = crate::crypto::index_view(self.vault_path, path, content);
+        let redacted = if self
+            .private_folders
+            .iter()
+            .any(|folder| crate::vault::path_in_folder(path, folder))
+        {
+            None
+        } else {
+            self.redactor.redact(path, content)
+        };
+        let Some(redacted) = redacted else {
+            if stub_path.exists() {
+                fs::remove_file(&stub_path).context("Failed to remove search stub")?;
+            }
+            return Ok(false);
+        };
+
+        let frontmatter = Vault::parse_frontmatter(&redacted.content);
+        let body = Vault::strip_frontmatter(&redacted.content);
+        let body = crate::crypto::strip_encrypted(&body);
+        let fallback = Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let title = frontmatter.title.unwrap_or(fallback);
+        let mut tags = frontmatter.tags;
+        tags.extend(crate::indexer::extract_tags(&body));
+        tags.dedup();
+
+        let stub = render_stub(
+            &self.redactor.redact_text(&title).0,
+            &tags,
+            &summary(&body),
+            &deep_link::note_uri(self.vault_path, path),
+        );
+        if let Some(parent) = stub_path.parent() {
+            fs::create_dir_all(parent).context("Failed to create search folder")?;
+        }
+        fs::write(&stub_path, stub).context("Failed to write search stub")?;
+        Ok(true)
+    }
+
+    /// `Folder/Note.md` → `<stub folder>/Folder/Note.html`
+    fn stub_path(&self, path: &str) -> PathBuf {
+        self.folder.join(Path::new(path).with_extension("html"))
+    }
+}
+
+/// Delete a stub folder written by `StubIndex`. Folders without the marker
+/// are left alone.
+pub fn clear(folder: &Path) -> Result<()> {
+    if !folder.exists() {
+        return Ok(());
+    }
+    if !folder.join(STUB_MARKER).exists() {
+        if fs::read_dir(folder)?.next().is_none() {
+            return Ok(());
+        }
+        bail!(
+            "{} is not a Synapse search folder; refusing to clear it",
+            folder.display()
+        );
+    }
+    fs::remove_dir_all(folder).context("Failed to clear search folder")
+}
+
+/// Plain-text opening of the note body
+fn summary(body: &str) -> String {
+    let text = body
+        .lines()
+        .map(|l| l.trim_start_matches(['#', '>', '-', '*', ' ']).trim())
+        .filter(|l| !l.is_empty() && !l.starts_with("```"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    text.chars().take(SUMMARY_CHARS).collect()
+}
+
+fn render_stub(title: &str, tags: &[String], summary: &str, uri: &str) -> String {
+    let keywords: Vec<&str> = tags.iter().map(|t| t.trim_start_matches('#')).collect();
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<meta name="keywords" content="{keywords}">
+<meta name="description" content="{summary}">
+<meta name="generator" content="Synapse">
+<meta http-equiv="refresh" content="0; url={uri}">
+</head>
+<body>
+<h1>{title}</h1>
+<p>{summary}</p>
+<p><a href="{uri}">Open in Synapse</a></p>
+</body>
+</html>
+"#,
+        title = escape_html(title),
+        keywords = escape_html(&keywords.join(", ")),
+        summary = escape_html(summary),
+        uri = escape_html(uri),
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::RedactionRules;
+
+    #[test]
+    fn test_stub_index() {
+        let root = std::env::temp_dir().join(format!("synapse-os-search-{}", uuid::Uuid::new_v4()));
+        let vault = root.join("vault");
+        let stubs = root.join("stubs");
+        fs::create_dir_all(vault.join("Private")).unwrap();
+        fs::write(
+            vault.join("Plan.md"),
+            "---\ntitle: Q3 <Plan>\ntags: [work]\n---\n# Goals\nShip the #release build\n",
+        )
+        .unwrap();
+        fs::write(vault.join("Diary.md"), "Today #private\n").unwrap();
+        fs::write(vault.join("Private/Keys.md"), "nothing here").unwrap();
+
+        let redactor = Redactor::new(RedactionRules::default()).unwrap();
+        let index = StubIndex {
+            folder: &stubs,
+            vault_path: &vault,
+            redactor: &redactor,
+            private_folders: &["Private".to_string()],
+        };
+        assert_eq!(index.rebuild().unwrap(), 1);
+
+        let stub = fs::read_to_string(stubs.join("Plan.html")).unwrap();
+        assert!(stub.contains("<title>Q3 &lt;Plan&gt;</title>"));
+        assert!(stub.contains(r#"content="work, release""#));
+        assert!(stub.contains(r#"content="Goals Ship the #release build""#));
+        assert!(stub.contains("synapse://open?vault="));
+        assert!(!stubs.join("Diary.html").exists());
+        assert!(!stubs.join("Private/Keys.html").exists());
+
+        // Only folders carrying the marker are cleared
+        assert!(clear(&vault).is_err());
+        clear(&stubs).unwrap();
+        assert!(!stubs.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,582 @@
+//! Importers for outliner graphs: Roam Research JSON exports, Logseq JSON
+//! exports and Logseq graph folders. Pages become notes and daily pages
+//! become the vault's daily notes (appended to if one already exists).
+//! Blocks become nested bullets, and `((block refs))` become `[[Note#^id]]`
+//! links to a `^id` anchor on the referenced block.
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::indexer;
+use crate::vault::Vault;
+
+/// Outcome of an outliner import
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutlinerImport {
+    /// Vault-relative paths of the notes written or appended to
+    pub imported: Vec<String>,
+    /// Block references turned into links
+    pub block_refs: usize,
+    /// Pages or files that couldn't be read, with the reason
+    pub failed: Vec<String>,
+}
+
+/// A page of the graph on its way to becoming a note
+struct Page {
+    title: String,
+    /// Set for daily pages
+    date: Option<NaiveDate>,
+    /// Frontmatter for the note
+    properties: serde_yaml::Mapping,
+    /// Markdown with `^id` anchors; links and block refs not yet rewritten
+    body: String,
+}
+
+/// Block uid → title of the page holding it
+type BlockPages = HashMap<String, String>;
+
+/// Import a Roam (a list of pages) or Logseq (`{"blocks": [...]}`) JSON
+/// export into `folder`; daily pages go to `daily_folder`
+pub fn import_json(
+    json: &str,
+    vault_path: &Path,
+    folder: &str,
+    daily_folder: &str,
+) -> Result<OutlinerImport> {
+    let value: Value = serde_json::from_str(json).context("Not a JSON export")?;
+    let pages = match &value {
+        Value::Array(pages) => pages,
+        Value::Object(map) => map
+            .get("blocks")
+            .and_then(Value::as_array)
+            .context("No pages in the export")?,
+        _ => bail!("No pages in the export"),
+    };
+
+    let mut referenced = HashSet::new();
+    for page in pages {
+        collect_refs(children(page), &mut referenced);
+    }
+
+    let mut report = OutlinerImport::default();
+    let mut block_pages = BlockPages::new();
+    let mut converted = Vec::new();
+    for page in pages {
+        let Some(title) = str_field(page, &["title", "page-name"]) else {
+            report.failed.push("A page without a title".to_string());
+            continue;
+        };
+        let mut properties = serde_yaml::Mapping::new();
+        if let Some(created) = page
+            .get("create-time")
+            .and_then(Value::as_i64)
+            .and_then(chrono::DateTime::from_timestamp_millis)
+        {
+            properties.insert(
+                "created".into(),
+                created.format("%Y-%m-%d").to_string().into(),
+            );
+        }
+        let mut body = String::new();
+        render_blocks(
+            children(page),
+            0,
+            title,
+            &referenced,
+            &mut block_pages,
+            &mut body,
+        );
+        converted.push(Page {
+            title: title.to_string(),
+            date: parse_daily_title(title),
+            properties,
+            body,
+        });
+    }
+
+    write_pages(
+        converted,
+        &block_pages,
+        vault_path,
+        folder,
+        daily_folder,
+        report,
+    )
+}
+
+/// Import a Logseq graph folder (its `pages/` and `journals/` Markdown files)
+/// into `folder`; journals go to `daily_folder`
+pub fn import_logseq_graph(
+    graph_dir: &Path,
+    vault_path: &Path,
+    folder: &str,
+    daily_folder: &str,
+) -> Result<OutlinerImport> {
+    let mut report = OutlinerImport::default();
+    let mut block_pages = BlockPages::new();
+    let mut pages = Vec::new();
+    for (dir, journal) in [("pages", false), ("journals", true)] {
+        let dir = graph_dir.join(dir);
+        if !dir.is_dir() {
+            continue;
+        }
+        let mut files: Vec<_> = std::fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .collect();
+        files.sort();
+        for file in files {
+            let stem = file
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            match std::fs::read_to_string(&file) {
+                Ok(content) => pages.push(logseq_page(&stem, journal, &content, &mut block_pages)),
+                Err(e) => report.failed.push(format!("{}: {}", file.display(), e)),
+            }
+        }
+    }
+    if pages.is_empty() && report.failed.is_empty() {
+        bail!("{} is not a Logseq graph", graph_dir.display());
+    }
+
+    write_pages(
+        pages,
+        &block_pages,
+        vault_path,
+        folder,
+        daily_folder,
+        report,
+    )
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+fn block_ref_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\(\(([\w-]+)\)\)").expect("valid pattern"))
+}
+
+/// `{{embed: ((uid))}}`, `{{[[embed]]: [[Page]]}}`, ...
+fn embed_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\{\{\s*\[*embed\]*\s*:\s*(\(\([\w-]+\)\)|\[\[[^\]]+\]\])\s*\}\}")
+            .expect("valid pattern")
+    })
+}
+
+fn children(value: &Value) -> &[Value] {
+    value
+        .get("children")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// The first of `keys` present as a string (Roam and Logseq name fields differently)
+fn str_field<'v>(value: &'v Value, keys: &[&str]) -> Option<&'v str> {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_str))
+}
+
+/// Uids some block refers to, so only those blocks get an anchor
+fn collect_refs(blocks: &[Value], referenced: &mut HashSet<String>) {
+    for block in blocks {
+        if let Some(text) = str_field(block, &["string", "content"]) {
+            for caps in block_ref_pattern().captures_iter(text) {
+                referenced.insert(caps[1].to_string());
+            }
+        }
+        collect_refs(children(block), referenced);
+    }
+}
+
+fn render_blocks(
+    blocks: &[Value],
+    depth: usize,
+    title: &str,
+    referenced: &HashSet<String>,
+    block_pages: &mut BlockPages,
+    out: &mut String,
+) {
+    for block in blocks {
+        let text = without_properties(str_field(block, &["string", "content"]).unwrap_or_default());
+        let anchor = match str_field(block, &["uid", "id"]) {
+            Some(uid) if referenced.contains(uid) => {
+                block_pages.insert(uid.to_string(), title.to_string());
+                format!(" ^{}", block_id(uid))
+            }
+            _ => String::new(),
+        };
+        let mut lines = text.lines();
+        let first = task_marker(lines.next().unwrap_or_default());
+        let indent = "  ".repeat(depth);
+        // Top-level heading blocks become headings, their children the list below
+        let child_depth = match block.get("heading").and_then(Value::as_u64) {
+            Some(level @ 1..=6) if depth == 0 => {
+                out.push_str(&format!(
+                    "{} {}{}\n",
+                    "#".repeat(level as usize),
+                    first,
+                    anchor
+                ));
+                0
+            }
+            _ => {
+                out.push_str(&format!("{}- {}{}\n", indent, first, anchor));
+                depth + 1
+            }
+        };
+        for line in lines {
+            out.push_str(&format!("{}  {}\n", indent, line));
+        }
+        render_blocks(
+            children(block),
+            child_depth,
+            title,
+            referenced,
+            block_pages,
+            out,
+        );
+    }
+}
+
+/// A Logseq Markdown page: leading `key:: value` lines become frontmatter,
+/// `id::` block properties become `^id` anchors
+fn logseq_page(stem: &str, journal: bool, content: &str, block_pages: &mut BlockPages) -> Page {
+    let mut properties = serde_yaml::Mapping::new();
+    let mut lines = content.lines().peekable();
+    while let Some((key, value)) = lines.peek().copied().and_then(property) {
+        let list = || {
+            value
+                .split(',')
+                .map(|item| item.trim().trim_start_matches('#'))
+                .map(|item| item.trim_start_matches("[[").trim_end_matches("]]"))
+                .filter(|item| !item.is_empty())
+                .map(|item| serde_yaml::Value::from(item.to_string()))
+                .collect::<serde_yaml::Sequence>()
+        };
+        match key.to_lowercase().as_str() {
+            "tags" => properties.insert("tags".into(), list().into()),
+            "alias" => properties.insert("aliases".into(), list().into()),
+            key => properties.insert(key.into(), value.to_string().into()),
+        };
+        lines.next();
+    }
+
+    // Namespaced pages are saved as `a___b.md` (or `a%2Fb.md`)
+    let title = match properties.get("title").and_then(|t| t.as_str()) {
+        Some(title) => title.to_string(),
+        None => indexer::percent_decode(&stem.replace("___", "/")),
+    };
+    let date = if journal {
+        NaiveDate::parse_from_str(stem, "%Y_%m_%d").ok()
+    } else {
+        parse_daily_title(&title)
+    };
+
+    let mut body: Vec<String> = Vec::new();
+    // The line each block starts on, innermost last, with its indentation
+    let mut open_blocks: Vec<(usize, usize)> = Vec::new();
+    for line in lines {
+        let line = expand_tabs(line);
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if let Some((key, value)) = property(trimmed) {
+            match key {
+                "id" => {
+                    if let Some((start, _)) = open_blocks.last() {
+                        body[*start].push_str(&format!(" ^{}", block_id(value)));
+                        block_pages.insert(value.to_string(), title.clone());
+                    }
+                    continue;
+                }
+                "collapsed" => continue,
+                _ => {}
+            }
+        }
+        if let Some(text) = trimmed.strip_prefix("- ") {
+            while open_blocks.last().is_some_and(|(_, open)| *open >= indent) {
+                open_blocks.pop();
+            }
+            open_blocks.push((body.len(), indent));
+            body.push(format!("{}- {}", &line[..indent], task_marker(text)));
+        } else {
+            body.push(line.to_string());
+        }
+    }
+
+    let mut body = body.join("\n").trim_start_matches('\n').to_string();
+    body.push('\n');
+    Page {
+        title,
+        date,
+        properties,
+        body,
+    }
+}
+
+/// Write the pages as notes, rewriting links to their new names
+fn write_pages(
+    pages: Vec<Page>,
+    block_pages: &BlockPages,
+    vault_path: &Path,
+    folder: &str,
+    daily_folder: &str,
+    mut report: OutlinerImport,
+) -> Result<OutlinerImport> {
+    // Where each page goes, and the name links to it use
+    let mut planned = HashSet::new();
+    let mut paths = Vec::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+    for page in &pages {
+        let path = match page.date {
+            Some(date) => {
+                Vault::note_relative_path(daily_folder, &date.format("%Y-%m-%d").to_string())
+            }
+            None => {
+                let mut candidate = page.title.clone();
+                let mut n = 1;
+                loop {
+                    let path =
+                        Vault::unique_file_path(vault_path, folder, &format!("{}.md", candidate));
+                    if !planned.contains(&path.to_lowercase()) {
+                        break path;
+                    }
+                    n += 1;
+                    candidate = format!("{} {}", page.title, n);
+                }
+            }
+        };
+        planned.insert(path.to_lowercase());
+        names.insert(page.title.to_lowercase(), note_name(&path));
+        paths.push(path);
+    }
+
+    for (page, path) in pages.into_iter().zip(paths) {
+        let (body, refs) = rewrite(&page.body, &names, block_pages);
+        report.block_refs += refs;
+        let name = note_name(&path);
+        let mut properties = page.properties;
+        if page.date.is_none() && name != page.title {
+            properties.insert("title".into(), page.title.clone().into());
+        }
+
+        let existing = match page.date {
+            Some(_) if vault_path.join(&path).exists() => {
+                Some(Vault::read_file(vault_path, &path)?)
+            }
+            _ => None,
+        };
+        let content = match existing {
+            Some(existing) => format!("{}\n\n{}", existing.trim_end(), body),
+            None if properties.is_empty() => body,
+            None => {
+                let yaml =
+                    serde_yaml::to_string(&properties).context("Failed to build frontmatter")?;
+                format!("---\n{}---\n\n{}", yaml, body)
+            }
+        };
+        Vault::write_file_atomic(vault_path, &path, &content)?;
+        report.imported.push(path);
+    }
+    Ok(report)
+}
+
+/// Turn embeds, `#[[tags]]`, page links and block refs into the vault's
+/// syntax. Returns the text and the number of block refs converted.
+fn rewrite(
+    body: &str,
+    names: &HashMap<String, String>,
+    block_pages: &BlockPages,
+) -> (String, usize) {
+    let body = embed_pattern().replace_all(body, "!$1");
+    let body = body.replace("#[[", "[[");
+
+    let mut linked = String::with_capacity(body.len());
+    let mut last = 0;
+    for mut link in indexer::parse_wikilinks(&body) {
+        let Some(name) = link_name(&link.target, names) else {
+            continue;
+        };
+        if name != link.target {
+            if link.alias.is_none() && link.subpath.is_none() {
+                link.alias = Some(link.target.clone());
+            }
+            link.target = name;
+        }
+        linked.push_str(&body[last..link.start]);
+        linked.push_str(&link.render());
+        last = link.end;
+    }
+    linked.push_str(&body[last..]);
+
+    let mut refs = 0;
+    let body = block_ref_pattern().replace_all(&linked, |caps: &regex::Captures| {
+        let uid = &caps[1];
+        match block_pages
+            .get(uid)
+            .and_then(|title| names.get(&title.to_lowercase()))
+        {
+            Some(name) => {
+                refs += 1;
+                format!("[[{}#^{}]]", name, block_id(uid))
+            }
+            None => caps[0].to_string(),
+        }
+    });
+    (body.into_owned(), refs)
+}
+
+/// The note a page link now points at: an imported page, or a daily note
+fn link_name(target: &str, names: &HashMap<String, String>) -> Option<String> {
+    names
+        .get(&target.to_lowercase())
+        .cloned()
+        .or_else(|| parse_daily_title(target).map(|date| date.format("%Y-%m-%d").to_string()))
+}
+
+/// Roam's `October 16th, 2026`, Logseq's `Oct 16th, 2026` or an ISO date
+fn parse_daily_title(title: &str) -> Option<NaiveDate> {
+    static ORDINAL: OnceLock<Regex> = OnceLock::new();
+    let ordinal =
+        ORDINAL.get_or_init(|| Regex::new(r"(\d)(st|nd|rd|th)\b").expect("valid pattern"));
+    let title = ordinal.replace_all(title.trim(), "$1");
+    ["%B %d, %Y", "%b %d, %Y", "%Y-%m-%d", "%Y_%m_%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(&title, format).ok())
+}
+
+/// `key:: value`
+fn property(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(":: ")?;
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| (key, value.trim()))
+}
+
+/// Block text without the `id::` and `collapsed::` properties Logseq keeps in it
+fn without_properties(text: &str) -> String {
+    text.lines()
+        .filter(|line| !matches!(property(line.trim()), Some(("id" | "collapsed", _))))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `{{[[TODO]]}} x` (Roam) or `TODO x` (Logseq) as a Markdown task
+fn task_marker(text: &str) -> String {
+    for (marker, checkbox) in [
+        ("{{[[TODO]]}} ", "[ ] "),
+        ("{{[[DONE]]}} ", "[x] "),
+        ("TODO ", "[ ] "),
+        ("LATER ", "[ ] "),
+        ("NOW ", "[ ] "),
+        ("DONE ", "[x] "),
+    ] {
+        if let Some(rest) = text.strip_prefix(marker) {
+            return format!("{}{}", checkbox, rest);
+        }
+    }
+    text.to_string()
+}
+
+/// A uid as a `^block-id`, which only allows ASCII letters, digits and `-`
+fn block_id(uid: &str) -> String {
+    uid.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// The file stem links use for a note
+fn note_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn expand_tabs(line: &str) -> String {
+    let tabs = line.len() - line.trim_start_matches('\t').len();
+    format!("{}{}", "  ".repeat(tabs), &line[tabs..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_import_roam_json() {
+        let vault = TestVault::with_notes(&[("Daily/2026-10-16.md", "# Today\n")]);
+        let json = r#"[
+            {"title": "Project/Plan", "create-time": 1760572800000, "children": [
+                {"string": "Goals", "uid": "ab_12", "heading": 2, "children": [
+                    {"string": "{{[[TODO]]}} Ship it", "uid": "x1"}
+                ]}
+            ]},
+            {"title": "October 16th, 2026", "children": [
+                {"string": "Worked on #[[Project/Plan]], see ((ab_12))"},
+                {"string": "{{embed: ((ab_12))}}"}
+            ]}
+        ]"#;
+
+        let report = import_json(json, vault.path(), "Imported", "Daily").unwrap();
+        assert_eq!(
+            report.imported,
+            vec!["Imported/Project_Plan.md", "Daily/2026-10-16.md"]
+        );
+        assert_eq!(report.block_refs, 2);
+
+        let plan = Vault::read_file(vault.path(), "Imported/Project_Plan.md").unwrap();
+        assert_eq!(
+            plan,
+            "---\ncreated: 2025-10-16\ntitle: Project/Plan\n---\n\n## Goals ^ab-12\n- [ ] Ship it\n"
+        );
+        let daily = Vault::read_file(vault.path(), "Daily/2026-10-16.md").unwrap();
+        assert_eq!(
+            daily,
+            "# Today\n\n- Worked on [[Project_Plan|Project/Plan]], see [[Project_Plan#^ab-12]]\n- ![[Project_Plan#^ab-12]]\n"
+        );
+    }
+
+    #[test]
+    fn test_import_logseq_graph() {
+        let graph = TestVault::with_notes(&[
+            (
+                "pages/Recipes___Soup.md",
+                "tags:: food, [[cooking]]\nalias:: Broth\n\n- Boil water\n\tid:: 6520a1b2-0000-4000-8000-000000000001\n\tcollapsed:: true\n\t- TODO Add salt\n",
+            ),
+            ("journals/2026_10_16.md", "- Made ((6520a1b2-0000-4000-8000-000000000001)) from [[Recipes/Soup]]\n"),
+        ]);
+        let vault = TestVault::new();
+
+        let report = import_logseq_graph(graph.path(), vault.path(), "Imported", "Daily").unwrap();
+        assert_eq!(
+            report.imported,
+            vec!["Imported/Recipes_Soup.md", "Daily/2026-10-16.md"]
+        );
+
+        let soup = Vault::read_file(vault.path(), "Imported/Recipes_Soup.md").unwrap();
+        assert_eq!(
+            soup,
+            "---\ntags:\n- food\n- cooking\naliases:\n- Broth\ntitle: Recipes/Soup\n---\n\n- Boil water ^6520a1b2-0000-4000-8000-000000000001\n  - [ ] Add salt\n"
+        );
+        let daily = Vault::read_file(vault.path(), "Daily/2026-10-16.md").unwrap();
+        assert_eq!(
+            daily,
+            "- Made [[Recipes_Soup#^6520a1b2-0000-4000-8000-000000000001]] from [[Recipes_Soup|Recipes/Soup]]\n"
+        );
+    }
+}
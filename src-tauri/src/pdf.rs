@@ -0,0 +1,302 @@
+//! PDF export. The selected notes are rendered into one HTML document with
+//! the HTML exporter and printed by a headless Chromium-based browser, which
+//! turns the headings into PDF bookmarks. Links between the exported notes
+//! jump within the document.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::database::Database;
+use crate::export::{self, Renderer};
+use crate::redact::Redactor;
+use crate::vault::{path_in_folder, Vault};
+
+/// Folder the document's images are copied into, beside it
+const ASSET_DIR: &str = "assets";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdfExportOptions {
+    /// The `.pdf` file to write
+    pub output: PathBuf,
+    /// Start each note on a new page
+    #[serde(default = "default_page_breaks")]
+    pub page_breaks: bool,
+    /// Browser to print with; Chrome, Chromium or Edge is looked for if unset
+    #[serde(default)]
+    pub browser: Option<PathBuf>,
+}
+
+fn default_page_breaks() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdfExport {
+    pub output: String,
+    /// Notes in the PDF, in order
+    pub notes: Vec<String>,
+    /// Selected notes the redaction rules left out
+    pub skipped: Vec<String>,
+}
+
+/// Print the notes `paths` name to one PDF. A folder stands for every note
+/// inside it, and `""` for the whole vault.
+pub fn export_pdf(
+    vault_path: &Path,
+    db: &Database,
+    redactor: &Redactor,
+    paths: &[String],
+    options: &PdfExportOptions,
+) -> Result<PdfExport> {
+    let browser = match &options.browser {
+        Some(browser) => browser.clone(),
+        None => find_browser().context("No Chrome, Chromium or Edge found to print with")?,
+    };
+    let notes = select_notes(vault_path, paths)?;
+
+    let work_dir = std::env::temp_dir().join(format!("synapse-pdf-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&work_dir)
+        .with_context(|| format!("Failed to create {}", work_dir.display()))?;
+    let result = write_document(vault_path, db, redactor, &notes, options, &work_dir).and_then(
+        |(html, export)| {
+            print_to_pdf(&browser, &html, &options.output)?;
+            Ok(export)
+        },
+    );
+    let _ = std::fs::remove_dir_all(&work_dir);
+    result
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// The notes `paths` name, folders expanded, in order and without repeats
+fn select_notes(vault_path: &Path, paths: &[String]) -> Result<Vec<String>> {
+    let all: Vec<String> = Vault::list_notes(vault_path)?
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+    let mut seen = HashSet::new();
+    let mut notes = Vec::new();
+    for path in paths {
+        let path = path.trim_matches('/');
+        let mut matched: Vec<&String> = if all.iter().any(|note| note == path) {
+            all.iter().filter(|note| *note == path).collect()
+        } else {
+            all.iter()
+                .filter(|note| path.is_empty() || path_in_folder(note, path))
+                .collect()
+        };
+        matched.sort();
+        notes.extend(
+            matched
+                .into_iter()
+                .filter(|note| seen.insert(note.as_str()))
+                .cloned(),
+        );
+    }
+    if notes.is_empty() {
+        bail!("No notes to export");
+    }
+    Ok(notes)
+}
+
+/// Render the notes into `dir/index.html`, copying their images beside it
+fn write_document(
+    vault_path: &Path,
+    db: &Database,
+    redactor: &Redactor,
+    notes: &[String],
+    options: &PdfExportOptions,
+    dir: &Path,
+) -> Result<(PathBuf, PdfExport)> {
+    let mut renderer = Renderer::new(vault_path, db, redactor, ASSET_DIR);
+    let mut included = Vec::new();
+    let mut skipped = Vec::new();
+    for path in notes {
+        let content = Vault::read_file(vault_path, path)?;
+        match redactor.redact(path, &content) {
+            Some(redaction) => {
+                let anchor = format!("#note-{}", included.len());
+                renderer.pages.insert(path.clone(), anchor);
+                included.push((path.clone(), redaction.content));
+            }
+            None => skipped.push(path.clone()),
+        }
+    }
+    if included.is_empty() {
+        bail!("Every selected note is excluded from exports");
+    }
+
+    let mut body = String::new();
+    for (i, (path, content)) in included.iter().enumerate() {
+        let html = renderer.render(path, content)?;
+        let class = if options.page_breaks && i > 0 {
+            "note page-break"
+        } else {
+            "note"
+        };
+        body.push_str(&format!(
+            "<section class=\"{}\" id=\"note-{}\">\n",
+            class, i
+        ));
+        // Every note gets a top-level bookmark
+        if !html.starts_with("<h1") {
+            let title = redactor.redact_text(&export::note_title(db, path)?).0;
+            body.push_str(&format!("<h1>{}</h1>\n", export::escape(&title)));
+        }
+        body.push_str(&html);
+        body.push_str("</section>\n");
+    }
+
+    let title = options
+        .output
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let html_path = dir.join("index.html");
+    renderer.copy_assets(&dir.join(ASSET_DIR))?;
+    std::fs::write(&html_path, export::page(&title, &body))
+        .with_context(|| format!("Failed to write {}", html_path.display()))?;
+
+    let export = PdfExport {
+        output: options.output.to_string_lossy().to_string(),
+        notes: included.into_iter().map(|(path, _)| path).collect(),
+        skipped,
+    };
+    Ok((html_path, export))
+}
+
+fn print_to_pdf(browser: &Path, html: &Path, output: &Path) -> Result<()> {
+    if let Some(dir) = output.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let _ = std::fs::remove_file(output);
+    let result = Command::new(browser)
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--no-pdf-header-footer")
+        .arg("--generate-pdf-document-outline")
+        .arg(format!("--print-to-pdf={}", output.display()))
+        .arg(html)
+        .output()
+        .with_context(|| format!("Failed to run {}", browser.display()))?;
+    if !result.status.success() || !output.exists() {
+        bail!(
+            "{} could not print the PDF: {}",
+            browser.display(),
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// A Chromium-based browser in its usual install location or on `PATH`
+fn find_browser() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    const INSTALLED: &[&str] = &[
+        "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+        "/Applications/Chromium.app/Contents/MacOS/Chromium",
+        "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+    ];
+    #[cfg(windows)]
+    const INSTALLED: &[&str] = &[
+        r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+        r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+        r"C:\Program Files\Microsoft\Edge\Application\msedge.exe",
+    ];
+    #[cfg(not(any(target_os = "macos", windows)))]
+    const INSTALLED: &[&str] = &[];
+    const ON_PATH: &[&str] = &[
+        "google-chrome",
+        "google-chrome-stable",
+        "chromium",
+        "chromium-browser",
+        "microsoft-edge",
+        "chrome",
+        "msedge",
+    ];
+
+    INSTALLED
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+        .or_else(|| {
+            let dirs = std::env::var_os("PATH")?;
+            std::env::split_paths(&dirs).find_map(|dir| {
+                ON_PATH
+                    .iter()
+                    .map(|name| {
+                        dir.join(name)
+                            .with_extension(std::env::consts::EXE_EXTENSION)
+                    })
+                    .find(|path| path.is_file())
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::RedactionRules;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_write_document_links_notes_within_it() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Projects/Plan.md",
+                "# Plan\nSee [[Notes#Later]] and ![[shot.png]]\n",
+            ),
+            (
+                "Projects/Notes.md",
+                "---\ntitle: Notes\n---\nIntro\n\n## Later\nMaybe\n",
+            ),
+            ("Projects/Secret.md", "#private\nHidden\n"),
+            ("Home.md", "[[Plan]]\n"),
+            ("shot.png", "png"),
+        ]);
+        let db = vault.index();
+        let redactor = Redactor::new(RedactionRules::default()).unwrap();
+        let options = PdfExportOptions {
+            output: vault.path().join("out.pdf"),
+            page_breaks: true,
+            browser: None,
+        };
+
+        let notes = select_notes(
+            vault.path(),
+            &["Projects".into(), "Projects/Plan.md".into()],
+        )
+        .unwrap();
+        assert_eq!(
+            notes,
+            vec![
+                "Projects/Notes.md",
+                "Projects/Plan.md",
+                "Projects/Secret.md"
+            ]
+        );
+
+        let dir = vault.path().join("build");
+        let (html_path, export) =
+            write_document(vault.path(), &db, &redactor, &notes, &options, &dir).unwrap();
+        assert_eq!(export.notes, vec!["Projects/Notes.md", "Projects/Plan.md"]);
+        assert_eq!(export.skipped, vec!["Projects/Secret.md"]);
+
+        let html = std::fs::read_to_string(html_path).unwrap();
+        assert!(html.contains("<section class=\"note\" id=\"note-0\">\n<h1>Notes</h1>"));
+        assert!(
+            html.contains("<section class=\"note page-break\" id=\"note-1\">\n<h1 id=\"plan\">")
+        );
+        assert!(html.contains("<a class=\"internal-link\" href=\"#note-0\">Notes &gt; Later</a>"));
+        assert!(html.contains("<img src=\"assets/shot.png\""));
+        assert!(!html.contains("Hidden"));
+        assert!(dir.join("assets/shot.png").exists());
+    }
+}
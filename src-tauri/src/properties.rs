@@ -0,0 +1,502 @@
+//! Frontmatter properties. Every custom frontmatter field is cached as
+//! typed rows (one per value of a list) so notes can be filtered and sorted
+//! by them: `status = "active" AND due < today`, `priority >= 2 OR NOT
+//! reviewed`, `(type = book OR type = paper) AND rating`.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::database::{CachedNote, Database};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyType {
+    Text,
+    Number,
+    Bool,
+    /// `YYYY-MM-DD`, optionally followed by a time
+    Date,
+}
+
+impl PropertyType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PropertyType::Text => "text",
+            PropertyType::Number => "number",
+            PropertyType::Bool => "bool",
+            PropertyType::Date => "date",
+        }
+    }
+
+    pub fn parse(s: &str) -> PropertyType {
+        match s {
+            "number" => PropertyType::Number,
+            "bool" => PropertyType::Bool,
+            "date" => PropertyType::Date,
+            _ => PropertyType::Text,
+        }
+    }
+}
+
+/// One value of a frontmatter field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Property {
+    pub key: String,
+    pub value: String,
+    #[serde(rename = "type")]
+    pub kind: PropertyType,
+}
+
+/// A note matching a property query, with its properties for display
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertyMatch {
+    pub note: CachedNote,
+    pub properties: Vec<Property>,
+}
+
+/// How to order the matches; by title when unset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropertySort {
+    pub key: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// The cacheable values of the custom frontmatter fields (`Frontmatter::extra`).
+/// Nested maps and empty values are left out.
+pub fn from_frontmatter(extra: &HashMap<String, serde_yaml::Value>) -> Vec<Property> {
+    let mut properties = Vec::new();
+    for (key, value) in extra {
+        let values = match value {
+            serde_yaml::Value::Sequence(items) => items.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            if let Some((value, kind)) = scalar(value) {
+                properties.push(Property {
+                    key: key.clone(),
+                    value,
+                    kind,
+                });
+            }
+        }
+    }
+    properties.sort_by(|a, b| (&a.key, &a.value).cmp(&(&b.key, &b.value)));
+    properties
+}
+
+/// Notes whose properties match `filter` (every note for an empty one)
+pub fn query(
+    db: &Database,
+    filter: &str,
+    sort: Option<&PropertySort>,
+) -> Result<Vec<PropertyMatch>> {
+    let filter = parse(filter)?;
+    let mut params = Vec::new();
+    let condition = match &filter {
+        Some(filter) => filter_sql(filter, &mut params),
+        None => "1".to_string(),
+    };
+    let order = match sort {
+        Some(sort) => {
+            params.push(sort.key.clone());
+            let p = params.len();
+            // Numbers sort numerically and before text; notes without the
+            // property come last either way
+            let value = format!(
+                "(SELECT CASE p.type WHEN 'number' THEN CAST(p.value AS REAL) ELSE lower(p.value) END
+                  FROM properties p WHERE p.note_path = n.path AND p.key = ?{p} COLLATE NOCASE
+                  ORDER BY 1 LIMIT 1)"
+            );
+            format!(
+                "{value} IS NULL, {value} {}, n.title COLLATE NOCASE",
+                if sort.descending { "DESC" } else { "ASC" }
+            )
+        }
+        None => "n.title COLLATE NOCASE".to_string(),
+    };
+    db.find_notes_ordered(&condition, &order, &params)?
+        .into_iter()
+        .map(|note| {
+            let properties = db.get_properties(&note.path)?;
+            Ok(PropertyMatch { note, properties })
+        })
+        .collect()
+}
+
+// ─── Filter language ────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn sql(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    Date(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    /// `key` alone: the note has the property
+    Has(String),
+    Compare(String, Op, Literal),
+    Not(Box<Filter>),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn parse(input: &str) -> Result<Option<Filter>> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+    };
+    let filter = parser.or()?;
+    if parser.pos < tokens.len() {
+        bail!(
+            "Unexpected {} in property filter",
+            describe(&tokens[parser.pos])
+        );
+    }
+    Ok(Some(filter))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => text.push(c),
+                        None => bail!("Unclosed quote in property filter"),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            '=' => Token::Op(Op::Eq),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ne),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !"()\"=!<>".contains(*c))
+                {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// `OR` binds loosest, then `AND` (or terms side by side), then `NOT`
+struct Parser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let matched =
+            matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn or(&mut self) -> Result<Filter> {
+        let mut either = vec![self.and()?];
+        while self.keyword("or") {
+            either.push(self.and()?);
+        }
+        Ok(if either.len() == 1 {
+            either.remove(0)
+        } else {
+            Filter::Or(either)
+        })
+    }
+
+    fn and(&mut self) -> Result<Filter> {
+        let mut all = vec![self.unary()?];
+        loop {
+            if self.keyword("and") {
+                all.push(self.unary()?);
+                continue;
+            }
+            match self.peek() {
+                None | Some(Token::Close) => break,
+                Some(Token::Word(w)) if w.eq_ignore_ascii_case("or") => break,
+                Some(_) => all.push(self.unary()?),
+            }
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            Filter::And(all)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Filter> {
+        if self.keyword("not") {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    bail!("Unclosed '(' in property filter");
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(Token::Word(key)) | Some(Token::Quoted(key)) => {
+                let Some(Token::Op(op)) = self.peek().cloned() else {
+                    return Ok(Filter::Has(key));
+                };
+                self.pos += 1;
+                let literal = match self.peek().cloned() {
+                    Some(Token::Quoted(text)) => Literal::Text(text),
+                    Some(Token::Word(word)) => literal(&word),
+                    Some(token) => {
+                        bail!("Expected a value after {}, found {}", key, describe(&token))
+                    }
+                    None => bail!("Expected a value after {}", key),
+                };
+                self.pos += 1;
+                if matches!(literal, Literal::Bool(_)) && !matches!(op, Op::Eq | Op::Ne) {
+                    bail!("true and false can only be compared with = or !=");
+                }
+                Ok(Filter::Compare(key, op, literal))
+            }
+            Some(token) => bail!("Expected a property name, found {}", describe(&token)),
+            None => bail!("Property filter ends without a property name"),
+        }
+    }
+}
+
+/// An unquoted value: a date (or `today`), a number, a boolean or text
+fn literal(word: &str) -> Literal {
+    if word.eq_ignore_ascii_case("today") {
+        return Literal::Date(chrono::Local::now().format("%Y-%m-%d").to_string());
+    }
+    if is_date(word) {
+        return Literal::Date(word.to_string());
+    }
+    match word.to_lowercase().as_str() {
+        "true" => return Literal::Bool(true),
+        "false" => return Literal::Bool(false),
+        _ => {}
+    }
+    match word.parse::<f64>() {
+        Ok(number) if number.is_finite() => Literal::Number(number),
+        _ => Literal::Text(word.to_string()),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(w) => format!("'{}'", w),
+        Token::Quoted(q) => format!("\"{}\"", q),
+        Token::Op(op) => format!("'{}'", op.sql()),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+/// SQL condition over `notes n`
+fn filter_sql(filter: &Filter, params: &mut Vec<String>) -> String {
+    let mut bind = |value: String| {
+        params.push(value);
+        format!("?{}", params.len())
+    };
+    let exists = |key: &str, condition: &str| {
+        format!(
+            "EXISTS (SELECT 1 FROM properties p WHERE p.note_path = n.path
+             AND p.key = {key} COLLATE NOCASE{condition})"
+        )
+    };
+    match filter {
+        Filter::Has(key) => exists(&bind(key.clone()), ""),
+        // Notes without the property count as different from any value
+        Filter::Compare(key, Op::Ne, literal) => {
+            let equal = Filter::Compare(key.clone(), Op::Eq, literal.clone());
+            format!("NOT ({})", filter_sql(&equal, params))
+        }
+        Filter::Compare(key, op, literal) => {
+            let key = bind(key.clone());
+            let op = op.sql();
+            let condition = match literal {
+                Literal::Text(text) => {
+                    let v = bind(text.clone());
+                    format!(" AND lower(p.value) {op} lower({v})")
+                }
+                Literal::Number(number) => {
+                    let v = bind(number.to_string());
+                    format!(
+                        " AND p.type = 'number' AND CAST(p.value AS REAL) {op} CAST({v} AS REAL)"
+                    )
+                }
+                Literal::Bool(value) => {
+                    let v = bind(value.to_string());
+                    format!(" AND p.type = 'bool' AND p.value {op} {v}")
+                }
+                Literal::Date(date) => {
+                    let v = bind(date.clone());
+                    format!(" AND p.type = 'date' AND substr(p.value, 1, length({v})) {op} {v}")
+                }
+            };
+            exists(&key, &condition)
+        }
+        Filter::Not(inner) => format!("NOT ({})", filter_sql(inner, params)),
+        Filter::And(all) => {
+            let clauses: Vec<String> = all.iter().map(|f| filter_sql(f, params)).collect();
+            format!("({})", clauses.join(" AND "))
+        }
+        Filter::Or(either) => {
+            let clauses: Vec<String> = either.iter().map(|f| filter_sql(f, params)).collect();
+            format!("({})", clauses.join(" OR "))
+        }
+    }
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// A scalar YAML value as cached text and its type
+fn scalar(value: &serde_yaml::Value) -> Option<(String, PropertyType)> {
+    match value {
+        serde_yaml::Value::Bool(b) => Some((b.to_string(), PropertyType::Bool)),
+        serde_yaml::Value::Number(n) => Some((n.to_string(), PropertyType::Number)),
+        serde_yaml::Value::String(s) if s.trim().is_empty() => None,
+        serde_yaml::Value::String(s) if is_date(s) => Some((s.clone(), PropertyType::Date)),
+        serde_yaml::Value::String(s) => Some((s.clone(), PropertyType::Text)),
+        serde_yaml::Value::Tagged(tagged) => scalar(&tagged.value),
+        _ => None,
+    }
+}
+
+/// `YYYY-MM-DD`, with anything after it separated by `T` or a space
+fn is_date(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+        && matches!(bytes.get(10), None | Some(b'T') | Some(b' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_query_filters_and_sorts_by_properties() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Ship.md",
+                "---\nstatus: active\ndue: 2024-03-01\npriority: 10\nowners: [ana, ben]\n---\n",
+            ),
+            (
+                "Write.md",
+                "---\nstatus: Active\ndue: 2024-01-15\npriority: 2\ndone: false\n---\n",
+            ),
+            ("Rest.md", "---\nstatus: paused\npriority: 1\n---\n"),
+            ("Plain.md", "# Plain\n"),
+        ]);
+        let db = vault.index();
+        let paths = |filter: &str, sort: Option<PropertySort>| -> Vec<String> {
+            query(&db, filter, sort.as_ref())
+                .unwrap()
+                .into_iter()
+                .map(|m| m.note.path)
+                .collect()
+        };
+        let by_priority = || {
+            Some(PropertySort {
+                key: "priority".to_string(),
+                descending: false,
+            })
+        };
+
+        assert_eq!(
+            paths(r#"status = "active" AND due < 2024-02"#, None),
+            vec!["Write.md"]
+        );
+        assert_eq!(
+            paths("priority >= 2 OR owners = ben", by_priority()),
+            vec!["Write.md", "Ship.md"]
+        );
+        assert_eq!(
+            paths("status not (done = false) and status != paused", None),
+            vec!["Ship.md"]
+        );
+        assert_eq!(
+            paths("", by_priority()),
+            vec!["Rest.md", "Write.md", "Ship.md", "Plain.md"]
+        );
+        assert!(query(&db, "status =", None).is_err());
+        assert!(query(&db, "(due", None).is_err());
+
+        let ship = query(&db, "owners", None).unwrap().remove(0);
+        assert!(ship.properties.contains(&Property {
+            key: "due".to_string(),
+            value: "2024-03-01".to_string(),
+            kind: PropertyType::Date,
+        }));
+    }
+}
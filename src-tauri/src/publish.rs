@@ -0,0 +1,288 @@
+//! Static site publishing. Notes carrying the publish tag (or a tag nested
+//! under it) become pages of a flat HTML site with an index page and a page
+//! per tag. Links between published notes point at their pages and links to
+//! anything else are left as text; images are copied into `assets/`.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::database::Database;
+use crate::export::{self, Renderer};
+use crate::indexer::{self, Slugger};
+use crate::redact::Redactor;
+use crate::vault::Vault;
+
+pub const DEFAULT_PUBLISH_TAG: &str = "#publish";
+
+const ASSET_DIR: &str = "assets";
+const TAG_DIR: &str = "tags";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishOptions {
+    /// Folder the site is written into
+    pub output: PathBuf,
+    /// Notes with this tag, or one nested under it, are published
+    #[serde(default = "default_tag")]
+    pub tag: String,
+    /// Heading of the index page; the vault's folder name if unset
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+fn default_tag() -> String {
+    DEFAULT_PUBLISH_TAG.to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedSite {
+    pub output: String,
+    /// Notes published, by vault path
+    pub pages: Vec<String>,
+    /// Tags that got a page
+    pub tags: Vec<String>,
+    /// Tagged notes the redaction rules left out
+    pub skipped: Vec<String>,
+}
+
+/// A note on its way to becoming a page
+struct Page {
+    path: String,
+    /// File name in the site root
+    file: String,
+    title: String,
+    /// Redacted content
+    content: String,
+    /// Tags shown on the page, without the publish tag
+    tags: Vec<String>,
+}
+
+/// Write the published notes of the vault to `options.output`
+pub fn publish(
+    vault_path: &Path,
+    db: &Database,
+    redactor: &Redactor,
+    options: &PublishOptions,
+) -> Result<PublishedSite> {
+    let selector = options.tag.trim().trim_start_matches('#');
+    if selector.is_empty() {
+        bail!("No publish tag given");
+    }
+    let mut candidates = Vec::new();
+    for (tag, _) in db.get_all_tags()? {
+        if tag_in(&tag, selector) {
+            candidates.extend(db.get_notes_by_tag(&tag)?);
+        }
+    }
+    candidates.sort();
+    candidates.dedup();
+
+    // `index.html` is taken by the index page
+    let mut slugger = Slugger::default();
+    slugger.slug("index");
+    let mut pages = Vec::new();
+    let mut skipped = Vec::new();
+    for path in candidates {
+        let content = Vault::read_file(vault_path, &path)?;
+        let Some(redaction) = redactor.redact(&path, &content) else {
+            skipped.push(path);
+            continue;
+        };
+        let stem = Path::new(&path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let slug = if indexer::heading_slug(&stem).is_empty() {
+            slugger.slug("note")
+        } else {
+            slugger.slug(&stem)
+        };
+        let frontmatter = Vault::parse_frontmatter(&redaction.content);
+        let mut tags: Vec<String> =
+            indexer::index_note(&path, &redaction.content, &frontmatter.tags)
+                .tags
+                .into_iter()
+                .filter(|tag| !tag_in(tag, selector))
+                .collect();
+        tags.sort();
+        pages.push(Page {
+            title: redactor.redact_text(&export::note_title(db, &path)?).0,
+            file: format!("{}.html", slug),
+            path,
+            content: redaction.content,
+            tags,
+        });
+    }
+    if pages.is_empty() {
+        bail!("No notes tagged #{} to publish", selector);
+    }
+    pages.sort_by_key(|page| page.title.to_lowercase());
+
+    let site_title = match &options.title {
+        Some(title) => title.clone(),
+        None => vault_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+    };
+    std::fs::create_dir_all(options.output.join(TAG_DIR))
+        .with_context(|| format!("Failed to create {}", options.output.display()))?;
+
+    let mut renderer = Renderer::new(vault_path, db, redactor, ASSET_DIR);
+    for page in &pages {
+        renderer.pages.insert(page.path.clone(), page.file.clone());
+    }
+    let mut by_tag: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in &pages {
+        let mut body = nav(&site_title, "");
+        body.push_str(&renderer.render(&page.path, &page.content)?);
+        if !page.tags.is_empty() {
+            body.push_str(&tag_links(page.tags.iter().map(String::as_str)));
+        }
+        write_page(&options.output.join(&page.file), &page.title, &body)?;
+        for tag in &page.tags {
+            by_tag.entry(tag).or_default().push(page);
+        }
+    }
+
+    for (tag, tagged) in &by_tag {
+        let mut body = nav(&site_title, "../");
+        body.push_str(&format!("<h1>{}</h1>\n", export::escape(tag)));
+        body.push_str(&note_list(tagged, "../"));
+        write_page(&options.output.join(tag_file(tag)), tag, &body)?;
+    }
+
+    let mut body = format!("<h1>{}</h1>\n", export::escape(&site_title));
+    body.push_str(&note_list(&pages.iter().collect::<Vec<_>>(), ""));
+    if !by_tag.is_empty() {
+        body.push_str("<h2>Tags</h2>\n");
+        body.push_str(&tag_links(by_tag.keys().copied()));
+    }
+    write_page(&options.output.join("index.html"), &site_title, &body)?;
+
+    renderer.copy_assets(&options.output.join(ASSET_DIR))?;
+
+    Ok(PublishedSite {
+        output: options.output.to_string_lossy().to_string(),
+        tags: by_tag.keys().map(|tag| tag.to_string()).collect(),
+        pages: pages.into_iter().map(|page| page.path).collect(),
+        skipped,
+    })
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// Whether `tag` is `parent` or nested below it
+fn tag_in(tag: &str, parent: &str) -> bool {
+    let tag = tag.trim_start_matches('#');
+    tag.eq_ignore_ascii_case(parent)
+        || (tag.len() > parent.len()
+            && tag.as_bytes()[parent.len()] == b'/'
+            && tag[..parent.len()].eq_ignore_ascii_case(parent))
+}
+
+/// `tags/<slug>.html`, relative to the site root
+fn tag_file(tag: &str) -> String {
+    let slug: Vec<String> = tag
+        .trim_start_matches('#')
+        .split('/')
+        .map(indexer::heading_slug)
+        .collect();
+    format!("{}/{}.html", TAG_DIR, slug.join("-"))
+}
+
+/// Link back to the index; `root` leads from the page to the site root
+fn nav(site_title: &str, root: &str) -> String {
+    format!(
+        "<nav><a href=\"{}index.html\">{}</a></nav>\n",
+        root,
+        export::escape(site_title)
+    )
+}
+
+fn note_list(pages: &[&Page], root: &str) -> String {
+    let mut html = String::from("<ul class=\"notes\">\n");
+    for page in pages {
+        html.push_str(&format!(
+            "<li><a href=\"{}{}\">{}</a></li>\n",
+            root,
+            export::escape(&page.file),
+            export::escape(&page.title)
+        ));
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+/// Links to tag pages from a page in the site root
+fn tag_links<'t>(tags: impl Iterator<Item = &'t str>) -> String {
+    let links: Vec<String> = tags
+        .map(|tag| {
+            format!(
+                "<a class=\"tag\" href=\"{}\">{}</a>",
+                export::escape(&tag_file(tag)),
+                export::escape(tag)
+            )
+        })
+        .collect();
+    format!("<p class=\"tags\">{}</p>\n", links.join(" "))
+}
+
+fn write_page(file: &Path, title: &str, body: &str) -> Result<()> {
+    std::fs::write(file, export::page(title, body))
+        .with_context(|| format!("Failed to write {}", file.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::RedactionRules;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_publish_tagged_notes() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Blog/Post.md",
+                "#publish\n# Post\nSee [[About]] and [[Draft]]. #rust\n\n![[pic.png]]\n",
+            ),
+            ("About.md", "---\ntags: [publish/site]\n---\n# About\nHi\n"),
+            ("Draft.md", "# Draft\nNot yet\n"),
+            ("Secret.md", "# Secret\n#publish #private\n"),
+            ("pic.png", "png"),
+        ]);
+        let db = vault.index();
+        let redactor = Redactor::new(RedactionRules::default()).unwrap();
+        let output = vault.path().join("site");
+        let options = PublishOptions {
+            output: output.clone(),
+            tag: default_tag(),
+            title: Some("Garden".to_string()),
+        };
+
+        let site = publish(vault.path(), &db, &redactor, &options).unwrap();
+        assert_eq!(site.pages, vec!["About.md", "Blog/Post.md"]);
+        assert_eq!(site.tags, vec!["#rust"]);
+        assert_eq!(site.skipped, vec!["Secret.md"]);
+
+        let post = std::fs::read_to_string(output.join("post.html")).unwrap();
+        assert!(post.contains("<a class=\"internal-link\" href=\"about.html\">About</a>"));
+        assert!(post.contains("<span class=\"internal-link\">Draft</span>"));
+        assert!(post.contains("<a class=\"tag\" href=\"tags/rust.html\">#rust</a>"));
+        assert!(post.contains("<img src=\"assets/pic.png\""));
+        assert!(output.join("assets/pic.png").exists());
+
+        let tag_page = std::fs::read_to_string(output.join("tags/rust.html")).unwrap();
+        assert!(tag_page.contains("<a href=\"../index.html\">Garden</a>"));
+        assert!(tag_page.contains("<li><a href=\"../post.html\">Post</a></li>"));
+
+        let index = std::fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains(
+            "<li><a href=\"about.html\">About</a></li>\n<li><a href=\"post.html\">Post</a></li>"
+        ));
+        assert!(!output.join("secret.html").exists());
+    }
+}
@@ -0,0 +1,508 @@
+//! Search queries over the cache: `tag:#inbox -path:Archive "weekly review"`.
+//! Terms side by side must all match; `OR`, `AND`, `NOT` (or `-`) and
+//! parentheses combine them otherwise.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{CachedNote, Database};
+
+/// One condition of a search query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    /// `tag:#project` — the tag or any nested tag below it
+    Tag(String),
+    /// `path:Projects/` — vault path prefix
+    Path(String),
+    /// `title:draft` or a bare word — title substring
+    Title(String),
+    /// `links:Note` — notes linking to `[[Note]]`
+    Links(String),
+    /// `created:>2024-01-01` — frontmatter `created` date
+    Created(Comparison, String),
+    /// `modified:2024-05` — frontmatter `modified` date
+    Modified(Comparison, String),
+    /// `starred:true`
+    Starred(bool),
+    /// `-term` or `NOT term` — anything but
+    Not(Box<Term>),
+    /// `a OR b` — either
+    Or(Vec<Term>),
+    /// `(a b)` — all of them, inside an `OR`
+    And(Vec<Term>),
+}
+
+/// How a date term compares. Dates are `YYYY`, `YYYY-MM` or `YYYY-MM-DD`
+/// and compare at that precision, so `created:2024` is any day in 2024.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Before,
+    BeforeOrOn,
+    After,
+    AfterOrOn,
+}
+
+/// A parsed search query. Its terms are ANDed together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let term = parser.or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected '{}' in query", token.text);
+        }
+        let terms = match term {
+            Term::And(terms) => terms,
+            term => vec![term],
+        };
+        Ok(Query { terms })
+    }
+
+    /// SQL condition over `notes n` plus its bound parameters
+    fn to_sql(&self) -> (String, Vec<String>) {
+        let mut params = Vec::new();
+        let filter = all_sql(&self.terms, &mut params);
+        (filter, params)
+    }
+
+    /// Notes matching every term, sorted by title
+    pub fn execute(&self, db: &Database) -> Result<Vec<CachedNote>> {
+        let (filter, params) = self.to_sql();
+        db.find_notes(&filter, &params)
+    }
+}
+
+/// An embedded ```query block and what it matched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddedQueryResult {
+    /// 1-based line of the opening fence; the renderer's key for the block
+    pub start_line: usize,
+    pub end_line: usize,
+    pub query: String,
+    pub notes: Vec<CachedNote>,
+    /// Parse error, shown in place of the list
+    pub error: Option<String>,
+}
+
+/// Run every ```query block in a note against the index
+pub fn evaluate_embedded(db: &Database, content: &str) -> Result<Vec<EmbeddedQueryResult>> {
+    let mut results = Vec::new();
+    for block in crate::indexer::extract_code_blocks(content) {
+        if !block.language.eq_ignore_ascii_case("query") {
+            continue;
+        }
+        let query = block
+            .content
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (notes, error) = match Query::parse(&query) {
+            Ok(parsed) => (parsed.execute(db)?, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        results.push(EmbeddedQueryResult {
+            start_line: block.start_line,
+            end_line: block.end_line,
+            query,
+            notes,
+            error,
+        });
+    }
+    Ok(results)
+}
+
+/// A word of the query; quoted words are never `AND`/`OR`/`NOT` or parentheses
+#[derive(Debug)]
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+impl Token {
+    fn is(&self, keyword: &str) -> bool {
+        !self.quoted && self.text == keyword
+    }
+}
+
+/// Recursive descent over the tokens: `OR` binds loosest, then `AND` (or
+/// plain adjacency), then `NOT`/`-`
+struct Parser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Term> {
+        let mut either = vec![self.and()?];
+        while self.peek().is_some_and(|t| t.is("OR")) {
+            self.pos += 1;
+            either.push(self.and()?);
+        }
+        Ok(if either.len() == 1 {
+            either.remove(0)
+        } else {
+            Term::Or(either)
+        })
+    }
+
+    fn and(&mut self) -> Result<Term> {
+        let mut all = Vec::new();
+        loop {
+            match self.peek() {
+                None => break,
+                Some(t) if t.is("OR") || t.is(")") => break,
+                Some(t) if t.is("AND") => self.pos += 1,
+                Some(_) => all.push(self.unary()?),
+            }
+        }
+        match all.len() {
+            0 => match self.peek() {
+                Some(token) => bail!("Expected a search term before '{}'", token.text),
+                None if self.pos > 0 => bail!("Query ends without a search term"),
+                None => Ok(Term::And(all)),
+            },
+            1 => Ok(all.remove(0)),
+            _ => Ok(Term::And(all)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Term> {
+        let Some(token) = self.next() else {
+            bail!("Query ends without a search term");
+        };
+        if token.is("NOT") || token.is("-") {
+            return Ok(Term::Not(Box::new(self.unary()?)));
+        }
+        if token.is("(") {
+            let inner = self.or()?;
+            if !self.next().is_some_and(|t| t.is(")")) {
+                bail!("Unclosed '(' in query");
+            }
+            return Ok(inner);
+        }
+        match token.text.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => Ok(Term::Not(Box::new(parse_term(rest)?))),
+            _ => parse_term(&token.text),
+        }
+    }
+}
+
+/// A single `op:value` term or bare word
+fn parse_term(token: &str) -> Result<Term> {
+    Ok(match token.split_once(':') {
+        Some(("tag", value)) => {
+            let tag = value.trim_start_matches('#');
+            if tag.is_empty() {
+                bail!("tag: needs a tag name");
+            }
+            Term::Tag(format!("#{}", tag))
+        }
+        Some(("path", value)) => Term::Path(value.trim_start_matches('/').to_string()),
+        Some(("title", value)) => Term::Title(value.to_string()),
+        Some(("links", value)) => Term::Links(
+            value
+                .trim_start_matches("[[")
+                .trim_end_matches("]]")
+                .to_string(),
+        ),
+        Some(("created", value)) => {
+            let (comparison, date) = parse_date(value)?;
+            Term::Created(comparison, date)
+        }
+        Some(("modified", value)) => {
+            let (comparison, date) = parse_date(value)?;
+            Term::Modified(comparison, date)
+        }
+        Some(("starred", value)) => match value.to_lowercase().as_str() {
+            "true" | "yes" => Term::Starred(true),
+            "false" | "no" => Term::Starred(false),
+            _ => bail!("starred: takes true or false"),
+        },
+        Some((op, _)) if is_operator(op) => bail!("Unknown search operator '{}:'", op),
+        _ => Term::Title(token.to_string()),
+    })
+}
+
+/// `>2024-01-01` and the like
+fn parse_date(value: &str) -> Result<(Comparison, String)> {
+    let (comparison, date) = if let Some(date) = value.strip_prefix(">=") {
+        (Comparison::AfterOrOn, date)
+    } else if let Some(date) = value.strip_prefix("<=") {
+        (Comparison::BeforeOrOn, date)
+    } else if let Some(date) = value.strip_prefix('>') {
+        (Comparison::After, date)
+    } else if let Some(date) = value.strip_prefix('<') {
+        (Comparison::Before, date)
+    } else {
+        (Comparison::Equal, value.strip_prefix('=').unwrap_or(value))
+    };
+    let valid = [4, 7, 10].contains(&date.len())
+        && date.char_indices().all(|(i, c)| match i {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        });
+    if !valid {
+        bail!("Dates in queries look like 2024, 2024-05 or 2024-05-01");
+    }
+    Ok((comparison, date.to_string()))
+}
+
+fn is_operator(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Split on whitespace, keeping "quoted phrases" (and `op:"quoted value"`)
+/// together. Parentheses stand alone when they open a word (`(`, `-(`) or
+/// close one that didn't open its own.
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    // Parentheses opened inside the current word, as in `title:f(x)`
+    let mut depth = 0usize;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            c if in_quotes => current.push(c),
+            c if c.is_whitespace() => {
+                flush(&mut tokens, &mut current, &mut quoted);
+                depth = 0;
+            }
+            '(' if (current.is_empty() || current == "-") && !quoted => {
+                flush(&mut tokens, &mut current, &mut quoted);
+                tokens.push(Token {
+                    text: "(".to_string(),
+                    quoted: false,
+                });
+            }
+            ')' if depth == 0 => {
+                flush(&mut tokens, &mut current, &mut quoted);
+                tokens.push(Token {
+                    text: ")".to_string(),
+                    quoted: false,
+                });
+            }
+            c => {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                current.push(c);
+            }
+        }
+    }
+    if in_quotes {
+        bail!("Unclosed quote in query");
+    }
+    flush(&mut tokens, &mut current, &mut quoted);
+    Ok(tokens)
+}
+
+/// End the word being read, if there is one
+fn flush(tokens: &mut Vec<Token>, current: &mut String, quoted: &mut bool) {
+    if !current.is_empty() || *quoted {
+        tokens.push(Token {
+            text: std::mem::take(current),
+            quoted: std::mem::take(quoted),
+        });
+    }
+}
+
+/// Terms that must all hold
+fn all_sql(terms: &[Term], params: &mut Vec<String>) -> String {
+    if terms.is_empty() {
+        return "1".to_string();
+    }
+    let clauses: Vec<String> = terms.iter().map(|t| term_sql(t, params)).collect();
+    format!("({})", clauses.join(" AND "))
+}
+
+fn term_sql(term: &Term, params: &mut Vec<String>) -> String {
+    let mut bind = |value: &str| {
+        params.push(value.to_string());
+        format!("?{}", params.len())
+    };
+    match term {
+        Term::Tag(tag) => {
+            let p = bind(tag);
+            format!(
+                "EXISTS (SELECT 1 FROM tags t WHERE t.note_path = n.path
+                 AND (lower(t.tag) = lower({p})
+                      OR lower(substr(t.tag, 1, length({p}) + 1)) = lower({p}) || '/'))"
+            )
+        }
+        Term::Path(prefix) => {
+            let p = bind(prefix);
+            format!("lower(substr(n.path, 1, length({p}))) = lower({p})")
+        }
+        Term::Title(text) => {
+            let p = bind(text);
+            format!("instr(lower(n.title), lower({p})) > 0")
+        }
+        Term::Links(target) => {
+            let p = bind(target);
+            format!(
+                "EXISTS (SELECT 1 FROM links l WHERE l.source_path = n.path
+                 AND lower(l.target_name) = lower({p}))"
+            )
+        }
+        Term::Created(comparison, date) => date_sql("n.created_at", *comparison, &bind(date)),
+        Term::Modified(comparison, date) => date_sql("n.modified_at", *comparison, &bind(date)),
+        Term::Starred(starred) => format!("n.starred = {}", i32::from(*starred)),
+        Term::Not(inner) => format!("NOT ({})", term_sql(inner, params)),
+        Term::Or(terms) => {
+            let clauses: Vec<String> = terms.iter().map(|t| term_sql(t, params)).collect();
+            format!("({})", clauses.join(" OR "))
+        }
+        Term::And(terms) => all_sql(terms, params),
+    }
+}
+
+/// Compare a date column at the precision of `p`; notes without the date
+/// never match (and so do match under `NOT`)
+fn date_sql(column: &str, comparison: Comparison, p: &str) -> String {
+    let op = match comparison {
+        Comparison::Equal => "=",
+        Comparison::Before => "<",
+        Comparison::BeforeOrOn => "<=",
+        Comparison::After => ">",
+        Comparison::AfterOrOn => ">=",
+    };
+    format!("coalesce(substr({column}, 1, length({p})) {op} {p}, 0)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_parse_query() {
+        let query =
+            Query::parse(r#"tag:inbox -path:/Archive "weekly review" links:[[Plan]]"#).unwrap();
+        assert_eq!(
+            query.terms,
+            vec![
+                Term::Tag("#inbox".to_string()),
+                Term::Not(Box::new(Term::Path("Archive".to_string()))),
+                Term::Title("weekly review".to_string()),
+                Term::Links("Plan".to_string()),
+            ]
+        );
+        let (sql, params) = query.to_sql();
+        assert!(sql.contains("NOT (lower(substr(n.path, 1, length(?2)))"));
+        assert_eq!(params.len(), 4);
+
+        assert!(Query::parse("stars:5").is_err());
+        assert!(Query::parse("\"open").is_err());
+    }
+
+    #[test]
+    fn test_boolean_and_date_queries() {
+        let query =
+            Query::parse("tag:work (created:>=2024-01 OR starred:true) NOT path:Archive").unwrap();
+        assert_eq!(
+            query.terms,
+            vec![
+                Term::Tag("#work".to_string()),
+                Term::Or(vec![
+                    Term::Created(Comparison::AfterOrOn, "2024-01".to_string()),
+                    Term::Starred(true),
+                ]),
+                Term::Not(Box::new(Term::Path("Archive".to_string()))),
+            ]
+        );
+        assert!(Query::parse("created:yesterday").is_err());
+        assert!(Query::parse("(tag:a").is_err());
+        assert!(Query::parse("tag:a OR").is_err());
+        assert_eq!(
+            Query::parse(r#""OR" links:"A (B)" title:f(x)"#)
+                .unwrap()
+                .terms,
+            vec![
+                Term::Title("OR".to_string()),
+                Term::Links("A (B)".to_string()),
+                Term::Title("f(x)".to_string())
+            ]
+        );
+
+        let vault = TestVault::with_notes(&[
+            ("Old.md", "---\ncreated: 2023-12-31\ntags: [work]\n---\n"),
+            (
+                "New.md",
+                "---\ncreated: 2024-02-01T09:00\ntags: [work]\n---\n",
+            ),
+            ("Undated.md", "#work\n"),
+            (
+                "Archive/Done.md",
+                "---\ncreated: 2024-03-01\ntags: [work]\n---\n",
+            ),
+        ]);
+        let db = vault.index();
+        db.toggle_star("Undated.md").unwrap();
+        let paths = |query: &str| -> Vec<String> {
+            Query::parse(query)
+                .unwrap()
+                .execute(&db)
+                .unwrap()
+                .into_iter()
+                .map(|n| n.path)
+                .collect()
+        };
+        assert_eq!(
+            paths("tag:work (created:>=2024-01 OR starred:true) NOT path:Archive"),
+            vec!["New.md", "Undated.md"]
+        );
+        assert_eq!(paths("created:2023"), vec!["Old.md"]);
+        // Undated notes fail every date comparison, so NOT keeps them
+        assert_eq!(
+            paths("-created:<2024 tag:work"),
+            vec!["Archive/Done.md", "New.md", "Undated.md"]
+        );
+    }
+
+    #[test]
+    fn test_saved_search_matches_indexed_notes() {
+        let vault = TestVault::with_notes(&[
+            ("Inbox/Call.md", "# Call\n#inbox\n"),
+            ("Archive/Old.md", "# Old\n#inbox\n"),
+            ("Plan.md", "# Plan\n#inbox/later\n"),
+        ]);
+        let db = vault.index();
+        db.save_search("Inbox", "tag:#inbox").unwrap();
+        db.save_search("Inbox", "tag:#inbox -path:Archive").unwrap();
+        assert_eq!(db.list_saved_searches().unwrap().len(), 1);
+
+        let saved = db.get_saved_search("Inbox").unwrap().unwrap();
+        let notes = Query::parse(&saved).unwrap().execute(&db).unwrap();
+        let paths: Vec<&str> = notes.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, vec!["Inbox/Call.md", "Plan.md"]);
+
+        db.delete_saved_search("Inbox").unwrap();
+        assert!(db.get_saved_search("Inbox").unwrap().is_none());
+    }
+}
@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::database::{Database, REDACTION_SETTING};
+use crate::vault::{path_in_folder, Vault};
+
+/// What to keep out of exported and published output. Stored as JSON in
+/// the `export.redaction` setting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRules {
+    /// A note carrying one of these tags (or a nested tag below it) is left
+    /// out; a heading carrying one drops its whole section
+    #[serde(default = "default_private_tags")]
+    pub private_tags: Vec<String>,
+    /// Vault path prefixes that are never exported
+    #[serde(default)]
+    pub excluded_paths: Vec<String>,
+    /// Regular expressions whose matches are replaced in exported text
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+}
+
+impl Default for RedactionRules {
+    fn default() -> Self {
+        RedactionRules {
+            private_tags: default_private_tags(),
+            excluded_paths: Vec::new(),
+            patterns: Vec::new(),
+            replacement: default_replacement(),
+        }
+    }
+}
+
+fn default_private_tags() -> Vec<String> {
+    vec!["#private".to_string()]
+}
+
+fn default_replacement() -> String {
+    "[redacted]".to_string()
+}
+
+/// A note after redaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redaction {
+    pub content: String,
+    /// Private sections removed
+    pub sections: usize,
+    /// Pattern matches replaced
+    pub matches: usize,
+}
+
+/// Compiled redaction rules. Every exporter runs notes through this before
+/// writing anything, so what leaves the vault never depends on the frontend.
+pub struct Redactor {
+    rules: RedactionRules,
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn new(rules: RedactionRules) -> Result<Redactor> {
+        let patterns = rules
+            .patterns
+            .iter()
+            .map(|p| Regex::new(p).with_context(|| format!("Invalid redaction pattern '{}'", p)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Redactor { rules, patterns })
+    }
+
+    /// The vault's configured rules (defaults if none are saved)
+    pub fn load(db: &Database) -> Result<Redactor> {
+        let rules = match db.get_setting(REDACTION_SETTING)? {
+            Some(json) => serde_json::from_str(&json).context("Invalid redaction settings")?,
+            None => RedactionRules::default(),
+        };
+        Redactor::new(rules)
+    }
+
+    pub fn rules(&self) -> &RedactionRules {
+        &self.rules
+    }
+
+    /// Whether a path falls under an excluded folder
+    pub fn excludes_path(&self, path: &str) -> bool {
+        self.rules
+            .excluded_paths
+            .iter()
+            .any(|folder| path_in_folder(path, folder))
+    }
+
+    /// Redact a note for export. `None` means the note must be left out.
+    pub fn redact(&self, path: &str, content: &str) -> Option<Redaction> {
+        if self.excludes_path(path) {
+            return None;
+        }
+        let frontmatter = Vault::parse_frontmatter(content);
+        if frontmatter.tags.iter().any(|t| self.is_private_tag(t)) {
+            return None;
+        }
+
+        let mut kept = String::with_capacity(content.len());
+        let mut sections = 0;
+        let mut in_fence = false;
+        // Level of the private heading whose section is being dropped
+        let mut skipping: Option<u8> = None;
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            let heading = if in_fence {
+                None
+            } else {
+                crate::indexer::parse_heading_line(line)
+            };
+            if let Some((level, text)) = &heading {
+                if skipping.is_some_and(|open| *level <= open) {
+                    skipping = None;
+                }
+                if skipping.is_none() && self.has_private_tag(text) {
+                    skipping = Some(*level);
+                    sections += 1;
+                }
+            }
+            if skipping.is_some() {
+                continue;
+            }
+            // A private tag in the body marks the whole note
+            if heading.is_none() && !in_fence && self.has_private_tag(line) {
+                return None;
+            }
+            kept.push_str(line);
+        }
+
+        let (content, matches) = self.redact_text(&kept);
+        Some(Redaction {
+            content,
+            sections,
+            matches,
+        })
+    }
+
+    /// Replace pattern matches in a piece of text (titles, link labels, ...)
+    pub fn redact_text(&self, text: &str) -> (String, usize) {
+        let mut out = text.to_string();
+        let mut matches = 0;
+        for pattern in &self.patterns {
+            let found = pattern.find_iter(&out).count();
+            if found > 0 {
+                matches += found;
+                out = pattern
+                    .replace_all(&out, regex::NoExpand(&self.rules.replacement))
+                    .into_owned();
+            }
+        }
+        (out, matches)
+    }
+
+    fn has_private_tag(&self, text: &str) -> bool {
+        crate::indexer::extract_tags(text)
+            .iter()
+            .any(|t| self.is_private_tag(t))
+    }
+
+    fn is_private_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim_start_matches('#');
+        self.rules.private_tags.iter().any(|private| {
+            let private = private.trim_start_matches('#');
+            tag.eq_ignore_ascii_case(private)
+                || (tag.len() > private.len()
+                    && tag.as_bytes()[private.len()] == b'/'
+                    && tag[..private.len()].eq_ignore_ascii_case(private))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_sections_and_patterns() {
+        let redactor = Redactor::new(RedactionRules {
+            excluded_paths: vec!["Journal/".to_string()],
+            patterns: vec![r"\b\d{3}-\d{4}\b".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        let content = "# Plan\nCall 555-1234\n## Salary #private/work\nsecret\n### Detail\nmore\n## Public\n```\n#private\n```\ndone\n";
+        let redacted = redactor.redact("Plan.md", content).unwrap();
+        assert_eq!(
+            redacted.content,
+            "# Plan\nCall [redacted]\n## Public\n```\n#private\n```\ndone\n"
+        );
+        assert_eq!((redacted.sections, redacted.matches), (1, 1));
+
+        assert!(redactor.redact("Journal/Day.md", "hi").is_none());
+        assert!(redactor.redact("Journaling.md", "hi").is_some());
+        assert!(redactor
+            .redact("a.md", "---\ntags: [private]\n---\nbody")
+            .is_none());
+        assert!(redactor.redact("b.md", "Body #Private\n").is_none());
+        assert!(redactor.redact("c.md", "Body #privateer\n").is_some());
+    }
+}
@@ -0,0 +1,346 @@
+//! Related notes for the note being edited: notes sharing its tags, linked
+//! alongside it, with a similar title or similar content. Notes it already
+//! links to are left out, since the point is suggesting links it lacks.
+//! Also ranks `[[` completions by the same kind of signals.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::database::{CachedNote, Database};
+use crate::embeddings;
+
+const TAG_WEIGHT: f64 = 1.0;
+const LINK_WEIGHT: f64 = 1.0;
+const TITLE_WEIGHT: f64 = 0.5;
+const CONTENT_WEIGHT: f64 = 1.0;
+/// Title and content similarity below these is noise, not a reason
+const MIN_TITLE_SIMILARITY: f64 = 0.3;
+const MIN_CONTENT_SIMILARITY: f64 = 0.2;
+
+/// A note worth linking from the current one, with why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedNote {
+    pub path: String,
+    pub title: String,
+    /// Weighted sum of the signals below; higher is more related
+    pub score: f64,
+    pub shared_tags: Vec<String>,
+    /// Notes linking to both, plus notes both link to
+    pub shared_links: usize,
+    /// Character trigram overlap of the titles (0 to 1)
+    pub title_similarity: f64,
+    /// Cosine similarity of the notes' lexical vectors (see `embeddings`);
+    /// `None` if either has none
+    pub content_similarity: Option<f64>,
+}
+
+/// Notes `keep` accepts related to `path`, most related first
+pub fn related_notes(
+    db: &Database,
+    path: &str,
+    limit: usize,
+    keep: impl Fn(&str) -> bool,
+) -> Result<Vec<RelatedNote>> {
+    let notes = db.get_all_notes()?;
+    let Some(current) = notes.iter().find(|note| note.path == path) else {
+        return Ok(Vec::new());
+    };
+
+    let mut outgoing: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut incoming: HashMap<String, HashSet<String>> = HashMap::new();
+    for link in db.get_resolved_links()? {
+        if let Some(target) = link.path.filter(|target| *target != link.source) {
+            outgoing
+                .entry(link.source.clone())
+                .or_default()
+                .insert(target.clone());
+            incoming.entry(target).or_default().insert(link.source);
+        }
+    }
+    let none = HashSet::new();
+    let own_out = outgoing.get(path).unwrap_or(&none);
+    let own_in = incoming.get(path).unwrap_or(&none);
+
+    let tags = db.get_all_note_tags()?;
+    let own_tags: HashSet<&String> = tags.get(path).into_iter().flatten().collect();
+    let vectors = embeddings::note_vectors(db)?;
+    let own_vector = vectors.get(path);
+    let own_trigrams = trigrams(&current.title);
+
+    let mut related = Vec::new();
+    for note in &notes {
+        if note.path == path || own_out.contains(&note.path) || !keep(&note.path) {
+            continue;
+        }
+
+        let other_tags: HashSet<&String> = tags.get(&note.path).into_iter().flatten().collect();
+        let mut shared_tags: Vec<String> = own_tags
+            .intersection(&other_tags)
+            .map(|tag| tag.to_string())
+            .collect();
+        shared_tags.sort();
+        let tag_score = jaccard(shared_tags.len(), own_tags.len() + other_tags.len());
+
+        let other_out = outgoing.get(&note.path).unwrap_or(&none);
+        let other_in = incoming.get(&note.path).unwrap_or(&none);
+        let shared_links =
+            own_in.intersection(other_in).count() + own_out.intersection(other_out).count();
+        let link_score = if shared_links == 0 {
+            0.0
+        } else {
+            let degrees =
+                ((own_in.len() + own_out.len()) * (other_in.len() + other_out.len())) as f64;
+            shared_links as f64 / degrees.sqrt()
+        };
+
+        let other_trigrams = trigrams(&note.title);
+        let title_similarity = jaccard(
+            own_trigrams.intersection(&other_trigrams).count(),
+            own_trigrams.len() + other_trigrams.len(),
+        );
+        let content_similarity = own_vector
+            .zip(vectors.get(&note.path))
+            .map(|(a, b)| embeddings::similarity(a, b) as f64);
+
+        let mut score = TAG_WEIGHT * tag_score + LINK_WEIGHT * link_score;
+        if title_similarity >= MIN_TITLE_SIMILARITY {
+            score += TITLE_WEIGHT * title_similarity;
+        }
+        if let Some(similarity) = content_similarity.filter(|s| *s >= MIN_CONTENT_SIMILARITY) {
+            score += CONTENT_WEIGHT * similarity;
+        }
+        if score > 0.0 {
+            related.push(RelatedNote {
+                path: note.path.clone(),
+                title: note.title.clone(),
+                score,
+                shared_tags,
+                shared_links,
+                title_similarity,
+                content_similarity,
+            });
+        }
+    }
+
+    related.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    related.truncate(limit);
+    Ok(related)
+}
+
+/// `[[` completions for `query`, best first: title match, how often the
+/// current note's neighbours (notes it links to or is linked from) link to
+/// each candidate, and how recently and often the candidate was opened
+pub fn link_suggestions(
+    db: &Database,
+    query: &str,
+    from_path: Option<&str>,
+    limit: usize,
+    keep: impl Fn(&str) -> bool,
+) -> Result<Vec<CachedNote>> {
+    let mut notes = db.get_all_notes()?;
+    notes.retain(|note| keep(&note.path));
+    let links = db.get_all_links()?;
+    // usize::MAX binds as -1, which SQLite treats as "no limit"
+    let recents = db.get_recent_notes(usize::MAX)?;
+
+    // Link text can name a note by title, file name or vault path
+    let mut by_name: HashMap<String, &str> = HashMap::new();
+    for note in &notes {
+        let without_ext = note.path.strip_suffix(".md").unwrap_or(&note.path);
+        let stem = without_ext.rsplit('/').next().unwrap_or(without_ext);
+        for name in [stem, without_ext, note.title.as_str()] {
+            by_name.entry(name.to_lowercase()).or_insert(&note.path);
+        }
+    }
+    let resolve = |target: &str| by_name.get(&target.to_lowercase()).copied();
+
+    let mut co_links: HashMap<&str, usize> = HashMap::new();
+    if let Some(from) = from_path {
+        let neighbours: HashSet<&str> = links
+            .iter()
+            .filter_map(|(source, target)| {
+                let target = resolve(target)?;
+                if source == from {
+                    Some(target)
+                } else if target == from {
+                    Some(source.as_str())
+                } else {
+                    None
+                }
+            })
+            .filter(|path| *path != from)
+            .collect();
+        for (source, target) in &links {
+            if neighbours.contains(source.as_str()) {
+                if let Some(target) = resolve(target) {
+                    *co_links.entry(target).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let now = chrono::Utc::now();
+    let frecency: HashMap<&str, f64> = recents
+        .iter()
+        .map(|r| {
+            let days = chrono::DateTime::parse_from_rfc3339(&r.last_opened)
+                .map(|t| (now - t.with_timezone(&chrono::Utc)).num_hours() as f64 / 24.0)
+                .unwrap_or(f64::MAX);
+            let decay = 1.0 / (1.0 + days.max(0.0) / 7.0);
+            (r.path.as_str(), (r.open_count as f64).ln_1p() * decay)
+        })
+        .collect();
+
+    let query_lower = query.trim().to_lowercase();
+    let mut scored: Vec<(f64, &CachedNote)> = notes
+        .iter()
+        .filter(|note| Some(note.path.as_str()) != from_path)
+        .filter_map(|note| {
+            let tier = title_match_tier(&note.title.to_lowercase(), &query_lower)?;
+            let match_score = [100.0, 75.0, 50.0, 25.0][tier];
+            let link_score = co_links
+                .get(note.path.as_str())
+                .map_or(0.0, |&n| 20.0 * (n as f64).ln_1p());
+            let recent_score = frecency.get(note.path.as_str()).map_or(0.0, |f| 20.0 * f);
+            Some((match_score + link_score + recent_score, note))
+        })
+        .collect();
+
+    scored.sort_by(|(a, na), (b, nb)| b.total_cmp(a).then_with(|| na.title.cmp(&nb.title)));
+    Ok(scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, note)| note.clone())
+        .collect())
+}
+
+/// How well a title matches a query: 0 exact, 1 prefix, 2 substring,
+/// 3 fuzzy (all query chars in order), None for no match
+pub fn title_match_tier(title_lower: &str, query_lower: &str) -> Option<usize> {
+    if title_lower == query_lower {
+        Some(0)
+    } else if title_lower.starts_with(query_lower) {
+        Some(1)
+    } else if title_lower.contains(query_lower) {
+        Some(2)
+    } else {
+        let mut query_chars = query_lower.chars();
+        let mut current = query_chars.next();
+        for c in title_lower.chars() {
+            if current == Some(c) {
+                current = query_chars.next();
+            }
+        }
+        current.is_none().then_some(3)
+    }
+}
+
+// ─── Internal helpers ───────────────────────────────────────────────────────
+
+/// Intersection over union, from the intersection and the two set sizes
+fn jaccard(shared: usize, total: usize) -> f64 {
+    match total - shared {
+        0 => 0.0,
+        union => shared as f64 / union as f64,
+    }
+}
+
+fn trigrams(title: &str) -> HashSet<String> {
+    let padded: Vec<char> = format!("  {} ", title.to_lowercase()).chars().collect();
+    padded.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_related_notes_combine_signals() {
+        let vault = TestVault::with_notes(&[
+            (
+                "Rust ownership.md",
+                "---\ntags: [rust, memory]\n---\nBorrowing rules and [[Lifetimes]].\n",
+            ),
+            (
+                "Rust ownership rules.md",
+                "---\ntags: [rust]\n---\nBorrowing rules.\n",
+            ),
+            ("Lifetimes.md", "# Lifetimes\n"),
+            ("Index.md", "[[Rust ownership]] [[Allocators]]\n"),
+            ("Allocators.md", "# Allocators\nHeap layout.\n"),
+            ("Gardening.md", "# Gardening\nTomatoes.\n"),
+        ]);
+        let db = vault.index();
+
+        let related = related_notes(&db, "Rust ownership.md", 10, |_| true).unwrap();
+        let paths: Vec<&str> = related.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths[0], "Rust ownership rules.md");
+        assert!(paths.contains(&"Allocators.md"));
+        // Already linked, or nothing in common
+        assert!(!paths.contains(&"Lifetimes.md"));
+        assert!(!paths.contains(&"Gardening.md"));
+
+        let rules = &related[0];
+        assert_eq!(rules.shared_tags, vec!["#rust"]);
+        assert!(rules.title_similarity >= MIN_TITLE_SIMILARITY);
+        assert!(rules.content_similarity.unwrap() > MIN_CONTENT_SIMILARITY);
+        let allocators = related.iter().find(|r| r.path == "Allocators.md").unwrap();
+        assert_eq!(allocators.shared_links, 1);
+
+        let kept = related_notes(&db, "Rust ownership.md", 10, |p| {
+            p != "Rust ownership rules.md"
+        });
+        assert!(kept
+            .unwrap()
+            .iter()
+            .all(|r| r.path != "Rust ownership rules.md"));
+        assert!(related_notes(&db, "Missing.md", 10, |_| true)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_link_suggestions_rank_match_co_links_and_opens() {
+        let vault = TestVault::with_notes(&[
+            ("Current.md", "# Plan notes\n"),
+            ("Home.md", "# Home\n[[Current]] [[Planets]]\n"),
+            ("Plan.md", "# Plan\n"),
+            ("Plan B.md", "# Plan B\n"),
+            ("Planets.md", "# Planets\n"),
+            ("Project plan.md", "# Project plan\n"),
+            ("Explanation.md", "# Explanation\n"),
+            ("Gardening.md", "# Gardening\n"),
+        ]);
+        let db = vault.index();
+        for _ in 0..3 {
+            db.record_note_opened("Project plan.md").unwrap();
+        }
+
+        let titles = |limit, keep: &dyn Fn(&str) -> bool| -> Vec<String> {
+            link_suggestions(&db, " Plan", Some("Current.md"), limit, keep)
+                .unwrap()
+                .into_iter()
+                .map(|note| note.title)
+                .collect()
+        };
+        // Exact beats prefix; a note linked beside the current one and a
+        // frequently opened one climb past a plain prefix match
+        assert_eq!(
+            titles(10, &|_| true),
+            vec!["Plan", "Planets", "Project plan", "Plan B", "Explanation"]
+        );
+        assert_eq!(titles(2, &|_| true), vec!["Plan", "Planets"]);
+        assert!(!titles(10, &|p| p != "Plan.md").contains(&"Plan".to_string()));
+
+        assert_eq!(title_match_tier("plan", "plan"), Some(0));
+        assert_eq!(title_match_tier("explanation", "plan"), Some(2));
+        assert_eq!(title_match_tier("pole lane", "plan"), Some(3));
+        assert_eq!(title_match_tier("gardening", "plan"), None);
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use spellbook::Dictionary;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::vault::Vault;
+
+/// Word list kept next to the cache database so it survives reindexing
+const CUSTOM_DICTIONARY_FILE: &str = "custom-dictionary.txt";
+const MAX_SUGGESTIONS: usize = 5;
+
+/// A misspelled word with its position in the checked text.
+/// Offsets are UTF-16 code units so they line up with editor positions.
+#[derive(Debug, Clone, Serialize)]
+pub struct Misspelling {
+    pub word: String,
+    pub start: usize,
+    pub end: usize,
+    pub suggestions: Vec<String>,
+}
+
+/// Hunspell dictionaries loaded on demand plus the vault's custom word list
+#[derive(Default)]
+pub struct SpellChecker {
+    dictionaries: HashMap<String, Dictionary>,
+    custom_words: Option<HashSet<String>>,
+}
+
+impl SpellChecker {
+    /// Check the prose in a markdown document against the dictionary for `lang`
+    pub fn check_text(
+        &mut self,
+        vault_path: &Path,
+        text: &str,
+        lang: &str,
+    ) -> Result<Vec<Misspelling>> {
+        let lang = normalize_lang(lang)?;
+        if !self.dictionaries.contains_key(&lang) {
+            let dictionary = load_dictionary(vault_path, &lang)?;
+            self.dictionaries.insert(lang.clone(), dictionary);
+        }
+        self.load_custom_words(vault_path)?;
+
+        let dictionary = &self.dictionaries[&lang];
+        let custom = self.custom_words.as_ref().expect("custom words loaded");
+
+        let mut verdicts: HashMap<&str, Option<Vec<String>>> = HashMap::new();
+        let mut misspelled = Vec::new();
+        for (start, end) in checkable_words(text) {
+            let word = &text[start..end];
+            let verdict = verdicts.entry(word).or_insert_with(|| {
+                if custom.contains(word)
+                    || custom.contains(&word.to_lowercase())
+                    || dictionary.check(word)
+                {
+                    return None;
+                }
+                let mut suggestions = Vec::new();
+                dictionary.suggest(word, &mut suggestions);
+                suggestions.truncate(MAX_SUGGESTIONS);
+                Some(suggestions)
+            });
+            if let Some(suggestions) = verdict {
+                misspelled.push((start, end, suggestions.clone()));
+            }
+        }
+
+        let spans: Vec<(usize, usize)> = misspelled.iter().map(|(s, e, _)| (*s, *e)).collect();
+        let utf16 = utf16_offsets(text, &spans);
+        Ok(misspelled
+            .into_iter()
+            .zip(utf16)
+            .map(
+                |((start, end, suggestions), (start16, end16))| Misspelling {
+                    word: text[start..end].to_string(),
+                    start: start16,
+                    end: end16,
+                    suggestions,
+                },
+            )
+            .collect())
+    }
+
+    /// Add a word to the vault's custom dictionary, appending it to the word list file
+    pub fn add_word(&mut self, vault_path: &Path, word: &str) -> Result<()> {
+        let word = word.trim();
+        if word.is_empty() || word.chars().any(char::is_whitespace) {
+            bail!("Custom dictionary entries must be a single word");
+        }
+        self.load_custom_words(vault_path)?;
+        let custom = self.custom_words.as_mut().expect("custom words loaded");
+        if !custom.insert(word.to_string()) {
+            return Ok(());
+        }
+
+        let path = custom_dictionary_path(vault_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create .synapse cache dir")?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context("Failed to open custom dictionary")?;
+        writeln!(file, "{}", word).context("Failed to write custom dictionary")?;
+        Ok(())
+    }
+
+    fn load_custom_words(&mut self, vault_path: &Path) -> Result<()> {
+        if self.custom_words.is_some() {
+            return Ok(());
+        }
+        let path = custom_dictionary_path(vault_path);
+        let words = if path.exists() {
+            fs::read_to_string(&path)
+                .context("Failed to read custom dictionary")?
+                .lines()
+                .map(str::trim)
+                .filter(|w| !w.is_empty())
+                .map(String::from)
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        self.custom_words = Some(words);
+        Ok(())
+    }
+}
+
+fn custom_dictionary_path(vault_path: &Path) -> PathBuf {
+    Vault::cache_dir(vault_path).join(CUSTOM_DICTIONARY_FILE)
+}
+
+/// Hunspell files are named `en_US.aff` / `en_US.dic`; accept `en-US` as well.
+/// Anything but letters and `_` is refused, since the name becomes a file path.
+fn normalize_lang(lang: &str) -> Result<String> {
+    let lang = lang.trim().replace('-', "_");
+    if lang.is_empty() || !lang.chars().all(|c| c.is_ascii_alphabetic() || c == '_') {
+        bail!("Invalid dictionary language: '{}'", lang);
+    }
+    Ok(lang)
+}
+
+/// Directories searched for `<lang>.aff` + `<lang>.dic`, vault-local first
+fn dictionary_dirs(vault_path: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![Vault::cache_dir(vault_path).join("dictionaries")];
+    if let Ok(config_dir) = Vault::config_dir() {
+        dirs.push(config_dir.join("dictionaries"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join("Library").join("Spelling"));
+    }
+    dirs.push(PathBuf::from("/usr/share/hunspell"));
+    dirs.push(PathBuf::from("/usr/share/myspell"));
+    dirs.push(PathBuf::from("/usr/share/myspell/dicts"));
+    dirs
+}
+
+fn load_dictionary(vault_path: &Path, lang: &str) -> Result<Dictionary> {
+    let dirs = dictionary_dirs(vault_path);
+    let dir = dirs
+        .iter()
+        .find(|dir| {
+            dir.join(format!("{}.aff", lang)).is_file()
+                && dir.join(format!("{}.dic", lang)).is_file()
+        })
+        .with_context(|| {
+            let searched: Vec<String> = dirs.iter().map(|d| d.display().to_string()).collect();
+            format!(
+                "No dictionary found for '{}' (searched {})",
+                lang,
+                searched.join(", ")
+            )
+        })?;
+
+    // Older dictionaries are often Latin-1; decode lossily rather than refusing them
+    let aff = fs::read(dir.join(format!("{}.aff", lang))).context("Failed to read .aff file")?;
+    let dic = fs::read(dir.join(format!("{}.dic", lang))).context("Failed to read .dic file")?;
+    Dictionary::new(
+        &String::from_utf8_lossy(&aff),
+        &String::from_utf8_lossy(&dic),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to parse dictionary '{}': {}", lang, e))
+}
+
+/// Byte ranges of the words worth spellchecking. Frontmatter, fenced code,
+/// inline code, wikilinks, URLs, tags and HTML are skipped, as are words with
+/// digits or inner capitals (identifiers, acronyms).
+fn checkable_words(text: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut offset = 0;
+    let mut in_frontmatter = false;
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+
+        if i == 0 && trimmed == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            if trimmed == "---" {
+                in_frontmatter = false;
+            }
+            continue;
+        }
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        words.extend(
+            line_words(line)
+                .into_iter()
+                .map(|(s, e)| (line_start + s, line_start + e)),
+        );
+    }
+    words
+}
+
+fn line_words(line: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        if let Some(skip) = skip_markup(rest) {
+            i += skip;
+            continue;
+        }
+        let c = rest.chars().next().expect("non-empty");
+        if !c.is_alphanumeric() {
+            i += c.len_utf8();
+            continue;
+        }
+
+        // Emails, paths and bare domains are not prose
+        let chunk_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let chunk = &rest[..chunk_len];
+        if chunk.contains('@') || chunk.contains("://") || chunk.contains('/') {
+            i += chunk_len;
+            continue;
+        }
+
+        let len = word_len(rest);
+        let word = &rest[..len];
+        let checkable = word.chars().count() > 1
+            && !word.chars().any(|c| c.is_numeric() || c == '_')
+            && !word.chars().skip(1).any(char::is_uppercase);
+        if checkable {
+            words.push((i, i + len));
+        }
+        i += len;
+    }
+    words
+}
+
+/// Length of a word: letters and digits, with apostrophes allowed between letters
+fn word_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let inner_apostrophe = (c == '\'' || c == '’')
+            && len > 0
+            && chars.peek().is_some_and(|(_, next)| next.is_alphabetic());
+        if c.is_alphanumeric() || c == '_' || inner_apostrophe {
+            len = idx + c.len_utf8();
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+/// If `s` starts with markup that should not be spellchecked, return how many bytes to skip
+fn skip_markup(s: &str) -> Option<usize> {
+    let until = |pat: &str, from: usize| s[from..].find(pat).map(|p| from + p + pat.len());
+    let to_whitespace = || s.find(char::is_whitespace).unwrap_or(s.len());
+
+    if s.starts_with("[[") || s.starts_with("![[") {
+        return Some(until("]]", 2).unwrap_or(s.len()));
+    }
+    if s.starts_with("](") {
+        return Some(until(")", 2).unwrap_or(s.len()));
+    }
+    if s.starts_with('`') {
+        let ticks = s.len() - s.trim_start_matches('`').len();
+        return Some(until(&s[..ticks], ticks).unwrap_or(ticks));
+    }
+    if s.starts_with("http://") || s.starts_with("https://") || s.starts_with("www.") {
+        return Some(to_whitespace());
+    }
+    let mut chars = s.chars();
+    let first = chars.next()?;
+    let second = chars.next();
+    match (first, second) {
+        // #tag and ^block-id
+        ('#' | '^', Some(c)) if c.is_alphanumeric() || c == '_' || c == '-' => {
+            Some(to_whitespace())
+        }
+        ('<', Some(c)) if c.is_ascii_alphabetic() || c == '/' || c == '!' => {
+            Some(until(">", 1).unwrap_or(1))
+        }
+        _ => None,
+    }
+}
+
+/// Convert sorted byte ranges into UTF-16 offsets
+fn utf16_offsets(text: &str, spans: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut out = Vec::with_capacity(spans.len());
+    let mut byte = 0;
+    let mut units = 0;
+    let mut chars = text.chars();
+    let mut advance_to = |target: usize| {
+        while byte < target {
+            let c = chars.next().expect("offset within text");
+            byte += c.len_utf8();
+            units += c.len_utf16();
+        }
+        units
+    };
+    for &(start, end) in spans {
+        let start16 = advance_to(start);
+        let end16 = advance_to(end);
+        out.push((start16, end16));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<&str> {
+        checkable_words(text)
+            .into_iter()
+            .map(|(s, e)| &text[s..e])
+            .collect()
+    }
+
+    #[test]
+    fn test_checkable_words_skip_markup() {
+        let text = "---\ntitle: Teh\n---\n# Helo wrld\nSee [[Some Nte]] and `cde` at https://exmple.com #tgg\n```\nfn mian() {}\n```\nit's dne ^blk1 with NASA and iPhone v2\n";
+        assert_eq!(
+            words(text),
+            vec!["Helo", "wrld", "See", "and", "at", "it's", "dne", "with", "and"]
+        );
+    }
+
+    #[test]
+    fn test_utf16_offsets() {
+        let text = "héllo 😀 wrld";
+        let start = text.find("wrld").unwrap();
+        assert_eq!(utf16_offsets(text, &[(start, start + 4)]), vec![(9, 13)]);
+    }
+
+    #[test]
+    fn test_normalize_lang() {
+        assert_eq!(normalize_lang(" en-US ").unwrap(), "en_US");
+        assert_eq!(normalize_lang("de_DE").unwrap(), "de_DE");
+        assert!(normalize_lang("../../x").is_err());
+        assert!(normalize_lang("/etc/x").is_err());
+        assert!(normalize_lang("").is_err());
+    }
+}
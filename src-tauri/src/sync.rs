@@ -0,0 +1,40 @@
+use std::sync::{Mutex, MutexGuard};
+
+/// `Mutex::lock` that recovers from poisoning instead of panicking.
+///
+/// Shared state here is swapped in whole (a path, a database handle, a
+/// connection between transactions), so a panic while it was held doesn't
+/// leave it half-written. Without this, one panicking command would make
+/// every later command that touches the same state panic too.
+pub trait LockExt<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            log::warn!("Recovering state left locked by a panic");
+            let guard = poisoned.into_inner();
+            self.clear_poison();
+            guard
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_recovers_from_poison() {
+        let state = Mutex::new(Some(1));
+        let _ = std::panic::catch_unwind(|| {
+            let _guard = state.lock().unwrap();
+            panic!("command failed");
+        });
+        assert!(state.is_poisoned());
+
+        assert_eq!(*state.lock_or_recover(), Some(1));
+        assert!(!state.is_poisoned());
+    }
+}
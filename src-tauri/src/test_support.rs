@@ -0,0 +1,130 @@
+//! Fixtures for tests: throwaway vaults under the system temp directory,
+//! never the user's configured vault or home directory.
+
+use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once, OnceLock};
+
+use crate::database::Database;
+use crate::sync::LockExt;
+
+/// A vault in a fresh temp directory, removed again on drop
+pub struct TestVault {
+    root: PathBuf,
+}
+
+impl TestVault {
+    pub fn new() -> Self {
+        let root = std::env::temp_dir().join(format!("synapse-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).expect("create test vault");
+        TestVault { root }
+    }
+
+    /// A vault holding the given `(path, content)` files
+    pub fn with_notes(notes: &[(&str, &str)]) -> Self {
+        let vault = Self::new();
+        for (path, content) in notes {
+            vault.write(path, content);
+        }
+        vault
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Write a file, creating its folders
+    pub fn write(&self, path: &str, content: &str) {
+        let file = self.root.join(path);
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent).expect("create test folder");
+        }
+        fs::write(file, content).expect("write test file");
+    }
+
+    pub fn remove(&self, path: &str) {
+        fs::remove_file(self.root.join(path)).expect("remove test file");
+    }
+
+    /// Open the vault's cache and index every file in it
+    pub fn index(&self) -> Database {
+        let db = Database::init_for_vault(&self.root).expect("open test cache");
+        db.reindex_vault(&self.root).expect("index test vault");
+        db
+    }
+}
+
+impl Drop for TestVault {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Point the keyring crate at an in-process store shared by every entry,
+/// so credential tests never touch the OS keychain
+pub fn use_memory_keychain() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| keyring::set_default_credential_builder(Box::new(MemoryKeychain)));
+}
+
+fn memory_secrets() -> &'static Mutex<HashMap<String, Vec<u8>>> {
+    static SECRETS: OnceLock<Mutex<HashMap<String, Vec<u8>>>> = OnceLock::new();
+    SECRETS.get_or_init(Default::default)
+}
+
+#[derive(Debug)]
+struct MemoryKeychain;
+
+impl CredentialBuilderApi for MemoryKeychain {
+    fn build(
+        &self,
+        _target: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MemoryCredential {
+            key: format!("{}/{}", service, user),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+struct MemoryCredential {
+    key: String,
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+        memory_secrets()
+            .lock_or_recover()
+            .insert(self.key.clone(), secret.to_vec());
+        Ok(())
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+        memory_secrets()
+            .lock_or_recover()
+            .get(&self.key)
+            .cloned()
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+        memory_secrets()
+            .lock_or_recover()
+            .remove(&self.key)
+            .map(|_| ())
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
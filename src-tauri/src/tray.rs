@@ -0,0 +1,166 @@
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, SubmenuBuilder};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, Runtime, WebviewUrl, WebviewWindowBuilder};
+
+use crate::commands::{self, AppState};
+
+const TRAY_ID: &str = "main";
+const QUICK_CAPTURE_WINDOW: &str = "quick-capture";
+const RECENT_PREFIX: &str = "recent:";
+/// Recent notes listed in the tray menu
+const RECENT_LIMIT: usize = 8;
+
+/// Add the tray icon. Its menu is rebuilt by `refresh` when the recent
+/// notes change.
+pub fn init<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Synapse")
+        .menu(&build_menu(app)?)
+        .on_menu_event(handle_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Rebuild the tray menu (recent notes, vault availability)
+pub fn refresh<R: Runtime>(app: &AppHandle<R>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => log::warn!("Failed to rebuild tray menu: {}", e),
+    }
+}
+
+fn build_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Menu<R>> {
+    let recents = recent_items(&app.state::<AppState>());
+    let mut recent_menu = SubmenuBuilder::new(app, "Recent notes").enabled(!recents.is_empty());
+    for (id, title) in recents {
+        recent_menu = recent_menu.text(id, title);
+    }
+
+    MenuBuilder::new(app)
+        .text("quick-note", "Quick note")
+        .text("daily-note", "Today's note")
+        .item(&recent_menu.build()?)
+        .separator()
+        .text("show", "Show Synapse")
+        .text("quit", "Quit")
+        .build()
+}
+
+/// `(menu id, title)` of the recent notes, newest first. Empty while no
+/// vault is open or it is locked.
+fn recent_items(state: &AppState) -> Vec<(String, String)> {
+    let recents = state
+        .database()
+        .ok()
+        .and_then(|db| db.get_recent_notes(RECENT_LIMIT).ok())
+        .unwrap_or_default();
+    recents
+        .into_iter()
+        .map(|note| (format!("{}{}", RECENT_PREFIX, note.path), note.title))
+        .collect()
+}
+
+fn handle_menu_event<R: Runtime>(app: &AppHandle<R>, event: MenuEvent) {
+    let id = event.id().as_ref();
+    let result = match id {
+        "quick-note" => open_quick_capture(app),
+        "daily-note" => match commands::daily_note(&app.state::<AppState>()) {
+            Ok(path) => open_in_main(app, &path),
+            Err(e) => {
+                log::warn!("Failed to open today's note: {}", e);
+                Ok(())
+            }
+        },
+        "show" => show_main(app),
+        "quit" => {
+            app.exit(0);
+            Ok(())
+        }
+        _ => match id.strip_prefix(RECENT_PREFIX) {
+            Some(path) => open_in_main(app, path),
+            None => Ok(()),
+        },
+    };
+    if let Err(e) = result {
+        log::warn!("Tray action '{}' failed: {}", id, e);
+    }
+}
+
+/// Small always-on-top window running the capture form
+pub fn open_quick_capture<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window(QUICK_CAPTURE_WINDOW) {
+        window.show()?;
+        return window.set_focus();
+    }
+    WebviewWindowBuilder::new(
+        app,
+        QUICK_CAPTURE_WINDOW,
+        WebviewUrl::App("index.html#quick-capture".into()),
+    )
+    .title("Quick note")
+    .inner_size(420.0, 220.0)
+    .resizable(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .center()
+    .build()?;
+    Ok(())
+}
+
+pub fn show_main<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window("main") {
+        window.unminimize()?;
+        window.show()?;
+        window.set_focus()?;
+    }
+    Ok(())
+}
+
+/// Bring up the main window and ask it to open a note
+fn open_in_main<R: Runtime>(app: &AppHandle<R>, path: &str) -> tauri::Result<()> {
+    show_main(app)?;
+    app.emit_to("main", "open-note", path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::LOCK_HASH_SETTING;
+    use crate::test_support::{use_memory_keychain, TestVault};
+
+    #[test]
+    fn test_recent_items_newest_first_and_hidden_while_locked() {
+        use_memory_keychain();
+        let vault = TestVault::with_notes(&[("Ideas.md", "# Ideas\n"), ("Plan.md", "# Plan\n")]);
+        let state = AppState::default();
+        assert!(recent_items(&state).is_empty());
+
+        let db = state.install_vault(vault.path().to_path_buf(), vault.index());
+        for path in ["Ideas.md", "Plan.md", "Ideas.md"] {
+            db.record_note_opened(path).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let items = recent_items(&state);
+        assert_eq!(
+            items,
+            vec![
+                ("recent:Ideas.md".to_string(), "Ideas".to_string()),
+                ("recent:Plan.md".to_string(), "Plan".to_string()),
+            ]
+        );
+        assert_eq!(items[0].0.strip_prefix(RECENT_PREFIX), Some("Ideas.md"));
+
+        db.set_setting(LOCK_HASH_SETTING, "hash").unwrap();
+        state.install_vault(vault.path().to_path_buf(), vault.index());
+        assert!(state.is_locked());
+        assert!(recent_items(&state).is_empty());
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
 use walkdir::WalkDir;
 
 /// Represents a file or folder in the vault
@@ -25,6 +26,10 @@ pub struct VaultEntry {
 /// Represents parsed YAML frontmatter from a note
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Frontmatter {
+    /// Stable identifier that survives renames (notes imported from the
+    /// legacy `~/.synapse/notes` store keep theirs)
+    #[serde(default)]
+    pub id: Option<String>,
     #[serde(default)]
     pub title: Option<String>,
     #[serde(default)]
@@ -40,18 +45,58 @@ pub struct Frontmatter {
     pub extra: HashMap<String, serde_yaml::Value>,
 }
 
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Marker file that makes a folder private
+pub const PRIVATE_MARKER: &str = ".private";
+
 /// Core vault operations
 pub struct Vault;
 
 impl Vault {
+    /// Use `dir` for app config instead of `~/.synapse`. Called once at
+    /// startup on platforms without a usable home directory (iOS, Android).
+    #[cfg(mobile)]
+    pub fn set_config_dir(dir: PathBuf) {
+        let _ = CONFIG_DIR.set(dir);
+    }
+
+    /// Directory for app-wide config and data (not per-vault)
+    pub fn config_dir() -> Result<PathBuf> {
+        if let Some(dir) = CONFIG_DIR.get() {
+            return Ok(dir.clone());
+        }
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(home.join(".synapse"))
+    }
+
     /// Get the path where we store vault config (which vault is open)
     pub fn config_path() -> Result<PathBuf> {
-        let home = dirs::home_dir().context("Could not determine home directory")?;
-        let config_dir = home.join(".synapse");
+        let config_dir = Self::config_dir()?;
         fs::create_dir_all(&config_dir).context("Failed to create config directory")?;
         Ok(config_dir.join("vault_config.json"))
     }
 
+    /// Turn a picked location into a vault directory. Pickers on mobile hand
+    /// back `file://` URLs; Android `content://` URIs from scoped storage have
+    /// no filesystem path, so vaults must live in app storage there.
+    pub fn resolve_location(location: &str) -> Result<PathBuf> {
+        let location = location.trim();
+        if location.starts_with("file://") {
+            let url = url::Url::parse(location).context("Invalid file URL")?;
+            return url
+                .to_file_path()
+                .map_err(|_| anyhow::anyhow!("Not a local file URL: {}", location));
+        }
+        if location.starts_with("content://") {
+            bail!("This folder can't be used as a vault; choose one in the app's storage");
+        }
+        if location.is_empty() {
+            bail!("No vault location given");
+        }
+        Ok(PathBuf::from(location))
+    }
+
     /// Read the currently configured vault path
     pub fn get_vault_path() -> Result<Option<PathBuf>> {
         let config_path = Self::config_path()?;
@@ -137,6 +182,15 @@ Start writing and connecting your ideas!
         vault_path.join(".synapse")
     }
 
+    /// The vault a file belongs to: the nearest folder above it with a
+    /// Synapse cache
+    pub fn find_vault_root(file: &Path) -> Option<PathBuf> {
+        file.ancestors()
+            .skip(1)
+            .find(|dir| Self::db_path(dir).is_file())
+            .map(Path::to_path_buf)
+    }
+
     /// Get the path to the cache database
     pub fn db_path(vault_path: &Path) -> PathBuf {
         Self::cache_dir(vault_path).join("cache.db")
@@ -218,36 +272,154 @@ Start writing and connecting your ideas!
             .collect())
     }
 
+    /// List JSON canvas (`.canvas`) files in the vault
+    pub fn list_canvases(vault_path: &Path) -> Result<Vec<VaultEntry>> {
+        let all = Self::list_entries(vault_path)?;
+        Ok(all
+            .into_iter()
+            .filter(|e| !e.is_dir && e.path.ends_with(".canvas"))
+            .collect())
+    }
+
+    /// List `.csv` files in the vault
+    pub fn list_csv_files(vault_path: &Path) -> Result<Vec<VaultEntry>> {
+        let all = Self::list_entries(vault_path)?;
+        Ok(all
+            .into_iter()
+            .filter(|e| !e.is_dir && e.path.ends_with(".csv"))
+            .collect())
+    }
+
+    /// List all raw `.excalidraw` drawings (`.excalidraw.md` ones are notes)
+    pub fn list_drawings(vault_path: &Path) -> Result<Vec<VaultEntry>> {
+        let all = Self::list_entries(vault_path)?;
+        Ok(all
+            .into_iter()
+            .filter(|e| !e.is_dir && e.path.ends_with(".excalidraw"))
+            .collect())
+    }
+
+    /// Folders marked private with a `.private` file
+    pub fn private_marker_folders(vault_path: &Path) -> Result<Vec<String>> {
+        let mut folders = Vec::new();
+        for entry in WalkDir::new(vault_path)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        {
+            let entry = entry.context("Failed to walk vault directory")?;
+            if entry.file_type().is_dir() && entry.path().join(PRIVATE_MARKER).is_file() {
+                let relative = entry
+                    .path()
+                    .strip_prefix(vault_path)
+                    .unwrap_or(entry.path())
+                    .to_string_lossy()
+                    .to_string();
+                folders.push(relative);
+            }
+        }
+        Ok(folders)
+    }
+
+    /// Add or remove a folder's `.private` marker
+    pub fn set_folder_private(vault_path: &Path, relative_path: &str, private: bool) -> Result<()> {
+        let marker = Self::resolve_path(vault_path, relative_path)?.join(PRIVATE_MARKER);
+        if private {
+            if !marker.parent().is_some_and(Path::is_dir) {
+                bail!("Not a folder: {}", relative_path);
+            }
+            fs::write(&marker, "").context("Failed to write private marker")
+        } else if marker.exists() {
+            fs::remove_file(&marker).context("Failed to remove private marker")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Where `relative_path` is on disk. Paths come from the webview, so an
+    /// absolute path or one with `..` (anything that isn't plain names) is
+    /// refused rather than let it reach outside the vault.
+    pub fn resolve_path(vault_path: &Path, relative_path: &str) -> Result<PathBuf> {
+        let path = Path::new(relative_path);
+        let plain = path.components().all(|c| matches!(c, Component::Normal(_)));
+        if relative_path.is_empty() || !plain {
+            bail!("Invalid vault path: {}", relative_path);
+        }
+        Ok(vault_path.join(path))
+    }
+
     /// Read a file's content by its relative path
     pub fn read_file(vault_path: &Path, relative_path: &str) -> Result<String> {
-        let full_path = vault_path.join(relative_path);
-        fs::read_to_string(&full_path)
-            .with_context(|| format!("Failed to read file: {}", relative_path))
+        let bytes = Self::read_bytes(vault_path, relative_path)?;
+        String::from_utf8(bytes).with_context(|| format!("Failed to read file: {}", relative_path))
+    }
+
+    /// Read a file's bytes by its relative path, decrypted if the vault is
+    /// encrypted
+    pub fn read_bytes(vault_path: &Path, relative_path: &str) -> Result<Vec<u8>> {
+        let full_path = Self::resolve_path(vault_path, relative_path)?;
+        let data = fs::read(&full_path)
+            .with_context(|| format!("Failed to read file: {}", relative_path))?;
+        crate::crypto::open_file(vault_path, relative_path, data)
     }
 
     /// Write content to a file by its relative path
     pub fn write_file(vault_path: &Path, relative_path: &str, content: &str) -> Result<()> {
-        let full_path = vault_path.join(relative_path);
+        let full_path = Self::resolve_path(vault_path, relative_path)?;
         // Ensure parent directory exists
         if let Some(parent) = full_path.parent() {
             fs::create_dir_all(parent)
                 .context("Failed to create parent directories for file")?;
         }
+        let content = crate::crypto::seal_file(vault_path, relative_path, content.as_bytes())?;
         fs::write(&full_path, content)
             .with_context(|| format!("Failed to write file: {}", relative_path))
     }
 
+    /// Write a file atomically: write a temp file next to it, then rename over the original
+    pub fn write_file_atomic(
+        vault_path: &Path,
+        relative_path: &str,
+        content: impl AsRef<[u8]>,
+    ) -> Result<()> {
+        let content = crate::crypto::seal_file(vault_path, relative_path, content.as_ref())?;
+        Self::write_bytes_atomic(vault_path, relative_path, content)
+    }
+
+    /// `write_file_atomic` without vault encryption: the bytes land on disk
+    /// as given
+    pub fn write_bytes_atomic(
+        vault_path: &Path,
+        relative_path: &str,
+        content: impl AsRef<[u8]>,
+    ) -> Result<()> {
+        let full_path = Self::resolve_path(vault_path, relative_path)?;
+        let parent = full_path
+            .parent()
+            .context("File path has no parent directory")?;
+        fs::create_dir_all(parent).context("Failed to create parent directories for file")?;
+
+        let file_name = full_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        // Hidden temp name so list_entries never picks it up
+        let tmp_path = parent.join(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+        fs::write(&tmp_path, content)
+            .with_context(|| format!("Failed to write temp file for: {}", relative_path))?;
+        if let Err(e) = fs::rename(&tmp_path, &full_path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e).with_context(|| format!("Failed to replace file: {}", relative_path));
+        }
+        Ok(())
+    }
+
     /// Create a new note file in the vault
     pub fn create_note(vault_path: &Path, relative_dir: &str, title: &str) -> Result<String> {
-        // Sanitize title for filename
-        let safe_name = sanitize_filename(title);
-        let relative_path = if relative_dir.is_empty() {
-            format!("{}.md", safe_name)
-        } else {
-            format!("{}/{}.md", relative_dir, safe_name)
-        };
+        let relative_path = Self::note_relative_path(relative_dir, title);
 
-        let full_path = vault_path.join(&relative_path);
+        let full_path = Self::resolve_path(vault_path, &relative_path)?;
         if full_path.exists() {
             anyhow::bail!("A note with this name already exists");
         }
@@ -257,28 +429,370 @@ Start writing and connecting your ideas!
             fs::create_dir_all(parent)?;
         }
 
+        let content = Self::new_note_content(title);
+        let content = crate::crypto::seal_file(vault_path, &relative_path, content.as_bytes())?;
+        fs::write(&full_path, content)
+            .with_context(|| format!("Failed to create note: {}", relative_path))?;
+
+        Ok(relative_path)
+    }
+
+    /// Save a fetched web page as a note: source metadata in the frontmatter,
+    /// description as a quote, then the page's readable text
+    pub fn create_clipped_note(
+        vault_path: &Path,
+        relative_dir: &str,
+        page: &crate::web_clip::PageMetadata,
+        source_url: &str,
+        text: &str,
+    ) -> Result<String> {
+        let title = page.title.clone().unwrap_or_else(|| source_url.to_string());
+        let relative_path = Self::note_relative_path(relative_dir, &title);
+        if Self::resolve_path(vault_path, &relative_path)?.exists() {
+            anyhow::bail!("A note with this name already exists");
+        }
+
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let mut props = serde_yaml::Mapping::new();
+        props.insert("title".into(), title.clone().into());
+        props.insert("created".into(), today.clone().into());
+        props.insert("modified".into(), today.into());
+        props.insert("tags".into(), vec!["clipping".to_string()].into());
+        props.insert("source".into(), source_url.into());
+        if let Some(site) = &page.site_name {
+            props.insert("site".into(), site.clone().into());
+        }
+        let yaml = serde_yaml::to_string(&props).context("Failed to build frontmatter")?;
+
+        let mut body = String::new();
+        if let Some(description) = &page.description {
+            body.push_str(&format!("> {}\n\n", description));
+        }
+        body.push_str(text);
+        let note = format!("---\n{}---\n\n{}\n", yaml, body.trim_end());
+        Self::write_file_atomic(vault_path, &relative_path, &note)?;
+        Ok(relative_path)
+    }
+
+    /// Vault-relative path for a note with this title inside `relative_dir`
+    pub fn note_relative_path(relative_dir: &str, title: &str) -> String {
+        // Sanitize title for filename
+        let safe_name = sanitize_filename(title);
+        let relative_dir = relative_dir.trim_matches('/');
+        if relative_dir.is_empty() {
+            format!("{}.md", safe_name)
+        } else {
+            format!("{}/{}.md", relative_dir, safe_name)
+        }
+    }
+
+    /// Default content for a brand-new note (frontmatter only)
+    pub fn new_note_content(title: &str) -> String {
         let now = chrono::Utc::now().format("%Y-%m-%d").to_string();
-        let frontmatter = format!(
+        format!(
             "---\ntitle: {}\ncreated: {}\nmodified: {}\ntags: []\n---\n\n",
             title, now, now
-        );
+        )
+    }
 
-        fs::write(&full_path, &frontmatter)
-            .with_context(|| format!("Failed to create note: {}", relative_path))?;
+    /// Fill `{{title}}`, `{{date}}` and `{{time}}` placeholders in a note template
+    pub fn render_template(template: &str, title: &str) -> String {
+        let now = chrono::Local::now();
+        template
+            .replace("{{title}}", title)
+            .replace("{{date}}", &now.format("%Y-%m-%d").to_string())
+            .replace("{{time}}", &now.format("%H:%M").to_string())
+    }
 
-        Ok(relative_path)
+    /// Initial content for a new note, from a vault template if given
+    pub fn initial_note_content(
+        vault_path: &Path,
+        title: &str,
+        template: Option<&str>,
+    ) -> Result<String> {
+        match template {
+            Some(template_path) => {
+                let template = Self::read_file(vault_path, template_path)
+                    .with_context(|| format!("Failed to read template: {}", template_path))?;
+                Ok(Self::render_template(&template, title))
+            }
+            None => Ok(Self::new_note_content(title)),
+        }
+    }
+
+    /// Append text to a note (optionally under a heading or as a timestamped bullet),
+    /// creating the note first if it doesn't exist
+    pub fn append_to_note(
+        vault_path: &Path,
+        relative_path: &str,
+        text: &str,
+        options: &AppendOptions,
+    ) -> Result<AppendResult> {
+        let relative_path = with_md_extension(relative_path);
+        let created = !Self::resolve_path(vault_path, &relative_path)?.exists();
+        let content = if created {
+            let title = Path::new(&relative_path)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            Self::initial_note_content(vault_path, &title, options.template.as_deref())?
+        } else {
+            Self::read_file(vault_path, &relative_path)?
+        };
+
+        let text = if options.timestamp {
+            let time = chrono::Local::now().format("%H:%M").to_string();
+            crate::editing::timestamp_bullet(text, &time)
+        } else {
+            text.to_string()
+        };
+        let updated = crate::editing::append_text(&content, &text, options.heading.as_deref());
+        Self::write_file_atomic(vault_path, &relative_path, &updated)?;
+
+        Ok(AppendResult {
+            path: relative_path,
+            created,
+        })
+    }
+
+    /// Move `source[start..end]` (byte offsets) into a new note and replace it
+    /// with a link (or embed) to that note. Returns the new note's path.
+    pub fn extract_to_note(
+        vault_path: &Path,
+        source_path: &str,
+        start: usize,
+        end: usize,
+        title: &str,
+        folder: &str,
+        options: &ExtractOptions,
+    ) -> Result<String> {
+        let source = Self::read_file(vault_path, source_path)?;
+        if start > end
+            || end > source.len()
+            || !source.is_char_boundary(start)
+            || !source.is_char_boundary(end)
+        {
+            anyhow::bail!("Selection is outside the note");
+        }
+        let selection = &source[start..end];
+        if selection.trim().is_empty() {
+            anyhow::bail!("Nothing selected to extract");
+        }
+
+        let title = title.trim();
+        let new_path = Self::note_relative_path(folder, title);
+        if Self::resolve_path(vault_path, &new_path)?.exists() {
+            anyhow::bail!("A note with this name already exists");
+        }
+
+        let content = Self::initial_note_content(vault_path, title, options.template.as_deref())?;
+        let content = crate::editing::append_text(&content, selection.trim(), None);
+        Self::write_file_atomic(vault_path, &new_path, &content)?;
+
+        let link = if options.embed {
+            format!("![[{}]]", title)
+        } else {
+            format!("[[{}]]", title)
+        };
+        let updated = format!("{}{}{}", &source[..start], link, &source[end..]);
+        Self::write_file_atomic(vault_path, source_path, &updated)?;
+
+        Ok(new_path)
+    }
+
+    /// Split a note into one note per heading at `level`, placed in `dest_folder`.
+    /// The original becomes an index linking to the new notes, and `[[Note#Section]]`
+    /// links in `referencing` notes are redirected to the note that now holds the section.
+    pub fn split_note(
+        vault_path: &Path,
+        relative_path: &str,
+        level: u8,
+        dest_folder: &str,
+        referencing: &[String],
+    ) -> Result<SplitResult> {
+        if !(1..=6).contains(&level) {
+            anyhow::bail!("Heading level must be between 1 and 6");
+        }
+        let content = Self::read_file(vault_path, relative_path)?;
+        let fm = Self::parse_frontmatter(&content);
+        let stem = Path::new(relative_path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let source_title = fm
+            .title
+            .clone()
+            .unwrap_or_else(|| crate::indexer::index_note(relative_path, &content, &[]).title);
+
+        let (fm_block, body) = crate::editing::split_frontmatter(&content);
+        let (sections, remaining) = crate::editing::split_sections(body, level);
+        if sections.is_empty() {
+            anyhow::bail!("No level {} headings to split on", level);
+        }
+
+        // Validate every target before touching the filesystem
+        let mut new_paths = Vec::new();
+        for section in &sections {
+            let path = Self::note_relative_path(dest_folder, &section.heading);
+            if new_paths.contains(&path) {
+                anyhow::bail!("Duplicate heading \"{}\" — rename it before splitting", section.heading);
+            }
+            if Self::resolve_path(vault_path, &path)?.exists() {
+                anyhow::bail!("A note named \"{}\" already exists", section.heading);
+            }
+            new_paths.push(path);
+        }
+
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        for (section, path) in sections.iter().zip(&new_paths) {
+            let mut props = serde_yaml::Mapping::new();
+            props.insert("title".into(), section.heading.clone().into());
+            props.insert("created".into(), today.clone().into());
+            props.insert("modified".into(), today.clone().into());
+            props.insert("tags".into(), fm.tags.clone().into());
+            props.insert("up".into(), format!("[[{}]]", source_title).into());
+            let yaml = serde_yaml::to_string(&props).context("Failed to build frontmatter")?;
+            let note = format!("---\n{}---\n\n{}\n", yaml, section.body);
+            Self::write_file_atomic(vault_path, path, &note)?;
+        }
+
+        let index = if fm_block.is_empty() {
+            remaining
+        } else {
+            format!("{}\n{}", fm_block, remaining)
+        };
+        Self::write_file_atomic(vault_path, relative_path, &index)?;
+
+        // Map old section anchors to the notes that now contain them
+        let mut anchors: HashMap<String, (String, Option<String>)> = HashMap::new();
+        for section in &sections {
+            anchors.insert(section.heading.to_lowercase(), (section.heading.clone(), None));
+            for sub in &section.subheadings {
+                anchors
+                    .entry(sub.to_lowercase())
+                    .or_insert_with(|| (section.heading.clone(), Some(sub.clone())));
+            }
+            for block in crate::indexer::extract_block_ids(&section.body) {
+                let anchor = format!("^{}", block.id);
+                anchors.insert(anchor.to_lowercase(), (section.heading.clone(), Some(anchor)));
+            }
+        }
+
+        let mut updated = Vec::new();
+        for path in referencing.iter().filter(|p| p.as_str() != relative_path) {
+            let Ok(text) = Self::read_file(vault_path, path) else {
+                continue;
+            };
+            let rewritten = crate::editing::rewrite_wikilinks(&text, |link| {
+                let target = link.target.to_lowercase();
+                if target != source_title.to_lowercase() && target != stem.to_lowercase() {
+                    return None;
+                }
+                let (new_target, subpath) = anchors.get(&link.subpath.as_ref()?.to_lowercase())?;
+                Some(crate::indexer::WikiLink {
+                    target: new_target.clone(),
+                    subpath: subpath.clone(),
+                    ..link.clone()
+                })
+            });
+            if let Some(rewritten) = rewritten {
+                Self::write_file_atomic(vault_path, path, &rewritten)?;
+                updated.push(path.clone());
+            }
+        }
+
+        Ok(SplitResult {
+            index_path: relative_path.to_string(),
+            created: new_paths,
+            updated,
+        })
+    }
+
+    /// Merge note `source` into `target` and, unless `keep_source`, delete
+    /// `source`. The bodies are combined per `strategy`, and the source's
+    /// frontmatter tags and aliases are added to the target's. Links to the
+    /// source are left to the caller.
+    pub fn merge_notes(
+        vault_path: &Path,
+        source: &str,
+        target: &str,
+        strategy: crate::editing::MergeStrategy,
+        keep_source: bool,
+    ) -> Result<()> {
+        if source == target {
+            anyhow::bail!("Can't merge a note into itself");
+        }
+        let source_content = Self::read_file(vault_path, source)?;
+        let target_content = Self::read_file(vault_path, target)?;
+        let source_fm = Self::parse_frontmatter(&source_content);
+        let target_fm = Self::parse_frontmatter(&target_content);
+
+        let (_, source_body) = crate::editing::split_frontmatter(&source_content);
+        let mut merged = crate::editing::merge_note_bodies(&target_content, source_body, strategy);
+        for (key, existing, added) in [
+            ("tags", &target_fm.tags, &source_fm.tags),
+            ("aliases", &target_fm.aliases, &source_fm.aliases),
+        ] {
+            let mut values = existing.clone();
+            for value in added {
+                if !values.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+                    values.push(value.clone());
+                }
+            }
+            if values.len() > existing.len() {
+                let values = values.into_iter().map(serde_yaml::Value::String).collect();
+                merged = crate::editing::set_frontmatter_property(
+                    &merged,
+                    key,
+                    &serde_yaml::Value::Sequence(values),
+                )?;
+            }
+        }
+
+        Self::write_file_atomic(vault_path, target, &merged)?;
+        if !keep_source {
+            Self::delete_entry(vault_path, source)?;
+        }
+        Ok(())
+    }
+
+    /// Where `relative_path` goes when archived into `archive_folder`: the
+    /// same folder path under it, with a counter added if the name is taken
+    pub fn archive_path(
+        vault_path: &Path,
+        archive_folder: &str,
+        relative_path: &str,
+    ) -> Result<String> {
+        let archive_folder = archive_folder.trim_matches('/');
+        if archive_folder.is_empty() {
+            anyhow::bail!("No archive folder is set");
+        }
+        if path_in_folder(relative_path, archive_folder) {
+            anyhow::bail!("{} is already archived", relative_path);
+        }
+        let (dir, name) = relative_path
+            .rsplit_once('/')
+            .unwrap_or(("", relative_path));
+        let dir = if dir.is_empty() {
+            archive_folder.to_string()
+        } else {
+            format!("{}/{}", archive_folder, dir)
+        };
+        Ok(Self::unique_file_path(vault_path, &dir, name))
     }
 
     /// Create a new folder in the vault
     pub fn create_folder(vault_path: &Path, relative_path: &str) -> Result<()> {
-        let full_path = vault_path.join(relative_path);
+        let full_path = Self::resolve_path(vault_path, relative_path)?;
         fs::create_dir_all(&full_path)
             .with_context(|| format!("Failed to create folder: {}", relative_path))
     }
 
     /// Delete a file or folder
     pub fn delete_entry(vault_path: &Path, relative_path: &str) -> Result<()> {
-        let full_path = vault_path.join(relative_path);
+        let full_path = Self::resolve_path(vault_path, relative_path)?;
         if full_path.is_dir() {
             fs::remove_dir_all(&full_path)
                 .with_context(|| format!("Failed to delete folder: {}", relative_path))?;
@@ -295,8 +809,8 @@ Start writing and connecting your ideas!
         old_relative: &str,
         new_relative: &str,
     ) -> Result<()> {
-        let old_path = vault_path.join(old_relative);
-        let new_path = vault_path.join(new_relative);
+        let old_path = Self::resolve_path(vault_path, old_relative)?;
+        let new_path = Self::resolve_path(vault_path, new_relative)?;
 
         // Ensure new parent directory exists
         if let Some(parent) = new_path.parent() {
@@ -311,9 +825,158 @@ Start writing and connecting your ideas!
         })
     }
 
+    /// Point wikilinks in the `referencing` notes at renamed notes. `renames`
+    /// maps an old link target (lowercase, as a name or vault path without
+    /// `.md`) to the new one; headings, block refs, aliases and embeds are
+    /// kept. Returns the notes that changed.
+    pub fn rewrite_links(
+        vault_path: &Path,
+        referencing: &[String],
+        renames: &HashMap<String, String>,
+    ) -> Result<Vec<String>> {
+        let mut updated = Vec::new();
+        for path in referencing {
+            let Ok(text) = Self::read_file(vault_path, path) else {
+                continue;
+            };
+            let rewritten = crate::editing::rewrite_wikilinks(&text, |link| {
+                let target = link.target.trim();
+                let key = target.strip_suffix(".md").unwrap_or(target).to_lowercase();
+                Some(crate::indexer::WikiLink {
+                    target: renames.get(&key)?.clone(),
+                    ..link.clone()
+                })
+            });
+            if let Some(rewritten) = rewritten {
+                Self::write_file_atomic(vault_path, path, &rewritten)?;
+                updated.push(path.clone());
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Point `[[Note#Heading]]` links in the `referencing` notes at renamed
+    /// headings. `targets` are the link targets (lowercase) that name the
+    /// note whose headings changed; `renames` pairs each heading before and
+    /// after. Returns the notes that changed.
+    pub fn rewrite_section_links(
+        vault_path: &Path,
+        referencing: &[String],
+        targets: &HashSet<String>,
+        renames: &[(crate::indexer::Heading, crate::indexer::Heading)],
+    ) -> Result<Vec<String>> {
+        let mut updated = Vec::new();
+        for path in referencing {
+            let Ok(text) = Self::read_file(vault_path, path) else {
+                continue;
+            };
+            let rewritten = crate::editing::rewrite_wikilinks(&text, |link| {
+                if !targets.contains(&link.target.trim().to_lowercase()) {
+                    return None;
+                }
+                let subpath = link.subpath.as_deref()?;
+                let renamed = renames.iter().find_map(|(before, after)| {
+                    crate::editing::rename_section_subpath(subpath, before, after)
+                })?;
+                Some(crate::indexer::WikiLink {
+                    subpath: Some(renamed),
+                    ..link.clone()
+                })
+            });
+            if let Some(rewritten) = rewritten {
+                Self::write_file_atomic(vault_path, path, &rewritten)?;
+                updated.push(path.clone());
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Rename tag `old` to `new` (without `#`) in the given notes, in their
+    /// bodies and frontmatter. Returns the notes that changed.
+    pub fn rename_tag(
+        vault_path: &Path,
+        paths: &[String],
+        old: &str,
+        new: &str,
+    ) -> Result<Vec<String>> {
+        let mut updated = Vec::new();
+        for path in paths {
+            let text = Self::read_file(vault_path, path)?;
+            if let Some(rewritten) = crate::editing::rename_tag(&text, old, new) {
+                Self::write_file_atomic(vault_path, path, &rewritten)?;
+                updated.push(path.clone());
+            }
+        }
+        Ok(updated)
+    }
+
+    /// A free relative path for `file_name` in `relative_dir`, adding a
+    /// counter (`scan 1.pdf`) if the name is taken
+    pub fn unique_file_path(vault_path: &Path, relative_dir: &str, file_name: &str) -> String {
+        let file_name = sanitize_filename(file_name);
+        let join = |name: &str| {
+            let dir = relative_dir.trim_matches('/');
+            if dir.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", dir, name)
+            }
+        };
+        let candidate = join(&file_name);
+        if !vault_path.join(&candidate).exists() {
+            return candidate;
+        }
+        let (stem, ext) = match file_name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+            _ => (file_name.as_str(), String::new()),
+        };
+        let mut counter = 1;
+        loop {
+            let candidate = join(&format!("{} {}{}", stem, counter, ext));
+            if !vault_path.join(&candidate).exists() {
+                return candidate;
+            }
+            counter += 1;
+        }
+    }
+
+    /// Folder attachments for `target_note` are saved to: `folder` from the
+    /// vault root, or from the note's own folder when it starts with `./`
+    pub fn attachment_folder(folder: &str, target_note: &str) -> String {
+        let Some(relative) = folder.strip_prefix("./") else {
+            return folder.trim_matches('/').to_string();
+        };
+        let note_dir = Path::new(target_note)
+            .parent()
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        [note_dir.as_str(), relative.trim_matches('/')]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Save an attachment's bytes into the attachments folder for
+    /// `target_note`, renaming it if the name is taken. Returns the vault
+    /// path to embed.
+    pub fn import_attachment(
+        vault_path: &Path,
+        folder: &str,
+        target_note: &str,
+        file_name: &str,
+        data: &[u8],
+    ) -> Result<String> {
+        let dir = Self::attachment_folder(folder, target_note);
+        let path = Self::unique_file_path(vault_path, &dir, file_name);
+        Self::write_file_atomic(vault_path, &path, data)?;
+        Ok(path)
+    }
+
     /// Duplicate a note
     pub fn duplicate_entry(vault_path: &Path, relative_path: &str) -> Result<String> {
-        let full_path = vault_path.join(relative_path);
+        let full_path = Self::resolve_path(vault_path, relative_path)?;
         let stem = full_path
             .file_stem()
             .unwrap_or_default()
@@ -348,6 +1011,20 @@ Start writing and connecting your ideas!
         Ok(new_relative)
     }
 
+    /// Notes that carry an `id` property, keyed by id
+    pub fn note_ids(vault_path: &Path) -> Result<HashMap<String, String>> {
+        let mut ids = HashMap::new();
+        for entry in Self::list_notes(vault_path)? {
+            let Ok(content) = Self::read_file(vault_path, &entry.path) else {
+                continue;
+            };
+            if let Some(id) = Self::parse_frontmatter(&content).id {
+                ids.insert(id, entry.path);
+            }
+        }
+        Ok(ids)
+    }
+
     /// Parse YAML frontmatter from a markdown file
     pub fn parse_frontmatter(content: &str) -> Frontmatter {
         if !content.starts_with("---") {
@@ -381,6 +1058,25 @@ Start writing and connecting your ideas!
     }
 }
 
+/// Add a `.md` extension to a note path if it has none
+pub fn with_md_extension(relative_path: &str) -> String {
+    if relative_path.ends_with(".md") {
+        relative_path.to_string()
+    } else {
+        format!("{}.md", relative_path)
+    }
+}
+
+/// Whether a vault path is `folder` itself or inside it
+pub fn path_in_folder(path: &str, folder: &str) -> bool {
+    let folder = folder.trim_matches('/');
+    !folder.is_empty()
+        && (path == folder
+            || path
+                .strip_prefix(folder)
+                .is_some_and(|rest| rest.starts_with('/')))
+}
+
 /// Sanitize a string for use as a filename
 fn sanitize_filename(name: &str) -> String {
     name.chars()
@@ -397,3 +1093,286 @@ fn sanitize_filename(name: &str) -> String {
 struct VaultConfig {
     vault_path: String,
 }
+
+/// Options for appending to a note
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppendOptions {
+    /// Append at the end of this heading's section instead of the end of the note
+    pub heading: Option<String>,
+    /// Prefix the text as a `- HH:MM` bullet
+    pub timestamp: bool,
+    /// Vault-relative template used if the note has to be created
+    pub template: Option<String>,
+}
+
+/// Outcome of an append
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppendResult {
+    pub path: String,
+    /// Whether the note was created by this append
+    pub created: bool,
+}
+
+/// Options for extracting a selection into a new note
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractOptions {
+    /// Vault-relative template for the new note
+    pub template: Option<String>,
+    /// Replace the selection with `![[Title]]` instead of `[[Title]]`
+    pub embed: bool,
+}
+
+/// Outcome of splitting a note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitResult {
+    /// The original note, now an index of links
+    pub index_path: String,
+    /// Notes created, one per section
+    pub created: Vec<String>,
+    /// Other notes whose section links were redirected
+    pub updated: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_rewrite_section_links_follows_renamed_headings() {
+        let vault = TestVault::with_notes(&[(
+            "Home.md",
+            "[[Plan#Goals]] [[plan#goals|aims]] [[Plan#Roadmap#Goals]] [[Plan#goals-1]]\n\
+             [[Plan#Risks]] [[Other#Goals]] [[#Goals]]\n",
+        )]);
+        let before = crate::indexer::extract_headings("# Plan\n## Goals\n");
+        let after = crate::indexer::extract_headings("# Plan\n## Next Steps\n");
+        let renames = vec![(before[1].clone(), after[1].clone())];
+        let targets = HashSet::from(["plan".to_string()]);
+
+        let updated =
+            Vault::rewrite_section_links(vault.path(), &["Home.md".into()], &targets, &renames)
+                .unwrap();
+        assert_eq!(updated, vec!["Home.md"]);
+        assert_eq!(
+            Vault::read_file(vault.path(), "Home.md").unwrap(),
+            "[[Plan#Next Steps]] [[plan#next-steps|aims]] [[Plan#Roadmap#Next Steps]] [[Plan#goals-1]]\n\
+             [[Plan#Risks]] [[Other#Goals]] [[#Goals]]\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_notes_unions_tags_and_deletes_source() {
+        let vault = TestVault::with_notes(&[
+            ("Target.md", "---\ntags:\n- rust\n---\n# Target\n\nbody\n"),
+            (
+                "Source.md",
+                "---\ntags: [Rust, tauri]\naliases: [src]\n---\nmore\n",
+            ),
+        ]);
+        Vault::merge_notes(
+            vault.path(),
+            "Source.md",
+            "Target.md",
+            crate::editing::MergeStrategy::Append,
+            false,
+        )
+        .unwrap();
+
+        assert!(!vault.path().join("Source.md").exists());
+        let merged = Vault::read_file(vault.path(), "Target.md").unwrap();
+        let fm = Vault::parse_frontmatter(&merged);
+        assert_eq!(fm.tags, vec!["rust", "tauri"]);
+        assert_eq!(fm.aliases, vec!["src"]);
+        assert!(merged.ends_with("# Target\n\nbody\n\nmore\n"));
+        assert!(Vault::merge_notes(
+            vault.path(),
+            "Target.md",
+            "Target.md",
+            crate::editing::MergeStrategy::Append,
+            false,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_archive_path_keeps_folders_and_avoids_clashes() {
+        let vault = TestVault::with_notes(&[("Archive/Projects/Plan.md", "old\n")]);
+        let archive = |path| Vault::archive_path(vault.path(), "Archive/", path);
+        assert_eq!(archive("Todo.md").unwrap(), "Archive/Todo.md");
+        assert_eq!(
+            archive("Projects/Plan.md").unwrap(),
+            "Archive/Projects/Plan 1.md"
+        );
+        assert!(archive("Archive/Projects/Plan.md").is_err());
+        assert!(Vault::archive_path(vault.path(), "", "Todo.md").is_err());
+    }
+
+    #[test]
+    fn test_rewrite_links_keeps_subpaths_and_aliases() {
+        let vault = TestVault::with_notes(&[(
+            "Home.md",
+            "[[Old]] [[old#Intro|see intro]] ![[Folder/Old^quote]] [[Other]]\n",
+        )]);
+        let renames = HashMap::from([
+            ("old".to_string(), "New".to_string()),
+            ("folder/old".to_string(), "Folder/New".to_string()),
+        ]);
+
+        let updated = Vault::rewrite_links(vault.path(), &["Home.md".into()], &renames).unwrap();
+        assert_eq!(updated, vec!["Home.md"]);
+        assert_eq!(
+            Vault::read_file(vault.path(), "Home.md").unwrap(),
+            "[[New]] [[New#Intro|see intro]] ![[Folder/New#^quote]] [[Other]]\n"
+        );
+    }
+
+    #[test]
+    fn test_import_attachment_into_configured_folder() {
+        let vault = TestVault::with_notes(&[("Projects/Plan.md", "# Plan\n")]);
+        assert_eq!(
+            Vault::attachment_folder("./assets", "Projects/Plan.md"),
+            "Projects/assets"
+        );
+        assert_eq!(Vault::attachment_folder("./", "Home.md"), "");
+        assert_eq!(
+            Vault::attachment_folder("/attachments/", "Home.md"),
+            "attachments"
+        );
+
+        let first = Vault::import_attachment(
+            vault.path(),
+            "attachments",
+            "Projects/Plan.md",
+            "shot.png",
+            b"png",
+        )
+        .unwrap();
+        let second = Vault::import_attachment(
+            vault.path(),
+            "attachments",
+            "Projects/Plan.md",
+            "shot.png",
+            b"png2",
+        )
+        .unwrap();
+        assert_eq!(first, "attachments/shot.png");
+        assert_eq!(second, "attachments/shot 1.png");
+        assert_eq!(Vault::read_bytes(vault.path(), &second).unwrap(), b"png2");
+    }
+
+    #[test]
+    fn test_append_to_note_creates_then_appends_in_place() {
+        let vault = TestVault::new();
+        let options = AppendOptions {
+            heading: Some("Log".into()),
+            ..AppendOptions::default()
+        };
+        let first = Vault::append_to_note(vault.path(), "Inbox", "- one", &options).unwrap();
+        assert!(first.created);
+        let second = Vault::append_to_note(vault.path(), "Inbox.md", "- two", &options).unwrap();
+        assert!(!second.created);
+
+        let content = Vault::read_file(vault.path(), "Inbox.md").unwrap();
+        assert!(content.ends_with("---\n\n## Log\n\n- one\n- two\n"));
+        // The atomic write leaves no temp files behind
+        let files: Vec<_> = fs::read_dir(vault.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_extract_to_note_leaves_a_link_behind() {
+        let vault =
+            TestVault::with_notes(&[("Meeting.md", "# Meeting\nkeep\nmove me\nkeep too\n")]);
+        let source = Vault::read_file(vault.path(), "Meeting.md").unwrap();
+        let start = source.find("move me").unwrap();
+        let end = start + "move me".len();
+
+        let options = ExtractOptions {
+            embed: true,
+            ..ExtractOptions::default()
+        };
+        let path = Vault::extract_to_note(
+            vault.path(),
+            "Meeting.md",
+            start,
+            end,
+            "Action items",
+            "Notes",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(path, "Notes/Action items.md");
+        assert_eq!(
+            Vault::read_file(vault.path(), "Meeting.md").unwrap(),
+            "# Meeting\nkeep\n![[Action items]]\nkeep too\n"
+        );
+        assert!(Vault::read_file(vault.path(), &path)
+            .unwrap()
+            .ends_with("move me\n"));
+        let db = vault.index();
+        assert_eq!(
+            db.get_backlinks("Action items").unwrap(),
+            vec!["Meeting.md"]
+        );
+
+        // The same title again, or a selection that isn't one, is refused
+        let extract = |start, end, title| {
+            Vault::extract_to_note(
+                vault.path(),
+                "Meeting.md",
+                start,
+                end,
+                title,
+                "Notes",
+                &options,
+            )
+        };
+        assert!(extract(0, 5, "Action items").is_err());
+        assert!(extract(5, 200, "Other").is_err());
+        assert!(extract(0, 0, "Other").is_err());
+    }
+
+    #[test]
+    fn test_resolve_location_accepts_paths_and_file_urls() {
+        assert_eq!(
+            Vault::resolve_location("  /home/ada/Notes ").unwrap(),
+            PathBuf::from("/home/ada/Notes")
+        );
+        // Pickers percent-encode the path
+        assert_eq!(
+            Vault::resolve_location("file:///home/ada/My%20Notes").unwrap(),
+            PathBuf::from("/home/ada/My Notes")
+        );
+        assert!(Vault::resolve_location("file://server/share").is_err());
+        assert!(Vault::resolve_location("content://com.android.externalstorage/tree/x").is_err());
+        assert!(Vault::resolve_location("  ").is_err());
+    }
+
+    #[test]
+    fn test_paths_outside_the_vault_are_refused() {
+        let vault = TestVault::with_notes(&[("Projects/Plan.md", "# Plan\n")]);
+        let absolute = vault.path().join("Projects/Plan.md");
+        let absolute = absolute.to_string_lossy();
+
+        assert!(Vault::read_file(vault.path(), "Projects/Plan.md").is_ok());
+        assert!(Vault::read_file(vault.path(), &absolute).is_err());
+        assert!(Vault::read_file(vault.path(), "/etc/passwd").is_err());
+        assert!(Vault::read_bytes(vault.path(), "../../.ssh/id_rsa").is_err());
+        assert!(Vault::read_bytes(vault.path(), "Projects/../../x").is_err());
+        assert!(Vault::write_file(vault.path(), "../Escaped.md", "x").is_err());
+        assert!(Vault::write_file_atomic(vault.path(), &absolute, "x").is_err());
+        assert!(Vault::create_folder(vault.path(), "../Escaped").is_err());
+        assert!(Vault::delete_entry(vault.path(), "").is_err());
+        assert!(Vault::rename_entry(vault.path(), "Projects/Plan.md", "../Plan.md").is_err());
+        assert!(Vault::create_note(vault.path(), "..", "Escaped").is_err());
+
+        assert!(!vault.path().parent().unwrap().join("Escaped.md").exists());
+        assert_eq!(
+            Vault::read_file(vault.path(), "Projects/Plan.md").unwrap(),
+            "# Plan\n"
+        );
+    }
+}
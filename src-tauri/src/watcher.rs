@@ -0,0 +1,191 @@
+//! Watches the open vault for changes made outside Synapse (another editor,
+//! a sync client, git) and reindexes the files touched, telling the
+//! frontend through `note-changed` and `note-deleted` events.
+
+use anyhow::{Context, Result};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::citations;
+use crate::commands::AppState;
+use crate::database::Database;
+
+/// Quiet period before a burst of events (a save, a `git checkout`) is
+/// handled as one batch
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// File types the indexer reads
+const INDEXED_EXTENSIONS: [&str; 4] = ["md", "canvas", "csv", "excalidraw"];
+
+/// A running watch; dropping it stops watching
+pub struct VaultWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+/// What a batch of events did to the index
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Changed(String),
+    Deleted(String),
+}
+
+/// Start watching `vault_path`, reindexing into `db`
+pub fn watch(app: &AppHandle, vault_path: &Path, db: Arc<Database>) -> Result<VaultWatcher> {
+    let app = app.clone();
+    let root = vault_path.to_path_buf();
+    // Events arrive with symlinks resolved (`/private/var` on macOS)
+    let canonical_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        let events = match result {
+            Ok(events) => events,
+            Err(e) => {
+                log::warn!("Vault watcher error: {}", e);
+                return;
+            }
+        };
+        let paths: Vec<String> = events
+            .iter()
+            .filter_map(|event| {
+                relative_path(&root, &event.path)
+                    .or_else(|| relative_path(&canonical_root, &event.path))
+            })
+            .collect();
+        let changes = apply(&root, &db, &paths);
+        // Nothing about the vault reaches the frontend while it is locked
+        if app.state::<AppState>().is_locked() {
+            return;
+        }
+        for change in changes {
+            let _ = match change {
+                Change::Changed(path) => app.emit("note-changed", path),
+                Change::Deleted(path) => app.emit("note-deleted", path),
+            };
+        }
+    })
+    .context("Failed to start vault watcher")?;
+    debouncer
+        .watcher()
+        .watch(vault_path, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", vault_path.display()))?;
+    Ok(VaultWatcher {
+        _debouncer: debouncer,
+    })
+}
+
+/// Bring the index in line with the files at `paths` (vault-relative):
+/// reindex the ones that exist, drop the ones that are gone, and for a
+/// removed folder every note that was in it
+pub fn apply(vault_path: &Path, db: &Database, paths: &[String]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for path in paths {
+        let file = vault_path.join(path);
+        if file.is_file() {
+            if !is_indexed(path) {
+                if is_bibliography(db, path) {
+                    if let Err(e) = citations::reindex_bibliography(vault_path, db) {
+                        log::warn!("Failed to reindex {}: {:#}", path, e);
+                    }
+                }
+                continue;
+            }
+            match db.reindex_note(vault_path, path) {
+                Ok(()) => changes.push(Change::Changed(path.clone())),
+                Err(e) => log::warn!("Failed to reindex {}: {:#}", path, e),
+            }
+        } else if !file.exists() {
+            for gone in cached_under(db, path) {
+                match db.delete_note(&gone) {
+                    Ok(()) => changes.push(Change::Deleted(gone)),
+                    Err(e) => log::warn!("Failed to remove {} from the index: {:#}", gone, e),
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// The path relative to the vault, or `None` for files outside it and in
+/// hidden folders (`.synapse`, `.git`), which are never indexed
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let hidden = relative.components().any(|c| match c {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    });
+    if hidden || relative.as_os_str().is_empty() {
+        return None;
+    }
+    Some(relative.to_string_lossy().to_string())
+}
+
+/// Whether the indexer reads this kind of file: notes, canvases, CSV, drawings
+pub fn is_indexed(path: &str) -> bool {
+    PathBuf::from(path)
+        .extension()
+        .is_some_and(|ext| INDEXED_EXTENSIONS.iter().any(|e| ext == *e))
+}
+
+/// Whether `path` is the `.bib` file citations are read from
+fn is_bibliography(db: &Database, path: &str) -> bool {
+    matches!(db.get_setting(citations::BIB_FILE_SETTING), Ok(Some(bib)) if bib == path)
+}
+
+/// Cached notes at `path` or, if it was a folder, inside it
+fn cached_under(db: &Database, path: &str) -> Vec<String> {
+    let folder = format!("{}{}", path, std::path::MAIN_SEPARATOR);
+    match db.get_all_notes() {
+        Ok(notes) => notes
+            .into_iter()
+            .map(|note| note.path)
+            .filter(|note| note == path || note.starts_with(&folder))
+            .collect(),
+        Err(e) => {
+            log::warn!("Failed to list cached notes: {:#}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestVault;
+
+    #[test]
+    fn test_apply_reindexes_and_removes() {
+        let vault = TestVault::with_notes(&[
+            ("Home.md", "# Home\n"),
+            ("Old/A.md", "# A\n"),
+            ("Old/B.md", "# B\n"),
+        ]);
+        let db = vault.index();
+
+        vault.write("Home.md", "# Home\n[[New]]\n");
+        vault.write("New.md", "# New\n");
+        vault.write("notes.txt", "not indexed");
+        std::fs::remove_dir_all(vault.path().join("Old")).unwrap();
+
+        let changes = apply(
+            vault.path(),
+            &db,
+            &["Home.md".into(), "New.md".into(), "notes.txt".into(), "Old".into()],
+        );
+        assert_eq!(changes.len(), 4);
+        assert!(changes.contains(&Change::Changed("New.md".into())));
+        assert!(changes.contains(&Change::Deleted(format!(
+            "Old{}A.md",
+            std::path::MAIN_SEPARATOR
+        ))));
+        assert_eq!(db.get_backlinks("New").unwrap(), vec!["Home.md"]);
+        assert_eq!(db.get_all_notes().unwrap().len(), 2);
+
+        assert_eq!(
+            relative_path(vault.path(), &vault.path().join(".synapse/cache.db")),
+            None
+        );
+    }
+}
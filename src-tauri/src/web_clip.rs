@@ -0,0 +1,293 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Enough for any page's <head>; the rest of an oversized page is ignored
+const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+const USER_AGENT: &str = concat!("Synapse/", env!("CARGO_PKG_VERSION"));
+/// Elements whose contents never belong in clipped text
+const SKIPPED_TAGS: &[&str] = &["script", "style", "noscript", "svg", "template"];
+/// Elements that start a new paragraph in clipped text
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "tr",
+    "blockquote",
+    "pre",
+    "section",
+    "article",
+];
+
+/// Metadata pulled from a web page for link pasting and clipping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageMetadata {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    pub image: Option<String>,
+    /// Ready-to-insert `[Title](url)`, or `<url>` when the page has no title
+    pub markdown: String,
+}
+
+/// A fetched page: its metadata plus the (possibly truncated) HTML
+pub struct FetchedPage {
+    pub metadata: PageMetadata,
+    pub html: String,
+}
+
+/// Download an http(s) page with a timeout and size cap and read its metadata
+pub async fn fetch_page(url: &str) -> Result<FetchedPage> {
+    let parsed = reqwest::Url::parse(url.trim()).context("Invalid URL")?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("Only http and https URLs can be fetched");
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::limited(5))
+        .build()
+        .context("Failed to build HTTP client")?;
+    let mut response = client
+        .get(parsed)
+        .send()
+        .await
+        .context("Failed to fetch page")?
+        .error_for_status()
+        .context("Page returned an error")?;
+
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map_or(true, |ct| ct.contains("html"));
+    if !is_html {
+        bail!("URL does not point to an HTML page");
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("Failed to read page")? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PAGE_BYTES {
+            body.truncate(MAX_PAGE_BYTES);
+            break;
+        }
+    }
+
+    let html = String::from_utf8_lossy(&body).into_owned();
+    let metadata = extract_metadata(&html, url.trim());
+    Ok(FetchedPage { metadata, html })
+}
+
+/// Read title/OpenGraph/Twitter metadata from raw HTML
+pub fn extract_metadata(html: &str, url: &str) -> PageMetadata {
+    let mut meta: Vec<(String, String)> = Vec::new();
+    let mut rest = html;
+    while let Some(start) = find_ci(rest, "<meta") {
+        let tag_end = rest[start..].find('>').map_or(rest.len(), |e| start + e);
+        let tag = &rest[start..tag_end];
+        let key = attr(tag, "property").or_else(|| attr(tag, "name"));
+        if let (Some(key), Some(content)) = (key, attr(tag, "content")) {
+            meta.push((key.to_lowercase(), content));
+        }
+        rest = &rest[tag_end..];
+    }
+    let get = |keys: &[&str]| {
+        keys.iter().find_map(|k| {
+            meta.iter()
+                .find(|(key, value)| key == k && !value.trim().is_empty())
+                .map(|(_, value)| clean_text(value))
+        })
+    };
+
+    let title_tag = find_ci(html, "<title").and_then(|start| {
+        let open_end = start + html[start..].find('>')? + 1;
+        let close = open_end + find_ci(&html[open_end..], "</title")?;
+        Some(clean_text(&html[open_end..close]))
+    });
+
+    let title = get(&["og:title", "twitter:title"])
+        .or(title_tag)
+        .filter(|t| !t.is_empty());
+    let markdown = match &title {
+        Some(title) => format!("[{}]({})", escape_link_text(title), url),
+        None => format!("<{}>", url),
+    };
+
+    PageMetadata {
+        url: url.to_string(),
+        title,
+        description: get(&["og:description", "twitter:description", "description"]),
+        site_name: get(&["og:site_name", "application-name"]),
+        image: get(&["og:image", "twitter:image"]),
+        markdown,
+    }
+}
+
+/// Readable text of a page's body: scripts, styles and tags removed,
+/// block elements turned into paragraphs
+pub fn readable_text(html: &str) -> String {
+    let body_start = find_ci(html, "<body")
+        .and_then(|s| html[s..].find('>').map(|e| s + e + 1))
+        .unwrap_or(0);
+    let body = &html[body_start..];
+
+    let mut text = String::new();
+    let mut i = 0;
+    while i < body.len() {
+        let rest = &body[i..];
+        if !rest.starts_with('<') {
+            let next = rest.find('<').unwrap_or(rest.len());
+            text.push_str(&rest[..next]);
+            i += next;
+            continue;
+        }
+        let tag_end = rest.find('>').map_or(rest.len(), |e| e + 1);
+        let closing = rest[1..].starts_with('/');
+        let name: String = rest[1..tag_end.max(1)]
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        i += tag_end;
+
+        if !closing && SKIPPED_TAGS.contains(&name.as_str()) {
+            let close = format!("</{}", name);
+            i += find_ci(&body[i..], &close).unwrap_or(body.len() - i);
+        } else if BLOCK_TAGS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+    }
+
+    let mut paragraphs = Vec::new();
+    for line in decode_entities(&text).lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            paragraphs.push(line);
+        }
+    }
+    paragraphs.join("\n\n")
+}
+
+/// Case-insensitive ASCII search
+fn find_ci(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Value of `name="..."` (or single-quoted / unquoted) inside a tag
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let mut search = tag;
+    while let Some(pos) = find_ci(search, name) {
+        let before = search[..pos].chars().last();
+        let after = search[pos + name.len()..].trim_start();
+        search = &search[pos + name.len()..];
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        return if quote == '"' || quote == '\'' {
+            value[1..]
+                .find(quote)
+                .map(|end| value[1..1 + end].to_string())
+        } else {
+            let end = value
+                .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .unwrap_or(value.len());
+            Some(value[..end].to_string())
+        };
+    }
+    None
+}
+
+fn clean_text(s: &str) -> String {
+    decode_entities(s)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let window = &rest.as_bytes()[..rest.len().min(12)];
+        let Some(semi) = window.iter().position(|b| *b == b';') else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn escape_link_text(title: &str) -> String {
+    title.replace('[', "\\[").replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_metadata() {
+        let html = r#"<html><head><TITLE> Plain &amp; Simple </TITLE>
+            <meta property="og:title" content="Rich &quot;Title&quot;">
+            <meta name='description' content='About [things]'>
+            <meta content="Example" property="og:site_name" />
+            </head><body><p>Hello <b>world</b></p><script>var x = "<p>";</script><div>Bye&#33;</div></body></html>"#;
+        let meta = extract_metadata(html, "https://example.com");
+        assert_eq!(meta.title.as_deref(), Some("Rich \"Title\""));
+        assert_eq!(meta.description.as_deref(), Some("About [things]"));
+        assert_eq!(meta.site_name.as_deref(), Some("Example"));
+        assert_eq!(readable_text(html), "Hello world\n\nBye!");
+
+        let meta = extract_metadata("<title>A [b]</title>", "https://x.y");
+        assert_eq!(meta.markdown, "[A \\[b\\]](https://x.y)");
+    }
+}
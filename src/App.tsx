@@ -1,4 +1,6 @@
 import { useEffect, useCallback, useState, useRef } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { useVaultStore } from './stores/vaultStore';
 import VaultSetup from './components/VaultSetup';
 import FileExplorer from './components/FileExplorer';
@@ -6,6 +8,12 @@ import BacklinksPanel from './components/BacklinksPanel';
 import Editor from './components/Editor';
 import ToastContainer, { showToast } from './components/Toast';
 
+interface NoteLink {
+  vault: string | null;
+  path: string;
+  new_vault: boolean;
+}
+
 export default function App() {
   const {
     vaultReady,
@@ -17,7 +25,9 @@ export default function App() {
     leftSidebarOpen,
     rightSidebarOpen,
     initVault,
+    openVault,
     createNote,
+    openNote,
     saveNote,
     toggleLeftSidebar,
     toggleRightSidebar,
@@ -26,10 +36,68 @@ export default function App() {
   const [readingMode, setReadingMode] = useState(false);
   const saveTimeoutRef = useRef<ReturnType<typeof setTimeout> | null>(null);
 
-  // Initialize vault on mount
+  const openLink = useCallback(
+    async (link: NoteLink) => {
+      // A file opened from outside any vault: offer its folder as a new vault
+      if (link.new_vault && !confirm(`"${link.path}" isn't in a Synapse vault. Open "${link.vault}" as a vault?`)) {
+        return;
+      }
+      if (link.vault && (link.new_vault || link.vault !== useVaultStore.getState().vaultPath)) {
+        await openVault(link.vault);
+      }
+      await openNote(link.path);
+    },
+    [openVault, openNote]
+  );
+
+  // Initialize vault on mount, then open the note a synapse:// link launched us with
+  useEffect(() => {
+    initVault()
+      .then(() => invoke<NoteLink | null>('take_launch_link'))
+      .then((link) => link && openLink(link));
+  }, [initVault, openLink]);
+
+  // Notes opened from the tray menu, OS search results, synapse:// links and Open With
   useEffect(() => {
-    initVault();
-  }, [initVault]);
+    const unlistenNote = listen<string>('open-note', (event) => {
+      openNote(event.payload);
+    });
+    const unlistenVaultNote = listen<NoteLink>('open-vault-note', (event) => {
+      openLink(event.payload).catch(() => showToast('Could not open that vault', 'error'));
+    });
+    return () => {
+      unlistenNote.then((fn) => fn());
+      unlistenVaultNote.then((fn) => fn());
+    };
+  }, [openNote, openLink]);
+
+  // The vault's cache was corrupt and has been rebuilt from the notes on disk
+  useEffect(() => {
+    const unlisten = listen<string>('cache-recovered', () => {
+      const { refreshEntries, refreshNotes } = useVaultStore.getState();
+      refreshEntries();
+      refreshNotes();
+      showToast('The vault cache was damaged and has been rebuilt', 'info');
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Files changed outside Synapse; the backend has already reindexed them
+  useEffect(() => {
+    const refresh = () => {
+      const { refreshEntries, refreshNotes } = useVaultStore.getState();
+      refreshEntries();
+      refreshNotes();
+    };
+    const unlistenChanged = listen<string>('note-changed', refresh);
+    const unlistenDeleted = listen<string>('note-deleted', refresh);
+    return () => {
+      unlistenChanged.then((fn) => fn());
+      unlistenDeleted.then((fn) => fn());
+    };
+  }, []);
 
   // Auto-save with debounce
   const handleContentChange = useCallback(
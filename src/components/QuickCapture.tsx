@@ -0,0 +1,62 @@
+import { useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { errorMessage } from '../stores/vaultStore';
+
+export default function QuickCapture() {
+    const [text, setText] = useState('');
+    const [newNote, setNewNote] = useState(false);
+    const [error, setError] = useState('');
+
+    const handleSave = async () => {
+        if (!text.trim()) return;
+        try {
+            setError('');
+            await invoke('quick_capture', {
+                text,
+                target: newNote ? { kind: 'newNote' } : { kind: 'inbox' },
+            });
+            setText('');
+            await getCurrentWindow().hide();
+        } catch (e) {
+            setError(errorMessage(e));
+        }
+    };
+
+    const handleKeyDown = (e: React.KeyboardEvent) => {
+        // Cmd/Ctrl+Enter saves, Escape dismisses
+        if (e.key === 'Enter' && (e.metaKey || e.ctrlKey)) {
+            e.preventDefault();
+            handleSave();
+        } else if (e.key === 'Escape') {
+            getCurrentWindow().hide();
+        }
+    };
+
+    return (
+        <div className="quick-capture">
+            <textarea
+                className="quick-capture-input"
+                value={text}
+                onChange={e => setText(e.target.value)}
+                onKeyDown={handleKeyDown}
+                placeholder="Capture a thought…"
+                autoFocus
+            />
+            {error && <div className="quick-capture-error">{error}</div>}
+            <div className="quick-capture-actions">
+                <label className="quick-capture-toggle">
+                    <input
+                        type="checkbox"
+                        checked={newNote}
+                        onChange={e => setNewNote(e.target.checked)}
+                    />
+                    New note
+                </label>
+                <button className="quick-capture-save" onClick={handleSave} disabled={!text.trim()}>
+                    Save
+                </button>
+            </div>
+        </div>
+    );
+}
@@ -1,6 +1,6 @@
 import { useState } from 'react';
 import { open } from '@tauri-apps/plugin-dialog';
-import { useVaultStore } from '../stores/vaultStore';
+import { errorMessage, useVaultStore } from '../stores/vaultStore';
 
 export default function VaultSetup() {
     const [creating, setCreating] = useState(false);
@@ -19,7 +19,7 @@ export default function VaultSetup() {
                 await createVault(selected as string);
             }
         } catch (e) {
-            setError(String(e));
+            setError(errorMessage(e));
             setCreating(false);
         }
     };
@@ -36,7 +36,7 @@ export default function VaultSetup() {
                 await openVault(selected as string);
             }
         } catch (e) {
-            setError(String(e));
+            setError(errorMessage(e));
             setCreating(false);
         }
     };
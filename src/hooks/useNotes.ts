@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '../stores/vaultStore';
 
 export interface Note {
     id: string;
@@ -29,7 +30,7 @@ export function useNotes() {
             setNotes(allNotes);
             setError(null);
         } catch (e) {
-            setError(`Failed to load notes: ${e}`);
+            setError(`Failed to load notes: ${errorMessage(e)}`);
         } finally {
             setLoading(false);
         }
@@ -55,7 +56,7 @@ export function useNotes() {
                 contentLoadedRef.current = activeNoteId;
                 setError(null);
             } catch (e) {
-                setError(`Failed to load note: ${e}`);
+                setError(`Failed to load note: ${errorMessage(e)}`);
             }
         };
 
@@ -71,7 +72,7 @@ export function useNotes() {
             setError(null);
             return note;
         } catch (e) {
-            setError(`Failed to create note: ${e}`);
+            setError(`Failed to create note: ${errorMessage(e)}`);
             return null;
         }
     }, [fetchNotes]);
@@ -84,7 +85,7 @@ export function useNotes() {
             await fetchNotes();
             setError(null);
         } catch (e) {
-            setError(`Failed to save note: ${e}`);
+            setError(`Failed to save note: ${errorMessage(e)}`);
         } finally {
             setSaving(false);
         }
@@ -101,7 +102,7 @@ export function useNotes() {
             await fetchNotes();
             setError(null);
         } catch (e) {
-            setError(`Failed to delete note: ${e}`);
+            setError(`Failed to delete note: ${errorMessage(e)}`);
         }
     }, [activeNoteId, fetchNotes]);
 
@@ -112,7 +113,7 @@ export function useNotes() {
             await fetchNotes();
             setError(null);
         } catch (e) {
-            setError(`Failed to rename note: ${e}`);
+            setError(`Failed to rename note: ${errorMessage(e)}`);
         }
     }, [fetchNotes]);
 
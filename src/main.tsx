@@ -2,9 +2,13 @@ import { StrictMode } from 'react'
 import { createRoot } from 'react-dom/client'
 import './index.css'
 import App from './App'
+import QuickCapture from './components/QuickCapture'
+
+// The tray's quick-note window loads the same bundle with this hash
+const isQuickCapture = window.location.hash === '#quick-capture'
 
 createRoot(document.getElementById('root')!).render(
   <StrictMode>
-    <App />
+    {isQuickCapture ? <QuickCapture /> : <App />}
   </StrictMode>,
 )
@@ -19,6 +19,9 @@ export interface CachedNote {
     modified_at: string | null;
     word_count: number;
     starred: boolean;
+    pinned: boolean;
+    pin_order: number | null;
+    aliases: string[];
 }
 
 export interface BacklinkResult {
@@ -31,6 +34,33 @@ export interface Heading {
     text: string;
     level: number;
     line: number;
+    slug: string;
+}
+
+export interface SchemaInfo {
+    schema_version: number;
+    app_version: string | null;
+    supported_version: number;
+}
+
+/** What a failed command rejects with (`SynapseError` in the backend) */
+export interface SynapseError {
+    code: 'vault_not_open' | 'vault_locked' | 'not_found' | 'permission_denied'
+        | 'already_exists' | 'invalid_input' | 'busy' | 'newer_schema'
+        | 'cache_corrupt' | 'io' | 'internal';
+    message: string;
+    path?: string;
+    schema?: SchemaInfo;
+    retryable: boolean;
+}
+
+export function isSynapseError(e: unknown): e is SynapseError {
+    return typeof e === 'object' && e !== null && 'code' in e && 'message' in e;
+}
+
+/** A caught error's text, for command errors and anything else thrown */
+export function errorMessage(e: unknown): string {
+    return isSynapseError(e) ? e.message : String(e);
 }
 
 // ─── Vault Store ──────────────────────────────────────────────────
@@ -136,7 +166,16 @@ export const useVaultStore = create<VaultState>((set, get) => ({
     openVault: async (path: string) => {
         try {
             set({ loading: true });
-            await invoke('open_vault', { path });
+            try {
+                await invoke('open_vault', { path });
+            } catch (e) {
+                // A cache from a newer Synapse can't be read; offer to rebuild it
+                if (!isSynapseError(e) || e.code !== 'newer_schema'
+                    || !confirm(`${e.message}\n\nRebuild the cache now? The old one is kept as a backup.`)) {
+                    throw e;
+                }
+                await invoke('rebuild_vault_cache', { path });
+            }
             set({ vaultPath: path, vaultReady: true, loading: false });
             await get().refreshEntries();
             await get().refreshNotes();
@@ -228,11 +267,16 @@ export const useVaultStore = create<VaultState>((set, get) => ({
 
     renameEntry: async (oldPath: string, newPath: string) => {
         try {
-            await invoke('rename_entry', { oldPath, newPath });
+            const updated = await invoke<string[]>('rename_entry', { oldPath, newPath });
             // If we renamed the active note, update the path
             if (get().activeNotePath === oldPath) {
                 set({ activeNotePath: newPath });
             }
+            // Reload the open note if its links to the renamed note were rewritten
+            const active = get().activeNotePath;
+            if (active && updated.includes(active)) {
+                await get().openNote(active);
+            }
             await get().refreshEntries();
             await get().refreshNotes();
         } catch (e) {